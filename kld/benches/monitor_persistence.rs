@@ -0,0 +1,95 @@
+//! Compares writing the whole channel monitor on every update with appending the update, for a channel
+//! that has forwarded some payments. Needs cockroach like the database tests.
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use kld::database::LdkDatabase;
+use lightning::chain::transaction::OutPoint;
+use lightning::ln::functional_test_utils::{
+    create_announced_chan_between_nodes, create_chanmon_cfgs, create_network, create_node_cfgs,
+    create_node_chanmgrs, send_payment,
+};
+use lightning::util::ser::Writeable;
+use test_utils::{init_db_test_context, TempDir};
+use tokio::runtime::Runtime;
+
+const PAYMENTS: usize = 100;
+
+fn monitor_persistence(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    let (settings, _cockroach, durable_connection) =
+        runtime.block_on(init_db_test_context(&temp_dir)).unwrap();
+    let database = {
+        let _guard = runtime.enter();
+        LdkDatabase::new(settings.into(), durable_connection.into())
+    };
+
+    let chanmon_cfgs = create_chanmon_cfgs(2);
+    let node_cfgs = create_node_cfgs(2, &chanmon_cfgs);
+    let node_chanmgrs = create_node_chanmgrs(2, &node_cfgs, &[None, None]);
+    let nodes = create_network(2, &node_cfgs, &node_chanmgrs);
+    let (_, _, channel_id, funding_tx) = create_announced_chan_between_nodes(&nodes, 0, 1);
+    let funding_txo = OutPoint {
+        txid: funding_tx.txid(),
+        index: 0,
+    };
+    for _ in 0..PAYMENTS {
+        send_payment(&nodes[0], &[&nodes[1]], 1000000);
+    }
+    let monitor = nodes[0]
+        .chain_monitor
+        .chain_monitor
+        .get_monitor(funding_txo)
+        .unwrap();
+    let updates = nodes[0]
+        .chain_monitor
+        .monitor_updates
+        .lock()
+        .unwrap()
+        .get(&channel_id)
+        .unwrap()
+        .clone();
+    let update = updates.last().unwrap();
+    println!(
+        "Monitor of {} bytes after {} updates, the last update is {} bytes",
+        monitor.encode().len(),
+        updates.len(),
+        update.encode().len()
+    );
+
+    let database = &database;
+    let monitor = &*monitor;
+
+    let mut group = c.benchmark_group("channel_monitor_persistence");
+    group.bench_function("full_monitor", |b| {
+        b.to_async(&runtime)
+            .iter(|| database.persist_channel_monitor(funding_txo, monitor))
+    });
+    group.bench_function("update", |b| {
+        b.to_async(&runtime)
+            .iter(|| database.persist_channel_monitor_update(funding_txo, update))
+    });
+    // The default of a full write every 100 updates.
+    let mut count = 0;
+    group.bench_function("update_with_full_monitor_every_100", |b| {
+        b.to_async(&runtime).iter_batched(
+            || {
+                count += 1;
+                count % 100 == 0
+            },
+            |full| async move {
+                if full {
+                    database.persist_channel_monitor(funding_txo, monitor).await
+                } else {
+                    database
+                        .persist_channel_monitor_update(funding_txo, update)
+                        .await
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, monitor_persistence);
+criterion_main!(benches);
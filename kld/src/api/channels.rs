@@ -8,7 +8,7 @@ use super::payloads::{
 use crate::api::SocketAddress;
 use crate::database::{forward::ForwardStatus, ChannelRecord};
 use crate::ldk::htlc_destination_to_string;
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use axum::extract::Path;
 use axum::extract::Query;
 use axum::{response::IntoResponse, Extension, Json};
@@ -17,7 +17,7 @@ use lightning::events::HTLCDestination;
 use lightning::ln::channelmanager::ChannelDetails;
 use lightning::ln::features::ChannelTypeFeatures;
 use lightning::ln::ChannelId;
-use lightning::util::config::MaxDustHTLCExposure;
+use lightning::util::config::{MaxDustHTLCExposure, UserConfig};
 
 use crate::api::bad_request;
 use crate::ldk::LightningInterface;
@@ -34,8 +34,8 @@ use super::codegen::get_v1_channel_list_peer_channels_response::{
     GetV1ChannelListPeerChannelsResponse, GetV1ChannelListPeerChannelsResponseOpener,
 };
 use super::codegen::get_v1_channel_localremotebal_response::GetV1ChannelLocalremotebalResponse;
-use super::internal_server;
 use super::ApiError;
+use super::{empty_string_as_none, internal_server, pagination};
 
 pub(crate) async fn list_channels(
     Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
@@ -288,6 +288,32 @@ pub(crate) async fn open_channel(
     Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
     Json(fund_channel): Json<FundChannel>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let (public_key, value, push_msat, user_config) =
+        prepare_channel(lightning_interface.as_ref(), &fund_channel).await?;
+    let result = lightning_interface
+        .open_channel(
+            public_key,
+            value,
+            push_msat,
+            fund_channel.fee_rate,
+            Some(user_config),
+        )
+        .await
+        .map_err(internal_server)?;
+
+    let response = FundChannelResponse {
+        tx: result.transaction,
+        txid: result.txid.to_string(),
+        channel_id: hex::encode(result.channel_id.0),
+    };
+    Ok(Json(response))
+}
+
+/// Connect to the peer and parse the amounts and config of a channel to open.
+pub(crate) async fn prepare_channel(
+    lightning_interface: &(dyn LightningInterface + Send + Sync),
+    fund_channel: &FundChannel,
+) -> Result<(PublicKey, u64, Option<u64>, UserConfig), ApiError> {
     let (public_key, net_address) = match fund_channel.id.split_once('@') {
         Some((public_key, net_address)) => (
             PublicKey::from_str(public_key).map_err(bad_request)?,
@@ -314,24 +340,7 @@ pub(crate) async fn open_channel(
     if let Some(announce) = fund_channel.announce {
         user_config.channel_handshake_config.announced_channel = announce;
     }
-
-    let result = lightning_interface
-        .open_channel(
-            public_key,
-            value,
-            push_msat,
-            fund_channel.fee_rate,
-            Some(user_config),
-        )
-        .await
-        .map_err(internal_server)?;
-
-    let response = FundChannelResponse {
-        tx: result.transaction,
-        txid: result.txid.to_string(),
-        channel_id: hex::encode(result.channel_id.0),
-    };
-    Ok(Json(response))
+    Ok((public_key, value, push_msat, user_config))
 }
 
 pub(crate) async fn set_channel_fee(
@@ -496,6 +505,13 @@ pub(crate) async fn local_remote_balance(
 #[derive(Serialize, Deserialize)]
 pub struct ListForwardsQueryParams {
     pub status: Option<GetV1ChannelListForwardsResponseItemStatus>,
+    /// Forwards in or out of the channel.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub channel: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub offset: Option<u64>,
+    pub limit: Option<u64>,
 }
 
 pub(crate) async fn list_forwards(
@@ -508,9 +524,15 @@ pub(crate) async fn list_forwards(
         Some(GetV1ChannelListForwardsResponseItemStatus::Offered) => Some(ForwardStatus::Succeeded),
         _ => Some(ForwardStatus::Failed),
     };
+    let channel_id = params
+        .channel
+        .as_deref()
+        .map(parse_channel_id)
+        .transpose()?;
+    let pagination = pagination(params.from, params.to, params.offset, params.limit)?;
     let mut response = vec![];
     for forward in lightning_interface
-        .fetch_forwards(status)
+        .fetch_forwards(status, channel_id, pagination)
         .await
         .map_err(internal_server)?
     {
@@ -537,9 +559,9 @@ pub(crate) async fn list_forwards(
                 .map(htlc_destination_to_string),
             fee_msat: forward.fee,
             in_channel: hex::encode(forward.inbound_channel_id.0),
-            in_msat: forward.amount,
+            in_msat: forward.amount.and_then(|a| forward.fee.map(|f| a + f)),
             out_channel: forward.outbound_channel_id.map(|x| hex::encode(x.0)),
-            out_msat: forward.amount.and_then(|a| forward.fee.map(|f| a - f)),
+            out_msat: forward.amount,
             payment_hash: match forward.htlc_destination {
                 Some(HTLCDestination::FailedPayment { payment_hash }) => {
                     Some(hex::encode(payment_hash.0))
@@ -558,11 +580,29 @@ pub(crate) async fn list_forwards(
     Ok(Json(response))
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct ChannelHistoryParams {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub channel: Option<String>,
+    /// Time range of the closing.
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub offset: Option<u64>,
+    pub limit: Option<u64>,
+}
+
 pub(crate) async fn channel_history(
     Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Query(params): Query<ChannelHistoryParams>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let channel_id = params
+        .channel
+        .as_deref()
+        .map(parse_channel_id)
+        .transpose()?;
+    let pagination = pagination(params.from, params.to, params.offset, params.limit)?;
     let channel_history = lightning_interface
-        .channel_history()
+        .channel_history(channel_id, pagination)
         .await
         .map_err(internal_server)?;
 
@@ -613,3 +653,11 @@ fn format_features(channel_type: ChannelTypeFeatures) -> Vec<String> {
         })
         .collect()
 }
+
+fn parse_channel_id(channel_id: &str) -> Result<ChannelId, ApiError> {
+    let bytes: [u8; 32] = hex::decode(channel_id)
+        .map_err(bad_request)?
+        .try_into()
+        .map_err(|_| bad_request(anyhow!("Channel ID must be 32 bytes")))?;
+    Ok(ChannelId::from_bytes(bytes))
+}
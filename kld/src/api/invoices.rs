@@ -1,8 +1,4 @@
-use std::{
-    str::FromStr,
-    sync::Arc,
-    time::{SystemTime, UNIX_EPOCH},
-};
+use std::{str::FromStr, sync::Arc, time::UNIX_EPOCH};
 
 use super::payloads::{GenerateInvoice, GenerateInvoiceResponse, Invoice, InvoiceStatus};
 use anyhow::anyhow;
@@ -17,9 +13,9 @@ use super::{
     codegen::get_v1_utility_decode_invoice_string_response::{
         GetV1UtilityDecodeInvoiceStringResponse, GetV1UtilityDecodeInvoiceStringResponseType,
     },
-    empty_string_as_none,
+    empty_string_as_none, pagination,
 };
-use crate::{ldk::LightningInterface, MillisatAmount};
+use crate::{database::invoice::InvoiceStatusFilter, ldk::LightningInterface, MillisatAmount};
 
 use super::{bad_request, internal_server, ApiError};
 
@@ -57,6 +53,11 @@ pub(crate) async fn generate_invoice(
 pub struct ListInvoiceParams {
     #[serde(default, deserialize_with = "empty_string_as_none")]
     pub label: Option<String>,
+    pub status: Option<InvoiceStatus>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub offset: Option<u64>,
+    pub limit: Option<u64>,
 }
 
 pub(crate) async fn list_invoices(
@@ -68,9 +69,15 @@ pub(crate) async fn list_invoices(
             return Err(bad_request(anyhow!("Label max length is 100 chars")));
         }
     }
+    let pagination = pagination(params.from, params.to, params.offset, params.limit)?;
+    let status = params.status.map(|status| match status {
+        InvoiceStatus::Unpaid => InvoiceStatusFilter::Unpaid,
+        InvoiceStatus::Paid => InvoiceStatusFilter::Paid,
+        InvoiceStatus::Expired => InvoiceStatusFilter::Expired,
+    });
     let mut response = vec![];
     let invoices = lightning_interface
-        .list_invoices(params.label)
+        .list_invoices(params.label, status, pagination)
         .await
         .map_err(internal_server)?;
     for invoice in invoices {
@@ -84,11 +91,7 @@ pub(crate) async fn list_invoices(
             .fold(MillisatAmount::default(), |sum, p| sum + p.amount);
         let status = if !invoice.payments.is_empty() {
             InvoiceStatus::Paid
-        } else if invoice.bolt11.expiry_time()
-            > SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map_err(internal_server)?
-        {
+        } else if invoice.bolt11.is_expired() {
             InvoiceStatus::Expired
         } else {
             InvoiceStatus::Unpaid
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use axum::extract::Query;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::database::ledger::{LedgerEntry, LedgerEntryType};
+use crate::ldk::LightningInterface;
+
+use super::payloads::LedgerRecord;
+use super::{bad_request, internal_server, ApiError};
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct LedgerParams {
+    /// Unix timestamp of the first entry (inclusive).
+    pub from: Option<i64>,
+    /// Unix timestamp of the last entry (exclusive).
+    pub to: Option<i64>,
+    /// json (default) or csv.
+    pub format: Option<String>,
+}
+
+/// The header of the universal CSV format that crypto tax tools (Koinly, CoinTracking, Accointing) import.
+const CSV_HEADER: &str = "Date,Sent Amount,Sent Currency,Received Amount,Received Currency,Fee Amount,Fee Currency,Net Worth Amount,Net Worth Currency,Label,Description,TxHash";
+
+pub(crate) async fn ledger(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Query(params): Query<LedgerParams>,
+) -> Result<Response, ApiError> {
+    let from = params
+        .from
+        .map(OffsetDateTime::from_unix_timestamp)
+        .transpose()
+        .map_err(bad_request)?;
+    let to = params
+        .to
+        .map(OffsetDateTime::from_unix_timestamp)
+        .transpose()
+        .map_err(bad_request)?;
+    let entries = lightning_interface
+        .ledger(from, to)
+        .await
+        .map_err(internal_server)?;
+    match params.format.as_deref() {
+        None | Some("json") => {
+            let records: Vec<LedgerRecord> = entries
+                .into_iter()
+                .map(|entry| LedgerRecord {
+                    entry_type: entry.entry_type.to_string(),
+                    reference: entry.reference,
+                    amount_msat: entry.amount,
+                    fee_msat: entry.fee,
+                    channel_id: entry.channel_id.map(|id| hex::encode(id.0)),
+                    timestamp: entry.timestamp.unix_timestamp(),
+                })
+                .collect();
+            Ok(Json(records).into_response())
+        }
+        Some("csv") => {
+            let mut csv = format!("{CSV_HEADER}\n");
+            for entry in entries.iter().filter_map(csv_row) {
+                csv.push_str(&entry);
+                csv.push('\n');
+            }
+            Ok(([(header::CONTENT_TYPE, "text/csv")], csv).into_response())
+        }
+        Some(format) => Err(bad_request(anyhow!("Unknown format {format}"))),
+    }
+}
+
+/// Transfers between the wallet and our channels only show their fee as a cost, without a fee there is nothing to report.
+fn csv_row(entry: &LedgerEntry) -> Option<String> {
+    let (sent, received, label) = if entry.entry_type.is_transfer() {
+        if entry.fee == 0 {
+            return None;
+        }
+        (None, None, "cost")
+    } else if entry.amount < 0 {
+        (Some(entry.amount.unsigned_abs()), None, "")
+    } else {
+        let label = match entry.entry_type {
+            LedgerEntryType::ForwardFee => "income",
+            _ => "",
+        };
+        (None, Some(entry.amount as u64), label)
+    };
+    let currency = |amount: Option<u64>| if amount.is_some() { "BTC" } else { "" };
+    let fee = (entry.fee > 0).then_some(entry.fee);
+    let timestamp = entry.timestamp;
+    Some(format!(
+        "{}-{:02}-{:02} {:02}:{:02}:{:02} UTC,{},{},{},{},{},{},,,{},{},{}",
+        timestamp.year(),
+        timestamp.month() as u8,
+        timestamp.day(),
+        timestamp.hour(),
+        timestamp.minute(),
+        timestamp.second(),
+        sent.map(btc).unwrap_or_default(),
+        currency(sent),
+        received.map(btc).unwrap_or_default(),
+        currency(received),
+        fee.map(btc).unwrap_or_default(),
+        currency(fee),
+        label,
+        entry.entry_type.to_string(),
+        entry.reference,
+    ))
+}
+
+/// Millisats as a decimal BTC amount.
+fn btc(msat: u64) -> String {
+    let amount = format!("{}.{:011}", msat / 100_000_000_000, msat % 100_000_000_000);
+    amount
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
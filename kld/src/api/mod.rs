@@ -1,17 +1,19 @@
 mod channels;
 mod invoices;
+mod ledger;
 mod macaroon_auth;
 mod network;
 pub mod payloads;
 mod payments;
 mod peers;
 pub mod routes;
+mod routing;
 mod skt_addr;
 mod utility;
 mod wallet;
 mod ws;
 
-pub use skt_addr::SocketAddress;
+pub use skt_addr::{AddressType, SocketAddress};
 
 pub use macaroon_auth::{KldMacaroon, MacaroonAuth};
 use serde::{Deserialize, Deserializer};
@@ -27,18 +29,29 @@ use crate::{
             set_channel_fee,
         },
         invoices::{decode_invoice, generate_invoice, list_invoices},
+        ledger::ledger,
         macaroon_auth::{admin_auth, readonly_auth},
         network::{
             fee_rates, get_network_channel, get_network_node, list_network_channels,
-            list_network_nodes,
+            list_network_nodes, rgs_snapshot,
         },
         payments::{keysend, list_payments, pay_invoice},
-        peers::{connect_peer, disconnect_peer, list_peers},
-        utility::{estimate_channel_liquidity_range, get_fees, score, sign},
-        wallet::{get_balance, list_funds, new_address, transfer},
+        peers::{connect_peer, disconnect_peer, list_peers, peer_history},
+        routing::routing_report,
+        utility::{
+            backup, estimate_channel_liquidity_range, get_fees, get_scorer_parameters, score,
+            scorer_liquidity, sign, update_node_announcement, update_scorer_parameters,
+            upload_scorer,
+        },
+        wallet::{
+            bump_fee, consolidation_report, create_psbt, export_descriptors, export_labels,
+            freeze_utxos, get_balance, import_labels, list_funds, list_psbts, list_sweeps,
+            list_transactions, new_address, submit_psbt, transfer, unfreeze_utxos, withdraw_many,
+        },
         ws::ws_handler,
     },
     bitcoind::bitcoind_interface::BitcoindInterface,
+    database::Pagination,
     ldk::LightningInterface,
     wallet::WalletInterface,
 };
@@ -58,6 +71,7 @@ use futures::{future::Shared, Future};
 use hyper::StatusCode;
 use log::{error, info, warn};
 use std::{net::SocketAddr, str::FromStr, sync::Arc, time::Duration};
+use time::OffsetDateTime;
 use tower_http::cors::CorsLayer;
 
 pub const API_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -113,6 +127,16 @@ impl RestApi {
             .route(routes::LIST_CHANNELS, get(list_channels))
             .route(routes::DECODE_INVOICE, get(decode_invoice))
             .route(routes::SCORER, get(score))
+            .route(routes::SCORER_PARAMETERS, get(get_scorer_parameters))
+            .route(routes::SCORER_LIQUIDITY, get(scorer_liquidity))
+            .route(routes::PEER_HISTORY, get(peer_history))
+            .route(routes::PSBT, get(list_psbts))
+            .route(routes::LIST_TRANSACTIONS, get(list_transactions))
+            .route(routes::LABELS, get(export_labels))
+            .route(routes::CONSOLIDATION, get(consolidation_report))
+            .route(routes::LIST_SWEEPS, get(list_sweeps))
+            .route(routes::LEDGER, get(ledger))
+            .route(routes::ROUTING_REPORT, get(routing_report))
             .layer(from_fn(readonly_auth));
 
         let admin_routes = Router::new()
@@ -134,16 +158,32 @@ impl RestApi {
             )
             .route(routes::NEW_ADDR, get(new_address))
             .route(routes::WITHDRAW, post(transfer))
+            .route(routes::WITHDRAW_MANY, post(withdraw_many))
+            .route(routes::PSBT, post(create_psbt))
+            .route(routes::SUBMIT_PSBT, post(submit_psbt))
+            .route(routes::BUMP_FEE, post(bump_fee))
+            .route(routes::LABELS, post(import_labels))
+            .route(routes::FREEZE_UTXOS, post(freeze_utxos))
+            .route(routes::UNFREEZE_UTXOS, post(unfreeze_utxos))
+            .route(routes::DESCRIPTORS, get(export_descriptors))
             .route(routes::CONNECT_PEER, post(connect_peer))
             .route(routes::DISCONNECT_PEER, delete(disconnect_peer))
             .route(routes::KEYSEND, post(keysend))
             .route(routes::GENERATE_INVOICE, post(generate_invoice))
             .route(routes::PAY_INVOICE, post(pay_invoice))
             .route(routes::WEBSOCKET, get(ws_handler))
+            .route(routes::BACKUP, get(backup))
+            .route(routes::NODE_ANNOUNCEMENT, post(update_node_announcement))
+            .route(routes::SCORER, post(upload_scorer))
+            .route(routes::SCORER_PARAMETERS, post(update_scorer_parameters))
             .layer(from_fn(admin_auth));
 
+        // Served to our LSP clients which have no macaroon.
+        let public_routes = Router::new().route(routes::RGS_SNAPSHOT, get(rgs_snapshot));
+
         let routes = readonly_routes
             .merge(admin_routes)
+            .merge(public_routes)
             .fallback(handler_404)
             .layer(cors)
             .layer(Extension(bitcoind_api))
@@ -252,6 +292,27 @@ pub mod codegen {
     include!(concat!(env!("OUT_DIR"), "/mod.rs"));
 }
 
+/// The page of a list from query parameters, with the time range in unix timestamps.
+pub(crate) fn pagination(
+    from: Option<i64>,
+    to: Option<i64>,
+    offset: Option<u64>,
+    limit: Option<u64>,
+) -> Result<Pagination, ApiError> {
+    Ok(Pagination {
+        from: from
+            .map(OffsetDateTime::from_unix_timestamp)
+            .transpose()
+            .map_err(bad_request)?,
+        to: to
+            .map(OffsetDateTime::from_unix_timestamp)
+            .transpose()
+            .map_err(bad_request)?,
+        offset,
+        limit,
+    })
+}
+
 pub(crate) fn empty_string_as_none<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
 where
     D: Deserializer<'de>,
@@ -1,25 +1,48 @@
 use super::payloads::{
-    FeeRates, FeeRatesResponse, NetworkChannel, NetworkNode, OnChainFeeEstimates,
+    FeeRates, FeeRatesResponse, NetworkChannel, NetworkNode, OnChainFeeEstimates, TargetFeeRate,
 };
 use crate::api::SocketAddress;
 use anyhow::anyhow;
-use axum::{extract::Path, response::IntoResponse, Extension, Json};
+use axum::{
+    extract::{Path, Query},
+    response::IntoResponse,
+    Extension, Json,
+};
 use bitcoin::secp256k1::PublicKey;
 use lightning::routing::gossip::{ChannelInfo, ChannelUpdateInfo, NodeId, NodeInfo};
+use serde::{Deserialize, Serialize};
 use std::{str::FromStr, sync::Arc};
 
-use crate::{bitcoind::bitcoind_interface::BitcoindInterface, ldk::LightningInterface};
+use crate::{
+    bitcoind::{bitcoind_interface::BitcoindInterface, target_name},
+    ldk::LightningInterface,
+};
 
 use super::{bad_request, internal_server, ApiError};
 
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ListNetworkParams {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+/// Announced nodes ordered by node ID.
 pub(crate) async fn list_network_nodes(
     Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Query(params): Query<ListNetworkParams>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let nodes: Vec<NetworkNode> = lightning_interface
+    let mut nodes: Vec<NetworkNode> = lightning_interface
         .nodes()
         .unordered_iter()
         .filter_map(|(node_id, announcement)| to_api_node(node_id, announcement))
         .collect();
+    nodes.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+    let nodes: Vec<NetworkNode> = nodes
+        .into_iter()
+        .skip(params.offset.unwrap_or_default())
+        .take(params.limit.unwrap_or(usize::MAX))
+        .collect();
     Ok(Json(nodes))
 }
 
@@ -48,12 +71,33 @@ pub(crate) async fn get_network_channel(
     Err(ApiError::NotFound(id))
 }
 
+pub(crate) async fn rgs_snapshot(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Path(timestamp): Path<u32>,
+) -> Result<impl IntoResponse, ApiError> {
+    lightning_interface
+        .rgs_snapshot(timestamp)
+        .ok_or_else(|| ApiError::NotFound("rapid gossip sync snapshot".to_string()))
+}
+
+/// Channels ordered by short channel ID. The offset and limit count channels, not their directions,
+/// so that both directions of a channel are on the same page.
 pub(crate) async fn list_network_channels(
     Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Query(params): Query<ListNetworkParams>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let graph_channels = lightning_interface.channels();
+    let mut short_channel_ids: Vec<&u64> = graph_channels.unordered_keys().collect();
+    short_channel_ids.sort();
     let mut channels = vec![];
-    for (short_channel_id, channel_info) in lightning_interface.channels().unordered_iter() {
-        channels.append(&mut to_api_channel(short_channel_id, channel_info))
+    for short_channel_id in short_channel_ids
+        .into_iter()
+        .skip(params.offset.unwrap_or_default())
+        .take(params.limit.unwrap_or(usize::MAX))
+    {
+        if let Some(channel_info) = graph_channels.get(short_channel_id) {
+            channels.append(&mut to_api_channel(short_channel_id, channel_info))
+        }
     }
     Ok(Json(channels))
 }
@@ -91,6 +135,7 @@ pub(crate) async fn fee_rates(
                 perkb: Some(fee_rates),
                 perkw: None,
                 onchain_fee_estimates,
+                targets: target_fee_rates(bitcoind_interface.as_ref(), 4),
             }
         }
         "perkw" => {
@@ -105,6 +150,7 @@ pub(crate) async fn fee_rates(
                 perkb: None,
                 perkw: Some(fee_rates),
                 onchain_fee_estimates,
+                targets: target_fee_rates(bitcoind_interface.as_ref(), 1),
             }
         }
         _ => return Err(bad_request(anyhow!("unknown fee style {}", style))),
@@ -112,6 +158,22 @@ pub(crate) async fn fee_rates(
     Ok(Json(response))
 }
 
+fn target_fee_rates(
+    bitcoind_interface: &(dyn BitcoindInterface + Send + Sync),
+    multiplier: u32,
+) -> Vec<TargetFeeRate> {
+    bitcoind_interface
+        .target_fee_rates()
+        .into_iter()
+        .map(|t| TargetFeeRate {
+            target: target_name(t.target).to_string(),
+            policy: t.policy.to_string(),
+            estimate: t.estimate.map(|e| e * multiplier),
+            fee_rate: t.fee_rate * multiplier,
+        })
+        .collect()
+}
+
 fn to_api_channel(short_channel_id: &u64, channel_info: &ChannelInfo) -> Vec<NetworkChannel> {
     let mut channels = vec![];
 
@@ -1,4 +1,4 @@
-use std::{fmt::Display, str::FromStr};
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
 
 use bitcoin::Transaction;
 use serde::{de::Visitor, Deserialize, Serialize};
@@ -77,6 +77,24 @@ pub struct WalletTransfer {
     pub utxos: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawMany {
+    /// Paid by a single transaction with change back to the wallet
+    pub outputs: Vec<WithdrawOutput>,
+    /// urgent, normal or slow
+    pub fee_rate: Option<FeeRate>,
+    /// A retried request with the same key returns the first transaction instead of paying again
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WithdrawOutput {
+    /// Any Bitcoin accepted type, including bech32
+    pub address: String,
+    pub satoshis: u64,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct WalletTransferResponse {
     /// Transaction
@@ -85,6 +103,239 @@ pub struct WalletTransferResponse {
     pub txid: String,
 }
 
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePsbt {
+    /// Withdraw on chain funds, the PSBT is broadcast when it is submitted
+    pub withdraw: Option<WalletTransfer>,
+    /// Open a channel, the PSBT funds the channel when it is submitted
+    pub fund_channel: Option<FundChannel>,
+    /// Sign the inputs with the wallet key
+    #[serde(default)]
+    pub sign: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Psbt {
+    pub id: String,
+    /// Base64 encoded PSBT
+    pub psbt: String,
+    /// withdraw or fund_channel
+    pub purpose: String,
+    pub txid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee: Option<u64>,
+    /// Temporary id of the channel funded by the PSBT (hex)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+    pub created_at: i64,
+    /// The PSBT can not be submitted after this time
+    pub expires_at: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmitPsbt {
+    pub id: String,
+    /// Base64 encoded PSBT with the signatures
+    pub psbt: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BumpFee {
+    /// Our unconfirmed transaction
+    pub txid: String,
+    /// The fee rate to reach, urgent, normal, slow, <sats>perkw or <sats>perkb
+    pub fee_rate: FeeRate,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BumpFeeResponse {
+    /// The replacement or the child transaction
+    pub tx: String,
+    pub txid: String,
+    pub original_txid: String,
+    /// rbf or cpfp
+    pub method: String,
+    /// Fee paid by the new transaction
+    pub fee: u64,
+    /// The channel funded by the original transaction (hex)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+    /// The PSBT of the original withdrawal or funding transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub psbt_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletTransaction {
+    pub txid: String,
+    pub confirmed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_height: Option<u32>,
+    /// Block time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// Sum of our outputs
+    pub received: u64,
+    /// Sum of our inputs
+    pub sent: u64,
+    /// Only known when all the inputs are ours
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee: Option<u64>,
+    /// Net change of the wallet balance
+    pub amount: i64,
+    /// channel_funding, cooperative_close, sweep, fee_bump, withdrawal or deposit
+    pub purpose: String,
+    /// The channel that was funded or closed (hex)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+    /// User assigned label
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WalletDescriptors {
+    pub descriptors: Vec<WalletDescriptor>,
+    /// Watch-only wallets only need to scan the chain from this height.
+    pub birthday_height: Option<u32>,
+}
+
+/// A public descriptor in the format of bitcoind importdescriptors.
+#[derive(Serialize, Deserialize)]
+pub struct WalletDescriptor {
+    /// Public descriptor with its checksum
+    pub desc: String,
+    /// The descriptor of the change addresses
+    pub internal: bool,
+    /// bech32 or p2tr
+    pub address_type: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletSweep {
+    pub txid: String,
+    /// The cold storage address.
+    pub address: String,
+    pub amount: u64,
+    /// Fee rate in sats per kw.
+    pub fee_rate: u32,
+    pub timestamp: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsolidationReport {
+    /// Economy fee rate estimate in sats per kw.
+    pub fee_rate: u32,
+    /// UTXOs that would be merged, smallest first.
+    pub utxos: Vec<String>,
+    /// Total value in sats of the UTXOs.
+    pub amount: u64,
+    pub fee: u64,
+    /// Whether the UTXOs would be consolidated now.
+    pub ready: bool,
+    /// Why the UTXOs would not be consolidated now.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<String>,
+}
+
+/// An entry of the accounting ledger.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerRecord {
+    /// payment_received, payment_sent, forward_fee, channel_open, channel_close, sweep, fee_bump, deposit or withdrawal.
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    /// The payment id, forward id or txid that the entry was recorded from.
+    pub reference: String,
+    /// Change of the balance excluding the fee, negative when spent.
+    pub amount_msat: i64,
+    pub fee_msat: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+    pub timestamp: i64,
+}
+
+/// Forwarding through a channel or with a peer in the time window of a routing report.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingStats {
+    /// Succeeded forwards that came in.
+    pub in_count: u64,
+    pub in_msat: u64,
+    /// Succeeded forwards that went out.
+    pub out_count: u64,
+    pub out_msat: u64,
+    /// Earned by forwarding out.
+    pub fees_msat: u64,
+    /// Failed forwards that came in by the kind of HTLC destination that they failed at.
+    pub failures: BTreeMap<String, u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capacity_sat: Option<u64>,
+    /// Fees earned per sat of capacity.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fees_msat_per_capacity_sat: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelRoutingStats {
+    pub channel_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counterparty: Option<String>,
+    #[serde(flatten)]
+    pub stats: RoutingStats,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerRoutingStats {
+    pub counterparty: String,
+    pub channels: u64,
+    #[serde(flatten)]
+    pub stats: RoutingStats,
+}
+
+/// Channels and peers ranked by their forwarding in the time window, best first.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<i64>,
+    pub channels: Vec<ChannelRoutingStats>,
+    pub peers: Vec<PeerRoutingStats>,
+}
+
+/// A label record in the BIP-329 export format.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Bip329Label {
+    /// tx, addr, pubkey, input, output or xpub
+    #[serde(rename = "type")]
+    pub label_type: String,
+    #[serde(rename = "ref")]
+    pub reference: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spendable: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ImportLabelsResponse {
+    pub imported: usize,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub enum OutputStatus {
     Unconfirmed,
@@ -101,6 +352,15 @@ pub struct ListFundsOutput {
     pub status: OutputStatus,
     #[serde(rename = "blockheight")]
     pub block_height: Option<u32>,
+    /// Frozen outputs are not spent by withdrawals or channel funding
+    #[serde(default)]
+    pub frozen: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FreezeUtxos {
+    /// Outputs as "txid:vout"
+    pub utxos: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -257,6 +517,21 @@ pub struct FeeRates {
     pub max_acceptable: u32,
 }
 
+/// The fee rate of a confirmation target, in the units of the requested style.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetFeeRate {
+    /// LDK confirmation target (e.g. on_chain_sweep)
+    pub target: String,
+    /// Fee source and the floor, ceiling and multiplier applied to its estimates
+    pub policy: String,
+    /// The last estimate from the fee source, before the policy was applied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<u32>,
+    /// The fee rate in use
+    pub fee_rate: u32,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct OnChainFeeEstimates {
     pub opening_channel_satoshis: u32,
@@ -272,6 +547,8 @@ pub struct FeeRatesResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub perkw: Option<FeeRates>,
     pub onchain_fee_estimates: OnChainFeeEstimates,
+    #[serde(default)]
+    pub targets: Vec<TargetFeeRate>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -319,6 +596,37 @@ pub struct Peer {
     pub connected: bool,
     pub netaddr: Option<String>,
     pub alias: String,
+    // Known addresses, most recently successful first.
+    pub addresses: Vec<PeerAddress>,
+    // Percentage of the last week that we were connected to the peer.
+    pub uptime: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PeerAddress {
+    pub address: String,
+    pub last_success: Option<i64>,
+    pub last_failure: Option<i64>,
+    // Consecutive failed connection attempts.
+    pub failures: u32,
+    // We will not try to connect to this address again before this time.
+    pub retry_after: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PeerEvent {
+    // connected, disconnected or connection_failed
+    pub event: String,
+    pub address: Option<String>,
+    pub timestamp: i64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PeerHistory {
+    pub id: String,
+    pub uptime: Option<f64>,
+    pub addresses: Vec<PeerAddress>,
+    pub events: Vec<PeerEvent>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -332,6 +640,67 @@ pub struct NetworkNode {
     pub addresses: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Default)]
+pub struct UpdateNodeAnnouncement {
+    pub alias: Option<String>,
+    pub color: Option<String>,
+    pub addresses: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NodeAnnouncement {
+    pub alias: String,
+    pub color: String,
+    pub addresses: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScorerLiquidity {
+    // When the scorer was last persisted, unix seconds.
+    pub last_updated: Option<i64>,
+    pub channels: Vec<ChannelLiquidity>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChannelLiquidity {
+    pub short_channel_id: u64,
+    pub source: String,
+    pub target: String,
+    pub capacity_sat: Option<u64>,
+    pub min_liquidity_msat: u64,
+    pub max_liquidity_msat: u64,
+    pub historical_min_liquidity_buckets: Option<Vec<u16>>,
+    pub historical_max_liquidity_buckets: Option<Vec<u16>>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct UpdateScorerParameters {
+    pub base_penalty_msat: Option<u64>,
+    pub base_penalty_amount_multiplier_msat: Option<u64>,
+    pub liquidity_penalty_multiplier_msat: Option<u64>,
+    pub liquidity_penalty_amount_multiplier_msat: Option<u64>,
+    pub historical_liquidity_penalty_multiplier_msat: Option<u64>,
+    pub historical_liquidity_penalty_amount_multiplier_msat: Option<u64>,
+    // Seconds
+    pub liquidity_offset_half_life: Option<u64>,
+    // Seconds
+    pub historical_no_updates_half_life: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScorerParameters {
+    pub base_penalty_msat: u64,
+    pub base_penalty_amount_multiplier_msat: u64,
+    pub liquidity_penalty_multiplier_msat: u64,
+    pub liquidity_penalty_amount_multiplier_msat: u64,
+    pub historical_liquidity_penalty_multiplier_msat: u64,
+    pub historical_liquidity_penalty_amount_multiplier_msat: u64,
+    // Seconds
+    pub liquidity_offset_half_life: u64,
+    // Seconds
+    pub historical_no_updates_half_life: u64,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct SignRequest {
     pub message: String,
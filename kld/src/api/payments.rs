@@ -19,7 +19,7 @@ use super::{
         GetV1PayListPaymentsResponse, GetV1PayListPaymentsResponsePaymentsItem,
         GetV1PayListPaymentsResponsePaymentsItemStatus,
     },
-    empty_string_as_none, internal_server, ApiError,
+    empty_string_as_none, internal_server, pagination, ApiError,
 };
 
 pub(crate) async fn keysend(
@@ -97,6 +97,13 @@ pub struct ListPaysParams {
     pub invoice: Option<String>,
     #[serde(default, deserialize_with = "empty_string_as_none")]
     pub direction: Option<String>,
+    /// pending, complete or failed, as in the response.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub status: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub offset: Option<u64>,
+    pub limit: Option<u64>,
 }
 
 pub(crate) async fn list_payments(
@@ -113,8 +120,23 @@ pub(crate) async fn list_payments(
         .map(|d| PaymentDirection::from_str(&d))
         .transpose()
         .map_err(bad_request)?;
+    let statuses: &[PaymentStatus] = match params.status.as_deref() {
+        None => &[],
+        Some("pending") => &[PaymentStatus::Pending],
+        Some("complete") => &[PaymentStatus::Succeeded],
+        Some("failed") => &[
+            PaymentStatus::RecipientRejected,
+            PaymentStatus::UserAbandoned,
+            PaymentStatus::RetriesExhausted,
+            PaymentStatus::Expired,
+            PaymentStatus::RouteNotFound,
+            PaymentStatus::Error,
+        ],
+        Some(status) => return Err(bad_request(anyhow!("Unknown payment status {status}"))),
+    };
+    let pagination = pagination(params.from, params.to, params.offset, params.limit)?;
     let payments: Vec<GetV1PayListPaymentsResponsePaymentsItem> = lightning_interface
-        .list_payments(invoice, direction)
+        .list_payments(invoice, direction, statuses, pagination)
         .await
         .map_err(internal_server)?
         .into_iter()
@@ -4,9 +4,10 @@ use super::codegen::{
     post_v1_peer_connect_body::PostV1PeerConnectBody,
     post_v1_peer_connect_response::PostV1PeerConnectResponse,
 };
-use super::payloads::Peer;
+use super::payloads::{Peer, PeerAddress, PeerEvent, PeerHistory};
 use crate::{
-    api::bad_request,
+    api::{bad_request, SocketAddress},
+    database::peer,
     ldk::{LightningInterface, PeerStatus},
 };
 use anyhow::Result;
@@ -29,6 +30,8 @@ pub(crate) async fn list_peers(
             connected: p.status == PeerStatus::Connected,
             netaddr: p.net_address.as_ref().map(|a| a.to_string()),
             alias: p.alias.clone(),
+            addresses: p.addresses.iter().map(to_peer_address).collect(),
+            uptime: p.uptime,
         })
         .collect();
 
@@ -71,3 +74,39 @@ pub(crate) async fn disconnect_peer(
 
     Ok(Json(()))
 }
+
+pub(crate) async fn peer_history(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let public_key = PublicKey::from_str(&id).map_err(bad_request)?;
+    let history = lightning_interface
+        .peer_history(public_key)
+        .await
+        .map_err(internal_server)?;
+
+    Ok(Json(PeerHistory {
+        id: hex::encode(history.public_key.serialize()),
+        uptime: history.uptime,
+        addresses: history.addresses.iter().map(to_peer_address).collect(),
+        events: history
+            .events
+            .into_iter()
+            .map(|e| PeerEvent {
+                event: e.event.to_string(),
+                address: e.address.map(|a| SocketAddress(a).to_string()),
+                timestamp: e.timestamp.unix_timestamp(),
+            })
+            .collect(),
+    }))
+}
+
+fn to_peer_address(address: &peer::PeerAddress) -> PeerAddress {
+    PeerAddress {
+        address: SocketAddress(address.address.clone()).to_string(),
+        last_success: address.last_success.map(|t| t.unix_timestamp()),
+        last_failure: address.last_failure.map(|t| t.unix_timestamp()),
+        failures: address.failures,
+        retry_after: address.retry_after().map(|t| t.unix_timestamp()),
+    }
+}
@@ -48,11 +48,11 @@ pub const LIST_CHANNEL_HISTORY: &str = "/v1/channel/history";
 /// --- Network ---
 /// Look up a node on the network.
 pub const LIST_NETWORK_NODE: &str = "/v1/network/listNode/:id";
-/// Return list of all nodes on the network
+/// Return list of all nodes on the network, paginated with offset and limit
 pub const LIST_NETWORK_NODES: &str = "/v1/network/listNode";
 /// Look up a channel on the network
 pub const LIST_NETWORK_CHANNEL: &str = "/v1/network/listChannel/:id";
-/// Return list of all channels on the network
+/// Return list of all channels on the network, paginated with offset and limit
 pub const LIST_NETWORK_CHANNELS: &str = "/v1/network/listChannel";
 /// Return feerate estimates, either satoshi-per-kw or satoshi-per-kb
 pub const FEE_RATES: &str = "/v1/network/feeRates/:style";
@@ -64,6 +64,28 @@ pub const GET_BALANCE: &str = "/v1/getBalance";
 pub const NEW_ADDR: &str = "/v1/newaddr";
 /// Withdraw on-chain funds to an address.
 pub const WITHDRAW: &str = "/v1/withdraw";
+/// Pay many addresses with a single transaction.
+pub const WITHDRAW_MANY: &str = "/v1/withdrawMany";
+/// Create a PSBT to withdraw or fund a channel (POST), or list the PSBTs waiting to be signed (GET).
+pub const PSBT: &str = "/v1/wallet/psbt";
+/// Submit a signed PSBT to broadcast it or fund its channel.
+pub const SUBMIT_PSBT: &str = "/v1/wallet/psbt/submit";
+/// Bump the fee of an unconfirmed transaction by RBF or CPFP.
+pub const BUMP_FEE: &str = "/v1/wallet/bumpFee";
+/// List the transactions of the wallet.
+pub const LIST_TRANSACTIONS: &str = "/v1/wallet/transactions";
+/// Export (GET) or import (POST) labels in BIP-329 format.
+pub const LABELS: &str = "/v1/wallet/labels";
+/// Reserve UTXOs so that they are never spent.
+pub const FREEZE_UTXOS: &str = "/v1/wallet/utxos/freeze";
+/// Release frozen UTXOs.
+pub const UNFREEZE_UTXOS: &str = "/v1/wallet/utxos/unfreeze";
+/// Public descriptors for watch-only wallets.
+pub const DESCRIPTORS: &str = "/v1/wallet/descriptors";
+/// Sweeps of the hot wallet balance to cold storage.
+pub const LIST_SWEEPS: &str = "/v1/wallet/sweeps";
+/// Dry run of consolidating the small UTXOs of the wallet.
+pub const CONSOLIDATION: &str = "/v1/wallet/consolidation";
 
 /// --- Payments ---
 /// Send funds to a node without an invoice.
@@ -82,5 +104,22 @@ pub const LIST_INVOICES: &str = "/v1/invoice/listInvoices";
 pub const DECODE_INVOICE: &str = "/v1/utility/decode/:invoice";
 
 /// --- Kuutamo Apis ---
+/// Download (GET) or upload (POST) the serialized scorer.
 pub const SCORER: &str = "/kld/scorer";
+/// Get (GET) or update (POST) the scorer parameters.
+pub const SCORER_PARAMETERS: &str = "/kld/scorer/parameters";
+/// The liquidity the scorer estimates for each channel direction, filtered with ?node_id= or ?scid=
+pub const SCORER_LIQUIDITY: &str = "/kld/scorer/liquidity";
 pub const LIST_CHANNELS: &str = "/kld/channels";
+/// Update the alias, color and addresses that we announce to the network.
+pub const NODE_ANNOUNCEMENT: &str = "/kld/node/announcement";
+/// Export an encrypted static channel backup.
+pub const BACKUP: &str = "/kld/backup";
+/// Connection history and uptime of a peer.
+pub const PEER_HISTORY: &str = "/kld/peers/:id/history";
+/// Accounting ledger, filtered with ?from= and ?to= unix timestamps and exported with ?format=json or csv.
+pub const LEDGER: &str = "/kld/ledger";
+/// Forwarding per channel and per peer in the ?from= and ?to= window, the ?top= channels and peers ranked by ?rank=fees, volume or efficiency.
+pub const ROUTING_REPORT: &str = "/kld/routing/report";
+/// Rapid gossip sync snapshot with the gossip seen since the timestamp. Does not require authentication.
+pub const RGS_SNAPSHOT: &str = "/kld/rgs/snapshot/:timestamp";
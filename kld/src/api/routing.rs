@@ -0,0 +1,149 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use axum::extract::Query;
+use axum::{response::IntoResponse, Extension, Json};
+use bitcoin::secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
+
+use crate::database::forward::ChannelForwardStats;
+use crate::ldk::LightningInterface;
+
+use super::payloads::{ChannelRoutingStats, PeerRoutingStats, RoutingReport, RoutingStats};
+use super::{bad_request, internal_server, pagination, ApiError};
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct RoutingReportParams {
+    /// Unix timestamp of the start of the window (inclusive).
+    pub from: Option<i64>,
+    /// Unix timestamp of the end of the window (exclusive).
+    pub to: Option<i64>,
+    /// Only the best N channels and peers.
+    pub top: Option<usize>,
+    /// fees (default), volume or efficiency (fees per sat of capacity).
+    pub rank: Option<String>,
+}
+
+pub(crate) async fn routing_report(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Query(params): Query<RoutingReportParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let window = pagination(params.from, params.to, None, None)?;
+    let rank: fn(&RoutingStats) -> f64 = match params.rank.as_deref() {
+        None | Some("fees") => |stats| stats.fees_msat as f64,
+        Some("volume") => |stats| (stats.in_msat + stats.out_msat) as f64,
+        Some("efficiency") => |stats| stats.fees_msat_per_capacity_sat.unwrap_or_default(),
+        Some(rank) => return Err(bad_request(anyhow!("Unknown rank {rank}"))),
+    };
+
+    let forward_stats = lightning_interface
+        .routing_stats(window.from, window.to)
+        .await
+        .map_err(internal_server)?;
+
+    // Closed channels are still needed for the counterparty and capacity of their forwards.
+    let mut known: HashMap<[u8; 32], (PublicKey, u64)> = HashMap::new();
+    for record in lightning_interface
+        .list_channels()
+        .await
+        .map_err(internal_server)?
+    {
+        if let Some(detail) = record.detail {
+            known.insert(
+                detail.channel_id.0,
+                (detail.counterparty.node_id, detail.channel_value_satoshis),
+            );
+        }
+    }
+
+    // Open channels without any forwards are reported too, they are the candidates to close.
+    let mut by_channel: BTreeMap<[u8; 32], ChannelForwardStats> = forward_stats
+        .into_iter()
+        .map(|stats| (stats.channel_id.0, stats))
+        .collect();
+    for channel in lightning_interface.list_active_channels() {
+        known.insert(
+            channel.channel_id.0,
+            (channel.counterparty.node_id, channel.channel_value_satoshis),
+        );
+        by_channel
+            .entry(channel.channel_id.0)
+            .or_insert_with(|| ChannelForwardStats::new(channel.channel_id));
+    }
+
+    let mut channels = vec![];
+    let mut peers: BTreeMap<PublicKey, PeerRoutingStats> = BTreeMap::new();
+    for (id, forwards) in by_channel {
+        let counterparty = known.get(&id).map(|(node_id, _)| *node_id);
+        let mut stats = RoutingStats {
+            in_count: forwards.inbound_count,
+            in_msat: forwards.inbound_amount,
+            out_count: forwards.outbound_count,
+            out_msat: forwards.outbound_amount,
+            fees_msat: forwards.fee,
+            failures: forwards.failures,
+            capacity_sat: known.get(&id).map(|(_, capacity)| *capacity),
+            fees_msat_per_capacity_sat: None,
+        };
+        stats.fees_msat_per_capacity_sat = fees_per_capacity(&stats);
+        if let Some(counterparty) = counterparty {
+            let peer = peers
+                .entry(counterparty)
+                .or_insert_with(|| PeerRoutingStats {
+                    counterparty: counterparty.to_string(),
+                    channels: 0,
+                    stats: RoutingStats::default(),
+                });
+            peer.channels += 1;
+            add(&mut peer.stats, &stats);
+        }
+        channels.push(ChannelRoutingStats {
+            channel_id: hex::encode(id),
+            counterparty: counterparty.map(|c| c.to_string()),
+            stats,
+        });
+    }
+    let mut peers: Vec<PeerRoutingStats> = peers
+        .into_values()
+        .map(|mut peer| {
+            peer.stats.fees_msat_per_capacity_sat = fees_per_capacity(&peer.stats);
+            peer
+        })
+        .collect();
+
+    channels.sort_by(|a, b| rank(&b.stats).total_cmp(&rank(&a.stats)));
+    peers.sort_by(|a, b| rank(&b.stats).total_cmp(&rank(&a.stats)));
+    if let Some(top) = params.top {
+        channels.truncate(top);
+        peers.truncate(top);
+    }
+
+    Ok(Json(RoutingReport {
+        from: params.from,
+        to: params.to,
+        channels,
+        peers,
+    }))
+}
+
+fn fees_per_capacity(stats: &RoutingStats) -> Option<f64> {
+    stats
+        .capacity_sat
+        .filter(|capacity| *capacity > 0)
+        .map(|capacity| stats.fees_msat as f64 / capacity as f64)
+}
+
+fn add(total: &mut RoutingStats, stats: &RoutingStats) {
+    total.in_count += stats.in_count;
+    total.in_msat += stats.in_msat;
+    total.out_count += stats.out_count;
+    total.out_msat += stats.out_msat;
+    total.fees_msat += stats.fees_msat;
+    for (reason, count) in &stats.failures {
+        *total.failures.entry(reason.clone()).or_default() += count;
+    }
+    if let Some(capacity) = stats.capacity_sat {
+        total.capacity_sat = Some(total.capacity_sat.unwrap_or_default() + capacity);
+    }
+}
@@ -21,11 +21,114 @@ impl SocketAddress {
         matches!(self.0, lightning::ln::msgs::SocketAddress::TcpIpV6 { .. })
     }
 
+    pub fn is_onion(&self) -> bool {
+        matches!(
+            self.0,
+            lightning::ln::msgs::SocketAddress::OnionV2(_)
+                | lightning::ln::msgs::SocketAddress::OnionV3 { .. }
+        )
+    }
+
+    pub fn address_type(&self) -> AddressType {
+        match self.0 {
+            lightning::ln::msgs::SocketAddress::TcpIpV4 { .. } => AddressType::Ipv4,
+            lightning::ln::msgs::SocketAddress::TcpIpV6 { .. } => AddressType::Ipv6,
+            lightning::ln::msgs::SocketAddress::OnionV2(_)
+            | lightning::ln::msgs::SocketAddress::OnionV3 { .. } => AddressType::Onion,
+            lightning::ln::msgs::SocketAddress::Hostname { .. } => AddressType::Hostname,
+        }
+    }
+
+    /// The name to resolve for onion and hostname addresses.
+    pub fn host_name(&self) -> Option<String> {
+        match &self.0 {
+            lightning::ln::msgs::SocketAddress::OnionV3 {
+                ed25519_pubkey,
+                checksum,
+                version,
+                ..
+            } => Some(onion_v3_host(ed25519_pubkey, *checksum, *version)),
+            lightning::ln::msgs::SocketAddress::Hostname { hostname, .. } => {
+                Some(hostname.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        match &self.0 {
+            lightning::ln::msgs::SocketAddress::TcpIpV4 { port, .. }
+            | lightning::ln::msgs::SocketAddress::TcpIpV6 { port, .. }
+            | lightning::ln::msgs::SocketAddress::OnionV3 { port, .. }
+            | lightning::ln::msgs::SocketAddress::Hostname { port, .. } => Some(*port),
+            lightning::ln::msgs::SocketAddress::OnionV2(_) => None,
+        }
+    }
+
     pub fn inner(self) -> lightning::ln::msgs::SocketAddress {
         self.0
     }
 }
 
+/// The kinds of addresses a node can announce, used to configure the order we try to connect to them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AddressType {
+    Ipv4,
+    Ipv6,
+    Onion,
+    Hostname,
+}
+
+impl Display for AddressType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressType::Ipv4 => write!(f, "ipv4"),
+            AddressType::Ipv6 => write!(f, "ipv6"),
+            AddressType::Onion => write!(f, "onion"),
+            AddressType::Hostname => write!(f, "hostname"),
+        }
+    }
+}
+
+impl FromStr for AddressType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ipv4" => Ok(AddressType::Ipv4),
+            "ipv6" => Ok(AddressType::Ipv6),
+            "onion" => Ok(AddressType::Onion),
+            "hostname" => Ok(AddressType::Hostname),
+            _ => anyhow::bail!("{s} is not an address type (ipv4, ipv6, onion, hostname)"),
+        }
+    }
+}
+
+/// The onion v3 address is base32(pubkey | checksum | version).
+fn onion_v3_host(ed25519_pubkey: &[u8; 32], checksum: u16, version: u8) -> String {
+    const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+    let mut bytes = ed25519_pubkey.to_vec();
+    bytes.extend_from_slice(&checksum.to_be_bytes());
+    bytes.push(version);
+
+    let mut host = String::with_capacity(62);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            host.push(ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        host.push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    host.push_str(".onion");
+    host
+}
+
 impl Deref for SocketAddress {
     type Target = lightning::ln::msgs::SocketAddress;
 
@@ -60,8 +163,13 @@ impl Display for SocketAddress {
             lightning::ln::msgs::SocketAddress::OnionV3 {
                 port,
                 ed25519_pubkey,
-                ..
-            } => write!(f, "{ed25519_pubkey:?}.onion:{port}")?,
+                checksum,
+                version,
+            } => write!(
+                f,
+                "{}:{port}",
+                onion_v3_host(ed25519_pubkey, *checksum, *version)
+            )?,
             lightning::ln::msgs::SocketAddress::Hostname { hostname, port } => {
                 write!(f, "{hostname:?}:{port}")?
             }
@@ -208,3 +316,12 @@ fn test_netaddress() {
     let v6_decoded: SocketAddress = bincode::deserialize(&bytes).unwrap();
     assert_eq!(v6_addr, v6_decoded);
 }
+
+#[test]
+fn test_onion_address() {
+    let onion = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion:9735";
+    let address = SocketAddress::from_str(onion).unwrap();
+    assert_eq!(AddressType::Onion, address.address_type());
+    assert_eq!(Some(9735), address.port());
+    assert_eq!(onion, address.to_string());
+}
@@ -1,12 +1,19 @@
-use super::payloads::{Chain, GetInfo, SignRequest, SignResponse};
+use super::payloads::{
+    Chain, ChannelLiquidity, GetInfo, NodeAnnouncement, ScorerLiquidity, ScorerParameters,
+    SignRequest, SignResponse, UpdateNodeAnnouncement, UpdateScorerParameters,
+};
 use super::API_VERSION;
 use anyhow::anyhow;
+use axum::body::Bytes;
+use axum::extract::Query;
 use axum::Json;
 use axum::{response::IntoResponse, Extension};
 use bitcoin::Network;
 use lightning::routing::gossip::NodeId;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::bitcoind::bitcoind_interface::BitcoindInterface;
 use crate::ldk::LightningInterface;
@@ -15,7 +22,7 @@ use crate::VERSION;
 use super::codegen::get_v1_estimate_channel_liquidity_body::GetV1EstimateChannelLiquidityBody;
 use super::codegen::get_v1_estimate_channel_liquidity_response::GetV1EstimateChannelLiquidityResponse;
 use super::codegen::get_v1_get_fees_response::GetV1GetFeesResponse;
-use super::{bad_request, internal_server, ApiError};
+use super::{bad_request, internal_server, ApiError, SocketAddress};
 
 pub(crate) async fn get_info(
     Extension(bitcoind_interface): Extension<Arc<dyn BitcoindInterface + Send + Sync>>,
@@ -49,7 +56,7 @@ pub(crate) async fn get_info(
         }],
         version: VERSION.to_string(),
         api_version: API_VERSION.to_string(),
-        color: "".to_string(),
+        color: lightning_interface.color(),
         network: lightning_interface.network().to_string(),
         address: lightning_interface
             .public_addresses()
@@ -61,6 +68,31 @@ pub(crate) async fn get_info(
     Ok(Json(info))
 }
 
+pub(crate) async fn update_node_announcement(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Json(body): Json<UpdateNodeAnnouncement>,
+) -> Result<impl IntoResponse, ApiError> {
+    let addresses = body
+        .addresses
+        .map(|addresses| {
+            addresses
+                .iter()
+                .map(|a| SocketAddress::from_str(a))
+                .collect::<anyhow::Result<Vec<SocketAddress>>>()
+        })
+        .transpose()
+        .map_err(bad_request)?;
+    let config = lightning_interface
+        .update_node_announcement(body.alias, body.color, addresses)
+        .await
+        .map_err(bad_request)?;
+    Ok(Json(NodeAnnouncement {
+        alias: config.alias,
+        color: config.color,
+        addresses: config.addresses.iter().map(|a| a.to_string()).collect(),
+    }))
+}
+
 const MESSAGE_MAX_LENGTH: u16 = 65535;
 
 pub(crate) async fn sign(
@@ -120,3 +152,124 @@ pub(crate) async fn score(
         .map_err(internal_server)?;
     Ok(score)
 }
+
+pub(crate) async fn upload_scorer(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    body: Bytes,
+) -> Result<impl IntoResponse, ApiError> {
+    lightning_interface
+        .upload_scorer(body.to_vec())
+        .await
+        .map_err(bad_request)?;
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScorerLiquidityQueryParams {
+    pub node_id: Option<String>,
+    pub scid: Option<u64>,
+}
+
+pub(crate) async fn scorer_liquidity(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Query(params): Query<ScorerLiquidityQueryParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let node_id = params
+        .node_id
+        .map(|id| NodeId::from_str(&id))
+        .transpose()
+        .map_err(|_| bad_request(anyhow!("node id decode error")))?;
+    let liquidity = lightning_interface
+        .scorer_liquidity(node_id, params.scid)
+        .await
+        .map_err(internal_server)?;
+    Ok(Json(ScorerLiquidity {
+        last_updated: liquidity.last_updated.map(|t| t.unix_timestamp()),
+        channels: liquidity
+            .channels
+            .into_iter()
+            .map(|c| ChannelLiquidity {
+                short_channel_id: c.short_channel_id,
+                source: c.source.to_string(),
+                target: c.target.to_string(),
+                capacity_sat: c.capacity_sats,
+                min_liquidity_msat: c.min_liquidity_msat,
+                max_liquidity_msat: c.max_liquidity_msat,
+                historical_min_liquidity_buckets: c.historical_min_liquidity_buckets,
+                historical_max_liquidity_buckets: c.historical_max_liquidity_buckets,
+            })
+            .collect(),
+    }))
+}
+
+pub(crate) async fn get_scorer_parameters(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+) -> Result<impl IntoResponse, ApiError> {
+    Ok(Json(to_api_scorer_parameters(
+        lightning_interface.scorer_parameters(),
+    )))
+}
+
+pub(crate) async fn update_scorer_parameters(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Json(body): Json<UpdateScorerParameters>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut parameters = lightning_interface.scorer_parameters();
+    if let Some(x) = body.base_penalty_msat {
+        parameters.base_penalty_msat = x;
+    }
+    if let Some(x) = body.base_penalty_amount_multiplier_msat {
+        parameters.base_penalty_amount_multiplier_msat = x;
+    }
+    if let Some(x) = body.liquidity_penalty_multiplier_msat {
+        parameters.liquidity_penalty_multiplier_msat = x;
+    }
+    if let Some(x) = body.liquidity_penalty_amount_multiplier_msat {
+        parameters.liquidity_penalty_amount_multiplier_msat = x;
+    }
+    if let Some(x) = body.historical_liquidity_penalty_multiplier_msat {
+        parameters.historical_liquidity_penalty_multiplier_msat = x;
+    }
+    if let Some(x) = body.historical_liquidity_penalty_amount_multiplier_msat {
+        parameters.historical_liquidity_penalty_amount_multiplier_msat = x;
+    }
+    if let Some(x) = body.liquidity_offset_half_life {
+        parameters.liquidity_offset_half_life = Duration::from_secs(x);
+    }
+    if let Some(x) = body.historical_no_updates_half_life {
+        parameters.historical_no_updates_half_life = Duration::from_secs(x);
+    }
+    let parameters = lightning_interface
+        .update_scorer_parameters(parameters)
+        .await
+        .map_err(bad_request)?;
+    Ok(Json(to_api_scorer_parameters(parameters)))
+}
+
+fn to_api_scorer_parameters(
+    parameters: crate::database::scorer_parameters::ScorerParameters,
+) -> ScorerParameters {
+    ScorerParameters {
+        base_penalty_msat: parameters.base_penalty_msat,
+        base_penalty_amount_multiplier_msat: parameters.base_penalty_amount_multiplier_msat,
+        liquidity_penalty_multiplier_msat: parameters.liquidity_penalty_multiplier_msat,
+        liquidity_penalty_amount_multiplier_msat: parameters
+            .liquidity_penalty_amount_multiplier_msat,
+        historical_liquidity_penalty_multiplier_msat: parameters
+            .historical_liquidity_penalty_multiplier_msat,
+        historical_liquidity_penalty_amount_multiplier_msat: parameters
+            .historical_liquidity_penalty_amount_multiplier_msat,
+        liquidity_offset_half_life: parameters.liquidity_offset_half_life.as_secs(),
+        historical_no_updates_half_life: parameters.historical_no_updates_half_life.as_secs(),
+    }
+}
+
+pub(crate) async fn backup(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let backup = lightning_interface
+        .static_channel_backup()
+        .await
+        .map_err(internal_server)?;
+    Ok(backup)
+}
@@ -1,19 +1,31 @@
+use super::channels::prepare_channel;
 use super::payloads::{
-    ChannelState, ListFunds, ListFundsChannel, ListFundsOutput, OutputStatus, WalletBalance,
-    WalletTransfer, WalletTransferResponse,
+    Bip329Label, BumpFee, BumpFeeResponse, ChannelState, ConsolidationReport, CreatePsbt,
+    FreezeUtxos, ImportLabelsResponse, ListFunds, ListFundsChannel, ListFundsOutput, OutputStatus,
+    Psbt, SubmitPsbt, WalletBalance, WalletDescriptor, WalletDescriptors, WalletSweep,
+    WalletTransaction, WalletTransfer, WalletTransferResponse, WithdrawMany,
 };
 use anyhow::anyhow;
 use axum::extract::Query;
+use axum::http::header;
 use axum::{response::IntoResponse, Extension, Json};
+use base64::{engine::general_purpose, Engine};
+use bdk::KeychainKind;
 use bitcoin::consensus::encode;
-use bitcoin::Address;
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::{Address, OutPoint, Txid};
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
+use uuid::Uuid;
 
+use crate::database::label::{Label, LabelType};
+use crate::database::psbt::PendingPsbt;
 use crate::ldk::LightningInterface;
 use crate::ldk::PeerStatus;
 use crate::to_string_empty;
-use crate::wallet::WalletInterface;
+use crate::wallet::{AddressType, WalletInterface};
 
 use super::codegen::get_v1_newaddr_response::GetV1NewaddrResponse;
 use super::{bad_request, internal_server, ApiError};
@@ -42,10 +54,13 @@ pub(crate) async fn new_address(
     Extension(wallet): Extension<Arc<dyn WalletInterface + Send + Sync>>,
     Query(params): Query<NewAddressQueryParams>,
 ) -> Result<impl IntoResponse, ApiError> {
-    if params.address_type.is_some_and(|t| t != "bech32") {
-        return Err(bad_request(anyhow!("Unsupported address type")));
-    }
-    let address_info = wallet.new_external_address().map_err(internal_server)?;
+    let address_type = match params.address_type {
+        Some(address_type) => AddressType::from_str(&address_type).map_err(bad_request)?,
+        None => AddressType::Bech32,
+    };
+    let address_info = wallet
+        .new_external_address(address_type)
+        .map_err(internal_server)?;
     let response = GetV1NewaddrResponse {
         address: address_info.address.to_string(),
     };
@@ -54,17 +69,24 @@ pub(crate) async fn new_address(
 
 pub(crate) async fn transfer(
     Extension(wallet): Extension<Arc<dyn WalletInterface + Send + Sync>>,
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
     Json(wallet_transfer): Json<WalletTransfer>,
 ) -> Result<impl IntoResponse, ApiError> {
     let address = Address::from_str(&wallet_transfer.address).map_err(bad_request)?;
-
-    let amount = if wallet_transfer.satoshis == "all" {
-        u64::MAX
-    } else {
-        u64::from_str(&wallet_transfer.satoshis).map_err(bad_request)?
-    };
+    let amount = parse_amount(&wallet_transfer.satoshis)?;
+    let unspendable = lightning_interface
+        .pending_psbt_inputs()
+        .await
+        .map_err(internal_server)?;
     let (tx, tx_details) = wallet
-        .transfer(address, amount, wallet_transfer.fee_rate, None, vec![])
+        .transfer(
+            address,
+            amount,
+            wallet_transfer.fee_rate,
+            None,
+            vec![],
+            unspendable,
+        )
         .await
         .map_err(internal_server)?;
     let tx_hex = encode::serialize_hex(&tx);
@@ -75,12 +97,368 @@ pub(crate) async fn transfer(
     Ok(Json(response))
 }
 
+pub(crate) async fn withdraw_many(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Json(withdraw_many): Json<WithdrawMany>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut outputs = vec![];
+    for output in withdraw_many.outputs {
+        let address = Address::from_str(&output.address)
+            .map_err(bad_request)?
+            .require_network(lightning_interface.network())
+            .map_err(bad_request)?;
+        outputs.push((address, output.satoshis));
+    }
+    if outputs.is_empty() {
+        return Err(bad_request(anyhow!("Expected at least one output")));
+    }
+    let tx = lightning_interface
+        .withdraw_many(
+            outputs,
+            withdraw_many.fee_rate,
+            withdraw_many.idempotency_key,
+        )
+        .await
+        .map_err(internal_server)?;
+    Ok(Json(WalletTransferResponse {
+        tx: encode::serialize_hex(&tx),
+        txid: tx.txid().to_string(),
+    }))
+}
+
+pub(crate) async fn create_psbt(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Json(create_psbt): Json<CreatePsbt>,
+) -> Result<impl IntoResponse, ApiError> {
+    let psbt = match (create_psbt.withdraw, create_psbt.fund_channel) {
+        (Some(withdraw), None) => {
+            let address = Address::from_str(&withdraw.address)
+                .map_err(bad_request)?
+                .require_network(lightning_interface.network())
+                .map_err(bad_request)?;
+            let amount = parse_amount(&withdraw.satoshis)?;
+            let utxos = withdraw
+                .utxos
+                .iter()
+                .map(|utxo| OutPoint::from_str(utxo))
+                .collect::<Result<Vec<OutPoint>, _>>()
+                .map_err(bad_request)?;
+            lightning_interface
+                .create_withdraw_psbt(address, amount, withdraw.fee_rate, utxos, create_psbt.sign)
+                .await
+                .map_err(internal_server)?
+        }
+        (None, Some(fund_channel)) => {
+            let (public_key, value, push_msat, user_config) =
+                prepare_channel(lightning_interface.as_ref(), &fund_channel).await?;
+            lightning_interface
+                .create_funding_psbt(
+                    public_key,
+                    value,
+                    push_msat,
+                    fund_channel.fee_rate,
+                    Some(user_config),
+                    create_psbt.sign,
+                )
+                .await
+                .map_err(internal_server)?
+        }
+        _ => {
+            return Err(bad_request(anyhow!(
+                "Expected one of withdraw or fundChannel"
+            )))
+        }
+    };
+    Ok(Json(to_api_psbt(&psbt)))
+}
+
+pub(crate) async fn list_psbts(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let psbts: Vec<Psbt> = lightning_interface
+        .list_psbts()
+        .await
+        .map_err(internal_server)?
+        .iter()
+        .map(to_api_psbt)
+        .collect();
+    Ok(Json(psbts))
+}
+
+pub(crate) async fn submit_psbt(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Json(submit_psbt): Json<SubmitPsbt>,
+) -> Result<impl IntoResponse, ApiError> {
+    let id = Uuid::from_str(&submit_psbt.id).map_err(bad_request)?;
+    let bytes = general_purpose::STANDARD
+        .decode(&submit_psbt.psbt)
+        .map_err(bad_request)?;
+    let psbt = PartiallySignedTransaction::deserialize(&bytes).map_err(bad_request)?;
+    let tx = lightning_interface
+        .submit_psbt(id, psbt)
+        .await
+        .map_err(internal_server)?;
+    let response = WalletTransferResponse {
+        tx: encode::serialize_hex(&tx),
+        txid: tx.txid().to_string(),
+    };
+    Ok(Json(response))
+}
+
+pub(crate) async fn bump_fee(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Json(bump_fee): Json<BumpFee>,
+) -> Result<impl IntoResponse, ApiError> {
+    let txid = Txid::from_str(&bump_fee.txid).map_err(bad_request)?;
+    let (tx, fee_bump) = lightning_interface
+        .bump_fee(txid, bump_fee.fee_rate)
+        .await
+        .map_err(internal_server)?;
+    let response = BumpFeeResponse {
+        tx: encode::serialize_hex(&tx),
+        txid: fee_bump.txid.to_string(),
+        original_txid: fee_bump.original_txid.to_string(),
+        method: fee_bump.method.to_string(),
+        fee: fee_bump.fee,
+        channel_id: fee_bump.channel_id.map(|id| hex::encode(id.0)),
+        psbt_id: fee_bump.psbt_id.map(|id| id.to_string()),
+    };
+    Ok(Json(response))
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ListTransactionsParams {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+const DEFAULT_TRANSACTIONS_LIMIT: usize = 100;
+
+pub(crate) async fn list_transactions(
+    Extension(wallet): Extension<Arc<dyn WalletInterface + Send + Sync>>,
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    Query(params): Query<ListTransactionsParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut transactions = wallet.list_transactions().map_err(internal_server)?;
+    // Unconfirmed first, then the most recent.
+    transactions.sort_by_key(|details| {
+        Reverse(
+            details
+                .confirmation_time
+                .as_ref()
+                .map_or(u32::MAX, |time| time.height),
+        )
+    });
+    let transactions: Vec<_> = transactions
+        .into_iter()
+        .skip(params.offset.unwrap_or_default())
+        .take(params.limit.unwrap_or(DEFAULT_TRANSACTIONS_LIMIT))
+        .collect();
+    let tags = lightning_interface
+        .tag_transactions(&transactions)
+        .await
+        .map_err(internal_server)?;
+    let labels: HashMap<String, String> = lightning_interface
+        .list_labels()
+        .await
+        .map_err(internal_server)?
+        .into_iter()
+        .filter(|label| label.label_type == LabelType::Tx)
+        .filter_map(|label| label.label.map(|l| (label.reference, l)))
+        .collect();
+    let mut response = vec![];
+    for (details, tag) in transactions.iter().zip(tags) {
+        response.push(WalletTransaction {
+            txid: details.txid.to_string(),
+            confirmed: details.confirmation_time.is_some(),
+            block_height: details.confirmation_time.as_ref().map(|time| time.height),
+            timestamp: details
+                .confirmation_time
+                .as_ref()
+                .map(|time| time.timestamp),
+            received: details.received,
+            sent: details.sent,
+            fee: details.fee,
+            amount: details.received as i64 - details.sent as i64,
+            purpose: tag.purpose.to_string(),
+            channel_id: tag.channel_id.map(|id| hex::encode(id.0)),
+            label: labels.get(&details.txid.to_string()).cloned(),
+        });
+    }
+    Ok(Json(response))
+}
+
+pub(crate) async fn export_descriptors(
+    Extension(wallet): Extension<Arc<dyn WalletInterface + Send + Sync>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let descriptors = wallet
+        .public_descriptors()
+        .map_err(internal_server)?
+        .into_iter()
+        .map(|(address_type, keychain, desc)| WalletDescriptor {
+            desc,
+            internal: keychain == KeychainKind::Internal,
+            address_type: address_type.to_string(),
+        })
+        .collect();
+    Ok(Json(WalletDescriptors {
+        descriptors,
+        birthday_height: wallet.birthday_height(),
+    }))
+}
+
+pub(crate) async fn list_sweeps(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let sweeps: Vec<WalletSweep> = lightning_interface
+        .list_sweeps()
+        .await
+        .map_err(internal_server)?
+        .into_iter()
+        .map(|sweep| WalletSweep {
+            txid: sweep.txid.to_string(),
+            address: sweep.address,
+            amount: sweep.amount,
+            fee_rate: sweep.fee_rate,
+            timestamp: sweep.created.unix_timestamp(),
+        })
+        .collect();
+    Ok(Json(sweeps))
+}
+
+pub(crate) async fn consolidation_report(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let consolidation = lightning_interface
+        .consolidation_report()
+        .await
+        .map_err(internal_server)?;
+    Ok(Json(ConsolidationReport {
+        fee_rate: consolidation.fee_rate,
+        utxos: consolidation
+            .utxos
+            .iter()
+            .map(|utxo| utxo.to_string())
+            .collect(),
+        amount: consolidation.amount,
+        fee: consolidation.fee,
+        ready: consolidation.skip_reason.is_none(),
+        skip_reason: consolidation.skip_reason,
+    }))
+}
+
+/// Labels as BIP-329 JSON lines.
+pub(crate) async fn export_labels(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let labels = lightning_interface
+        .list_labels()
+        .await
+        .map_err(internal_server)?;
+    let mut jsonl = String::new();
+    for label in labels {
+        let label = Bip329Label {
+            label_type: label.label_type.to_string(),
+            reference: label.reference,
+            label: label.label,
+            origin: label.origin,
+            spendable: label.spendable,
+        };
+        jsonl.push_str(&serde_json::to_string(&label).map_err(internal_server)?);
+        jsonl.push('\n');
+    }
+    Ok(([(header::CONTENT_TYPE, "application/jsonl")], jsonl))
+}
+
+/// Import BIP-329 JSON lines.
+pub(crate) async fn import_labels(
+    Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
+    body: String,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut labels = vec![];
+    for line in body.lines().filter(|line| !line.trim().is_empty()) {
+        let label: Bip329Label = serde_json::from_str(line).map_err(bad_request)?;
+        labels.push(Label {
+            label_type: LabelType::from_str(&label.label_type).map_err(bad_request)?,
+            reference: label.reference,
+            label: label.label,
+            origin: label.origin,
+            spendable: label.spendable,
+        });
+    }
+    let imported = labels.len();
+    lightning_interface
+        .import_labels(labels)
+        .await
+        .map_err(internal_server)?;
+    Ok(Json(ImportLabelsResponse { imported }))
+}
+
+pub(crate) async fn freeze_utxos(
+    Extension(wallet): Extension<Arc<dyn WalletInterface + Send + Sync>>,
+    Json(freeze_utxos): Json<FreezeUtxos>,
+) -> Result<impl IntoResponse, ApiError> {
+    set_frozen(wallet, freeze_utxos, true)
+}
+
+pub(crate) async fn unfreeze_utxos(
+    Extension(wallet): Extension<Arc<dyn WalletInterface + Send + Sync>>,
+    Json(freeze_utxos): Json<FreezeUtxos>,
+) -> Result<impl IntoResponse, ApiError> {
+    set_frozen(wallet, freeze_utxos, false)
+}
+
+fn set_frozen(
+    wallet: Arc<dyn WalletInterface + Send + Sync>,
+    freeze_utxos: FreezeUtxos,
+    frozen: bool,
+) -> Result<Json<FreezeUtxos>, ApiError> {
+    let outpoints = freeze_utxos
+        .utxos
+        .iter()
+        .map(|utxo| OutPoint::from_str(utxo))
+        .collect::<Result<Vec<OutPoint>, _>>()
+        .map_err(bad_request)?;
+    let updated = wallet
+        .freeze_utxos(&outpoints, frozen)
+        .map_err(internal_server)?;
+    Ok(Json(FreezeUtxos {
+        utxos: updated
+            .iter()
+            .map(|outpoint| outpoint.to_string())
+            .collect(),
+    }))
+}
+
+fn parse_amount(satoshis: &str) -> Result<u64, ApiError> {
+    if satoshis == "all" {
+        Ok(u64::MAX)
+    } else {
+        u64::from_str(satoshis).map_err(bad_request)
+    }
+}
+
+fn to_api_psbt(psbt: &PendingPsbt) -> Psbt {
+    Psbt {
+        id: psbt.id.to_string(),
+        psbt: general_purpose::STANDARD.encode(psbt.psbt.serialize()),
+        purpose: psbt.purpose.to_string(),
+        txid: psbt.txid().to_string(),
+        fee: psbt.fee(),
+        channel_id: psbt.temporary_channel_id.map(|id| hex::encode(id.0)),
+        created_at: psbt.created.unix_timestamp(),
+        expires_at: psbt.expires.unix_timestamp(),
+    }
+}
+
 pub(crate) async fn list_funds(
     Extension(wallet): Extension<Arc<dyn WalletInterface + Send + Sync>>,
     Extension(lightning_interface): Extension<Arc<dyn LightningInterface + Send + Sync>>,
 ) -> Result<impl IntoResponse, ApiError> {
     let mut outputs = vec![];
     let utxos = wallet.list_utxos().map_err(internal_server)?;
+    let frozen = wallet.frozen_utxos().map_err(internal_server)?;
     for (utxo, detail) in utxos {
         outputs.push(ListFundsOutput {
             txid: utxo.outpoint.txid.to_string(),
@@ -96,6 +474,7 @@ pub(crate) async fn list_funds(
                 OutputStatus::Unconfirmed
             },
             block_height: detail.confirmation_time.map(|t| t.height),
+            frozen: frozen.contains(&utxo.outpoint),
         });
     }
 
@@ -1,8 +1,5 @@
 use std::{
-    sync::{
-        atomic::{AtomicU32, Ordering},
-        Arc,
-    },
+    sync::Arc,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
@@ -13,7 +10,7 @@ use crate::settings::Settings;
 use async_trait::async_trait;
 use base64::{engine::general_purpose, Engine};
 use bitcoin::{consensus::encode, Address, BlockHash, Transaction, Txid};
-use bitcoincore_rpc_json::{EstimateMode, EstimateSmartFeeResult, GetBlockchainInfoResult};
+use bitcoincore_rpc_json::GetBlockchainInfoResult;
 use lightning::chain::chaininterface::{BroadcasterInterface, ConfirmationTarget, FeeEstimator};
 use lightning_block_sync::{
     http::{HttpEndpoint, JsonResponse},
@@ -25,13 +22,14 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use tokio::runtime::Handle;
 
-use crate::{ldk::MIN_FEERATE, quit_signal, Service};
+use crate::{quit_signal, Service};
 
 use super::bitcoind_interface::BitcoindInterface;
+use super::fee_source::{FeeEstimates, TargetFeeRate};
 
 pub struct BitcoindClient {
     client: Arc<RpcClient>,
-    priorities: Arc<Priorities>,
+    fee_estimates: Arc<FeeEstimates>,
     handle: Handle,
 }
 
@@ -46,10 +44,10 @@ impl BitcoindClient {
             RpcClient::new(&credentials, http_endpoint).context("failed to create rpc client")?,
         );
 
-        let priorities = Arc::new(Priorities::new());
+        let fee_estimates = Arc::new(FeeEstimates::new(settings, client.clone()));
         let bitcoind_client = BitcoindClient {
             client,
-            priorities,
+            fee_estimates,
             handle: tokio::runtime::Handle::current(),
         };
 
@@ -113,50 +111,20 @@ impl BitcoindClient {
             .deserialize()
     }
 
+    /// Fetch new fee estimates for every confirmation target.
+    pub async fn update_fee_estimates(&self) {
+        self.fee_estimates.update().await;
+    }
+
     pub fn poll_for_fee_estimates(&self) {
-        let client = self.client.clone();
-        let priorities = self.priorities.clone();
+        let fee_estimates = self.fee_estimates.clone();
         tokio::spawn(async move {
             loop {
-                BitcoindClient::estimate_fee(priorities.clone(), client.clone()).await;
+                fee_estimates.update().await;
                 tokio::time::sleep(Duration::from_secs(60)).await;
             }
         });
     }
-
-    async fn estimate_fee(priorities: Arc<Priorities>, client: Arc<RpcClient>) {
-        for class in priorities.list_class() {
-            match client
-                .call_method::<JsonString>(
-                    "estimatesmartfee",
-                    &[json!(class.n_blocks), json!(class.estimate_mode)],
-                )
-                .await
-                .map(|r| serde_json::from_str::<EstimateSmartFeeResult>(&r.0))
-            {
-                Ok(Ok(result)) => {
-                    // Bitcoind returns fee in BTC/kB.
-                    // So convert to sats and divide by 4 to get sats per 1000 weight units.
-                    let fee = ((result
-                        .fee_rate
-                        .map(|amount| amount.to_sat())
-                        .unwrap_or(class.default_fee_rate as u64)
-                        / 4) as u32)
-                        .max(MIN_FEERATE);
-                    Priorities::store(class, fee);
-                }
-                Ok(Err(e)) => error!("Could not fetch fee estimate: {}", e),
-                Err(e) => error!("Could not fetch fee estimate: {}", e),
-            };
-        }
-    }
-
-    /// To set up the lowest fee base for a testing environment
-    pub fn set_lowest_fee_estimates(&self) {
-        for class in self.priorities.list_class() {
-            Priorities::store(class, 253);
-        }
-    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -188,6 +156,10 @@ impl BitcoindInterface for BitcoindClient {
         (urgent, normal, slow)
     }
 
+    fn target_fee_rates(&self) -> Vec<TargetFeeRate> {
+        self.fee_estimates.list()
+    }
+
     async fn block_height(&self) -> Result<u64> {
         self.get_blockchain_info().await.map(|i| i.blocks)
     }
@@ -234,14 +206,14 @@ impl BitcoindMetrics for BitcoindClient {
         }
     }
     fn fee_for(&self, target: ConfirmationTarget) -> u32 {
-        self.priorities.get(&target)
+        self.fee_estimates.get(target)
     }
 }
 
-struct JsonString(String);
+pub(super) struct JsonString(String);
 
 impl JsonString {
-    fn deserialize<'a, T>(&'a self) -> Result<T>
+    pub(super) fn deserialize<'a, T>(&'a self) -> Result<T>
     where
         T: Deserialize<'a>,
     {
@@ -259,7 +231,7 @@ impl TryInto<JsonString> for JsonResponse {
 
 impl FeeEstimator for BitcoindClient {
     fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
-        self.priorities.get(&confirmation_target)
+        self.fee_estimates.get(confirmation_target)
     }
 }
 
@@ -316,78 +288,3 @@ impl BlockSource for BitcoindClient {
         Box::pin(async move { self.client.get_best_block().await })
     }
 }
-
-struct PriorityClass {
-    // sats per 1000 weight unit
-    fee_rate: AtomicU32,
-    default_fee_rate: u32,
-    n_blocks: u16,
-    estimate_mode: EstimateMode,
-}
-
-struct Priorities {
-    background: Arc<PriorityClass>,
-    normal: Arc<PriorityClass>,
-    high: Arc<PriorityClass>,
-}
-
-impl Priorities {
-    fn new() -> Priorities {
-        Priorities {
-            background: Arc::new(PriorityClass {
-                fee_rate: AtomicU32::new(MIN_FEERATE),
-                default_fee_rate: MIN_FEERATE,
-                n_blocks: 72,
-                estimate_mode: EstimateMode::Conservative,
-            }),
-            normal: Arc::new(PriorityClass {
-                fee_rate: AtomicU32::new(5000),
-                default_fee_rate: 5000,
-                n_blocks: 18,
-                estimate_mode: EstimateMode::Conservative,
-            }),
-            high: Arc::new(PriorityClass {
-                fee_rate: AtomicU32::new(10000),
-                default_fee_rate: 10000,
-                n_blocks: 6,
-                estimate_mode: EstimateMode::Conservative,
-            }),
-        }
-    }
-
-    /// Return a base class and a mulipler
-    fn priority_of(&self, conf_target: &ConfirmationTarget) -> (Arc<PriorityClass>, Option<f32>) {
-        match conf_target {
-            ConfirmationTarget::OnChainSweep => (self.high.clone(), None),
-            ConfirmationTarget::NonAnchorChannelFee => (self.normal.clone(), None),
-            ConfirmationTarget::ChannelCloseMinimum
-            | ConfirmationTarget::AnchorChannelFee
-            | ConfirmationTarget::MinAllowedAnchorChannelRemoteFee
-            | ConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee => {
-                (self.background.clone(), None)
-            }
-        }
-    }
-
-    fn get(&self, conf_target: &ConfirmationTarget) -> u32 {
-        let (priority, multiplier) = self.priority_of(conf_target);
-        let base = priority.fee_rate.load(Ordering::Acquire);
-        if let Some(multiplier) = multiplier {
-            ((base as f32) * multiplier) as u32
-        } else {
-            base
-        }
-    }
-
-    fn store(class: Arc<PriorityClass>, fee: u32) {
-        class.fee_rate.store(fee, Ordering::Release);
-    }
-
-    fn list_class(&self) -> Vec<Arc<PriorityClass>> {
-        vec![
-            self.background.clone(),
-            self.normal.clone(),
-            self.high.clone(),
-        ]
-    }
-}
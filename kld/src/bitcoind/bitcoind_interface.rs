@@ -3,6 +3,7 @@ use async_trait::async_trait;
 use bitcoincore_rpc_json::GetBlockchainInfoResult;
 
 use super::bitcoind_client::MempoolInfo;
+use super::fee_source::TargetFeeRate;
 
 #[async_trait]
 pub trait BitcoindInterface: Send + Sync {
@@ -12,5 +13,8 @@ pub trait BitcoindInterface: Send + Sync {
 
     fn fee_rates_kw(&self) -> (u32, u32, u32);
 
+    /// The fee rate of every confirmation target with the policy it was chosen by.
+    fn target_fee_rates(&self) -> Vec<TargetFeeRate>;
+
     async fn block_height(&self) -> Result<u64>;
 }
@@ -0,0 +1,526 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use bitcoincore_rpc_json::{EstimateMode, EstimateSmartFeeResult};
+use lightning::chain::chaininterface::{ConfirmationTarget, FEERATE_FLOOR_SATS_PER_KW};
+use lightning_block_sync::rpc::RpcClient;
+use log::error;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use crate::{ldk::MIN_FEERATE, settings::Settings};
+
+use super::bitcoind_client::{JsonString, MempoolInfo};
+
+/// Maximum virtual size of the transactions in a block.
+const BLOCK_VSIZE: u64 = 1_000_000;
+
+/// How long a fetched mempool is reused for, so that several targets estimating from the
+/// mempool only fetch it once per poll.
+const MEMPOOL_CACHE_DURATION: Duration = Duration::from_secs(30);
+
+/// Every confirmation target that LDK asks us to estimate.
+pub const CONFIRMATION_TARGETS: [ConfirmationTarget; 6] = [
+    ConfirmationTarget::OnChainSweep,
+    ConfirmationTarget::MinAllowedAnchorChannelRemoteFee,
+    ConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee,
+    ConfirmationTarget::AnchorChannelFee,
+    ConfirmationTarget::NonAnchorChannelFee,
+    ConfirmationTarget::ChannelCloseMinimum,
+];
+
+pub fn target_name(target: ConfirmationTarget) -> &'static str {
+    match target {
+        ConfirmationTarget::OnChainSweep => "on_chain_sweep",
+        ConfirmationTarget::MinAllowedAnchorChannelRemoteFee => {
+            "min_allowed_anchor_channel_remote_fee"
+        }
+        ConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee => {
+            "min_allowed_non_anchor_channel_remote_fee"
+        }
+        ConfirmationTarget::AnchorChannelFee => "anchor_channel_fee",
+        ConfirmationTarget::NonAnchorChannelFee => "non_anchor_channel_fee",
+        ConfirmationTarget::ChannelCloseMinimum => "channel_close_minimum",
+    }
+}
+
+/// Fee rate used for a target until its source has given us an estimate.
+fn default_fee_rate(target: ConfirmationTarget) -> u32 {
+    match target {
+        ConfirmationTarget::OnChainSweep => 10000,
+        ConfirmationTarget::NonAnchorChannelFee => 5000,
+        ConfirmationTarget::MinAllowedAnchorChannelRemoteFee
+        | ConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee
+        | ConfirmationTarget::AnchorChannelFee
+        | ConfirmationTarget::ChannelCloseMinimum => MIN_FEERATE,
+    }
+}
+
+/// Estimates a fee rate in sats per 1000 weight units.
+#[async_trait]
+pub trait FeeSource: Send + Sync {
+    async fn fee_rate(&self) -> Result<u32>;
+}
+
+/// Bitcoind's `estimatesmartfee` for confirmation within a number of blocks.
+pub struct EstimateSmartFee {
+    client: Arc<RpcClient>,
+    blocks: u16,
+    mode: EstimateMode,
+}
+
+#[async_trait]
+impl FeeSource for EstimateSmartFee {
+    async fn fee_rate(&self) -> Result<u32> {
+        let result: EstimateSmartFeeResult = self
+            .client
+            .call_method::<JsonString>("estimatesmartfee", &[json!(self.blocks), json!(self.mode)])
+            .await?
+            .deserialize()?;
+        // Bitcoind returns fee in BTC/kB.
+        // So convert to sats and divide by 4 to get sats per 1000 weight units.
+        result
+            .fee_rate
+            .map(|amount| (amount.to_sat() / 4) as u32)
+            .ok_or_else(|| {
+                anyhow!(
+                    "estimatesmartfee has no estimate for {} blocks: {}",
+                    self.blocks,
+                    result.errors.unwrap_or_default().join(", ")
+                )
+            })
+    }
+}
+
+/// Estimates the fee rate needed to get into the next blocks from the transactions in our mempool.
+pub struct MempoolHistogram {
+    mempool: Arc<MempoolCache>,
+    blocks: u16,
+}
+
+#[async_trait]
+impl FeeSource for MempoolHistogram {
+    async fn fee_rate(&self) -> Result<u32> {
+        let (histogram, min_fee_rate) = self.mempool.histogram().await?;
+        Ok(histogram_fee_rate(
+            &histogram,
+            self.blocks as u64 * BLOCK_VSIZE,
+            min_fee_rate,
+        ))
+    }
+}
+
+/// A fixed fee rate.
+pub struct StaticFee(u32);
+
+#[async_trait]
+impl FeeSource for StaticFee {
+    async fn fee_rate(&self) -> Result<u32> {
+        Ok(self.0)
+    }
+}
+
+#[derive(Deserialize)]
+struct MempoolEntry {
+    vsize: u64,
+    fees: MempoolEntryFees,
+}
+
+#[derive(Deserialize)]
+struct MempoolEntryFees {
+    // BTC
+    modified: f64,
+}
+
+/// Fee rates (sats per 1000 weight units) and virtual sizes of the mempool transactions, highest fee rate first.
+type Histogram = Vec<(u32, u64)>;
+
+pub struct MempoolCache {
+    client: Arc<RpcClient>,
+    cached: Mutex<Option<(Instant, Arc<Histogram>, u32)>>,
+}
+
+impl MempoolCache {
+    fn new(client: Arc<RpcClient>) -> MempoolCache {
+        MempoolCache {
+            client,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// The mempool histogram and the mempool minimum fee rate.
+    async fn histogram(&self) -> Result<(Arc<Histogram>, u32)> {
+        let mut cached = self.cached.lock().await;
+        if let Some((fetched, histogram, min_fee_rate)) = cached.as_ref() {
+            if fetched.elapsed() < MEMPOOL_CACHE_DURATION {
+                return Ok((histogram.clone(), *min_fee_rate));
+            }
+        }
+        let mempool_info: MempoolInfo = self
+            .client
+            .call_method::<JsonString>("getmempoolinfo", &[])
+            .await?
+            .deserialize()?;
+        let entries: HashMap<String, MempoolEntry> = self
+            .client
+            .call_method::<JsonString>("getrawmempool", &[json!(true)])
+            .await?
+            .deserialize()?;
+        let histogram = Arc::new(build_histogram(entries.values()));
+        // BTC/kB to sats per 1000 weight units.
+        let min_fee_rate = (mempool_info.mempool_min_fee as f64 * 25_000_000.0).ceil() as u32;
+        *cached = Some((Instant::now(), histogram.clone(), min_fee_rate));
+        Ok((histogram, min_fee_rate))
+    }
+}
+
+fn build_histogram<'a>(entries: impl Iterator<Item = &'a MempoolEntry>) -> Histogram {
+    let mut histogram: Histogram = entries
+        .filter(|entry| entry.vsize > 0)
+        .map(|entry| {
+            let sats = (entry.fees.modified * 100_000_000.0).round() as u64;
+            // sats per vbyte is 250 sats per 1000 weight units.
+            ((sats * 250 / entry.vsize) as u32, entry.vsize)
+        })
+        .collect();
+    histogram.sort_by(|a, b| b.0.cmp(&a.0));
+    histogram
+}
+
+/// The fee rate of the transaction at `vsize` from the top of the mempool, or the mempool minimum
+/// if the mempool would be cleared before then.
+fn histogram_fee_rate(histogram: &Histogram, vsize: u64, min_fee_rate: u32) -> u32 {
+    let mut total = 0;
+    for (fee_rate, tx_vsize) in histogram {
+        total += tx_vsize;
+        if total >= vsize {
+            return (*fee_rate).max(min_fee_rate);
+        }
+    }
+    min_fee_rate
+}
+
+/// Where the fee rate for a confirmation target comes from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeeSourceConfig {
+    EstimateSmartFee { blocks: u16, mode: EstimateMode },
+    Mempool { blocks: u16 },
+    Static(u32),
+}
+
+impl FeeSourceConfig {
+    fn build(&self, client: &Arc<RpcClient>, mempool: &Arc<MempoolCache>) -> Box<dyn FeeSource> {
+        match *self {
+            FeeSourceConfig::EstimateSmartFee { blocks, mode } => Box::new(EstimateSmartFee {
+                client: client.clone(),
+                blocks,
+                mode,
+            }),
+            FeeSourceConfig::Mempool { blocks } => Box::new(MempoolHistogram {
+                mempool: mempool.clone(),
+                blocks,
+            }),
+            FeeSourceConfig::Static(fee_rate) => Box::new(StaticFee(fee_rate)),
+        }
+    }
+}
+
+impl FromStr for FeeSourceConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let number = |i: usize| -> Result<u32> {
+            parts
+                .get(i)
+                .with_context(|| format!("fee source {s} is missing a value"))?
+                .parse()
+                .with_context(|| format!("fee source {s} has an invalid value"))
+        };
+        let blocks = || -> Result<u16> {
+            let blocks = number(1)?;
+            if blocks == 0 || blocks > 1008 {
+                bail!("fee source {s} must target between 1 and 1008 blocks");
+            }
+            Ok(blocks as u16)
+        };
+        let source = match parts[0] {
+            "estimatesmartfee" if parts.len() <= 3 => FeeSourceConfig::EstimateSmartFee {
+                blocks: blocks()?,
+                mode: match parts.get(2) {
+                    None | Some(&"conservative") => EstimateMode::Conservative,
+                    Some(&"economical") => EstimateMode::Economical,
+                    Some(mode) => bail!("{mode} is not an estimate mode (conservative, economical)"),
+                },
+            },
+            "mempool" if parts.len() == 2 => FeeSourceConfig::Mempool { blocks: blocks()? },
+            "static" if parts.len() == 2 => FeeSourceConfig::Static(number(1)?),
+            _ => bail!(
+                "{s} is not a fee source (estimatesmartfee:<blocks>[:<mode>], mempool:<blocks>, static:<sats per kw>)"
+            ),
+        };
+        Ok(source)
+    }
+}
+
+impl Display for FeeSourceConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeeSourceConfig::EstimateSmartFee { blocks, mode } => match mode {
+                EstimateMode::Economical => write!(f, "estimatesmartfee:{blocks}:economical"),
+                _ => write!(f, "estimatesmartfee:{blocks}"),
+            },
+            FeeSourceConfig::Mempool { blocks } => write!(f, "mempool:{blocks}"),
+            FeeSourceConfig::Static(fee_rate) => write!(f, "static:{fee_rate}"),
+        }
+    }
+}
+
+/// The source of the fee rate for a confirmation target and the limits applied to its estimates.
+/// Written as `<source>[,floor=<sats per kw>][,ceiling=<sats per kw>][,multiplier=<factor>]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeePolicy {
+    pub source: FeeSourceConfig,
+    pub floor: u32,
+    pub ceiling: Option<u32>,
+    pub multiplier: f32,
+}
+
+impl FeePolicy {
+    pub fn apply(&self, estimate: u32) -> u32 {
+        let fee_rate = (estimate as f32 * self.multiplier).round() as u32;
+        let fee_rate = fee_rate.max(self.floor);
+        self.ceiling
+            .map_or(fee_rate, |ceiling| fee_rate.min(ceiling))
+            .max(FEERATE_FLOOR_SATS_PER_KW)
+    }
+}
+
+impl FromStr for FeePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        let mut policy = FeePolicy {
+            source: parts.next().unwrap_or_default().trim().parse()?,
+            floor: MIN_FEERATE,
+            ceiling: None,
+            multiplier: 1.0,
+        };
+        for part in parts {
+            let (key, value) = part
+                .trim()
+                .split_once('=')
+                .with_context(|| format!("{part} is not a key=value pair"))?;
+            let invalid = || format!("{value} is not a valid {key}");
+            match key {
+                "floor" => policy.floor = value.parse().with_context(invalid)?,
+                "ceiling" => policy.ceiling = Some(value.parse().with_context(invalid)?),
+                "multiplier" => policy.multiplier = value.parse().with_context(invalid)?,
+                _ => bail!("{key} is not a fee policy option (floor, ceiling, multiplier)"),
+            }
+        }
+        if policy.floor < FEERATE_FLOOR_SATS_PER_KW {
+            bail!("Fee floor must be at least {FEERATE_FLOOR_SATS_PER_KW} sats per kw");
+        }
+        if policy.ceiling.is_some_and(|ceiling| ceiling < policy.floor) {
+            bail!("Fee ceiling must not be below the floor");
+        }
+        if !policy.multiplier.is_finite() || policy.multiplier <= 0.0 {
+            bail!("Fee multiplier must be positive");
+        }
+        Ok(policy)
+    }
+}
+
+impl Display for FeePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},floor={}", self.source, self.floor)?;
+        if let Some(ceiling) = self.ceiling {
+            write!(f, ",ceiling={ceiling}")?;
+        }
+        if self.multiplier != 1.0 {
+            write!(f, ",multiplier={}", self.multiplier)?;
+        }
+        Ok(())
+    }
+}
+
+/// The fee rate we use for a confirmation target and how we got to it.
+#[derive(Debug, Clone)]
+pub struct TargetFeeRate {
+    pub target: ConfirmationTarget,
+    pub policy: FeePolicy,
+    /// The last estimate from the source before the policy was applied, sats per 1000 weight units.
+    pub estimate: Option<u32>,
+    /// Sats per 1000 weight units.
+    pub fee_rate: u32,
+}
+
+struct TargetEstimator {
+    target: ConfirmationTarget,
+    policy: FeePolicy,
+    source: Box<dyn FeeSource>,
+    // Zero until the source has given an estimate.
+    estimate: AtomicU32,
+    fee_rate: AtomicU32,
+}
+
+/// Fee rates for every confirmation target, each estimated from the source configured in the settings.
+pub struct FeeEstimates {
+    estimators: Vec<TargetEstimator>,
+}
+
+impl FeeEstimates {
+    pub fn new(settings: &Settings, client: Arc<RpcClient>) -> FeeEstimates {
+        let mempool = Arc::new(MempoolCache::new(client.clone()));
+        let estimators = CONFIRMATION_TARGETS
+            .iter()
+            .map(|target| {
+                let policy = settings.fee_policy(*target).clone();
+                TargetEstimator {
+                    target: *target,
+                    source: policy.source.build(&client, &mempool),
+                    estimate: AtomicU32::new(0),
+                    fee_rate: AtomicU32::new(policy.apply(default_fee_rate(*target))),
+                    policy,
+                }
+            })
+            .collect();
+        FeeEstimates { estimators }
+    }
+
+    /// Fetch new estimates for every target, keeping the previous fee rate of targets whose source fails.
+    pub async fn update(&self) {
+        for estimator in &self.estimators {
+            match estimator.source.fee_rate().await {
+                Ok(estimate) => {
+                    estimator.estimate.store(estimate, Ordering::Release);
+                    estimator
+                        .fee_rate
+                        .store(estimator.policy.apply(estimate), Ordering::Release);
+                }
+                Err(e) => error!(
+                    "Could not fetch fee estimate for {}: {e}",
+                    target_name(estimator.target)
+                ),
+            }
+        }
+    }
+
+    pub fn get(&self, target: ConfirmationTarget) -> u32 {
+        self.estimators
+            .iter()
+            .find(|estimator| estimator.target == target)
+            .map(|estimator| estimator.fee_rate.load(Ordering::Acquire))
+            .unwrap_or(MIN_FEERATE)
+    }
+
+    pub fn list(&self) -> Vec<TargetFeeRate> {
+        self.estimators
+            .iter()
+            .map(|estimator| TargetFeeRate {
+                target: estimator.target,
+                policy: estimator.policy.clone(),
+                estimate: Some(estimator.estimate.load(Ordering::Acquire)).filter(|e| *e > 0),
+                fee_rate: estimator.fee_rate.load(Ordering::Acquire),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_fee_policy() -> Result<()> {
+        let policy: FeePolicy = "estimatesmartfee:6".parse()?;
+        assert_eq!(
+            FeeSourceConfig::EstimateSmartFee {
+                blocks: 6,
+                mode: EstimateMode::Conservative
+            },
+            policy.source
+        );
+        assert_eq!(MIN_FEERATE, policy.floor);
+        assert_eq!(None, policy.ceiling);
+        assert_eq!(1.0, policy.multiplier);
+
+        let policy: FeePolicy = "mempool:2,floor=253,ceiling=50000,multiplier=1.5".parse()?;
+        assert_eq!(FeeSourceConfig::Mempool { blocks: 2 }, policy.source);
+        assert_eq!(253, policy.floor);
+        assert_eq!(Some(50000), policy.ceiling);
+        assert_eq!(1.5, policy.multiplier);
+        assert_eq!(policy, policy.to_string().parse()?);
+
+        let policy: FeePolicy = "estimatesmartfee:12:economical,floor=1000".parse()?;
+        assert_eq!(policy, policy.to_string().parse()?);
+        let policy: FeePolicy = "static:3000".parse()?;
+        assert_eq!(FeeSourceConfig::Static(3000), policy.source);
+
+        assert!("bitcoind:6".parse::<FeePolicy>().is_err());
+        assert!("mempool".parse::<FeePolicy>().is_err());
+        assert!("mempool:0".parse::<FeePolicy>().is_err());
+        assert!("estimatesmartfee:6:fast".parse::<FeePolicy>().is_err());
+        assert!("static:3000,floor=100".parse::<FeePolicy>().is_err());
+        assert!("static:3000,floor=3000,ceiling=2000"
+            .parse::<FeePolicy>()
+            .is_err());
+        assert!("static:3000,multiplier=0".parse::<FeePolicy>().is_err());
+        assert!("static:3000,fast".parse::<FeePolicy>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_fee_policy() -> Result<()> {
+        let policy: FeePolicy = "static:0,floor=1000,ceiling=20000,multiplier=2".parse()?;
+        assert_eq!(1000, policy.apply(300));
+        assert_eq!(8000, policy.apply(4000));
+        assert_eq!(20000, policy.apply(15000));
+        Ok(())
+    }
+
+    #[test]
+    fn test_histogram_fee_rate() {
+        let entry = |sats: u64, vsize: u64| MempoolEntry {
+            vsize,
+            fees: MempoolEntryFees {
+                modified: sats as f64 / 100_000_000.0,
+            },
+        };
+        // 5, 20, 10 and 5 sats per vbyte.
+        let entries = [
+            entry(5_000_000, 1_000_000),
+            entry(20_000_000, 1_000_000),
+            entry(5_000_000, 500_000),
+            entry(2_500_000, 500_000),
+        ];
+        let histogram = build_histogram(entries.iter());
+        assert_eq!(
+            vec![
+                (5000, 1_000_000),
+                (2500, 500_000),
+                (1250, 1_000_000),
+                (1250, 500_000)
+            ],
+            histogram
+        );
+        assert_eq!(5000, histogram_fee_rate(&histogram, BLOCK_VSIZE, 253));
+        assert_eq!(1250, histogram_fee_rate(&histogram, 2 * BLOCK_VSIZE, 253));
+        // The mempool clears before six blocks.
+        assert_eq!(253, histogram_fee_rate(&histogram, 6 * BLOCK_VSIZE, 253));
+        assert_eq!(2000, histogram_fee_rate(&histogram, 2 * BLOCK_VSIZE, 2000));
+    }
+}
@@ -1,15 +1,20 @@
-use std::{str::FromStr, sync::Mutex};
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use async_trait::async_trait;
 use bitcoin::{BlockHash, Transaction, Txid};
 use lightning::chain::chaininterface::{BroadcasterInterface, ConfirmationTarget, FeeEstimator};
 use lightning_block_sync::{AsyncBlockSourceResult, BlockData, BlockHeaderData, BlockSource};
 
-use crate::Service;
+use crate::{settings::Settings, Service};
 
 pub struct MockBitcoindClient {
     broadcast_transactions: Mutex<Vec<Txid>>,
     synchronised: bool,
+    // The fee policies are applied to the estimates like the real client does when set.
+    settings: Option<Arc<Settings>>,
 }
 
 impl Default for MockBitcoindClient {
@@ -17,6 +22,7 @@ impl Default for MockBitcoindClient {
         Self {
             broadcast_transactions: Default::default(),
             synchronised: true,
+            settings: None,
         }
     }
 }
@@ -39,6 +45,10 @@ impl MockBitcoindClient {
     pub fn set_synchronised(&mut self, synchronised: bool) {
         self.synchronised = synchronised;
     }
+
+    pub fn set_fee_policies(&mut self, settings: Arc<Settings>) {
+        self.settings = Some(settings);
+    }
 }
 
 impl BroadcasterInterface for MockBitcoindClient {
@@ -80,13 +90,16 @@ impl BlockSource for MockBitcoindClient {
 
 impl FeeEstimator for MockBitcoindClient {
     fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
-        match confirmation_target {
+        let estimate = match confirmation_target {
             ConfirmationTarget::NonAnchorChannelFee => 2000,
             ConfirmationTarget::OnChainSweep => 10000,
             ConfirmationTarget::ChannelCloseMinimum
             | ConfirmationTarget::AnchorChannelFee
             | ConfirmationTarget::MinAllowedAnchorChannelRemoteFee
             | ConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee => 500,
-        }
+        };
+        self.settings.as_ref().map_or(estimate, |settings| {
+            settings.fee_policy(confirmation_target).apply(estimate)
+        })
     }
 }
@@ -1,8 +1,10 @@
 mod bitcoind_client;
 pub mod bitcoind_interface;
+mod fee_source;
 mod utxo_lookup;
 
 pub use bitcoind_client::{BitcoindClient, BitcoindMetrics, MempoolInfo};
+pub use fee_source::{target_name, FeePolicy, FeeSource, FeeSourceConfig, TargetFeeRate};
 pub use utxo_lookup::BitcoindUtxoLookup;
 
 #[cfg(test)]
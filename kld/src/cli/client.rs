@@ -6,7 +6,8 @@ use std::{
     str::FromStr,
 };
 
-use anyhow::Result;
+use crate::commands::PageArgs;
+use anyhow::{Context, Result};
 use kld::api::codegen::{
     get_kld_channel_response::GetKldChannelResponseItem,
     get_v1_channel_history_response::GetV1ChannelHistoryResponseItem,
@@ -22,10 +23,14 @@ use kld::api::codegen::{
     post_v1_peer_connect_response::PostV1PeerConnectResponse,
 };
 use kld::api::payloads::{
-    ChannelFee, FeeRate, FeeRatesResponse, FundChannel, FundChannelResponse, GenerateInvoice,
-    GenerateInvoiceResponse, GetInfo, Invoice, KeysendRequest, ListFunds, NetworkChannel,
-    NetworkNode, PayInvoice, PaymentResponse, Peer, SetChannelFeeResponse, SignRequest,
-    SignResponse, WalletBalance, WalletTransfer, WalletTransferResponse,
+    BumpFee, BumpFeeResponse, ChannelFee, ConsolidationReport, CreatePsbt, FeeRate,
+    FeeRatesResponse, FreezeUtxos, FundChannel, FundChannelResponse, GenerateInvoice,
+    GenerateInvoiceResponse, GetInfo, ImportLabelsResponse, Invoice, KeysendRequest, LedgerRecord,
+    ListFunds, NetworkChannel, NetworkNode, NodeAnnouncement, PayInvoice, PaymentResponse, Peer,
+    PeerHistory, Psbt, RoutingReport, ScorerLiquidity, ScorerParameters, SetChannelFeeResponse,
+    SignRequest, SignResponse, SubmitPsbt, UpdateNodeAnnouncement, UpdateScorerParameters,
+    WalletBalance, WalletDescriptors, WalletSweep, WalletTransaction, WalletTransfer,
+    WalletTransferResponse, WithdrawMany, WithdrawOutput,
 };
 use kld::api::routes;
 use reqwest::{
@@ -71,13 +76,37 @@ impl Api {
         deserialize::<GetInfo>(response)
     }
 
+    pub fn update_node_announcement(
+        &self,
+        alias: Option<String>,
+        color: Option<String>,
+        addresses: Option<Vec<String>>,
+    ) -> Result<String> {
+        let response = self
+            .request_with_body(
+                Method::POST,
+                routes::NODE_ANNOUNCEMENT,
+                UpdateNodeAnnouncement {
+                    alias,
+                    color,
+                    addresses,
+                },
+            )
+            .send()?;
+        deserialize::<NodeAnnouncement>(response)
+    }
+
     pub fn get_balance(&self) -> Result<String> {
         let response = self.request(Method::GET, routes::GET_BALANCE).send()?;
         deserialize::<WalletBalance>(response)
     }
 
-    pub fn new_address(&self) -> Result<String> {
-        let response = self.request(Method::GET, routes::NEW_ADDR).send()?;
+    pub fn new_address(&self, address_type: Option<String>) -> Result<String> {
+        let mut request = self.request(Method::GET, routes::NEW_ADDR);
+        if let Some(address_type) = address_type {
+            request = request.query(&[("addressType", address_type)]);
+        }
+        let response = request.send()?;
         deserialize::<GetV1NewaddrResponse>(response)
     }
 
@@ -100,6 +129,166 @@ impl Api {
         deserialize::<WalletTransferResponse>(response)
     }
 
+    pub fn withdraw_many(
+        &self,
+        outputs: Vec<String>,
+        fee_rate: Option<String>,
+        idempotency_key: Option<String>,
+    ) -> Result<String> {
+        let outputs = outputs
+            .iter()
+            .map(|output| {
+                let (address, satoshis) = output
+                    .rsplit_once(':')
+                    .with_context(|| format!("Expected address:satoshis, got {output}"))?;
+                Ok(WithdrawOutput {
+                    address: address.to_string(),
+                    satoshis: satoshis.parse()?,
+                })
+            })
+            .collect::<Result<Vec<WithdrawOutput>>>()?;
+        let withdraw_many = WithdrawMany {
+            outputs,
+            fee_rate: fee_rate.map(|f| FeeRate::from_str(&f)).transpose()?,
+            idempotency_key,
+        };
+        let response = self
+            .request_with_body(Method::POST, routes::WITHDRAW_MANY, withdraw_many)
+            .send()?;
+        deserialize::<WalletTransferResponse>(response)
+    }
+
+    pub fn create_withdraw_psbt(
+        &self,
+        address: String,
+        satoshis: String,
+        fee_rate: Option<String>,
+        sign: bool,
+    ) -> Result<String> {
+        let create_psbt = CreatePsbt {
+            withdraw: Some(WalletTransfer {
+                address,
+                satoshis,
+                fee_rate: fee_rate.map(|f| FeeRate::from_str(&f)).transpose()?,
+                min_conf: None,
+                utxos: vec![],
+            }),
+            fund_channel: None,
+            sign,
+        };
+        let response = self
+            .request_with_body(Method::POST, routes::PSBT, create_psbt)
+            .send()?;
+        deserialize::<Psbt>(response)
+    }
+
+    pub fn create_funding_psbt(
+        &self,
+        id: String,
+        satoshis: String,
+        fee_rate: Option<String>,
+        sign: bool,
+    ) -> Result<String> {
+        let create_psbt = CreatePsbt {
+            withdraw: None,
+            fund_channel: Some(FundChannel {
+                id,
+                satoshis,
+                fee_rate: fee_rate.map(|f| FeeRate::from_str(&f)).transpose()?,
+                announce: None,
+                min_conf: None,
+                utxos: vec![],
+                push_msat: None,
+                close_to: None,
+                request_amt: None,
+                compact_lease: None,
+            }),
+            sign,
+        };
+        let response = self
+            .request_with_body(Method::POST, routes::PSBT, create_psbt)
+            .send()?;
+        deserialize::<Psbt>(response)
+    }
+
+    pub fn list_psbts(&self) -> Result<String> {
+        let response = self.request(Method::GET, routes::PSBT).send()?;
+        deserialize::<Vec<Psbt>>(response)
+    }
+
+    pub fn submit_psbt(&self, id: String, psbt: String) -> Result<String> {
+        let response = self
+            .request_with_body(Method::POST, routes::SUBMIT_PSBT, SubmitPsbt { id, psbt })
+            .send()?;
+        deserialize::<WalletTransferResponse>(response)
+    }
+
+    pub fn bump_fee(&self, txid: String, fee_rate: String) -> Result<String> {
+        let bump_fee = BumpFee {
+            txid,
+            fee_rate: FeeRate::from_str(&fee_rate)?,
+        };
+        let response = self
+            .request_with_body(Method::POST, routes::BUMP_FEE, bump_fee)
+            .send()?;
+        deserialize::<BumpFeeResponse>(response)
+    }
+
+    pub fn freeze_utxos(&self, utxos: Vec<String>, frozen: bool) -> Result<String> {
+        let route = if frozen {
+            routes::FREEZE_UTXOS
+        } else {
+            routes::UNFREEZE_UTXOS
+        };
+        let response = self
+            .request_with_body(Method::POST, route, FreezeUtxos { utxos })
+            .send()?;
+        deserialize::<FreezeUtxos>(response)
+    }
+
+    pub fn export_descriptors(&self) -> Result<String> {
+        let response = self.request(Method::GET, routes::DESCRIPTORS).send()?;
+        deserialize::<WalletDescriptors>(response)
+    }
+
+    pub fn list_sweeps(&self) -> Result<String> {
+        let response = self.request(Method::GET, routes::LIST_SWEEPS).send()?;
+        deserialize::<Vec<WalletSweep>>(response)
+    }
+
+    pub fn consolidation_report(&self) -> Result<String> {
+        let response = self.request(Method::GET, routes::CONSOLIDATION).send()?;
+        deserialize::<ConsolidationReport>(response)
+    }
+
+    pub fn list_transactions(&self, offset: Option<usize>, limit: Option<usize>) -> Result<String> {
+        let response = self
+            .request(Method::GET, routes::LIST_TRANSACTIONS)
+            .query(&page_query(offset, limit))
+            .send()?;
+        deserialize::<Vec<WalletTransaction>>(response)
+    }
+
+    pub fn export_labels(&self) -> Result<String> {
+        let response = self.request(Method::GET, routes::LABELS).send()?;
+        if response.status().is_success() {
+            Ok(response.text()?.trim_end().to_string())
+        } else {
+            Ok(to_string_pretty(
+                &response.json::<kld::api::payloads::Error>()?,
+            )?)
+        }
+    }
+
+    pub fn import_labels(&self, path: PathBuf) -> Result<String> {
+        let labels = fs::read_to_string(&path)?;
+        let response = self
+            .request(Method::POST, routes::LABELS)
+            .body(labels)
+            .send()?;
+        deserialize::<ImportLabelsResponse>(response)
+    }
+
     pub fn list_funds(&self) -> Result<String> {
         let response = self.request(Method::GET, routes::LIST_FUNDS).send()?;
         deserialize::<ListFunds>(response)
@@ -137,6 +326,13 @@ impl Api {
         deserialize::<()>(response)
     }
 
+    pub fn peer_history(&self, id: String) -> Result<String> {
+        let response = self
+            .request(Method::GET, &routes::PEER_HISTORY.replace(":id", &id))
+            .send()?;
+        deserialize::<PeerHistory>(response)
+    }
+
     pub fn open_channel(
         &self,
         id: String,
@@ -209,18 +405,29 @@ impl Api {
         deserialize::<()>(response)
     }
 
-    pub fn list_network_nodes(&self, id: Option<String>) -> Result<String> {
+    pub fn list_network_nodes(
+        &self,
+        id: Option<String>,
+        offset: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<String> {
         let response = if let Some(id) = id {
             self.request(Method::GET, &routes::LIST_NETWORK_NODE.replace(":id", &id))
                 .send()?
         } else {
             self.request(Method::GET, routes::LIST_NETWORK_NODES)
+                .query(&page_query(offset, limit))
                 .send()?
         };
         deserialize::<Vec<NetworkNode>>(response)
     }
 
-    pub fn list_network_channels(&self, id: Option<String>) -> Result<String> {
+    pub fn list_network_channels(
+        &self,
+        id: Option<String>,
+        offset: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<String> {
         let response = if let Some(id) = id {
             self.request(
                 Method::GET,
@@ -229,6 +436,7 @@ impl Api {
             .send()?
         } else {
             self.request(Method::GET, routes::LIST_NETWORK_CHANNELS)
+                .query(&page_query(offset, limit))
                 .send()?
         };
         deserialize::<Vec<NetworkChannel>>(response)
@@ -280,13 +488,23 @@ impl Api {
         deserialize::<GenerateInvoiceResponse>(response)
     }
 
-    pub fn list_invoices(&self, label: Option<String>) -> Result<String> {
-        let route = if let Some(label) = label {
-            format!("{}?{label}", routes::LIST_INVOICES)
-        } else {
-            routes::LIST_INVOICES.to_string()
-        };
-        let response = self.request(Method::GET, &route).send()?;
+    pub fn list_invoices(
+        &self,
+        label: Option<String>,
+        status: Option<String>,
+        page: PageArgs,
+    ) -> Result<String> {
+        let mut params = page.query();
+        if let Some(label) = label {
+            params.push(("label", label));
+        }
+        if let Some(status) = status {
+            params.push(("status", status));
+        }
+        let response = self
+            .request(Method::GET, routes::LIST_INVOICES)
+            .query(&params)
+            .send()?;
         deserialize::<Vec<Invoice>>(response)
     }
 
@@ -305,14 +523,19 @@ impl Api {
         &self,
         bolt11: Option<String>,
         direction: Option<String>,
+        status: Option<String>,
+        page: PageArgs,
     ) -> Result<String> {
-        let mut params = vec![];
+        let mut params = page.query();
         if let Some(bolt11) = bolt11 {
-            params.push(("bolt11", bolt11));
+            params.push(("invoice", bolt11));
         }
         if let Some(direction) = direction {
             params.push(("direction", direction));
         }
+        if let Some(status) = status {
+            params.push(("status", status));
+        }
         let response = self
             .request(Method::GET, routes::LIST_PAYMENTS)
             .query(&params)
@@ -340,11 +563,19 @@ impl Api {
         deserialize::<GetV1GetFeesResponse>(response)
     }
 
-    pub fn list_forwards(&self, status: Option<String>) -> Result<String> {
-        let mut params = vec![];
+    pub fn list_forwards(
+        &self,
+        status: Option<String>,
+        channel: Option<String>,
+        page: PageArgs,
+    ) -> Result<String> {
+        let mut params = page.query();
         if let Some(status) = status {
             params.push(("status", status));
         }
+        if let Some(channel) = channel {
+            params.push(("channel", channel));
+        }
         let response = self
             .request(Method::GET, routes::LIST_FORWARDS)
             .query(&params)
@@ -352,9 +583,14 @@ impl Api {
         deserialize::<Vec<GetV1ChannelListForwardsResponseItem>>(response)
     }
 
-    pub fn channel_history(&self) -> Result<String> {
+    pub fn channel_history(&self, channel: Option<String>, page: PageArgs) -> Result<String> {
+        let mut params = page.query();
+        if let Some(channel) = channel {
+            params.push(("channel", channel));
+        }
         let response = self
             .request(Method::GET, routes::LIST_CHANNEL_HISTORY)
+            .query(&params)
             .send()?;
         deserialize::<Vec<GetV1ChannelHistoryResponseItem>>(response)
     }
@@ -377,6 +613,112 @@ impl Api {
         Ok(format!("scorer save in {}", path.display()))
     }
 
+    pub fn upload_scorer(&self, path: PathBuf) -> Result<String> {
+        let scorer = fs::read(&path)?;
+        let response = self
+            .request(Method::POST, routes::SCORER)
+            .body(scorer)
+            .send()?;
+        if response.status().is_success() {
+            Ok(format!("scorer uploaded from {}", path.display()))
+        } else {
+            Ok(to_string_pretty(
+                &response.json::<kld::api::payloads::Error>()?,
+            )?)
+        }
+    }
+
+    pub fn scorer_liquidity(&self, node_id: Option<String>, scid: Option<u64>) -> Result<String> {
+        let mut query = vec![];
+        if let Some(node_id) = node_id {
+            query.push(("node_id", node_id));
+        }
+        if let Some(scid) = scid {
+            query.push(("scid", scid.to_string()));
+        }
+        let response = self
+            .request(Method::GET, routes::SCORER_LIQUIDITY)
+            .query(&query)
+            .send()?;
+        deserialize::<ScorerLiquidity>(response)
+    }
+
+    pub fn scorer_parameters(&self) -> Result<String> {
+        let response = self
+            .request(Method::GET, routes::SCORER_PARAMETERS)
+            .send()?;
+        deserialize::<ScorerParameters>(response)
+    }
+
+    pub fn update_scorer_parameters(&self, parameters: UpdateScorerParameters) -> Result<String> {
+        let response = self
+            .request_with_body(Method::POST, routes::SCORER_PARAMETERS, parameters)
+            .send()?;
+        deserialize::<ScorerParameters>(response)
+    }
+
+    pub fn backup(&self, path: PathBuf) -> Result<String> {
+        let backup = self.request(Method::GET, routes::BACKUP).send()?.bytes()?;
+        let mut f = File::create(&path)?;
+        f.write_all(&backup)?;
+
+        Ok(format!("channel backup save in {}", path.display()))
+    }
+
+    pub fn ledger(
+        &self,
+        from: Option<i64>,
+        to: Option<i64>,
+        format: Option<String>,
+    ) -> Result<String> {
+        let mut query = vec![];
+        if let Some(from) = from {
+            query.push(("from", from.to_string()));
+        }
+        if let Some(to) = to {
+            query.push(("to", to.to_string()));
+        }
+        if let Some(format) = &format {
+            query.push(("format", format.clone()));
+        }
+        let response = self
+            .request(Method::GET, routes::LEDGER)
+            .query(&query)
+            .send()?;
+        if format.as_deref() == Some("csv") && response.status().is_success() {
+            Ok(response.text()?.trim_end().to_string())
+        } else {
+            deserialize::<Vec<LedgerRecord>>(response)
+        }
+    }
+
+    pub fn routing_report(
+        &self,
+        from: Option<i64>,
+        to: Option<i64>,
+        top: Option<usize>,
+        rank: Option<String>,
+    ) -> Result<String> {
+        let mut query = vec![];
+        if let Some(from) = from {
+            query.push(("from", from.to_string()));
+        }
+        if let Some(to) = to {
+            query.push(("to", to.to_string()));
+        }
+        if let Some(top) = top {
+            query.push(("top", top.to_string()));
+        }
+        if let Some(rank) = rank {
+            query.push(("rank", rank));
+        }
+        let response = self
+            .request(Method::GET, routes::ROUTING_REPORT)
+            .query(&query)
+            .send()?;
+        deserialize::<RoutingReport>(response)
+    }
+
     fn request_builder(&self, method: Method, route: &str) -> RequestBuilder {
         self.client
             .request(method, format!("https://{}{}", self.host, route))
@@ -399,6 +741,18 @@ impl Api {
     }
 }
 
+/// Query parameters for the list endpoints that are paginated with an offset and a limit.
+fn page_query(offset: Option<usize>, limit: Option<usize>) -> Vec<(&'static str, usize)> {
+    let mut query = vec![];
+    if let Some(offset) = offset {
+        query.push(("offset", offset));
+    }
+    if let Some(limit) = limit {
+        query.push(("limit", limit));
+    }
+    query
+}
+
 fn deserialize<T: DeserializeOwned + Serialize>(response: Response) -> Result<String> {
     if response.status().is_success() {
         Ok(to_string_pretty(&response.json::<T>()?)?)
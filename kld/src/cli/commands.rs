@@ -1,6 +1,6 @@
 use std::{net::SocketAddr, path::PathBuf};
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -29,10 +29,26 @@ pub enum KldCliSubCommand {
         #[arg()]
         message: String,
     },
+    /// Update the alias, color and addresses that the node announces to the network.
+    UpdateNodeAnnouncement {
+        /// The node alias (max 32 bytes)
+        #[arg(long)]
+        alias: Option<String>,
+        /// The node color in hex, e.g. 6e2cf7
+        #[arg(long)]
+        color: Option<String>,
+        /// Comma separated list of public addresses
+        #[arg(long, value_delimiter = ',')]
+        addresses: Option<Vec<String>>,
+    },
     /// Fetch confirmed and unconfirmed on-chain balance.
     GetBalance,
     /// Generates new on-chain address for receiving funds.
-    NewAddress,
+    NewAddress {
+        /// Address type [bech32/p2tr]
+        #[arg(short, long)]
+        address_type: Option<String>,
+    },
     /// Send on-chain funds out of the wallet.
     Withdraw {
         /// The address to withdraw to.
@@ -45,8 +61,101 @@ pub enum KldCliSubCommand {
         #[arg(short, long)]
         fee_rate: Option<String>,
     },
+    /// Send on-chain funds to many addresses with a single transaction.
+    WithdrawMany {
+        /// The outputs as address:satoshis.
+        #[arg(required = true)]
+        outputs: Vec<String>,
+        /// Fee rate [urgent/normal/slow/<sats>perkw/<sats>perkb]
+        #[arg(short, long)]
+        fee_rate: Option<String>,
+        /// A retried withdrawal with the same key does not pay again.
+        #[arg(short, long)]
+        idempotency_key: Option<String>,
+    },
+    /// Bump the fee of an unconfirmed wallet transaction by RBF or CPFP.
+    BumpFee {
+        /// The transaction ID.
+        #[arg()]
+        txid: String,
+        /// Fee rate [urgent/normal/slow/<sats>perkw/<sats>perkb]
+        #[arg()]
+        fee_rate: String,
+    },
     /// Show available funds from the internal wallet.
     ListFunds,
+    /// Stop the wallet from spending the outputs.
+    FreezeUtxos {
+        /// The outputs as txid:vout.
+        #[arg(required = true)]
+        utxos: Vec<String>,
+    },
+    /// Allow the wallet to spend previously frozen outputs.
+    UnfreezeUtxos {
+        /// The outputs as txid:vout.
+        #[arg(required = true)]
+        utxos: Vec<String>,
+    },
+    /// Fetch the transactions of the internal wallet, most recent first.
+    ListTransactions {
+        /// Number of transactions to skip.
+        #[arg(long)]
+        offset: Option<usize>,
+        /// Maximum number of transactions (default 100).
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Export the public descriptors of the wallet for a watch-only wallet.
+    ExportDescriptors,
+    /// List the sweeps of the hot wallet balance to cold storage.
+    ListSweeps,
+    /// Show which small UTXOs would be consolidated and at what fee, without spending them.
+    ConsolidationReport,
+    /// Export the wallet labels in BIP-329 format.
+    ExportLabels,
+    /// Import wallet labels from a file in BIP-329 format.
+    ImportLabels { path: PathBuf },
+    /// Create a PSBT withdrawing on-chain funds, to be signed externally and submitted.
+    CreateWithdrawPsbt {
+        /// The address to withdraw to.
+        #[arg()]
+        address: String,
+        /// The amount to withdraw (in Satoshis). The string "all" will empty the wallet.
+        #[arg()]
+        amount: String,
+        /// Fee rate [urgent/normal/slow/<sats>perkw/<sats>perkb]
+        #[arg(short, long)]
+        fee_rate: Option<String>,
+        /// Sign the inputs with the wallet key.
+        #[arg(short, long)]
+        sign: bool,
+    },
+    /// Open a channel funded by a PSBT, to be signed externally and submitted.
+    CreateFundingPsbt {
+        /// The public key of the node to open a channel with. Optionally provide host and port [id@host:port].
+        #[arg()]
+        public_key: String,
+        /// Amount of satoshis to commit to the channel.
+        #[arg()]
+        sats: String,
+        /// Fee rate [urgent/normal/slow/<sats>perkw/<sats>perkb]
+        #[arg(short, long)]
+        fee_rate: Option<String>,
+        /// Sign the inputs with the wallet key.
+        #[arg(short, long)]
+        sign: bool,
+    },
+    /// Fetch the PSBTs waiting to be submitted.
+    ListPsbts,
+    /// Submit a signed PSBT to withdraw or fund the channel.
+    SubmitPsbt {
+        /// The id of the PSBT.
+        #[arg()]
+        id: String,
+        /// Base64 encoded PSBT with the signatures.
+        #[arg()]
+        psbt: String,
+    },
     /// Fetch a list of this nodes peers.
     ListPeers,
     /// Connect with a network peer.
@@ -61,6 +170,12 @@ pub enum KldCliSubCommand {
         #[arg()]
         public_key: String,
     },
+    /// Show the connection history and uptime of a peer.
+    PeerHistory {
+        /// The public key of the peer.
+        #[arg()]
+        public_key: String,
+    },
     /// Fetch a list of channels
     ListChannels,
     /// Fetch a list of this nodes open channels.
@@ -117,12 +232,24 @@ pub enum KldCliSubCommand {
         /// Provide Node ID to get info about a single node.
         #[arg(short, long)]
         id: Option<String>,
+        /// Number of nodes to skip.
+        #[arg(long)]
+        offset: Option<usize>,
+        /// Maximum number of nodes.
+        #[arg(long)]
+        limit: Option<usize>,
     },
     /// Get channel information from the network graph.
     NetworkChannels {
         /// Provide short channel ID to get info about a single channel.
         #[arg(short, long)]
         id: Option<String>,
+        /// Number of channels to skip.
+        #[arg(long)]
+        offset: Option<usize>,
+        /// Maximum number of channels, each with up to one entry per direction.
+        #[arg(long)]
+        limit: Option<usize>,
     },
     /// Return feerate estimates, either satoshi-per-kw or satoshi-per-kb.
     FeeRates {
@@ -159,6 +286,11 @@ pub enum KldCliSubCommand {
         /// Label of the invoice
         #[arg(short, long)]
         label: Option<String>,
+        /// The status of the invoices (Unpaid, Paid, Expired)
+        #[arg(short, long)]
+        status: Option<String>,
+        #[command(flatten)]
+        page: PageArgs,
     },
     /// Pay an invoice
     PayInvoice {
@@ -177,6 +309,11 @@ pub enum KldCliSubCommand {
         /// Direction (inbound/outbound)
         #[arg(short, long)]
         direction: Option<String>,
+        /// The status of the payments (pending, complete, failed)
+        #[arg(short, long)]
+        status: Option<String>,
+        #[command(flatten)]
+        page: PageArgs,
     },
     /// Estimate channel liquidity to a target node
     EstimateChannelLiquidity {
@@ -193,15 +330,127 @@ pub enum KldCliSubCommand {
     GetFees,
     /// Fetch a list of the forwarded htlcs.
     ListForwards {
-        /// The status of the forwards (succeeded, failed)
+        /// The status of the forwards (settled, failed)
         #[arg(short, long)]
         status: Option<String>,
+        /// Only forwards in or out of the channel (channel ID)
+        #[arg(short, long)]
+        channel: Option<String>,
+        #[command(flatten)]
+        page: PageArgs,
     },
     /// Fetch a list of historic (closed) channels
-    ListChannelHistory,
+    ListChannelHistory {
+        /// Only this channel (channel ID)
+        #[arg(short, long)]
+        channel: Option<String>,
+        /// Time range of the closing
+        #[command(flatten)]
+        page: PageArgs,
+    },
     /// Decode invoice
     Decode { invoice: String },
 
     /// Download scorer to the path, if unspecific, will use `scorer.bin` as default
     Scorer { path: Option<PathBuf> },
+    /// Replace the node's scorer with the one at the path, e.g. downloaded from another node.
+    UploadScorer { path: PathBuf },
+    /// Fetch the liquidity the scorer estimates for each channel direction.
+    ScorerLiquidity {
+        /// Only channels of this node
+        #[arg(long)]
+        node_id: Option<String>,
+        /// Only this channel
+        #[arg(long)]
+        scid: Option<u64>,
+    },
+    /// Fetch the parameters of the scorer.
+    ScorerParameters,
+    /// Update the parameters of the scorer. They take effect without a restart.
+    UpdateScorerParameters {
+        #[arg(long)]
+        base_penalty_msat: Option<u64>,
+        #[arg(long)]
+        base_penalty_amount_multiplier_msat: Option<u64>,
+        #[arg(long)]
+        liquidity_penalty_multiplier_msat: Option<u64>,
+        #[arg(long)]
+        liquidity_penalty_amount_multiplier_msat: Option<u64>,
+        #[arg(long)]
+        historical_liquidity_penalty_multiplier_msat: Option<u64>,
+        #[arg(long)]
+        historical_liquidity_penalty_amount_multiplier_msat: Option<u64>,
+        /// Half life in seconds
+        #[arg(long)]
+        liquidity_offset_half_life: Option<u64>,
+        /// Half life in seconds
+        #[arg(long)]
+        historical_no_updates_half_life: Option<u64>,
+    },
+
+    /// Download an encrypted static channel backup to the path, if unspecific, will use `channel_backup.bin` as default
+    Backup { path: Option<PathBuf> },
+    /// Fetch the accounting ledger of payments, forwarding fees and on-chain transactions.
+    Ledger {
+        /// Unix timestamp of the first entry.
+        #[arg(long)]
+        from: Option<i64>,
+        /// Unix timestamp after the last entry.
+        #[arg(long)]
+        to: Option<i64>,
+        /// json or csv for crypto tax tools.
+        #[arg(long)]
+        format: Option<String>,
+    },
+    /// Report the forwarding per channel and per peer to decide which channels to close or grow.
+    RoutingReport {
+        /// Unix timestamp of the start of the window.
+        #[arg(long)]
+        from: Option<i64>,
+        /// Unix timestamp of the end of the window.
+        #[arg(long)]
+        to: Option<i64>,
+        /// Only the best N channels and peers.
+        #[arg(long)]
+        top: Option<usize>,
+        /// Rank by fees (default), volume or efficiency (fees per sat of capacity).
+        #[arg(long)]
+        rank: Option<String>,
+    },
+}
+
+/// Time range and page of a list, oldest first.
+#[derive(Args, Debug, Default)]
+pub struct PageArgs {
+    /// Unix timestamp of the first record
+    #[arg(long)]
+    pub from: Option<i64>,
+    /// Unix timestamp after the last record
+    #[arg(long)]
+    pub to: Option<i64>,
+    /// Number of records to skip
+    #[arg(long)]
+    pub offset: Option<u64>,
+    /// Maximum number of records
+    #[arg(long)]
+    pub limit: Option<u64>,
+}
+
+impl PageArgs {
+    pub fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![];
+        if let Some(from) = self.from {
+            query.push(("from", from.to_string()));
+        }
+        if let Some(to) = self.to {
+            query.push(("to", to.to_string()));
+        }
+        if let Some(offset) = self.offset {
+            query.push(("offset", offset.to_string()));
+        }
+        if let Some(limit) = self.limit {
+            query.push(("limit", limit.to_string()));
+        }
+        query
+    }
 }
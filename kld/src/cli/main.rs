@@ -5,6 +5,7 @@ use crate::client::Api;
 use anyhow::{bail, Result};
 use clap::Parser;
 use commands::{KldCliCommand, KldCliSubCommand};
+use kld::api::payloads::UpdateScorerParameters;
 
 fn main() {
     let args = KldCliCommand::parse();
@@ -21,18 +22,54 @@ fn run_command(args: KldCliCommand) -> Result<()> {
     let output = match args.command {
         KldCliSubCommand::Sign { message } => api.sign(message)?,
         KldCliSubCommand::GetInfo => api.get_info()?,
+        KldCliSubCommand::UpdateNodeAnnouncement {
+            alias,
+            color,
+            addresses,
+        } => api.update_node_announcement(alias, color, addresses)?,
         KldCliSubCommand::GetBalance => api.get_balance()?,
-        KldCliSubCommand::NewAddress => api.new_address()?,
+        KldCliSubCommand::NewAddress { address_type } => api.new_address(address_type)?,
         KldCliSubCommand::Withdraw {
             address,
             amount: satoshis,
             fee_rate,
         } => api.withdraw(address, satoshis, fee_rate)?,
+        KldCliSubCommand::WithdrawMany {
+            outputs,
+            fee_rate,
+            idempotency_key,
+        } => api.withdraw_many(outputs, fee_rate, idempotency_key)?,
+        KldCliSubCommand::BumpFee { txid, fee_rate } => api.bump_fee(txid, fee_rate)?,
         KldCliSubCommand::ListFunds => api.list_funds()?,
+        KldCliSubCommand::FreezeUtxos { utxos } => api.freeze_utxos(utxos, true)?,
+        KldCliSubCommand::UnfreezeUtxos { utxos } => api.freeze_utxos(utxos, false)?,
+        KldCliSubCommand::ListTransactions { offset, limit } => {
+            api.list_transactions(offset, limit)?
+        }
+        KldCliSubCommand::ExportDescriptors => api.export_descriptors()?,
+        KldCliSubCommand::ListSweeps => api.list_sweeps()?,
+        KldCliSubCommand::ConsolidationReport => api.consolidation_report()?,
+        KldCliSubCommand::ExportLabels => api.export_labels()?,
+        KldCliSubCommand::ImportLabels { path } => api.import_labels(path)?,
+        KldCliSubCommand::CreateWithdrawPsbt {
+            address,
+            amount: satoshis,
+            fee_rate,
+            sign,
+        } => api.create_withdraw_psbt(address, satoshis, fee_rate, sign)?,
+        KldCliSubCommand::CreateFundingPsbt {
+            public_key,
+            sats: satoshis,
+            fee_rate,
+            sign,
+        } => api.create_funding_psbt(public_key, satoshis, fee_rate, sign)?,
+        KldCliSubCommand::ListPsbts => api.list_psbts()?,
+        KldCliSubCommand::SubmitPsbt { id, psbt } => api.submit_psbt(id, psbt)?,
         KldCliSubCommand::ListPeerChannels => api.list_peer_channels()?,
         KldCliSubCommand::ListPeers => api.list_peers()?,
         KldCliSubCommand::ConnectPeer { public_key } => api.connect_peer(public_key)?,
         KldCliSubCommand::DisconnectPeer { public_key } => api.disconnect_peer(public_key)?,
+        KldCliSubCommand::PeerHistory { public_key } => api.peer_history(public_key)?,
         KldCliSubCommand::OpenChannel {
             public_key,
             sats: satoshis,
@@ -62,8 +99,12 @@ fn run_command(args: KldCliCommand) -> Result<()> {
             };
             api.force_close_channel(id, need_broadcast)?
         }
-        KldCliSubCommand::NetworkNodes { id } => api.list_network_nodes(id)?,
-        KldCliSubCommand::NetworkChannels { id } => api.list_network_channels(id)?,
+        KldCliSubCommand::NetworkNodes { id, offset, limit } => {
+            api.list_network_nodes(id, offset, limit)?
+        }
+        KldCliSubCommand::NetworkChannels { id, offset, limit } => {
+            api.list_network_channels(id, offset, limit)?
+        }
         KldCliSubCommand::FeeRates { style } => api.fee_rates(style)?,
         KldCliSubCommand::Keysend { public_key, amount } => api.keysend(public_key, amount)?,
         KldCliSubCommand::GenerateInvoice {
@@ -72,21 +113,68 @@ fn run_command(args: KldCliCommand) -> Result<()> {
             description,
             expiry,
         } => api.generate_invoice(amount, label, description, expiry)?,
-        KldCliSubCommand::ListInvoices { label } => api.list_invoices(label)?,
+        KldCliSubCommand::ListInvoices {
+            label,
+            status,
+            page,
+        } => api.list_invoices(label, status, page)?,
         KldCliSubCommand::PayInvoice { bolt11, label } => api.pay_invoice(bolt11, label)?,
-        KldCliSubCommand::ListPayments { bolt11, direction } => {
-            api.list_payments(bolt11, direction)?
-        }
+        KldCliSubCommand::ListPayments {
+            bolt11,
+            direction,
+            status,
+            page,
+        } => api.list_payments(bolt11, direction, status, page)?,
         KldCliSubCommand::EstimateChannelLiquidity { scid, target } => {
             api.estimate_channel_liquidity(scid, target)?
         }
         KldCliSubCommand::LocalRemoteBalance => api.local_remote_balance()?,
         KldCliSubCommand::GetFees => api.get_fees()?,
-        KldCliSubCommand::ListForwards { status } => api.list_forwards(status)?,
-        KldCliSubCommand::ListChannelHistory => api.channel_history()?,
+        KldCliSubCommand::ListForwards {
+            status,
+            channel,
+            page,
+        } => api.list_forwards(status, channel, page)?,
+        KldCliSubCommand::ListChannelHistory { channel, page } => {
+            api.channel_history(channel, page)?
+        }
         KldCliSubCommand::Decode { invoice } => api.decode(invoice)?,
         KldCliSubCommand::Scorer { path } => api.scorer(path.unwrap_or("scorer.bin".into()))?,
+        KldCliSubCommand::UploadScorer { path } => api.upload_scorer(path)?,
+        KldCliSubCommand::ScorerLiquidity { node_id, scid } => {
+            api.scorer_liquidity(node_id, scid)?
+        }
+        KldCliSubCommand::ScorerParameters => api.scorer_parameters()?,
+        KldCliSubCommand::UpdateScorerParameters {
+            base_penalty_msat,
+            base_penalty_amount_multiplier_msat,
+            liquidity_penalty_multiplier_msat,
+            liquidity_penalty_amount_multiplier_msat,
+            historical_liquidity_penalty_multiplier_msat,
+            historical_liquidity_penalty_amount_multiplier_msat,
+            liquidity_offset_half_life,
+            historical_no_updates_half_life,
+        } => api.update_scorer_parameters(UpdateScorerParameters {
+            base_penalty_msat,
+            base_penalty_amount_multiplier_msat,
+            liquidity_penalty_multiplier_msat,
+            liquidity_penalty_amount_multiplier_msat,
+            historical_liquidity_penalty_multiplier_msat,
+            historical_liquidity_penalty_amount_multiplier_msat,
+            liquidity_offset_half_life,
+            historical_no_updates_half_life,
+        })?,
+        KldCliSubCommand::Backup { path } => {
+            api.backup(path.unwrap_or("channel_backup.bin".into()))?
+        }
         KldCliSubCommand::ListChannels => api.list_channels()?,
+        KldCliSubCommand::Ledger { from, to, format } => api.ledger(from, to, format)?,
+        KldCliSubCommand::RoutingReport {
+            from,
+            to,
+            top,
+            rank,
+        } => api.routing_report(from, to, top, rank)?,
     };
     if output != "null" {
         println!("{output}");
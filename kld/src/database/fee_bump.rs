@@ -0,0 +1,88 @@
+use anyhow::Result;
+use bitcoin::Txid;
+use lightning::ln::ChannelId;
+use postgres_types::{FromSql, ToSql};
+use time::OffsetDateTime;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+use super::{microsecond_timestamp, RowExt};
+
+/// A transaction that bumps the fee of one of our unconfirmed transactions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeBump {
+    /// The replacement (RBF) or the child (CPFP) transaction.
+    pub txid: Txid,
+    pub original_txid: Txid,
+    pub method: FeeBumpMethod,
+    /// The target fee rate in sats per 1000 weight units.
+    pub fee_rate: u32,
+    /// The fee paid by the new transaction.
+    pub fee: u64,
+    pub channel_id: Option<ChannelId>,
+    pub psbt_id: Option<Uuid>,
+    pub created: OffsetDateTime,
+}
+
+impl FeeBump {
+    pub fn new(
+        txid: Txid,
+        original_txid: Txid,
+        method: FeeBumpMethod,
+        fee_rate: u32,
+        fee: u64,
+    ) -> FeeBump {
+        FeeBump {
+            txid,
+            original_txid,
+            method,
+            fee_rate,
+            fee,
+            channel_id: None,
+            psbt_id: None,
+            created: microsecond_timestamp(),
+        }
+    }
+}
+
+impl TryFrom<Row> for FeeBump {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self, Self::Error> {
+        let channel_id: Option<[u8; 32]> = row
+            .get::<&str, Option<&[u8]>>("channel_id")
+            .map(|x| x.try_into())
+            .transpose()?;
+        Ok(FeeBump {
+            txid: row.read("txid")?,
+            original_txid: row.read("original_txid")?,
+            method: row.get("method"),
+            fee_rate: row.get::<&str, i64>("fee_rate") as u32,
+            fee: row.get::<&str, i64>("fee") as u64,
+            channel_id: channel_id.map(ChannelId::from_bytes),
+            psbt_id: row.get("psbt_id"),
+            created: row.get_timestamp("created"),
+        })
+    }
+}
+
+#[derive(Debug, ToSql, FromSql, PartialEq, Eq, Clone, Copy)]
+#[postgres(name = "fee_bump_method")]
+pub enum FeeBumpMethod {
+    /// Replace the transaction with one paying a higher fee.
+    #[postgres(name = "rbf")]
+    Rbf,
+    /// Spend one of its outputs with a child paying for both.
+    #[postgres(name = "cpfp")]
+    Cpfp,
+}
+
+impl ToString for FeeBumpMethod {
+    fn to_string(&self) -> String {
+        match self {
+            FeeBumpMethod::Rbf => "rbf",
+            FeeBumpMethod::Cpfp => "cpfp",
+        }
+        .to_owned()
+    }
+}
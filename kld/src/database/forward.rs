@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use crate::MillisatAmount;
 
 use lightning::events::HTLCDestination;
@@ -14,6 +16,7 @@ pub struct Forward {
     pub id: Uuid,
     pub inbound_channel_id: ChannelId,
     pub outbound_channel_id: Option<ChannelId>,
+    /// The amount forwarded on the outbound channel, the inbound HTLC was this plus the fee.
     pub amount: Option<MillisatAmount>,
     pub fee: Option<MillisatAmount>,
     pub status: ForwardStatus,
@@ -81,6 +84,16 @@ impl TryFrom<Row> for Forward {
     }
 }
 
+/// The kind of destination that a forward failed at, stored to aggregate the failures.
+pub fn failure_reason(htlc_destination: &HTLCDestination) -> &'static str {
+    match htlc_destination {
+        HTLCDestination::NextHopChannel { .. } => "NextHopChannel",
+        HTLCDestination::UnknownNextHop { .. } => "UnknownNextHop",
+        HTLCDestination::InvalidForward { .. } => "InvalidForward",
+        HTLCDestination::FailedPayment { .. } => "FailedPayment",
+    }
+}
+
 #[derive(Debug, ToSql, FromSql, PartialEq, Clone, Copy)]
 #[postgres(name = "forward_status")]
 pub enum ForwardStatus {
@@ -105,3 +118,33 @@ pub struct TotalForwards {
     pub amount: MillisatAmount,
     pub fee: MillisatAmount,
 }
+
+/// The forwards through a channel in a time window.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChannelForwardStats {
+    pub channel_id: ChannelId,
+    /// Succeeded forwards that came in through the channel.
+    pub inbound_count: u64,
+    pub inbound_amount: MillisatAmount,
+    /// Succeeded forwards that went out through the channel.
+    pub outbound_count: u64,
+    pub outbound_amount: MillisatAmount,
+    /// The fees are earned by the outbound channel as it is the one that sets them.
+    pub fee: MillisatAmount,
+    /// Failed forwards that came in through the channel by failure reason.
+    pub failures: BTreeMap<String, u64>,
+}
+
+impl ChannelForwardStats {
+    pub fn new(channel_id: ChannelId) -> ChannelForwardStats {
+        ChannelForwardStats {
+            channel_id,
+            inbound_count: 0,
+            inbound_amount: 0,
+            outbound_count: 0,
+            outbound_amount: 0,
+            fee: 0,
+            failures: BTreeMap::new(),
+        }
+    }
+}
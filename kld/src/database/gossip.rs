@@ -0,0 +1,37 @@
+use postgres_types::{FromSql, ToSql};
+use time::OffsetDateTime;
+use tokio_postgres::Row;
+
+use super::RowExt;
+
+/// When we first saw a channel announcement, or the current channel update for one direction of a channel.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GossipTimestamp {
+    pub short_channel_id: u64,
+    pub gossip_type: GossipType,
+    // The timestamp of the channel update itself, zero for announcements.
+    pub update_timestamp: u32,
+    pub seen: OffsetDateTime,
+}
+
+impl From<Row> for GossipTimestamp {
+    fn from(row: Row) -> Self {
+        GossipTimestamp {
+            short_channel_id: row.get::<&str, i64>("short_channel_id") as u64,
+            gossip_type: row.get("gossip_type"),
+            update_timestamp: row.get::<&str, i64>("update_timestamp") as u32,
+            seen: row.get_timestamp("seen"),
+        }
+    }
+}
+
+#[derive(Debug, ToSql, FromSql, PartialEq, Eq, Hash, Clone, Copy)]
+#[postgres(name = "gossip_type")]
+pub enum GossipType {
+    #[postgres(name = "announcement")]
+    Announcement,
+    #[postgres(name = "one_to_two")]
+    OneToTwo,
+    #[postgres(name = "two_to_one")]
+    TwoToOne,
+}
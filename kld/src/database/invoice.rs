@@ -8,6 +8,14 @@ use crate::MillisatAmount;
 
 use super::payment::Payment;
 
+/// Filter for the invoices that were paid, expired unpaid or can still be paid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvoiceStatusFilter {
+    Unpaid,
+    Paid,
+    Expired,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Invoice {
     pub payment_hash: PaymentHash,
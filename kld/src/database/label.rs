@@ -0,0 +1,89 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use postgres_types::{FromSql, ToSql};
+use tokio_postgres::Row;
+
+/// A user assigned label of a wallet record, as in BIP-329.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub label_type: LabelType,
+    /// The txid, address, pubkey, outpoint or xpub that is labelled.
+    pub reference: String,
+    pub label: Option<String>,
+    /// The descriptor of the wallet the record belongs to.
+    pub origin: Option<String>,
+    /// Only for outputs.
+    pub spendable: Option<bool>,
+}
+
+impl Label {
+    pub fn tx(txid: impl ToString, label: impl ToString) -> Label {
+        Label {
+            label_type: LabelType::Tx,
+            reference: txid.to_string(),
+            label: Some(label.to_string()),
+            origin: None,
+            spendable: None,
+        }
+    }
+}
+
+impl From<Row> for Label {
+    fn from(row: Row) -> Self {
+        Label {
+            label_type: row.get("label_type"),
+            reference: row.get("reference"),
+            label: row.get("label"),
+            origin: row.get("origin"),
+            spendable: row.get("spendable"),
+        }
+    }
+}
+
+#[derive(Debug, ToSql, FromSql, PartialEq, Eq, Clone, Copy)]
+#[postgres(name = "label_type")]
+pub enum LabelType {
+    #[postgres(name = "tx")]
+    Tx,
+    #[postgres(name = "addr")]
+    Addr,
+    #[postgres(name = "pubkey")]
+    Pubkey,
+    #[postgres(name = "input")]
+    Input,
+    #[postgres(name = "output")]
+    Output,
+    #[postgres(name = "xpub")]
+    Xpub,
+}
+
+impl FromStr for LabelType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tx" => Ok(LabelType::Tx),
+            "addr" => Ok(LabelType::Addr),
+            "pubkey" => Ok(LabelType::Pubkey),
+            "input" => Ok(LabelType::Input),
+            "output" => Ok(LabelType::Output),
+            "xpub" => Ok(LabelType::Xpub),
+            _ => Err(anyhow!("Unknown label type {s}")),
+        }
+    }
+}
+
+impl ToString for LabelType {
+    fn to_string(&self) -> String {
+        match self {
+            LabelType::Tx => "tx",
+            LabelType::Addr => "addr",
+            LabelType::Pubkey => "pubkey",
+            LabelType::Input => "input",
+            LabelType::Output => "output",
+            LabelType::Xpub => "xpub",
+        }
+        .to_owned()
+    }
+}
@@ -2,12 +2,22 @@ use crate::database::{microsecond_timestamp, to_primitive, RowExt};
 use crate::ldk::{ldk_error, ChainMonitor};
 use crate::logger::KldLogger;
 use crate::settings::Settings;
-use bitcoin_hashes::Hash;
+use crate::MillisatAmount;
+use bitcoin_hashes::{sha256, Hash};
 
-use super::forward::{Forward, ForwardStatus, TotalForwards};
-use super::invoice::Invoice;
-use super::payment::{Payment, PaymentDirection};
-use super::{DurableConnection, Params};
+use super::fee_bump::FeeBump;
+use super::forward::{failure_reason, ChannelForwardStats, Forward, ForwardStatus, TotalForwards};
+use super::gossip::GossipTimestamp;
+use super::invoice::{Invoice, InvoiceStatusFilter};
+use super::label::Label;
+use super::ledger::LedgerEntry;
+use super::node_announcement::NodeAnnouncementConfig;
+use super::payment::{Payment, PaymentDirection, PaymentStatus};
+use super::psbt::PendingPsbt;
+use super::scorer_parameters::ScorerParameters;
+use super::sweep::Sweep;
+use super::withdrawal::Withdrawal;
+use super::{DurableConnection, Pagination, Params};
 use anyhow::bail;
 use anyhow::{anyhow, Result};
 use bitcoin::secp256k1::PublicKey;
@@ -15,14 +25,16 @@ use bitcoin::BlockHash;
 use bitcoin::Txid;
 use lightning::chain::chaininterface::{BroadcasterInterface, FeeEstimator};
 use lightning::chain::chainmonitor::MonitorUpdateId;
-use lightning::chain::channelmonitor::{ChannelMonitor, ChannelMonitorUpdate};
+use lightning::chain::channelmonitor::{
+    ChannelMonitor, ChannelMonitorUpdate, CLOSED_CHANNEL_UPDATE_ID,
+};
 use lightning::chain::transaction::OutPoint;
 use lightning::chain::{self, ChannelMonitorUpdateStatus, Watch};
 use lightning::ln::channelmanager::{ChannelDetails, ChannelManager, ChannelManagerReadArgs};
 use lightning::ln::msgs::SocketAddress;
 use lightning::ln::ChannelId;
 use lightning::ln::PaymentHash;
-use lightning::routing::gossip::{NetworkGraph, NodeId};
+use lightning::routing::gossip::{ChannelInfo, NetworkGraph, NodeId, NodeInfo};
 use lightning::routing::router::Router;
 use lightning::routing::scoring::{
     ProbabilisticScorer, ProbabilisticScoringDecayParameters, WriteableScore,
@@ -33,20 +45,36 @@ use lightning::sign::{
 };
 use lightning::util::logger::Logger;
 use lightning::util::persist::Persister;
-use lightning::util::ser::ReadableArgs;
 use lightning::util::ser::Writeable;
-use log::{debug, error};
+use lightning::util::ser::{MaybeReadable, Readable, ReadableArgs};
+use log::{debug, error, info, warn};
 
-use super::peer::Peer;
+use super::peer::{Peer, PeerAddress, PeerEvent, PeerEventType};
 use super::{ChannelRecord, SpendableOutputRecord};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::{AsRef, TryInto};
+use std::future::Future;
 use std::io::Cursor;
 use std::ops::Deref;
-use std::sync::{Arc, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::SystemTime;
 use std::{fs, io};
+use time::OffsetDateTime;
 use tokio::runtime::Handle;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+// Rows per statement when writing gossip timestamps for the whole network graph.
+const GOSSIP_TIMESTAMP_BATCH_SIZE: usize = 1000;
+
+// Rows per statement when writing the channels and nodes of the network graph.
+const NETWORK_GRAPH_BATCH_SIZE: usize = 1000;
+
+// A withdrawal that is still being built after this long was interrupted, so its idempotency key can be reclaimed.
+const STALE_WITHDRAWAL_RESERVATION: time::Duration = time::Duration::minutes(10);
+
+// The serialized network graph starts with the version prefix and the chain hash.
+const NETWORK_GRAPH_HEADER_LEN: usize = 2 + 32;
 
 pub struct LdkDatabase {
     settings: Arc<Settings>,
@@ -54,6 +82,8 @@ pub struct LdkDatabase {
     // Persist graph/scorer gets called from a background thread in LDK so need a handle to the runtime.
     runtime: Handle,
     chain_monitor: OnceLock<Arc<ChainMonitor>>,
+    // What was last written of the network graph, so persisting it only writes the changes.
+    graph_digests: Mutex<GraphDigests>,
 }
 
 impl LdkDatabase {
@@ -63,6 +93,7 @@ impl LdkDatabase {
             durable_connection,
             runtime: Handle::current(),
             chain_monitor: OnceLock::new(),
+            graph_digests: Mutex::new(GraphDigests::default()),
         }
     }
 
@@ -83,56 +114,189 @@ impl LdkDatabase {
             .is_none())
     }
 
+    /// Record a successful connection to the peer at this address, which clears any backoff.
     pub async fn persist_peer(&self, peer: &Peer) -> Result<()> {
         self.durable_connection
             .get()
             .await
             .execute(
-                "UPSERT INTO peers (public_key, address) \
-            VALUES ($1, $2)",
+                "INSERT INTO peers (public_key, address, last_success, failures) \
+            VALUES ($1, $2, CURRENT_TIMESTAMP, 0) \
+            ON CONFLICT (public_key, address) \
+            DO UPDATE SET last_success = excluded.last_success, failures = 0",
                 &[&peer.public_key.encode(), &peer.address.encode()],
             )
             .await?;
         Ok(())
     }
 
-    pub async fn fetch_peer(&self, public_key: &PublicKey) -> Result<Option<Peer>> {
-        debug!("Fetching peer from database");
+    /// Record a failed connection attempt to a known address of the peer.
+    pub async fn persist_peer_failure(&self, peer: &Peer) -> Result<()> {
         self.durable_connection
             .get()
             .await
-            .query_opt(
-                "SELECT * FROM peers WHERE public_key = $1",
+            .execute(
+                "UPDATE peers \
+            SET last_failure = CURRENT_TIMESTAMP, failures = failures + 1 \
+            WHERE public_key = $1 AND address = $2",
+                &[&peer.public_key.encode(), &peer.address.encode()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// The addresses of the peer, most recently successful first.
+    pub async fn fetch_peer_addresses(&self, public_key: &PublicKey) -> Result<Vec<PeerAddress>> {
+        debug!("Fetching peer addresses from database");
+        self.durable_connection
+            .get()
+            .await
+            .query(
+                "SELECT * FROM peers WHERE public_key = $1 \
+                ORDER BY last_success DESC NULLS LAST",
                 &[&public_key.encode()],
             )
             .await?
-            .map(|row| {
-                let public_key: Vec<u8> = row.get("public_key");
-                let net_address: Vec<u8> = row.get("address");
-                Peer::deserialize(public_key, net_address)
-            })
-            .transpose()
+            .iter()
+            .map(PeerAddress::try_from)
+            .collect()
     }
 
-    pub async fn fetch_peers(&self) -> Result<HashMap<PublicKey, SocketAddress>> {
+    pub async fn fetch_peers(&self) -> Result<HashMap<PublicKey, Vec<PeerAddress>>> {
         debug!("Fetching peers from database");
-        let mut peers = HashMap::new();
+        let mut peers: HashMap<PublicKey, Vec<PeerAddress>> = HashMap::new();
         for row in self
             .durable_connection
             .get()
             .await
-            .query("SELECT * FROM peers", &[])
+            .query(
+                "SELECT * FROM peers ORDER BY last_success DESC NULLS LAST",
+                &[],
+            )
             .await?
         {
-            let public_key: Vec<u8> = row.get("public_key");
-            let address: Vec<u8> = row.get("address");
-            let peer = Peer::deserialize(public_key, address)?;
-            peers.insert(peer.public_key, peer.address);
+            let public_key = PublicKey::from_slice(row.get::<&str, &[u8]>("public_key"))?;
+            peers
+                .entry(public_key)
+                .or_default()
+                .push(PeerAddress::try_from(&row)?);
         }
         debug!("Fetched {} peers", peers.len());
         Ok(peers)
     }
 
+    pub async fn persist_peer_event(&self, event: &PeerEvent) -> Result<()> {
+        debug!(
+            "Persist peer event {} for {}",
+            event.event.to_string(),
+            event.public_key
+        );
+        self.durable_connection
+            .get()
+            .await
+            .execute(
+                "INSERT INTO peer_events (id, public_key, address, event, timestamp) \
+                VALUES ($1, $2, $3, $4, $5)",
+                &[
+                    &event.id,
+                    &event.public_key.encode(),
+                    &event.address.as_ref().map(|a| a.encode()),
+                    &event.event,
+                    &to_primitive(&event.timestamp),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Record a disconnection of every peer whose last event is a connection, so that the time the node was
+    /// down is not counted as uptime of its peers. Without a timestamp, after a crash, they are disconnected
+    /// just after the last event that the node recorded.
+    pub async fn close_peer_connections(&self, timestamp: Option<OffsetDateTime>) -> Result<()> {
+        let client = self.durable_connection.get().await;
+        let rows = client
+            .query(
+                "SELECT
+                    public_key,
+                    address,
+                    timestamp
+                FROM
+                    peer_events e
+                WHERE
+                    event = 'connected' AND timestamp = (
+                        SELECT max(timestamp) FROM peer_events WHERE public_key = e.public_key
+                    )",
+                &[],
+            )
+            .await?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let timestamp = match timestamp {
+            Some(timestamp) => timestamp,
+            None => {
+                let row = client
+                    .query_one("SELECT max(timestamp) AS timestamp FROM peer_events", &[])
+                    .await?;
+                row.get_timestamp("timestamp") + time::Duration::microseconds(1)
+            }
+        };
+        drop(client);
+        for row in rows {
+            let mut event = PeerEvent::new(
+                PublicKey::from_slice(row.get::<&str, &[u8]>("public_key"))?,
+                row.read_optional("address")?,
+                PeerEventType::Disconnected,
+            );
+            event.timestamp = timestamp.max(row.get_timestamp("timestamp"));
+            self.persist_peer_event(&event).await?;
+        }
+        Ok(())
+    }
+
+    /// Connection events since the given time, optionally for a single peer. For each peer the last
+    /// event before `since` is included as well, so that the connection state at that time is known.
+    pub async fn fetch_peer_events(
+        &self,
+        public_key: Option<&PublicKey>,
+        since: OffsetDateTime,
+    ) -> Result<Vec<PeerEvent>> {
+        let mut statement = "
+            SELECT
+                id,
+                public_key,
+                address,
+                event,
+                timestamp
+            FROM
+                peer_events e
+            WHERE
+                (timestamp >= $1 OR timestamp = (
+                    SELECT max(timestamp) FROM peer_events
+                    WHERE public_key = e.public_key AND timestamp < $1
+                ))
+            "
+        .to_string();
+        let mut params = Params::default();
+        params.push(to_primitive(&since));
+        if let Some(public_key) = public_key {
+            statement.push_str("AND public_key = $2 ");
+            params.push(public_key.encode());
+        }
+        statement.push_str("ORDER BY timestamp ASC");
+        let rows = self
+            .durable_connection
+            .get()
+            .await
+            .query(&statement, &params.to_params())
+            .await?;
+        let mut events = vec![];
+        for row in rows {
+            events.push(row.try_into()?);
+        }
+        Ok(events)
+    }
+
     pub async fn delete_peer(&self, public_key: &PublicKey) -> Result<()> {
         debug!("Delete peer");
         self.durable_connection
@@ -147,6 +311,89 @@ impl LdkDatabase {
         Ok(())
     }
 
+    pub async fn persist_node_announcement(&self, config: &NodeAnnouncementConfig) -> Result<()> {
+        debug!("Persist node announcement");
+        let addresses: Vec<Vec<u8>> = config.addresses.iter().map(|a| a.encode()).collect();
+        self.durable_connection
+            .get()
+            .await
+            .execute(
+                "UPSERT INTO node_announcement (id, alias, color, addresses, timestamp) \
+                VALUES ('node_announcement', $1, $2, $3, CURRENT_TIMESTAMP)",
+                &[&config.alias, &config.color, &addresses],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn fetch_node_announcement(&self) -> Result<Option<NodeAnnouncementConfig>> {
+        debug!("Fetching node announcement from database");
+        self.durable_connection
+            .wait()
+            .await
+            .query_opt("SELECT alias, color, addresses FROM node_announcement", &[])
+            .await?
+            .map(|row| {
+                let mut addresses = vec![];
+                for bytes in row.get::<&str, Vec<Vec<u8>>>("addresses") {
+                    let address = SocketAddress::read(&mut bytes.as_slice())
+                        .map_err(|e| anyhow!("{}", e))?
+                        .ok_or(anyhow!("Error parsing address"))?;
+                    addresses.push(address.into());
+                }
+                Ok(NodeAnnouncementConfig {
+                    alias: row.get("alias"),
+                    color: row.get("color"),
+                    addresses,
+                })
+            })
+            .transpose()
+    }
+
+    pub async fn persist_scorer_parameters(&self, parameters: &ScorerParameters) -> Result<()> {
+        debug!("Persist scorer parameters");
+        self.durable_connection
+            .get()
+            .await
+            .execute(
+                "UPSERT INTO scorer_parameters (\
+                id, \
+                base_penalty_msat, \
+                base_penalty_amount_multiplier_msat, \
+                liquidity_penalty_multiplier_msat, \
+                liquidity_penalty_amount_multiplier_msat, \
+                historical_liquidity_penalty_multiplier_msat, \
+                historical_liquidity_penalty_amount_multiplier_msat, \
+                liquidity_offset_half_life, \
+                historical_no_updates_half_life, \
+                timestamp) \
+                VALUES ('scorer_parameters', $1, $2, $3, $4, $5, $6, $7, $8, CURRENT_TIMESTAMP)",
+                &[
+                    &(parameters.base_penalty_msat as i64),
+                    &(parameters.base_penalty_amount_multiplier_msat as i64),
+                    &(parameters.liquidity_penalty_multiplier_msat as i64),
+                    &(parameters.liquidity_penalty_amount_multiplier_msat as i64),
+                    &(parameters.historical_liquidity_penalty_multiplier_msat as i64),
+                    &(parameters.historical_liquidity_penalty_amount_multiplier_msat as i64),
+                    &(parameters.liquidity_offset_half_life.as_secs() as i64),
+                    &(parameters.historical_no_updates_half_life.as_secs() as i64),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn fetch_scorer_parameters(&self) -> Result<Option<ScorerParameters>> {
+        debug!("Fetching scorer parameters from database");
+        Ok(self
+            .durable_connection
+            .wait()
+            .await
+            .query_opt("SELECT * FROM scorer_parameters", &[])
+            .await?
+            .map(ScorerParameters::from))
+    }
+
     pub async fn persist_initializing_channel(
         &self,
         initializing_channel_id: &ChannelId,
@@ -334,22 +581,35 @@ impl LdkDatabase {
         Ok(())
     }
 
-    pub async fn fetch_channel_history(&self) -> Result<Vec<ChannelRecord>> {
+    /// Closed channels, in the order they were closed. The time range is of the closing.
+    pub async fn fetch_channel_history(
+        &self,
+        channel_id: Option<ChannelId>,
+        pagination: Pagination,
+    ) -> Result<Vec<ChannelRecord>> {
+        let mut statement = "
+            SELECT
+                data,
+                open_timestamp,
+                update_timestamp,
+                closure_reason
+            FROM
+                channels
+            WHERE is_usable = false"
+            .to_string();
+        let mut params = Params::default();
+        if let Some(channel_id) = channel_id {
+            params.push(channel_id.0.to_vec());
+            statement.push_str(&format!(" AND channel_id = ${}", params.count()));
+        }
+        statement.push_str(&pagination.time_range("update_timestamp", &mut params));
+        statement.push_str(" ORDER BY update_timestamp, channel_id");
+        statement.push_str(&pagination.page(&mut params));
         let rows = self
             .durable_connection
             .get()
             .await
-            .query(
-                "SELECT
-                    data,
-                    open_timestamp,
-                    update_timestamp,
-                    closure_reason
-            FROM
-                channels
-            WHERE is_usable = false",
-                &[],
-            )
+            .query(&statement, &params.to_params())
             .await?;
 
         let mut outputs = vec![];
@@ -489,6 +749,7 @@ impl LdkDatabase {
             .query(
                 r#"SELECT
                 data,
+                channel_id,
                 is_spent
             FROM
                 spendable_outputs"#,
@@ -498,10 +759,29 @@ impl LdkDatabase {
 
         let mut outputs = vec![];
         for row in rows {
-            outputs.push(SpendableOutputRecord {
-                descriptor: row.read("data")?,
-                is_spent: row.get::<&str, bool>("is_spent"),
-            });
+            outputs.push(row.try_into()?);
+        }
+        Ok(outputs)
+    }
+
+    /// The spendable outputs of the transactions.
+    pub async fn fetch_spendable_outputs_by_txids(
+        &self,
+        txids: &[Txid],
+    ) -> Result<Vec<SpendableOutputRecord>> {
+        let txids: Vec<Vec<u8>> = txids.iter().map(|txid| txid.encode()).collect();
+        let rows = self
+            .durable_connection
+            .get()
+            .await
+            .query(
+                "SELECT data, channel_id, is_spent FROM spendable_outputs WHERE txid = ANY($1)",
+                &[&txids],
+            )
+            .await?;
+        let mut outputs = vec![];
+        for row in rows {
+            outputs.push(row.try_into()?);
         }
         Ok(outputs)
     }
@@ -540,11 +820,40 @@ impl LdkDatabase {
         Ok(())
     }
 
-    pub async fn fetch_invoices(&self, label: Option<String>) -> Result<Vec<Invoice>> {
+    /// Invoices oldest first, with their payments.
+    pub async fn fetch_invoices(
+        &self,
+        label: Option<String>,
+        status: Option<InvoiceStatusFilter>,
+        pagination: Pagination,
+    ) -> Result<Vec<Invoice>> {
         debug!("Fetching invoices from database");
         let connection = self.durable_connection.get().await;
         let mut params = Params::default();
-        let mut query = "
+        // Paginate the invoices before joining them with their payments.
+        let mut invoices_query = "SELECT * FROM invoices WHERE 1 = 1".to_string();
+        if let Some(label) = label {
+            params.push(label);
+            invoices_query.push_str(&format!(" AND label = ${}", params.count()));
+        }
+        let paid = "EXISTS (SELECT 1 FROM payments WHERE hash = payment_hash)";
+        let expired =
+            "timestamp + COALESCE(expiry, 3600) * INTERVAL '1 second' <= now()::TIMESTAMP";
+        match status {
+            Some(InvoiceStatusFilter::Paid) => invoices_query.push_str(&format!(" AND {paid}")),
+            Some(InvoiceStatusFilter::Unpaid) => {
+                invoices_query.push_str(&format!(" AND NOT {paid} AND NOT ({expired})"))
+            }
+            Some(InvoiceStatusFilter::Expired) => {
+                invoices_query.push_str(&format!(" AND NOT {paid} AND {expired}"))
+            }
+            None => {}
+        }
+        invoices_query.push_str(&pagination.time_range("timestamp", &mut params));
+        invoices_query.push_str(" ORDER BY timestamp, payment_hash");
+        invoices_query.push_str(&pagination.page(&mut params));
+        let query = format!(
+            "
             SELECT
                 i.label as invoice_label,
                 i.payment_hash,
@@ -563,14 +872,11 @@ impl LdkDatabase {
                 p.direction,
                 p.timestamp,
                 p.label
-            FROM invoices i
-            LEFT OUTER JOIN payments p ON i.payment_hash = p.hash"
-            .to_string();
-        if let Some(label) = &label {
-            params.push(label);
-            query.push_str(&format!("\nWHERE i.label = ${}", params.count()));
-        }
-        let mut invoices: HashMap<PaymentHash, Invoice> = HashMap::new();
+            FROM ({invoices_query}) i
+            LEFT OUTER JOIN payments p ON i.payment_hash = p.hash
+            ORDER BY i.timestamp, i.payment_hash, p.timestamp"
+        );
+        let mut invoices: Vec<Invoice> = vec![];
         for row in connection.query(&query, &params.to_params()).await? {
             let payment_hash: Vec<u8> = row.get("payment_hash");
             let payment_hash = PaymentHash(payment_hash.as_slice().try_into()?);
@@ -579,7 +885,10 @@ impl LdkDatabase {
             } else {
                 None
             };
-            if let Some(invoice) = invoices.get_mut(&payment_hash) {
+            if let Some(invoice) = invoices
+                .last_mut()
+                .filter(|invoice| invoice.payment_hash == payment_hash)
+            {
                 if let Some(payment) = payment {
                     invoice.payments.push(payment);
                 }
@@ -602,10 +911,10 @@ impl LdkDatabase {
                 if let Some(payment) = payment {
                     invoice.payments.push(payment);
                 }
-                invoices.insert(invoice.payment_hash, invoice);
+                invoices.push(invoice);
             }
         }
-        Ok(Vec::from_iter(invoices.into_values()))
+        Ok(invoices)
     }
 
     pub async fn persist_payment(&self, payment: &Payment) -> Result<()> {
@@ -643,10 +952,13 @@ impl LdkDatabase {
         Ok(())
     }
 
+    /// Payments oldest first. An empty list of statuses matches any status.
     pub async fn fetch_payments(
         &self,
         payment_hash: Option<PaymentHash>,
         direction: Option<PaymentDirection>,
+        statuses: &[PaymentStatus],
+        pagination: Pagination,
     ) -> Result<Vec<Payment>> {
         let connection = self.durable_connection.get().await;
         let mut payments = vec![];
@@ -670,12 +982,23 @@ impl LdkDatabase {
             .to_string();
         if let Some(hash) = &payment_hash {
             params.push(hash.0.to_vec());
-            query.push_str(&format!("AND p.hash = ${}", params.count()));
+            query.push_str(&format!(" AND p.hash = ${}", params.count()));
         }
         if let Some(direction) = direction {
             params.push(direction);
-            query.push_str(&format!("AND p.direction = ${}", params.count()));
+            query.push_str(&format!(" AND p.direction = ${}", params.count()));
+        }
+        if !statuses.is_empty() {
+            let mut placeholders = vec![];
+            for status in statuses {
+                params.push(*status);
+                placeholders.push(format!("${}", params.count()));
+            }
+            query.push_str(&format!(" AND p.status IN ({})", placeholders.join(", ")));
         }
+        query.push_str(&pagination.time_range("p.timestamp", &mut params));
+        query.push_str(" ORDER BY p.timestamp, p.id");
+        query.push_str(&pagination.page(&mut params));
         for row in connection
             .query(&query.to_string(), &params.to_params())
             .await?
@@ -688,6 +1011,7 @@ impl LdkDatabase {
     pub async fn persist_forward(&self, forward: Forward) -> Result<()> {
         debug!("Persist forward with ID {}", forward.id);
 
+        let reason = forward.htlc_destination.as_ref().map(failure_reason);
         let htlc_destination = if let Some(htlc_destination) = forward.htlc_destination {
             let mut bytes = vec![];
             htlc_destination.write(&mut bytes)?;
@@ -707,8 +1031,9 @@ impl LdkDatabase {
                     fee,
                     status,
                     htlc_destination,
+                    failure_reason,
                     timestamp
-                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
                 &[
                     &forward.id,
                     &forward.inbound_channel_id.0.to_vec(),
@@ -717,6 +1042,7 @@ impl LdkDatabase {
                     &(forward.fee.map(|x| x as i64)),
                     &forward.status,
                     &htlc_destination,
+                    &reason,
                     &to_primitive(&forward.timestamp),
                 ],
             )
@@ -724,7 +1050,13 @@ impl LdkDatabase {
         Ok(())
     }
 
-    pub async fn fetch_forwards(&self, status: Option<ForwardStatus>) -> Result<Vec<Forward>> {
+    /// Forwards oldest first, optionally only those in or out of the channel.
+    pub async fn fetch_forwards(
+        &self,
+        status: Option<ForwardStatus>,
+        channel_id: Option<ChannelId>,
+        pagination: Pagination,
+    ) -> Result<Vec<Forward>> {
         let mut statement = "
             SELECT
                 id,
@@ -737,14 +1069,23 @@ impl LdkDatabase {
                 timestamp
             FROM
                 forwards
-            "
-        .to_string();
+            WHERE 1 = 1"
+            .to_string();
         let mut params = Params::default();
         if let Some(status) = status {
-            statement.push_str("WHERE status = $1");
             params.push(status);
+            statement.push_str(&format!(" AND status = ${}", params.count()));
         }
-        statement.push_str("ORDER BY timestamp ASC");
+        if let Some(channel_id) = channel_id {
+            params.push(channel_id.0.to_vec());
+            statement.push_str(&format!(
+                " AND (inbound_channel_id = ${0} OR outbound_channel_id = ${0})",
+                params.count()
+            ));
+        }
+        statement.push_str(&pagination.time_range("timestamp", &mut params));
+        statement.push_str(" ORDER BY timestamp ASC, id");
+        statement.push_str(&pagination.page(&mut params));
         let mut forwards = vec![];
         let rows = self
             .durable_connection
@@ -777,90 +1118,808 @@ impl LdkDatabase {
             .into())
     }
 
-    pub async fn fetch_channel_monitors<T: EntropySource + SignerProvider>(
+    /// Forwards in the time window aggregated per channel, ordered by channel id.
+    pub async fn fetch_forward_stats(
         &self,
-        source: &T,
-    ) -> Result<
-        Vec<(
-            BlockHash,
-            ChannelMonitor<<T as SignerProvider>::EcdsaSigner>,
-        )>,
-    > {
-        let rows = self
-            .durable_connection
-            .wait()
-            .await
+        from: Option<OffsetDateTime>,
+        to: Option<OffsetDateTime>,
+    ) -> Result<Vec<ChannelForwardStats>> {
+        let window = Pagination {
+            from,
+            to,
+            ..Default::default()
+        };
+        let mut params = Params::default();
+        let time_range = window.time_range("timestamp", &mut params);
+        let params = params.to_params();
+        let client = self.durable_connection.get().await;
+
+        let mut stats: BTreeMap<[u8; 32], ChannelForwardStats> = BTreeMap::new();
+
+        let inbound = client
             .query(
-                "SELECT out_point, monitor \
-            FROM channel_monitors",
-                &[],
+                &format!(
+                    "SELECT
+                        inbound_channel_id AS channel_id,
+                        count(*) AS count,
+                        CAST(sum(amount + fee) AS INT) AS amount
+                    FROM forwards
+                    WHERE status = 'succeeded'{time_range}
+                    GROUP BY inbound_channel_id"
+                ),
+                &params,
             )
             .await?;
-        let mut monitors: Vec<(
-            BlockHash,
-            ChannelMonitor<<T as SignerProvider>::EcdsaSigner>,
-        )> = vec![];
-        for row in rows {
-            let out_point: Vec<u8> = row.get("out_point");
+        for row in &inbound {
+            let channel = channel_stats(&mut stats, row)?;
+            channel.inbound_count = row.get::<&str, i64>("count") as u64;
+            channel.inbound_amount = row.get::<&str, i64>("amount") as MillisatAmount;
+        }
 
-            let (txid_bytes, index_bytes) = out_point.split_at(32);
-            let txid = Txid::from_raw_hash(bitcoin_hashes::sha256d::Hash::from_slice(txid_bytes)?);
-            let index = u16::from_be_bytes(index_bytes.try_into().unwrap());
+        let outbound = client
+            .query(
+                &format!(
+                    "SELECT
+                        outbound_channel_id AS channel_id,
+                        count(*) AS count,
+                        CAST(sum(amount) AS INT) AS amount,
+                        CAST(sum(fee) AS INT) AS fee
+                    FROM forwards
+                    WHERE status = 'succeeded' AND outbound_channel_id IS NOT NULL{time_range}
+                    GROUP BY outbound_channel_id"
+                ),
+                &params,
+            )
+            .await?;
+        for row in &outbound {
+            let channel = channel_stats(&mut stats, row)?;
+            channel.outbound_count = row.get::<&str, i64>("count") as u64;
+            channel.outbound_amount = row.get::<&str, i64>("amount") as MillisatAmount;
+            channel.fee = row.get::<&str, i64>("fee") as MillisatAmount;
+        }
 
-            let monitor: Vec<u8> = row.get("monitor");
-            let mut buffer = Cursor::new(&monitor);
-            match <(
-                BlockHash,
-                ChannelMonitor<<T as SignerProvider>::EcdsaSigner>,
-            )>::read(&mut buffer, (source, source))
-            {
-                Ok((blockhash, channel_monitor)) => {
-                    if channel_monitor.get_funding_txo().0.txid != txid
-                        || channel_monitor.get_funding_txo().0.index != index
-                    {
-                        bail!("Unable to find ChannelMonitor for: {}:{}", txid, index);
-                    }
-                    monitors.push((blockhash, channel_monitor));
+        // Failures recorded before the reason was stored are reported as unknown.
+        let failures = client
+            .query(
+                &format!(
+                    "SELECT
+                        inbound_channel_id AS channel_id,
+                        COALESCE(failure_reason, 'Unknown') AS reason,
+                        count(*) AS count
+                    FROM forwards
+                    WHERE status = 'failed'{time_range}
+                    GROUP BY inbound_channel_id, failure_reason"
+                ),
+                &params,
+            )
+            .await?;
+        for row in &failures {
+            let channel = channel_stats(&mut stats, row)?;
+            channel
+                .failures
+                .insert(row.get("reason"), row.get::<&str, i64>("count") as u64);
+        }
+
+        Ok(stats.into_values().collect())
+    }
+
+    pub async fn persist_gossip_timestamps(&self, timestamps: &[GossipTimestamp]) -> Result<()> {
+        debug!("Persist {} gossip timestamps", timestamps.len());
+        for chunk in timestamps.chunks(GOSSIP_TIMESTAMP_BATCH_SIZE) {
+            let mut statement = "UPSERT INTO gossip_timestamps \
+                (short_channel_id, gossip_type, update_timestamp, seen) VALUES "
+                .to_string();
+            let mut params = Params::default();
+            for (i, timestamp) in chunk.iter().enumerate() {
+                if i > 0 {
+                    statement.push(',');
                 }
-                Err(e) => bail!("Failed to deserialize ChannelMonitor: {}", e),
+                let n = params.count();
+                statement.push_str(&format!("(${}, ${}, ${}, ${})", n + 1, n + 2, n + 3, n + 4));
+                params.push(timestamp.short_channel_id as i64);
+                params.push(timestamp.gossip_type);
+                params.push(timestamp.update_timestamp as i64);
+                params.push(to_primitive(&timestamp.seen));
             }
+            self.durable_connection
+                .get()
+                .await
+                .execute(&statement, &params.to_params())
+                .await?;
         }
-        Ok(monitors)
+        Ok(())
     }
 
-    pub async fn fetch_channel_manager<
-        M: Deref,
-        T: Deref,
-        ES: Deref,
-        NS: Deref,
-        SP: Deref,
-        F: Deref,
-        R: Deref,
-        L: Deref,
-    >(
-        &self,
-        read_args: ChannelManagerReadArgs<'_, M, T, ES, NS, SP, F, R, L>,
-    ) -> Result<(BlockHash, ChannelManager<M, T, ES, NS, SP, F, R, L>)>
-    where
-        <M as Deref>::Target: Watch<<SP::Target as SignerProvider>::EcdsaSigner>,
-        <T as Deref>::Target: BroadcasterInterface,
-        <ES as Deref>::Target: EntropySource,
-        <NS as Deref>::Target: NodeSigner,
-        <SP as Deref>::Target: SignerProvider,
-        <F as Deref>::Target: FeeEstimator,
-        <R as Deref>::Target: Router,
-        <L as Deref>::Target: Logger,
-    {
-        let row = self
+    pub async fn fetch_gossip_timestamps(&self) -> Result<Vec<GossipTimestamp>> {
+        Ok(self
             .durable_connection
             .get()
             .await
-            .query_one(
-                "SELECT manager \
-            FROM channel_manager",
+            .query(
+                "SELECT short_channel_id, gossip_type, update_timestamp, seen \
+                FROM gossip_timestamps",
                 &[],
             )
-            .await?;
+            .await?
+            .into_iter()
+            .map(GossipTimestamp::from)
+            .collect())
+    }
+
+    /// Remove the gossip timestamps of channels that have been pruned from the network graph.
+    pub async fn delete_gossip_timestamps(&self, short_channel_ids: &[u64]) -> Result<()> {
+        debug!(
+            "Delete gossip timestamps for {} channels",
+            short_channel_ids.len()
+        );
+        for chunk in short_channel_ids.chunks(GOSSIP_TIMESTAMP_BATCH_SIZE) {
+            let short_channel_ids: Vec<i64> = chunk.iter().map(|id| *id as i64).collect();
+            self.durable_connection
+                .get()
+                .await
+                .execute(
+                    "DELETE FROM gossip_timestamps WHERE short_channel_id = ANY($1)",
+                    &[&short_channel_ids],
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn persist_psbt(&self, psbt: &PendingPsbt) -> Result<()> {
+        debug!("Persist PSBT {}", psbt.id);
+        self.durable_connection
+            .get()
+            .await
+            .execute(
+                "UPSERT INTO psbts (\
+                id, \
+                psbt, \
+                purpose, \
+                txid, \
+                temporary_channel_id, \
+                counterparty, \
+                completed, \
+                created, \
+                expires) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+                &[
+                    &psbt.id,
+                    &psbt.psbt.serialize(),
+                    &psbt.purpose,
+                    &psbt.txid().encode(),
+                    &psbt.temporary_channel_id.map(|id| id.0.to_vec()),
+                    &psbt.counterparty.map(|pk| pk.encode()),
+                    &psbt.completed,
+                    &to_primitive(&psbt.created),
+                    &to_primitive(&psbt.expires),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn fetch_psbt(&self, id: &Uuid) -> Result<Option<PendingPsbt>> {
+        self.durable_connection
+            .get()
+            .await
+            .query_opt("SELECT * FROM psbts WHERE id = $1", &[id])
+            .await?
+            .map(PendingPsbt::try_from)
+            .transpose()
+    }
+
+    /// PSBTs that have not been submitted and have not expired, oldest first.
+    pub async fn fetch_pending_psbts(&self) -> Result<Vec<PendingPsbt>> {
+        let rows = self
+            .durable_connection
+            .get()
+            .await
+            .query(
+                "SELECT * FROM psbts WHERE completed = false AND expires > $1 ORDER BY created",
+                &[&to_primitive(&microsecond_timestamp())],
+            )
+            .await?;
+        let mut psbts = vec![];
+        for row in rows {
+            psbts.push(row.try_into()?);
+        }
+        Ok(psbts)
+    }
+
+    /// The submitted PSBT that created the transaction.
+    pub async fn fetch_psbt_by_txid(&self, txid: &Txid) -> Result<Option<PendingPsbt>> {
+        self.durable_connection
+            .get()
+            .await
+            .query_opt(
+                "SELECT * FROM psbts WHERE txid = $1 AND completed = true",
+                &[&txid.encode()],
+            )
+            .await?
+            .map(PendingPsbt::try_from)
+            .transpose()
+    }
+
+    /// The channel funded by the transaction, its temporary id if the channel is still pending.
+    pub async fn fetch_funding_channel_id(&self, txid: &Txid) -> Result<Option<ChannelId>> {
+        let row = self
+            .durable_connection
+            .get()
+            .await
+            .query_opt(
+                "SELECT initializing_channel_id, channel_id FROM initializing_channels WHERE txid = $1",
+                &[&txid.encode()],
+            )
+            .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let channel_id: [u8; 32] = row
+            .get::<&str, Option<&[u8]>>("channel_id")
+            .unwrap_or(row.get::<&str, &[u8]>("initializing_channel_id"))
+            .try_into()?;
+        Ok(Some(ChannelId::from_bytes(channel_id)))
+    }
+
+    pub async fn persist_fee_bump(&self, fee_bump: &FeeBump) -> Result<()> {
+        debug!(
+            "Persist fee bump {} of {}",
+            fee_bump.txid, fee_bump.original_txid
+        );
+        self.durable_connection
+            .get()
+            .await
+            .execute(
+                "INSERT INTO fee_bumps (\
+                txid, \
+                original_txid, \
+                method, \
+                fee_rate, \
+                fee, \
+                channel_id, \
+                psbt_id, \
+                created) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                &[
+                    &fee_bump.txid.encode(),
+                    &fee_bump.original_txid.encode(),
+                    &fee_bump.method,
+                    &(fee_bump.fee_rate as i64),
+                    &(fee_bump.fee as i64),
+                    &fee_bump.channel_id.map(|id| id.0.to_vec()),
+                    &fee_bump.psbt_id,
+                    &to_primitive(&fee_bump.created),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// The fee bump that created the transaction.
+    pub async fn fetch_fee_bump(&self, txid: &Txid) -> Result<Option<FeeBump>> {
+        self.durable_connection
+            .get()
+            .await
+            .query_opt("SELECT * FROM fee_bumps WHERE txid = $1", &[&txid.encode()])
+            .await?
+            .map(FeeBump::try_from)
+            .transpose()
+    }
+
+    /// Reserve the idempotency key of a withdrawal, false if it was already used. A reservation without a transaction
+    /// that is older than STALE_WITHDRAWAL_RESERVATION was left by a crash and is taken over.
+    pub async fn reserve_withdrawal(
+        &self,
+        idempotency_key: &str,
+        now: OffsetDateTime,
+    ) -> Result<bool> {
+        let rows = self
+            .durable_connection
+            .get()
+            .await
+            .execute(
+                "INSERT INTO withdrawals (idempotency_key, created) VALUES ($1, $2) \
+                ON CONFLICT (idempotency_key) DO UPDATE SET created = excluded.created \
+                WHERE withdrawals.tx IS NULL AND withdrawals.created < $3",
+                &[
+                    &idempotency_key,
+                    &to_primitive(&now),
+                    &to_primitive(&(now - STALE_WITHDRAWAL_RESERVATION)),
+                ],
+            )
+            .await?;
+        if rows == 1 {
+            debug!("Reserved withdrawal {idempotency_key}");
+        }
+        Ok(rows == 1)
+    }
+
+    pub async fn persist_withdrawal_tx(
+        &self,
+        idempotency_key: &str,
+        tx: &bitcoin::Transaction,
+    ) -> Result<()> {
+        debug!(
+            "Persist withdrawal {idempotency_key} with txid {}",
+            tx.txid()
+        );
+        // Only one of two requests that both reserved the key, after one was taken over as stale, gets to broadcast.
+        let rows = self
+            .durable_connection
+            .get()
+            .await
+            .execute(
+                "UPDATE withdrawals SET txid = $1, tx = $2 WHERE idempotency_key = $3 AND tx IS NULL",
+                &[&tx.txid().encode(), &tx.encode(), &idempotency_key],
+            )
+            .await?;
+        if rows != 1 {
+            bail!("Withdrawal {idempotency_key} was already made");
+        }
+        Ok(())
+    }
+
+    pub async fn fetch_withdrawal(&self, idempotency_key: &str) -> Result<Option<Withdrawal>> {
+        self.durable_connection
+            .get()
+            .await
+            .query_opt(
+                "SELECT * FROM withdrawals WHERE idempotency_key = $1",
+                &[&idempotency_key],
+            )
+            .await?
+            .map(Withdrawal::try_from)
+            .transpose()
+    }
+
+    /// Release the idempotency key of a withdrawal that failed.
+    pub async fn delete_withdrawal(&self, idempotency_key: &str) -> Result<()> {
+        self.durable_connection
+            .get()
+            .await
+            .execute(
+                "DELETE FROM withdrawals WHERE idempotency_key = $1 AND tx IS NULL",
+                &[&idempotency_key],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn persist_sweep(&self, sweep: &Sweep) -> Result<()> {
+        debug!("Persist sweep {} to {}", sweep.txid, sweep.address);
+        self.durable_connection
+            .get()
+            .await
+            .execute(
+                "INSERT INTO sweeps (\
+                txid, \
+                address, \
+                address_index, \
+                amount, \
+                fee_rate, \
+                created) \
+                VALUES ($1, $2, $3, $4, $5, $6)",
+                &[
+                    &sweep.txid.encode(),
+                    &sweep.address,
+                    &(sweep.address_index as i64),
+                    &(sweep.amount as i64),
+                    &(sweep.fee_rate as i64),
+                    &to_primitive(&sweep.created),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// All the sweeps to cold storage, oldest first.
+    pub async fn fetch_sweeps(&self) -> Result<Vec<Sweep>> {
+        let rows = self
+            .durable_connection
+            .get()
+            .await
+            .query("SELECT * FROM sweeps ORDER BY created", &[])
+            .await?;
+        let mut sweeps = vec![];
+        for row in rows {
+            sweeps.push(row.try_into()?);
+        }
+        Ok(sweeps)
+    }
+
+    /// Index of the cold storage address for the next sweep, so that addresses are not reused.
+    pub async fn next_sweep_address_index(&self) -> Result<u32> {
+        let row = self
+            .durable_connection
+            .get()
+            .await
+            .query_one("SELECT MAX(address_index) FROM sweeps", &[])
+            .await?;
+        Ok(row
+            .get::<usize, Option<i64>>(0)
+            .map_or(0, |index| index as u32 + 1))
+    }
+
+    /// All the fee bumps of the transaction, oldest first.
+    pub async fn fetch_fee_bumps(&self, original_txid: &Txid) -> Result<Vec<FeeBump>> {
+        let rows = self
+            .durable_connection
+            .get()
+            .await
+            .query(
+                "SELECT * FROM fee_bumps WHERE original_txid = $1 ORDER BY created",
+                &[&original_txid.encode()],
+            )
+            .await?;
+        let mut fee_bumps = vec![];
+        for row in rows {
+            fee_bumps.push(row.try_into()?);
+        }
+        Ok(fee_bumps)
+    }
+
+    /// The funding transactions among the txids with their vout, when known, and channel id.
+    pub async fn fetch_funding_transactions(
+        &self,
+        txids: &[Txid],
+    ) -> Result<Vec<(Txid, Option<u32>, ChannelId)>> {
+        let txids: Vec<Vec<u8>> = txids.iter().map(|txid| txid.encode()).collect();
+        let rows = self
+            .durable_connection
+            .get()
+            .await
+            .query(
+                "SELECT txid, vout, initializing_channel_id, channel_id FROM initializing_channels WHERE txid = ANY($1)",
+                &[&txids],
+            )
+            .await?;
+        let mut funding = vec![];
+        for row in rows {
+            let channel_id: [u8; 32] = row
+                .get::<&str, Option<&[u8]>>("channel_id")
+                .unwrap_or(row.get::<&str, &[u8]>("initializing_channel_id"))
+                .try_into()?;
+            funding.push((
+                row.read("txid")?,
+                row.get::<&str, Option<i32>>("vout").map(|vout| vout as u32),
+                ChannelId::from_bytes(channel_id),
+            ));
+        }
+        Ok(funding)
+    }
+
+    /// The fee bumps that created any of the transactions.
+    pub async fn fetch_fee_bumps_by_txids(&self, txids: &[Txid]) -> Result<Vec<FeeBump>> {
+        let txids: Vec<Vec<u8>> = txids.iter().map(|txid| txid.encode()).collect();
+        let rows = self
+            .durable_connection
+            .get()
+            .await
+            .query("SELECT * FROM fee_bumps WHERE txid = ANY($1)", &[&txids])
+            .await?;
+        let mut fee_bumps = vec![];
+        for row in rows {
+            fee_bumps.push(row.try_into()?);
+        }
+        Ok(fee_bumps)
+    }
+
+    pub async fn persist_labels(&self, labels: &[Label]) -> Result<()> {
+        debug!("Persist {} labels", labels.len());
+        let client = self.durable_connection.get().await;
+        for label in labels {
+            client
+                .execute(
+                    "UPSERT INTO labels (label_type, reference, label, origin, spendable) \
+                    VALUES ($1, $2, $3, $4, $5)",
+                    &[
+                        &label.label_type,
+                        &label.reference,
+                        &label.label,
+                        &label.origin,
+                        &label.spendable,
+                    ],
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn fetch_labels(&self) -> Result<Vec<Label>> {
+        Ok(self
+            .durable_connection
+            .get()
+            .await
+            .query("SELECT * FROM labels ORDER BY label_type, reference", &[])
+            .await?
+            .into_iter()
+            .map(Label::from)
+            .collect())
+    }
+
+    /// Append the entries that are not in the ledger yet, recorded entries are never changed.
+    pub async fn persist_ledger_entries(&self, entries: &[LedgerEntry]) -> Result<()> {
+        let client = self.durable_connection.get().await;
+        for entry in entries {
+            client
+                .execute(
+                    "INSERT INTO ledger (\
+                    entry_type, \
+                    reference, \
+                    amount, \
+                    fee, \
+                    channel_id, \
+                    timestamp, \
+                    created) \
+                    VALUES ($1, $2, $3, $4, $5, $6, $7) \
+                    ON CONFLICT DO NOTHING",
+                    &[
+                        &entry.entry_type,
+                        &entry.reference,
+                        &entry.amount,
+                        &(entry.fee as i64),
+                        &entry.channel_id.map(|id| id.0.to_vec()),
+                        &to_primitive(&entry.timestamp),
+                        &to_primitive(&microsecond_timestamp()),
+                    ],
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// The ledger entries from (inclusive) and to (exclusive) the times, oldest first.
+    pub async fn fetch_ledger(
+        &self,
+        from: Option<OffsetDateTime>,
+        to: Option<OffsetDateTime>,
+    ) -> Result<Vec<LedgerEntry>> {
+        let mut statement = "SELECT * FROM ledger WHERE 1 = 1 ".to_string();
+        let mut params = Params::default();
+        if let Some(from) = from {
+            params.push(to_primitive(&from));
+            statement.push_str(&format!("AND timestamp >= ${} ", params.count()));
+        }
+        if let Some(to) = to {
+            params.push(to_primitive(&to));
+            statement.push_str(&format!("AND timestamp < ${} ", params.count()));
+        }
+        statement.push_str("ORDER BY timestamp, entry_type, reference");
+        let rows = self
+            .durable_connection
+            .get()
+            .await
+            .query(&statement, &params.to_params())
+            .await?;
+        let mut entries = vec![];
+        for row in rows {
+            entries.push(row.try_into()?);
+        }
+        Ok(entries)
+    }
+
+    /// The channel monitors with the updates since they were last written applied. Then the monitors are
+    /// written again so that the updates are not replayed on the next start.
+    pub async fn fetch_channel_monitors<T: EntropySource + SignerProvider, B: Deref, F: Deref>(
+        &self,
+        source: &T,
+        broadcaster: &B,
+        fee_estimator: &F,
+    ) -> Result<
+        Vec<(
+            BlockHash,
+            ChannelMonitor<<T as SignerProvider>::EcdsaSigner>,
+        )>,
+    >
+    where
+        B::Target: BroadcasterInterface,
+        F::Target: FeeEstimator,
+    {
+        let client = self.durable_connection.wait().await;
+        let rows = client
+            .query(
+                "SELECT out_point, monitor \
+            FROM channel_monitors",
+                &[],
+            )
+            .await?;
+        let mut updates: HashMap<Vec<u8>, Vec<(i64, Vec<u8>)>> = HashMap::new();
+        for row in client
+            .query(
+                "SELECT out_point, update, update_id \
+                FROM channel_monitor_updates \
+                ORDER BY out_point, update_id",
+                &[],
+            )
+            .await?
+        {
+            updates
+                .entry(row.get("out_point"))
+                .or_default()
+                .push((row.get("update_id"), row.get("update")));
+        }
+        drop(client);
+
+        let mut monitors: Vec<(
+            BlockHash,
+            ChannelMonitor<<T as SignerProvider>::EcdsaSigner>,
+        )> = vec![];
+        for row in rows {
+            let out_point: Vec<u8> = row.get("out_point");
+
+            let (txid_bytes, index_bytes) = out_point.split_at(32);
+            let txid = Txid::from_raw_hash(bitcoin_hashes::sha256d::Hash::from_slice(txid_bytes)?);
+            let index = u16::from_be_bytes(index_bytes.try_into().unwrap());
+
+            let monitor: Vec<u8> = row.get("monitor");
+            let mut buffer = Cursor::new(&monitor);
+            let (blockhash, channel_monitor) = match <(
+                BlockHash,
+                ChannelMonitor<<T as SignerProvider>::EcdsaSigner>,
+            )>::read(
+                &mut buffer, (source, source)
+            ) {
+                Ok((blockhash, channel_monitor)) => {
+                    if channel_monitor.get_funding_txo().0.txid != txid
+                        || channel_monitor.get_funding_txo().0.index != index
+                    {
+                        bail!("Unable to find ChannelMonitor for: {}:{}", txid, index);
+                    }
+                    (blockhash, channel_monitor)
+                }
+                Err(e) => bail!("Failed to deserialize ChannelMonitor: {}", e),
+            };
+
+            if let Some(updates) = updates.remove(&out_point) {
+                let mut applied = 0;
+                for (update_id, update) in updates {
+                    let latest_update_id = channel_monitor.get_latest_update_id();
+                    // Updates written after the monitor that includes them are deleted here.
+                    if update_id <= stored_update_id(latest_update_id) {
+                        continue;
+                    }
+                    // An update that was not stored was never completed, so neither were the ones after it.
+                    if update_id as u64 != latest_update_id + 1 {
+                        warn!(
+                            "Missing update {} for ChannelMonitor {}:{}",
+                            latest_update_id + 1,
+                            txid,
+                            index
+                        );
+                        break;
+                    }
+                    let update = ChannelMonitorUpdate::read(&mut Cursor::new(&update))
+                        .map_err(|e| anyhow!("Failed to deserialize ChannelMonitorUpdate: {e}"))?;
+                    channel_monitor
+                        .update_monitor(&update, broadcaster, fee_estimator, &KldLogger::global())
+                        .map_err(|_| {
+                            anyhow!(
+                                "Failed to apply update {} to ChannelMonitor {}:{}",
+                                update_id,
+                                txid,
+                                index
+                            )
+                        })?;
+                    applied += 1;
+                }
+                info!(
+                    "Applied {} updates to ChannelMonitor {}:{}",
+                    applied, txid, index
+                );
+                let mut monitor_buf = vec![];
+                channel_monitor.write(&mut monitor_buf)?;
+                write_channel_monitor(
+                    &self.durable_connection,
+                    &out_point,
+                    &monitor_buf,
+                    channel_monitor.get_latest_update_id(),
+                )
+                .await?;
+            }
+            monitors.push((blockhash, channel_monitor));
+        }
+        Ok(monitors)
+    }
+
+    /// Write the whole monitor of the channel and delete the updates that it includes.
+    pub async fn persist_channel_monitor<ChannelSigner: WriteableEcdsaChannelSigner>(
+        &self,
+        funding_txo: OutPoint,
+        monitor: &ChannelMonitor<ChannelSigner>,
+    ) -> Result<()> {
+        let mut out_point_buf = vec![];
+        funding_txo.write(&mut out_point_buf)?;
+        let mut monitor_buf = vec![];
+        monitor.write(&mut monitor_buf)?;
+        write_channel_monitor(
+            &self.durable_connection,
+            &out_point_buf,
+            &monitor_buf,
+            monitor.get_latest_update_id(),
+        )
+        .await
+    }
+
+    /// Append an update to the persisted monitor of the channel.
+    pub async fn persist_channel_monitor_update(
+        &self,
+        funding_txo: OutPoint,
+        update: &ChannelMonitorUpdate,
+    ) -> Result<()> {
+        let mut out_point_buf = vec![];
+        funding_txo.write(&mut out_point_buf)?;
+        let mut update_buf = vec![];
+        update.write(&mut update_buf)?;
+        write_channel_monitor_update(
+            &self.durable_connection,
+            &out_point_buf,
+            &update_buf,
+            update.update_id,
+        )
+        .await
+    }
+
+    // LDK is told that the monitor is persisted once the write completes.
+    fn complete_in_background(
+        &self,
+        funding_txo: OutPoint,
+        update_id: MonitorUpdateId,
+        write: impl Future<Output = Result<()>> + Send + 'static,
+    ) -> ChannelMonitorUpdateStatus {
+        let chain_monitor = self
+            .chain_monitor
+            .get()
+            .expect("bad initialisation")
+            .clone();
+        tokio::spawn(async move {
+            match write.await {
+                Ok(()) => {
+                    if let Err(e) = chain_monitor.channel_monitor_updated(funding_txo, update_id) {
+                        error!("Failed to update chain monitor: {}", ldk_error(e));
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to persist channel update: {e}");
+                }
+            }
+        });
+        ChannelMonitorUpdateStatus::InProgress
+    }
+
+    pub async fn fetch_channel_manager<
+        M: Deref,
+        T: Deref,
+        ES: Deref,
+        NS: Deref,
+        SP: Deref,
+        F: Deref,
+        R: Deref,
+        L: Deref,
+    >(
+        &self,
+        read_args: ChannelManagerReadArgs<'_, M, T, ES, NS, SP, F, R, L>,
+    ) -> Result<(BlockHash, ChannelManager<M, T, ES, NS, SP, F, R, L>)>
+    where
+        <M as Deref>::Target: Watch<<SP::Target as SignerProvider>::EcdsaSigner>,
+        <T as Deref>::Target: BroadcasterInterface,
+        <ES as Deref>::Target: EntropySource,
+        <NS as Deref>::Target: NodeSigner,
+        <SP as Deref>::Target: SignerProvider,
+        <F as Deref>::Target: FeeEstimator,
+        <R as Deref>::Target: Router,
+        <L as Deref>::Target: Logger,
+    {
+        let row = self
+            .durable_connection
+            .get()
+            .await
+            .query_one(
+                "SELECT manager \
+            FROM channel_manager",
+                &[],
+            )
+            .await?;
         let manager: Vec<u8> = row.get("manager");
         <(BlockHash, ChannelManager<M, T, ES, NS, SP, F, R, L>)>::read(
             &mut Cursor::new(manager),
@@ -870,15 +1929,143 @@ impl LdkDatabase {
     }
 
     pub async fn fetch_graph(&self) -> Result<Option<NetworkGraph<Arc<KldLogger>>>> {
-        match fs::read(format!("{}/network_graph", self.settings.data_dir)) {
-            Ok(bytes) => {
-                let graph = NetworkGraph::read(&mut Cursor::new(bytes), KldLogger::global())
-                    .map_err(|e| anyhow!(e))?;
-                Ok(Some(graph))
+        let connection = self.durable_connection.wait().await;
+        let Some(row) = connection
+            .query_opt("SELECT header, trailer FROM network_graph", &[])
+            .await?
+        else {
+            drop(connection);
+            return self.migrate_graph_from_disk().await;
+        };
+        let mut parts = GraphParts {
+            header: row.get("header"),
+            trailer: row.get("trailer"),
+            ..Default::default()
+        };
+        for row in connection
+            .query(
+                "SELECT short_channel_id, data FROM network_graph_channels",
+                &[],
+            )
+            .await?
+        {
+            let short_channel_id: i64 = row.get("short_channel_id");
+            parts
+                .channels
+                .push((short_channel_id as u64, row.get("data")));
+        }
+        for row in connection
+            .query("SELECT node_id, data FROM network_graph_nodes", &[])
+            .await?
+        {
+            let node_id = NodeId::from_slice(row.get("node_id")).map_err(|e| anyhow!(e))?;
+            parts.nodes.push((node_id, row.get("data")));
+        }
+        let graph = NetworkGraph::read(&mut Cursor::new(parts.join()), KldLogger::global())
+            .map_err(|e| anyhow!(e))?;
+        *self.graph_digests.lock().unwrap_or_else(|e| e.into_inner()) = GraphDigests::from(&parts);
+        Ok(Some(graph))
+    }
+
+    /// Earlier versions wrote the network graph to the data directory.
+    async fn migrate_graph_from_disk(&self) -> Result<Option<NetworkGraph<Arc<KldLogger>>>> {
+        let path = format!("{}/network_graph", self.settings.data_dir);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(anyhow!(e)),
+        };
+        let graph = NetworkGraph::read(&mut Cursor::new(&bytes), KldLogger::global())
+            .map_err(|e| anyhow!(e))?;
+        self.persist_graph_bytes(&bytes).await?;
+        fs::remove_file(&path)?;
+        info!("Migrated network graph from {path} to the database");
+        Ok(Some(graph))
+    }
+
+    /// Write the channels and nodes that changed since the last persist and delete the removed ones.
+    async fn persist_graph_bytes(&self, bytes: &[u8]) -> Result<()> {
+        let parts = GraphParts::split(bytes)?;
+        let digests = GraphDigests::from(&parts);
+        let (channels, removed_channels, nodes, removed_nodes) = {
+            let persisted = self.graph_digests.lock().unwrap_or_else(|e| e.into_inner());
+            (
+                changed_rows(&parts.channels, &digests.channels, &persisted.channels),
+                removed_keys(&digests.channels, &persisted.channels),
+                changed_rows(&parts.nodes, &digests.nodes, &persisted.nodes),
+                removed_keys(&digests.nodes, &persisted.nodes),
+            )
+        };
+        // The rows only make up a graph together with the header and trailer, so they are written in one transaction.
+        let mut client = self.durable_connection.get_mut().await;
+        let transaction = client.transaction().await?;
+        for chunk in channels.chunks(NETWORK_GRAPH_BATCH_SIZE) {
+            let mut statement =
+                "UPSERT INTO network_graph_channels (short_channel_id, data) VALUES ".to_string();
+            let mut params = Params::default();
+            for (i, (short_channel_id, data)) in chunk.iter().enumerate() {
+                if i > 0 {
+                    statement.push(',');
+                }
+                let n = params.count();
+                statement.push_str(&format!("(${}, ${})", n + 1, n + 2));
+                params.push(*short_channel_id as i64);
+                params.push(data.as_slice());
             }
-            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(anyhow!(e)),
+            transaction.execute(&statement, &params.to_params()).await?;
         }
+        for chunk in nodes.chunks(NETWORK_GRAPH_BATCH_SIZE) {
+            let mut statement =
+                "UPSERT INTO network_graph_nodes (node_id, data) VALUES ".to_string();
+            let mut params = Params::default();
+            for (i, (node_id, data)) in chunk.iter().enumerate() {
+                if i > 0 {
+                    statement.push(',');
+                }
+                let n = params.count();
+                statement.push_str(&format!("(${}, ${})", n + 1, n + 2));
+                params.push(node_id.as_slice());
+                params.push(data.as_slice());
+            }
+            transaction.execute(&statement, &params.to_params()).await?;
+        }
+        for chunk in removed_channels.chunks(NETWORK_GRAPH_BATCH_SIZE) {
+            let short_channel_ids: Vec<i64> = chunk.iter().map(|id| *id as i64).collect();
+            transaction
+                .execute(
+                    "DELETE FROM network_graph_channels WHERE short_channel_id = ANY($1)",
+                    &[&short_channel_ids],
+                )
+                .await?;
+        }
+        for chunk in removed_nodes.chunks(NETWORK_GRAPH_BATCH_SIZE) {
+            let node_ids: Vec<Vec<u8>> = chunk.iter().map(|id| id.as_slice().to_vec()).collect();
+            transaction
+                .execute(
+                    "DELETE FROM network_graph_nodes WHERE node_id = ANY($1)",
+                    &[&node_ids],
+                )
+                .await?;
+        }
+        transaction
+            .execute(
+                "UPSERT INTO network_graph (id, header, trailer, timestamp) \
+                VALUES ('graph', $1, $2, CURRENT_TIMESTAMP)",
+                &[&parts.header, &parts.trailer],
+            )
+            .await?;
+        transaction.commit().await?;
+        debug!(
+            "Persisted network graph with {} of {} channels and {} of {} nodes changed, {} channels and {} nodes removed",
+            channels.len(),
+            parts.channels.len(),
+            nodes.len(),
+            parts.nodes.len(),
+            removed_channels.len(),
+            removed_nodes.len()
+        );
+        *self.graph_digests.lock().unwrap_or_else(|e| e.into_inner()) = digests;
+        Ok(())
     }
 
     pub async fn fetch_scorer(
@@ -910,6 +2097,30 @@ impl LdkDatabase {
         Ok(scorer)
     }
 
+    pub async fn persist_scorer_binary(&self, scorer: &[u8]) -> Result<()> {
+        self.durable_connection
+            .get()
+            .await
+            .execute(
+                "UPSERT INTO scorer (id, scorer, timestamp) \
+                VALUES ('scorer', $1, CURRENT_TIMESTAMP)",
+                &[&scorer],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// When the scorer was last persisted.
+    pub async fn fetch_scorer_timestamp(&self) -> Result<Option<OffsetDateTime>> {
+        Ok(self
+            .durable_connection
+            .get()
+            .await
+            .query_opt("SELECT timestamp FROM scorer", &[])
+            .await?
+            .map(|row| row.get_timestamp("timestamp")))
+    }
+
     pub async fn fetch_scorer_binary(&self) -> Result<Vec<u8>> {
         let row = self
             .durable_connection
@@ -958,16 +2169,20 @@ where
         Ok(())
     }
 
-    // Network graph could get very large so just write it to disk for now.
+    // The graph is persisted rarely but also on shutdown, so block until it is written.
     fn persist_graph(
         &self,
         network_graph: &lightning::routing::gossip::NetworkGraph<L>,
     ) -> Result<(), io::Error> {
         let mut buf = vec![];
         network_graph.write(&mut buf)?;
-        if let Err(e) = fs::write(format!("{}/network_graph", self.settings.data_dir), &buf) {
-            error!("Failed to persist graph: {e}");
-        }
+        tokio::task::block_in_place(|| {
+            self.runtime.block_on(async {
+                if let Err(e) = self.persist_graph_bytes(&buf).await {
+                    error!("Failed to persist graph: {e}");
+                }
+            })
+        });
         Ok(())
     }
 
@@ -1016,99 +2231,233 @@ impl<ChannelSigner: WriteableEcdsaChannelSigner> chain::chainmonitor::Persist<Ch
         let latest_update_id = monitor.get_latest_update_id();
 
         let durable_connection = self.durable_connection.clone();
-        let chain_monitor = self
-            .chain_monitor
-            .get()
-            .expect("bad initialisation")
-            .clone();
-        tokio::spawn(async move {
-            let result = durable_connection
-                .get()
-                .await
-                .execute(
-                    "UPSERT INTO channel_monitors (out_point, monitor, update_id) \
-                VALUES ($1, $2, $3)",
-                    &[&out_point_buf, &monitor_buf, &(latest_update_id as i64)],
-                )
-                .await;
-            match result {
-                Ok(_) => {
-                    debug!(
-                        "Stored channel: {}:{} with update id: {}",
-                        funding_txo.txid, funding_txo.index, latest_update_id
-                    );
-                    if let Err(e) = chain_monitor.channel_monitor_updated(funding_txo, update_id) {
-                        error!("Failed to update chain monitor: {}", ldk_error(e));
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to persist channel update: {e}");
-                }
-            }
-        });
-        ChannelMonitorUpdateStatus::InProgress
+        self.complete_in_background(funding_txo, update_id, async move {
+            write_channel_monitor(
+                &durable_connection,
+                &out_point_buf,
+                &monitor_buf,
+                latest_update_id,
+            )
+            .await?;
+            debug!(
+                "Stored channel: {}:{} with update id: {}",
+                funding_txo.txid, funding_txo.index, latest_update_id
+            );
+            Ok(())
+        })
     }
 
-    // Updates are applied to the monitor when fetched from database.
+    // The CHANNEL_MONITOR_UPDATES table stores the updates since the monitor was last written, which are
+    // applied to the monitor when fetched from database. Updates without a ChannelMonitorUpdate come from
+    // the chain so the whole monitor is written for them.
     fn update_persisted_channel(
         &self,
         funding_txo: OutPoint,
-        _update: Option<&ChannelMonitorUpdate>,
+        update: Option<&ChannelMonitorUpdate>,
         monitor: &ChannelMonitor<ChannelSigner>,
         update_id: MonitorUpdateId,
     ) -> ChannelMonitorUpdateStatus {
-        self.persist_new_channel(funding_txo, monitor, update_id)
-
-        // Hope we can enable this soon. Probably after https://github.com/lightningdevkit/rust-lightning/issues/1426
-        /*
+        match update {
+            Some(update)
+                if update.update_id != CLOSED_CHANNEL_UPDATE_ID
+                    && update.update_id % self.settings.monitor_updates_before_full_write != 0 =>
+            {
                 let mut out_point_buf = vec![];
                 funding_txo.write(&mut out_point_buf).unwrap();
 
-                // If its the last update then store the last monitor and delete the updates.
-                if update.as_ref().map_or(true, |x| x.update_id == CLOSED_CHANNEL_UPDATE_ID) {
-                    let mut monitor_buf = vec![];
-                    monitor.write(&mut monitor_buf).unwrap();
-                    let ciphertext = self.cipher.encrypt(&monitor_buf);
-
-                    tokio::task::block_in_place(move || {
-                        Handle::current().block_on(async move {
-                            let mut lock = self.client.write().await;
-                            let tx = lock.transaction().await.unwrap();
-                            tx.execute(
-                                "UPSERT INTO channel_monitors (out_point, monitor, update_id) VALUES ($1, $2, $3)",
-                                &[
-                                    &out_point_buf,
-                                    &ciphertext,
-                                    &(monitor.get_latest_update_id() as i64),
-                                ],
-                            )
-                            .await
-                            .unwrap();
-                            let deleted = tx
-                                .execute(
-                                    "DELETE FROM channel_monitor_updates WHERE out_point = $1",
-                                    &[&out_point_buf],
-                                )
-                                .await
-                                .unwrap();
-                            tx.commit().await.unwrap();
-                            debug!("Stored latest monitor and deleted {} updates.", deleted);
-                        })
-                    })
-                } else {
-                    let update = update.as_ref().unwrap();
-                    let mut update_buf = vec![];
-                    update.write(&mut update_buf).unwrap();
-                    let ciphertext = self.cipher.encrypt(&update_buf);
-
-                    block_in_place!(
-                        "UPSERT INTO channel_monitor_updates (out_point, update, update_id) \
-                        VALUES ($1, $2, $3)",
-                        &[&out_point_buf, &ciphertext, &(update.update_id as i64)],
-                        self
+                let mut update_buf = vec![];
+                update.write(&mut update_buf).unwrap();
+                let channel_update_id = update.update_id;
+
+                let durable_connection = self.durable_connection.clone();
+                self.complete_in_background(funding_txo, update_id, async move {
+                    write_channel_monitor_update(
+                        &durable_connection,
+                        &out_point_buf,
+                        &update_buf,
+                        channel_update_id,
+                    )
+                    .await?;
+                    debug!(
+                        "Stored update {} of channel: {}:{}",
+                        channel_update_id, funding_txo.txid, funding_txo.index
                     );
-                }
-                ChannelMonitorUpdateStatus::Completed
-        */
+                    Ok(())
+                })
+            }
+            _ => self.persist_new_channel(funding_txo, monitor, update_id),
+        }
+    }
+}
+
+// CLOSED_CHANNEL_UPDATE_ID is u64::MAX so it is stored as the largest i64 to still come after all other updates.
+fn stored_update_id(update_id: u64) -> i64 {
+    if update_id == CLOSED_CHANNEL_UPDATE_ID {
+        i64::MAX
+    } else {
+        update_id as i64
+    }
+}
+
+/// Write the whole monitor and delete the updates that it includes, in one statement so that they are never
+/// lost. The writes are not ordered, so the monitor is not replaced by an older one that was written later.
+async fn write_channel_monitor(
+    durable_connection: &DurableConnection,
+    out_point: &[u8],
+    monitor: &[u8],
+    update_id: u64,
+) -> Result<()> {
+    let deleted = durable_connection
+        .get()
+        .await
+        .execute(
+            "WITH written AS (
+                INSERT INTO channel_monitors (out_point, monitor, update_id) VALUES ($1, $2, $3)
+                ON CONFLICT (out_point) DO UPDATE
+                SET monitor = excluded.monitor, update_id = excluded.update_id, timestamp = current_timestamp()
+                WHERE channel_monitors.update_id <= excluded.update_id
+                RETURNING update_id
+            )
+            DELETE FROM channel_monitor_updates
+            WHERE out_point = $1 AND update_id <= (SELECT update_id FROM written)",
+            &[&out_point, &monitor, &stored_update_id(update_id)],
+        )
+        .await?;
+    if deleted > 0 {
+        debug!("Compacted {deleted} channel monitor updates");
+    }
+    Ok(())
+}
+
+async fn write_channel_monitor_update(
+    durable_connection: &DurableConnection,
+    out_point: &[u8],
+    update: &[u8],
+    update_id: u64,
+) -> Result<()> {
+    durable_connection
+        .get()
+        .await
+        .execute(
+            "UPSERT INTO channel_monitor_updates (out_point, update, update_id) \
+            VALUES ($1, $2, $3)",
+            &[&out_point, &update, &stored_update_id(update_id)],
+        )
+        .await?;
+    Ok(())
+}
+
+/// The stats of the channel of the row, added when first seen.
+fn channel_stats<'a>(
+    stats: &'a mut BTreeMap<[u8; 32], ChannelForwardStats>,
+    row: &Row,
+) -> Result<&'a mut ChannelForwardStats> {
+    let channel_id: [u8; 32] = row.get::<&str, &[u8]>("channel_id").try_into()?;
+    Ok(stats
+        .entry(channel_id)
+        .or_insert_with(|| ChannelForwardStats::new(ChannelId::from_bytes(channel_id))))
+}
+
+/// The serialized network graph split into its channels and nodes, which are stored as rows of their own. The
+/// header and trailer are the version and chain hash before them and the TLV fields after them.
+#[derive(Default)]
+struct GraphParts {
+    header: Vec<u8>,
+    channels: Vec<(u64, Vec<u8>)>,
+    nodes: Vec<(NodeId, Vec<u8>)>,
+    trailer: Vec<u8>,
+}
+
+impl GraphParts {
+    fn split(bytes: &[u8]) -> Result<GraphParts> {
+        if bytes.len() < NETWORK_GRAPH_HEADER_LEN {
+            bail!("Network graph of {} bytes has no header", bytes.len());
+        }
+        let mut parts = GraphParts {
+            header: bytes[..NETWORK_GRAPH_HEADER_LEN].to_vec(),
+            ..Default::default()
+        };
+        let mut cursor = Cursor::new(bytes);
+        cursor.set_position(NETWORK_GRAPH_HEADER_LEN as u64);
+        let channels: u64 = Readable::read(&mut cursor).map_err(|e| anyhow!(e))?;
+        for _ in 0..channels {
+            let short_channel_id: u64 = Readable::read(&mut cursor).map_err(|e| anyhow!(e))?;
+            let start = cursor.position() as usize;
+            ChannelInfo::read(&mut cursor).map_err(|e| anyhow!(e))?;
+            let end = cursor.position() as usize;
+            parts
+                .channels
+                .push((short_channel_id, bytes[start..end].to_vec()));
+        }
+        let nodes: u64 = Readable::read(&mut cursor).map_err(|e| anyhow!(e))?;
+        for _ in 0..nodes {
+            let node_id = NodeId::read(&mut cursor).map_err(|e| anyhow!(e))?;
+            let start = cursor.position() as usize;
+            NodeInfo::read(&mut cursor).map_err(|e| anyhow!(e))?;
+            let end = cursor.position() as usize;
+            parts.nodes.push((node_id, bytes[start..end].to_vec()));
+        }
+        parts.trailer = bytes[cursor.position() as usize..].to_vec();
+        Ok(parts)
+    }
+
+    fn join(&self) -> Vec<u8> {
+        let mut bytes = self.header.clone();
+        (self.channels.len() as u64).write(&mut bytes).unwrap();
+        for (short_channel_id, data) in &self.channels {
+            short_channel_id.write(&mut bytes).unwrap();
+            bytes.extend_from_slice(data);
+        }
+        (self.nodes.len() as u64).write(&mut bytes).unwrap();
+        for (node_id, data) in &self.nodes {
+            node_id.write(&mut bytes).unwrap();
+            bytes.extend_from_slice(data);
+        }
+        bytes.extend_from_slice(&self.trailer);
+        bytes
     }
 }
+
+#[derive(Default)]
+struct GraphDigests {
+    channels: HashMap<u64, sha256::Hash>,
+    nodes: HashMap<NodeId, sha256::Hash>,
+}
+
+impl From<&GraphParts> for GraphDigests {
+    fn from(parts: &GraphParts) -> Self {
+        GraphDigests {
+            channels: parts
+                .channels
+                .iter()
+                .map(|(id, data)| (*id, sha256::Hash::hash(data)))
+                .collect(),
+            nodes: parts
+                .nodes
+                .iter()
+                .map(|(id, data)| (*id, sha256::Hash::hash(data)))
+                .collect(),
+        }
+    }
+}
+
+fn changed_rows<'a, K: Eq + std::hash::Hash>(
+    rows: &'a [(K, Vec<u8>)],
+    digests: &HashMap<K, sha256::Hash>,
+    persisted: &HashMap<K, sha256::Hash>,
+) -> Vec<&'a (K, Vec<u8>)> {
+    rows.iter()
+        .filter(|(id, _)| persisted.get(id) != digests.get(id))
+        .collect()
+}
+
+fn removed_keys<K: Eq + std::hash::Hash + Copy>(
+    digests: &HashMap<K, sha256::Hash>,
+    persisted: &HashMap<K, sha256::Hash>,
+) -> Vec<K> {
+    persisted
+        .keys()
+        .filter(|id| !digests.contains_key(id))
+        .copied()
+        .collect()
+}
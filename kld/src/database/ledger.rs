@@ -0,0 +1,178 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use bdk::TransactionDetails;
+use lightning::ln::ChannelId;
+use postgres_types::{FromSql, ToSql};
+use time::OffsetDateTime;
+use tokio_postgres::Row;
+
+use super::{
+    forward::Forward,
+    payment::{Payment, PaymentDirection},
+    RowExt,
+};
+use crate::MillisatAmount;
+
+/// A record of the accounting ledger. Entries are only ever appended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerEntry {
+    pub entry_type: LedgerEntryType,
+    /// The payment id, forward id or txid that the entry was recorded from.
+    pub reference: String,
+    /// Change of the balance in millisats excluding the fee, negative when spent.
+    pub amount: i64,
+    /// Fee paid in millisats.
+    pub fee: MillisatAmount,
+    /// The channel that was paid through, opened or closed.
+    pub channel_id: Option<ChannelId>,
+    pub timestamp: OffsetDateTime,
+}
+
+impl LedgerEntry {
+    pub fn payment(payment: &Payment) -> LedgerEntry {
+        let (entry_type, amount) = match payment.direction {
+            PaymentDirection::Inbound => (LedgerEntryType::PaymentReceived, payment.amount as i64),
+            PaymentDirection::Outbound => (LedgerEntryType::PaymentSent, -(payment.amount as i64)),
+        };
+        LedgerEntry {
+            entry_type,
+            reference: hex::encode(payment.id.0),
+            amount,
+            fee: payment.fee.unwrap_or_default(),
+            channel_id: None,
+            timestamp: payment.timestamp,
+        }
+    }
+
+    /// The fee that we earned by forwarding.
+    pub fn forward(forward: &Forward) -> LedgerEntry {
+        LedgerEntry {
+            entry_type: LedgerEntryType::ForwardFee,
+            reference: forward.id.to_string(),
+            amount: forward.fee.unwrap_or_default() as i64,
+            fee: 0,
+            channel_id: forward.outbound_channel_id,
+            timestamp: forward.timestamp,
+        }
+    }
+
+    /// A confirmed transaction of the wallet. BDK only knows the fee when all the inputs are ours,
+    /// so it is passed separately. None when the transaction is not confirmed.
+    pub fn transaction(
+        details: &TransactionDetails,
+        entry_type: LedgerEntryType,
+        channel_id: Option<ChannelId>,
+        fee: u64,
+    ) -> Option<LedgerEntry> {
+        let confirmation_time = details.confirmation_time.as_ref()?;
+        // When we spent from the wallet the fee came out of what was sent.
+        let amount = if details.sent > 0 {
+            details.received as i64 - details.sent as i64 + fee as i64
+        } else {
+            details.received as i64
+        };
+        Some(LedgerEntry {
+            entry_type,
+            reference: details.txid.to_string(),
+            amount: amount * 1000,
+            fee: fee * 1000,
+            channel_id,
+            timestamp: OffsetDateTime::from_unix_timestamp(confirmation_time.timestamp as i64)
+                .ok()?,
+        })
+    }
+}
+
+impl TryFrom<Row> for LedgerEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self, Self::Error> {
+        let channel_id: Option<[u8; 32]> = row
+            .get::<&str, Option<&[u8]>>("channel_id")
+            .map(|x| x.try_into())
+            .transpose()?;
+        Ok(LedgerEntry {
+            entry_type: row.get("entry_type"),
+            reference: row.get("reference"),
+            amount: row.get("amount"),
+            fee: row.get::<&str, i64>("fee") as MillisatAmount,
+            channel_id: channel_id.map(ChannelId::from_bytes),
+            timestamp: row.get_timestamp("timestamp"),
+        })
+    }
+}
+
+#[derive(Debug, ToSql, FromSql, PartialEq, Eq, Clone, Copy)]
+#[postgres(name = "ledger_entry_type")]
+pub enum LedgerEntryType {
+    /// Invoices and keysends paid to us.
+    #[postgres(name = "payment_received")]
+    PaymentReceived,
+    #[postgres(name = "payment_sent")]
+    PaymentSent,
+    #[postgres(name = "forward_fee")]
+    ForwardFee,
+    #[postgres(name = "channel_open")]
+    ChannelOpen,
+    #[postgres(name = "channel_close")]
+    ChannelClose,
+    /// Spending the outputs of a force closed channel to the wallet.
+    #[postgres(name = "sweep")]
+    Sweep,
+    #[postgres(name = "fee_bump")]
+    FeeBump,
+    #[postgres(name = "deposit")]
+    Deposit,
+    #[postgres(name = "withdrawal")]
+    Withdrawal,
+}
+
+impl LedgerEntryType {
+    /// Moving funds between the wallet and our channels is not a gain or a loss, only its fee is a cost.
+    pub fn is_transfer(&self) -> bool {
+        matches!(
+            self,
+            LedgerEntryType::ChannelOpen
+                | LedgerEntryType::ChannelClose
+                | LedgerEntryType::Sweep
+                | LedgerEntryType::FeeBump
+        )
+    }
+}
+
+impl FromStr for LedgerEntryType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "payment_received" => Ok(LedgerEntryType::PaymentReceived),
+            "payment_sent" => Ok(LedgerEntryType::PaymentSent),
+            "forward_fee" => Ok(LedgerEntryType::ForwardFee),
+            "channel_open" => Ok(LedgerEntryType::ChannelOpen),
+            "channel_close" => Ok(LedgerEntryType::ChannelClose),
+            "sweep" => Ok(LedgerEntryType::Sweep),
+            "fee_bump" => Ok(LedgerEntryType::FeeBump),
+            "deposit" => Ok(LedgerEntryType::Deposit),
+            "withdrawal" => Ok(LedgerEntryType::Withdrawal),
+            _ => Err(anyhow!("Unknown ledger entry type {s}")),
+        }
+    }
+}
+
+impl ToString for LedgerEntryType {
+    fn to_string(&self) -> String {
+        match self {
+            LedgerEntryType::PaymentReceived => "payment_received",
+            LedgerEntryType::PaymentSent => "payment_sent",
+            LedgerEntryType::ForwardFee => "forward_fee",
+            LedgerEntryType::ChannelOpen => "channel_open",
+            LedgerEntryType::ChannelClose => "channel_close",
+            LedgerEntryType::Sweep => "sweep",
+            LedgerEntryType::FeeBump => "fee_bump",
+            LedgerEntryType::Deposit => "deposit",
+            LedgerEntryType::Withdrawal => "withdrawal",
+        }
+        .to_owned()
+    }
+}
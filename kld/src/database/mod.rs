@@ -1,9 +1,18 @@
+pub mod fee_bump;
 pub mod forward;
+pub mod gossip;
 pub mod invoice;
+pub mod label;
 mod ldk_database;
+pub mod ledger;
+pub mod node_announcement;
 pub mod payment;
 pub mod peer;
+pub mod psbt;
+pub mod scorer_parameters;
+pub mod sweep;
 mod wallet_database;
+pub mod withdrawal;
 
 use std::{
     sync::{Arc, RwLock},
@@ -13,11 +22,15 @@ use std::{
 use async_trait::async_trait;
 pub use ldk_database::LdkDatabase;
 use lightning::ln::channelmanager::ChannelDetails;
+use lightning::ln::ChannelId;
 use lightning::sign::SpendableOutputDescriptor;
 use lightning::util::ser::MaybeReadable;
 use postgres_types::ToSql;
 use time::{OffsetDateTime, PrimitiveDateTime};
-use tokio::{sync::OwnedRwLockReadGuard, task::JoinHandle};
+use tokio::{
+    sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard},
+    task::JoinHandle,
+};
 pub use wallet_database::WalletDatabase;
 
 use anyhow::{Context, Result};
@@ -42,9 +55,26 @@ pub struct ChannelRecord {
 
 pub struct SpendableOutputRecord {
     pub descriptor: SpendableOutputDescriptor,
+    pub channel_id: Option<ChannelId>,
     pub is_spent: bool,
 }
 
+impl TryFrom<Row> for SpendableOutputRecord {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self, Self::Error> {
+        let channel_id: Option<[u8; 32]> = row
+            .get::<&str, Option<&[u8]>>("channel_id")
+            .map(|x| x.try_into())
+            .transpose()?;
+        Ok(SpendableOutputRecord {
+            descriptor: row.read("data")?,
+            channel_id: channel_id.map(ChannelId::from_bytes),
+            is_spent: row.get("is_spent"),
+        })
+    }
+}
+
 pub struct DurableConnection {
     client: Arc<AsyncRwLock<Client>>, // Used across await points.
     connection_task: Arc<RwLock<JoinHandle<()>>>,
@@ -168,6 +198,11 @@ impl DurableConnection {
         self.client.clone().read_owned().await
     }
 
+    // Exclusive use of the current connection, so that no other statement runs inside a transaction started with it.
+    async fn get_mut(&self) -> OwnedRwLockWriteGuard<Client> {
+        self.client.clone().write_owned().await
+    }
+
     /// Block on trying to reconnect to the database if the connection has been dropped.
     /// This can probably only be used during start up when we have to wait. Take care not to block async tasks.
     async fn wait(&self) -> OwnedRwLockReadGuard<Client> {
@@ -243,6 +278,48 @@ impl<'a> Params<'a> {
     }
 }
 
+/// Which rows of a list to fetch: those in the time range, oldest first, skipping `offset` rows and
+/// returning at most `limit`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pagination {
+    /// Inclusive.
+    pub from: Option<OffsetDateTime>,
+    /// Exclusive.
+    pub to: Option<OffsetDateTime>,
+    pub offset: Option<u64>,
+    pub limit: Option<u64>,
+}
+
+impl Pagination {
+    /// Conditions on the time column, to follow a WHERE clause.
+    pub fn time_range(&self, column: &str, params: &mut Params) -> String {
+        let mut conditions = String::new();
+        if let Some(from) = self.from {
+            params.push(to_primitive(&from));
+            conditions.push_str(&format!(" AND {column} >= ${}", params.count()));
+        }
+        if let Some(to) = self.to {
+            params.push(to_primitive(&to));
+            conditions.push_str(&format!(" AND {column} < ${}", params.count()));
+        }
+        conditions
+    }
+
+    /// LIMIT and OFFSET clauses, to follow the ORDER BY clause.
+    pub fn page(&self, params: &mut Params) -> String {
+        let mut clauses = String::new();
+        if let Some(limit) = self.limit {
+            params.push(limit as i64);
+            clauses.push_str(&format!(" LIMIT ${}", params.count()));
+        }
+        if let Some(offset) = self.offset {
+            params.push(offset as i64);
+            clauses.push_str(&format!(" OFFSET ${}", params.count()));
+        }
+        clauses
+    }
+}
+
 pub fn microsecond_timestamp() -> OffsetDateTime {
     let timestamp = OffsetDateTime::now_utc();
     timestamp
@@ -0,0 +1,104 @@
+use anyhow::{bail, Result};
+use hex::FromHex;
+
+use crate::api::{AddressType, SocketAddress};
+use crate::settings::Settings;
+
+/// LDK panics when announcing more addresses than this.
+const MAX_ANNOUNCED_ADDRESSES: usize = 100;
+
+/// The alias, colour and addresses that we announce to the network. Once updated through the API
+/// they are stored in the database and take precedence over the startup settings, except for the
+/// onion address which always comes from the settings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeAnnouncementConfig {
+    pub alias: String,
+    pub color: String,
+    pub addresses: Vec<SocketAddress>,
+}
+
+impl NodeAnnouncementConfig {
+    pub fn from_settings(settings: &Settings) -> NodeAnnouncementConfig {
+        NodeAnnouncementConfig {
+            alias: settings.node_alias.clone(),
+            color: settings.node_alias_color.clone(),
+            addresses: settings
+                .public_addresses
+                .iter()
+                .chain(settings.onion_address.iter())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// The onion address belongs to the Tor hidden service of the node, so the configured one replaces
+    /// any onion address of a stored announcement.
+    pub fn merge_onion_address(&mut self, settings: &Settings) {
+        let Some(onion_address) = &settings.onion_address else {
+            return;
+        };
+        self.addresses
+            .retain(|address| address.address_type() != AddressType::Onion);
+        if self.addresses.len() < MAX_ANNOUNCED_ADDRESSES {
+            self.addresses.push(onion_address.clone());
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.alias.len() > 32 {
+            bail!("Node alias must be at most 32 bytes");
+        }
+        if <[u8; 3]>::from_hex(&self.color).is_err() {
+            bail!("Node color must be 3 bytes of hex, e.g. 6e2cf7");
+        }
+        if self.addresses.len() > MAX_ANNOUNCED_ADDRESSES {
+            bail!("Cannot announce more than {MAX_ANNOUNCED_ADDRESSES} addresses");
+        }
+        Ok(())
+    }
+
+    pub fn alias_bytes(&self) -> [u8; 32] {
+        let mut alias = [0; 32];
+        let len = self.alias.len().min(32);
+        alias[..len].copy_from_slice(&self.alias.as_bytes()[..len]);
+        alias
+    }
+
+    pub fn rgb(&self) -> [u8; 3] {
+        <[u8; 3]>::from_hex(&self.color).unwrap_or([110, 44, 247])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_merge_onion_address() -> Result<()> {
+        let public = SocketAddress::from_str("127.0.0.1:9735")?;
+        let old_onion = SocketAddress::from_str(
+            "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion:9735",
+        )?;
+        let new_onion = SocketAddress::from_str(
+            "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion:9735",
+        )?;
+        let mut config = NodeAnnouncementConfig {
+            alias: "kld".to_string(),
+            color: "6e2cf7".to_string(),
+            addresses: vec![public.clone(), old_onion],
+        };
+
+        config.merge_onion_address(&Settings::default());
+        assert_eq!(2, config.addresses.len());
+
+        let settings = Settings {
+            onion_address: Some(new_onion.clone()),
+            ..Settings::default()
+        };
+        config.merge_onion_address(&settings);
+        assert_eq!(vec![public, new_onion], config.addresses);
+        Ok(())
+    }
+}
@@ -1,6 +1,19 @@
 use anyhow::{anyhow, Result};
 use bitcoin::secp256k1::PublicKey;
 use lightning::{ln::msgs::SocketAddress, util::ser::MaybeReadable};
+use postgres_types::{FromSql, ToSql};
+use time::{Duration, OffsetDateTime};
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+use super::{microsecond_timestamp, RowExt};
+
+/// Delay before retrying an address after its first failure, doubled for every further consecutive failure.
+const BACKOFF_BASE: Duration = Duration::seconds(2);
+const BACKOFF_MAX: Duration = Duration::minutes(10);
+
+/// Peer uptime is reported over this period.
+pub const UPTIME_WINDOW: Duration = Duration::days(7);
 
 #[derive(PartialEq, Eq, Debug)]
 pub struct Peer {
@@ -21,3 +34,190 @@ impl Peer {
         })
     }
 }
+
+/// One of the addresses we know for a peer and how well connecting to it has gone.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct PeerAddress {
+    pub address: SocketAddress,
+    pub last_success: Option<OffsetDateTime>,
+    pub last_failure: Option<OffsetDateTime>,
+    // Consecutive failures since the last success.
+    pub failures: u32,
+}
+
+impl PeerAddress {
+    /// The earliest time we should try this address again, if it is failing.
+    pub fn retry_after(&self) -> Option<OffsetDateTime> {
+        if self.failures == 0 {
+            return None;
+        }
+        let backoff = BACKOFF_BASE
+            .saturating_mul(2i32.saturating_pow(self.failures - 1))
+            .min(BACKOFF_MAX);
+        self.last_failure.map(|t| t + backoff)
+    }
+
+    pub fn is_backing_off(&self, now: OffsetDateTime) -> bool {
+        self.retry_after().is_some_and(|t| t > now)
+    }
+}
+
+impl TryFrom<&Row> for PeerAddress {
+    type Error = anyhow::Error;
+
+    fn try_from(row: &Row) -> std::result::Result<Self, Self::Error> {
+        Ok(PeerAddress {
+            address: row.read("address")?,
+            last_success: row.get_timestamp_optional("last_success"),
+            last_failure: row.get_timestamp_optional("last_failure"),
+            failures: row.get::<&str, i64>("failures") as u32,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PeerEvent {
+    pub id: Uuid,
+    pub public_key: PublicKey,
+    pub address: Option<SocketAddress>,
+    pub event: PeerEventType,
+    pub timestamp: OffsetDateTime,
+}
+
+impl PeerEvent {
+    pub fn new(
+        public_key: PublicKey,
+        address: Option<SocketAddress>,
+        event: PeerEventType,
+    ) -> PeerEvent {
+        PeerEvent {
+            id: Uuid::new_v4(),
+            public_key,
+            address,
+            event,
+            timestamp: microsecond_timestamp(),
+        }
+    }
+}
+
+impl TryFrom<Row> for PeerEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> std::result::Result<Self, Self::Error> {
+        Ok(PeerEvent {
+            id: row.get("id"),
+            public_key: PublicKey::from_slice(row.get::<&str, &[u8]>("public_key"))?,
+            address: row.read_optional("address")?,
+            event: row.get("event"),
+            timestamp: row.get_timestamp("timestamp"),
+        })
+    }
+}
+
+#[derive(Debug, ToSql, FromSql, PartialEq, Clone, Copy)]
+#[postgres(name = "peer_event_type")]
+pub enum PeerEventType {
+    #[postgres(name = "connected")]
+    Connected,
+    #[postgres(name = "disconnected")]
+    Disconnected,
+    #[postgres(name = "connection_failed")]
+    ConnectionFailed,
+}
+
+impl ToString for PeerEventType {
+    fn to_string(&self) -> String {
+        match self {
+            PeerEventType::Connected => "connected",
+            PeerEventType::Disconnected => "disconnected",
+            PeerEventType::ConnectionFailed => "connection_failed",
+        }
+        .to_owned()
+    }
+}
+
+/// Percentage of the time between `from` and `to` that we were connected to the peer.
+/// The events must be in time order and can start with the last event before `from`, which gives the
+/// connection state at the start of the period. Time before the first event is not counted.
+pub fn uptime(events: &[PeerEvent], from: OffsetDateTime, to: OffsetDateTime) -> Option<f64> {
+    let mut observed = Duration::ZERO;
+    let mut connected = Duration::ZERO;
+    let mut state: Option<(OffsetDateTime, bool)> = None;
+    for event in events {
+        let timestamp = event.timestamp.clamp(from, to);
+        if let Some((since, was_connected)) = state {
+            observed += timestamp - since;
+            if was_connected {
+                connected += timestamp - since;
+            }
+        }
+        state = Some((timestamp, event.event == PeerEventType::Connected));
+    }
+    let (since, is_connected) = state?;
+    observed += to - since;
+    if is_connected {
+        connected += to - since;
+    }
+    if observed.is_zero() {
+        return None;
+    }
+    Some(100.0 * connected.as_seconds_f64() / observed.as_seconds_f64())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_utils::random_public_key;
+
+    #[test]
+    fn test_backoff() {
+        let now = OffsetDateTime::now_utc();
+        let mut address = PeerAddress {
+            address: SocketAddress::TcpIpV4 {
+                addr: [127, 0, 0, 1],
+                port: 9735,
+            },
+            last_success: None,
+            last_failure: Some(now),
+            failures: 0,
+        };
+        assert!(!address.is_backing_off(now));
+        address.failures = 1;
+        assert_eq!(Some(now + BACKOFF_BASE), address.retry_after());
+        address.failures = 3;
+        assert_eq!(Some(now + BACKOFF_BASE * 4), address.retry_after());
+        assert!(address.is_backing_off(now + BACKOFF_BASE * 3));
+        assert!(!address.is_backing_off(now + BACKOFF_BASE * 4));
+        address.failures = 100;
+        assert_eq!(Some(now + BACKOFF_MAX), address.retry_after());
+    }
+
+    #[test]
+    fn test_uptime() {
+        let public_key = random_public_key();
+        let from = OffsetDateTime::now_utc();
+        let to = from + Duration::hours(10);
+        let event = |hours: i64, event: PeerEventType| {
+            let mut event = PeerEvent::new(public_key, None, event);
+            event.timestamp = from + Duration::hours(hours);
+            event
+        };
+        assert_eq!(None, uptime(&[], from, to));
+
+        // Connected before the period and dropped for two hours.
+        let events = vec![
+            event(-5, PeerEventType::Connected),
+            event(4, PeerEventType::Disconnected),
+            event(5, PeerEventType::ConnectionFailed),
+            event(6, PeerEventType::Connected),
+        ];
+        assert_eq!(Some(80.0), uptime(&events, from, to));
+
+        // Only observed for the last half of the period.
+        let events = vec![
+            event(5, PeerEventType::Connected),
+            event(9, PeerEventType::Disconnected),
+        ];
+        assert_eq!(Some(80.0), uptime(&events, from, to));
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::Result;
+use bitcoin::{psbt::PartiallySignedTransaction, secp256k1::PublicKey, OutPoint, Txid};
+use lightning::ln::ChannelId;
+use postgres_types::{FromSql, ToSql};
+use time::{Duration, OffsetDateTime};
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+use super::{microsecond_timestamp, RowExt};
+
+/// LDK drops an outbound channel after about an hour if it is not given the funding transaction,
+/// so a funding PSBT has to be signed and submitted well before then.
+pub const FUNDING_PSBT_EXPIRY: Duration = Duration::minutes(50);
+
+/// A PSBT created by the wallet that waits to be signed externally and submitted back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingPsbt {
+    pub id: Uuid,
+    pub psbt: PartiallySignedTransaction,
+    pub purpose: PsbtPurpose,
+    // Only set when funding a channel.
+    pub temporary_channel_id: Option<ChannelId>,
+    pub counterparty: Option<PublicKey>,
+    pub completed: bool,
+    pub created: OffsetDateTime,
+    pub expires: OffsetDateTime,
+}
+
+impl PendingPsbt {
+    pub fn withdraw(psbt: PartiallySignedTransaction, expiry: Duration) -> PendingPsbt {
+        let created = microsecond_timestamp();
+        PendingPsbt {
+            id: Uuid::new_v4(),
+            psbt,
+            purpose: PsbtPurpose::Withdraw,
+            temporary_channel_id: None,
+            counterparty: None,
+            completed: false,
+            created,
+            expires: created + expiry,
+        }
+    }
+
+    pub fn fund_channel(
+        psbt: PartiallySignedTransaction,
+        temporary_channel_id: ChannelId,
+        counterparty: PublicKey,
+        expiry: Duration,
+    ) -> PendingPsbt {
+        let created = microsecond_timestamp();
+        PendingPsbt {
+            id: Uuid::new_v4(),
+            psbt,
+            purpose: PsbtPurpose::FundChannel,
+            temporary_channel_id: Some(temporary_channel_id),
+            counterparty: Some(counterparty),
+            completed: false,
+            created,
+            expires: created + expiry.min(FUNDING_PSBT_EXPIRY),
+        }
+    }
+
+    /// All the inputs are segwit so this is the txid of the final transaction too.
+    pub fn txid(&self) -> Txid {
+        self.psbt.unsigned_tx.txid()
+    }
+
+    pub fn is_expired(&self, now: OffsetDateTime) -> bool {
+        self.expires <= now
+    }
+
+    pub fn inputs(&self) -> Vec<OutPoint> {
+        self.psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|input| input.previous_output)
+            .collect()
+    }
+
+    /// The fee in sats, if the PSBT has the outputs spent by all its inputs.
+    pub fn fee(&self) -> Option<u64> {
+        let mut inputs = 0;
+        for input in &self.psbt.inputs {
+            inputs += input.witness_utxo.as_ref()?.value;
+        }
+        let outputs: u64 = self
+            .psbt
+            .unsigned_tx
+            .output
+            .iter()
+            .map(|output| output.value)
+            .sum();
+        inputs.checked_sub(outputs)
+    }
+
+    /// Add the signatures of the same PSBT signed externally.
+    pub fn combine(
+        &self,
+        signed: PartiallySignedTransaction,
+    ) -> Result<PartiallySignedTransaction> {
+        if signed.unsigned_tx != self.psbt.unsigned_tx {
+            anyhow::bail!("PSBT does not spend the same transaction as {}", self.id);
+        }
+        let mut psbt = self.psbt.clone();
+        psbt.combine(signed)?;
+        Ok(psbt)
+    }
+}
+
+impl TryFrom<Row> for PendingPsbt {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> std::result::Result<Self, Self::Error> {
+        let temporary_channel_id: Option<[u8; 32]> = row
+            .get::<&str, Option<&[u8]>>("temporary_channel_id")
+            .map(|x| x.try_into())
+            .transpose()?;
+        Ok(PendingPsbt {
+            id: row.get("id"),
+            psbt: PartiallySignedTransaction::deserialize(row.get::<&str, &[u8]>("psbt"))?,
+            purpose: row.get("purpose"),
+            temporary_channel_id: temporary_channel_id.map(ChannelId::from_bytes),
+            counterparty: row
+                .get::<&str, Option<&[u8]>>("counterparty")
+                .map(PublicKey::from_slice)
+                .transpose()?,
+            completed: row.get("completed"),
+            created: row.get_timestamp("created"),
+            expires: row.get_timestamp("expires"),
+        })
+    }
+}
+
+#[derive(Debug, ToSql, FromSql, PartialEq, Eq, Clone, Copy)]
+#[postgres(name = "psbt_purpose")]
+pub enum PsbtPurpose {
+    #[postgres(name = "withdraw")]
+    Withdraw,
+    #[postgres(name = "fund_channel")]
+    FundChannel,
+}
+
+impl ToString for PsbtPurpose {
+    fn to_string(&self) -> String {
+        match self {
+            PsbtPurpose::Withdraw => "withdraw",
+            PsbtPurpose::FundChannel => "fund_channel",
+        }
+        .to_owned()
+    }
+}
@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use lightning::routing::scoring::{
+    ProbabilisticScoringDecayParameters, ProbabilisticScoringFeeParameters,
+};
+use tokio_postgres::Row;
+
+use crate::settings::Settings;
+
+/// The parameters of our probabilistic scorer. Once updated through the API they are stored in the
+/// database and take precedence over the startup settings. Anything not set uses the LDK default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScorerParameters {
+    pub base_penalty_msat: u64,
+    pub base_penalty_amount_multiplier_msat: u64,
+    pub liquidity_penalty_multiplier_msat: u64,
+    pub liquidity_penalty_amount_multiplier_msat: u64,
+    pub historical_liquidity_penalty_multiplier_msat: u64,
+    pub historical_liquidity_penalty_amount_multiplier_msat: u64,
+    pub liquidity_offset_half_life: Duration,
+    pub historical_no_updates_half_life: Duration,
+}
+
+impl Default for ScorerParameters {
+    fn default() -> Self {
+        let fee = ProbabilisticScoringFeeParameters::default();
+        let decay = ProbabilisticScoringDecayParameters::default();
+        ScorerParameters {
+            base_penalty_msat: fee.base_penalty_msat,
+            base_penalty_amount_multiplier_msat: fee.base_penalty_amount_multiplier_msat,
+            liquidity_penalty_multiplier_msat: fee.liquidity_penalty_multiplier_msat,
+            liquidity_penalty_amount_multiplier_msat: fee.liquidity_penalty_amount_multiplier_msat,
+            historical_liquidity_penalty_multiplier_msat: fee
+                .historical_liquidity_penalty_multiplier_msat,
+            historical_liquidity_penalty_amount_multiplier_msat: fee
+                .historical_liquidity_penalty_amount_multiplier_msat,
+            liquidity_offset_half_life: decay.liquidity_offset_half_life,
+            historical_no_updates_half_life: decay.historical_no_updates_half_life,
+        }
+    }
+}
+
+impl ScorerParameters {
+    pub fn from_settings(settings: &Settings) -> ScorerParameters {
+        let default = ScorerParameters::default();
+        ScorerParameters {
+            base_penalty_msat: settings
+                .scorer_base_penalty_msat
+                .unwrap_or(default.base_penalty_msat),
+            base_penalty_amount_multiplier_msat: settings
+                .scorer_base_penalty_amount_multiplier_msat
+                .unwrap_or(default.base_penalty_amount_multiplier_msat),
+            liquidity_penalty_multiplier_msat: settings
+                .scorer_liquidity_penalty_multiplier_msat
+                .unwrap_or(default.liquidity_penalty_multiplier_msat),
+            liquidity_penalty_amount_multiplier_msat: settings
+                .scorer_liquidity_penalty_amount_multiplier_msat
+                .unwrap_or(default.liquidity_penalty_amount_multiplier_msat),
+            historical_liquidity_penalty_multiplier_msat: settings
+                .scorer_historical_liquidity_penalty_multiplier_msat
+                .unwrap_or(default.historical_liquidity_penalty_multiplier_msat),
+            historical_liquidity_penalty_amount_multiplier_msat: settings
+                .scorer_historical_liquidity_penalty_amount_multiplier_msat
+                .unwrap_or(default.historical_liquidity_penalty_amount_multiplier_msat),
+            liquidity_offset_half_life: settings
+                .scorer_liquidity_offset_half_life
+                .map(Duration::from_secs)
+                .unwrap_or(default.liquidity_offset_half_life),
+            historical_no_updates_half_life: settings
+                .scorer_historical_no_updates_half_life
+                .map(Duration::from_secs)
+                .unwrap_or(default.historical_no_updates_half_life),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.liquidity_offset_half_life.is_zero()
+            || self.historical_no_updates_half_life.is_zero()
+        {
+            bail!("Scorer half lives must be greater than zero");
+        }
+        // Stored as INT in the database.
+        for value in [
+            self.base_penalty_msat,
+            self.base_penalty_amount_multiplier_msat,
+            self.liquidity_penalty_multiplier_msat,
+            self.liquidity_penalty_amount_multiplier_msat,
+            self.historical_liquidity_penalty_multiplier_msat,
+            self.historical_liquidity_penalty_amount_multiplier_msat,
+            self.liquidity_offset_half_life.as_secs(),
+            self.historical_no_updates_half_life.as_secs(),
+        ] {
+            if value > i64::MAX as u64 {
+                bail!("Scorer parameter {value} is too large");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn fee_parameters(&self) -> ProbabilisticScoringFeeParameters {
+        ProbabilisticScoringFeeParameters {
+            base_penalty_msat: self.base_penalty_msat,
+            base_penalty_amount_multiplier_msat: self.base_penalty_amount_multiplier_msat,
+            liquidity_penalty_multiplier_msat: self.liquidity_penalty_multiplier_msat,
+            liquidity_penalty_amount_multiplier_msat: self.liquidity_penalty_amount_multiplier_msat,
+            historical_liquidity_penalty_multiplier_msat: self
+                .historical_liquidity_penalty_multiplier_msat,
+            historical_liquidity_penalty_amount_multiplier_msat: self
+                .historical_liquidity_penalty_amount_multiplier_msat,
+            ..Default::default()
+        }
+    }
+
+    pub fn decay_parameters(&self) -> ProbabilisticScoringDecayParameters {
+        ProbabilisticScoringDecayParameters {
+            liquidity_offset_half_life: self.liquidity_offset_half_life,
+            historical_no_updates_half_life: self.historical_no_updates_half_life,
+        }
+    }
+}
+
+impl From<Row> for ScorerParameters {
+    fn from(row: Row) -> Self {
+        let get = |name: &str| row.get::<&str, i64>(name) as u64;
+        ScorerParameters {
+            base_penalty_msat: get("base_penalty_msat"),
+            base_penalty_amount_multiplier_msat: get("base_penalty_amount_multiplier_msat"),
+            liquidity_penalty_multiplier_msat: get("liquidity_penalty_multiplier_msat"),
+            liquidity_penalty_amount_multiplier_msat: get(
+                "liquidity_penalty_amount_multiplier_msat",
+            ),
+            historical_liquidity_penalty_multiplier_msat: get(
+                "historical_liquidity_penalty_multiplier_msat",
+            ),
+            historical_liquidity_penalty_amount_multiplier_msat: get(
+                "historical_liquidity_penalty_amount_multiplier_msat",
+            ),
+            liquidity_offset_half_life: Duration::from_secs(get("liquidity_offset_half_life")),
+            historical_no_updates_half_life: Duration::from_secs(get(
+                "historical_no_updates_half_life",
+            )),
+        }
+    }
+}
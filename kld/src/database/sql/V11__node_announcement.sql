@@ -0,0 +1,7 @@
+CREATE TABLE node_announcement (
+    id              STRING PRIMARY KEY,
+    alias           STRING NOT NULL,
+    color           STRING NOT NULL,
+    addresses       BYTES[] NOT NULL,
+    timestamp       TIMESTAMP NOT NULL DEFAULT current_timestamp()
+);
@@ -0,0 +1,15 @@
+ALTER TABLE peers ADD COLUMN last_success TIMESTAMP;
+ALTER TABLE peers ADD COLUMN last_failure TIMESTAMP;
+ALTER TABLE peers ADD COLUMN failures INT NOT NULL DEFAULT 0;
+
+CREATE TYPE peer_event_type AS ENUM ('connected', 'disconnected', 'connection_failed');
+
+CREATE TABLE peer_events (
+    id              UUID NOT NULL,
+    public_key      BYTES NOT NULL,
+    address         BYTES,
+    event           peer_event_type NOT NULL,
+    timestamp       TIMESTAMP NOT NULL DEFAULT current_timestamp(),
+    PRIMARY KEY ( id ),
+    INDEX ( public_key, timestamp )
+);
@@ -0,0 +1,9 @@
+CREATE TYPE gossip_type AS ENUM ('announcement', 'one_to_two', 'two_to_one');
+
+CREATE TABLE gossip_timestamps (
+    short_channel_id    INT NOT NULL,
+    gossip_type         gossip_type NOT NULL,
+    update_timestamp    INT NOT NULL,
+    seen                TIMESTAMP NOT NULL,
+    PRIMARY KEY ( short_channel_id, gossip_type )
+);
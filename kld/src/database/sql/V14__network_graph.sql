@@ -0,0 +1,18 @@
+-- The network graph is stored as a row per channel and per node so that persisting it only writes
+-- the entries which changed. network_graph keeps the rest of the serialized graph.
+CREATE TABLE network_graph (
+    id              BYTES PRIMARY KEY,
+    header          BYTES NOT NULL,
+    trailer         BYTES NOT NULL,
+    timestamp       TIMESTAMP NOT NULL DEFAULT current_timestamp()
+);
+
+CREATE TABLE network_graph_channels (
+    short_channel_id    INT PRIMARY KEY,
+    data                BYTES NOT NULL
+);
+
+CREATE TABLE network_graph_nodes (
+    node_id         BYTES PRIMARY KEY,
+    data            BYTES NOT NULL
+);
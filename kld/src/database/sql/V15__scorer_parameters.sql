@@ -0,0 +1,12 @@
+CREATE TABLE scorer_parameters (
+    id                                                  STRING PRIMARY KEY,
+    base_penalty_msat                                   INT NOT NULL,
+    base_penalty_amount_multiplier_msat                 INT NOT NULL,
+    liquidity_penalty_multiplier_msat                   INT NOT NULL,
+    liquidity_penalty_amount_multiplier_msat            INT NOT NULL,
+    historical_liquidity_penalty_multiplier_msat        INT NOT NULL,
+    historical_liquidity_penalty_amount_multiplier_msat INT NOT NULL,
+    liquidity_offset_half_life                          INT NOT NULL,
+    historical_no_updates_half_life                     INT NOT NULL,
+    timestamp                                           TIMESTAMP NOT NULL DEFAULT current_timestamp()
+);
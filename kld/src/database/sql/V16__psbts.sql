@@ -0,0 +1,15 @@
+CREATE TYPE psbt_purpose AS ENUM ('withdraw', 'fund_channel');
+
+CREATE TABLE psbts (
+    id                  UUID NOT NULL,
+    psbt                BYTES NOT NULL,
+    purpose             psbt_purpose NOT NULL,
+    txid                BYTES NOT NULL,
+    temporary_channel_id BYTES,
+    counterparty        BYTES,
+    completed           BOOLEAN NOT NULL DEFAULT false,
+    created             TIMESTAMP NOT NULL,
+    expires             TIMESTAMP NOT NULL,
+    PRIMARY KEY ( id ),
+    INDEX ( completed, expires )
+);
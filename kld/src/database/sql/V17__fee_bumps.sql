@@ -0,0 +1,20 @@
+CREATE TYPE fee_bump_method AS ENUM ('rbf', 'cpfp');
+
+CREATE TABLE fee_bumps (
+    /* The replacement transaction for RBF or the child transaction for CPFP */
+    txid                BYTES NOT NULL,
+    original_txid       BYTES NOT NULL,
+    method              fee_bump_method NOT NULL,
+    fee_rate            INT8 NOT NULL,
+    fee                 INT8 NOT NULL,
+    /* The channel funded by the original transaction */
+    channel_id          BYTES,
+    /* The PSBT that created the original transaction */
+    psbt_id             UUID,
+    created             TIMESTAMP NOT NULL,
+    PRIMARY KEY ( txid ),
+    INDEX ( original_txid )
+);
+
+CREATE INDEX ON initializing_channels ( txid );
+CREATE INDEX ON psbts ( txid );
@@ -0,0 +1,12 @@
+/* BIP-329 label types */
+CREATE TYPE label_type AS ENUM ('tx', 'addr', 'pubkey', 'input', 'output', 'xpub');
+
+CREATE TABLE labels (
+    label_type          label_type NOT NULL,
+    /* txid, address, pubkey, txid:vout or xpub depending on the type */
+    reference           STRING NOT NULL,
+    label               STRING,
+    origin              STRING,
+    spendable           BOOLEAN,
+    PRIMARY KEY ( label_type, reference )
+);
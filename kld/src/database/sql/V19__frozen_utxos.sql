@@ -0,0 +1,2 @@
+/* Frozen UTXOs are never spent by the wallet until they are unfrozen */
+ALTER TABLE wallet_utxos ADD COLUMN is_frozen BOOL NOT NULL DEFAULT false;
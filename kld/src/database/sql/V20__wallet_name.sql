@@ -0,0 +1,8 @@
+-- The BIP84 and the BIP86 (taproot) wallets share the tables.
+ALTER TABLE wallet_script_pubkeys ADD COLUMN wallet TEXT NOT NULL DEFAULT 'bip84';
+ALTER TABLE wallet_utxos ADD COLUMN wallet TEXT NOT NULL DEFAULT 'bip84';
+ALTER TABLE wallet_transactions ADD COLUMN wallet TEXT NOT NULL DEFAULT 'bip84';
+ALTER TABLE wallet_transaction_details ADD COLUMN wallet TEXT NOT NULL DEFAULT 'bip84';
+ALTER TABLE wallet_last_derivation_indices ADD COLUMN wallet TEXT NOT NULL DEFAULT 'bip84';
+ALTER TABLE wallet_checksums ADD COLUMN wallet TEXT NOT NULL DEFAULT 'bip84';
+ALTER TABLE wallet_sync_time ADD COLUMN wallet TEXT NOT NULL DEFAULT 'bip84';
@@ -0,0 +1,2 @@
+ALTER TABLE wallet_last_derivation_indices ALTER PRIMARY KEY USING COLUMNS (wallet, keychain);
+ALTER TABLE wallet_sync_time ALTER PRIMARY KEY USING COLUMNS (wallet, id);
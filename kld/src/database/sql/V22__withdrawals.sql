@@ -0,0 +1,8 @@
+CREATE TABLE withdrawals (
+    idempotency_key     TEXT NOT NULL,
+    /* Null while the transaction is being built */
+    txid                BYTES,
+    tx                  BYTES,
+    created             TIMESTAMP NOT NULL,
+    PRIMARY KEY ( idempotency_key )
+);
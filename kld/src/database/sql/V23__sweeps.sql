@@ -0,0 +1,10 @@
+CREATE TABLE sweeps (
+    txid                BYTES NOT NULL,
+    /* The cold storage address and its index in the destination */
+    address             TEXT NOT NULL,
+    address_index       INT8 NOT NULL,
+    amount              INT8 NOT NULL,
+    fee_rate            INT8 NOT NULL,
+    created             TIMESTAMP NOT NULL,
+    PRIMARY KEY ( txid )
+);
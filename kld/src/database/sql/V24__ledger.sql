@@ -0,0 +1,27 @@
+CREATE TYPE ledger_entry_type AS ENUM (
+    'payment_received',
+    'payment_sent',
+    'forward_fee',
+    'channel_open',
+    'channel_close',
+    'sweep',
+    'fee_bump',
+    'deposit',
+    'withdrawal'
+);
+
+/* Append only, entries are never updated or deleted */
+CREATE TABLE ledger (
+    entry_type          ledger_entry_type NOT NULL,
+    /* The payment id, forward id or txid the entry was recorded from */
+    reference           STRING NOT NULL,
+    /* Change of the balance in msats excluding the fee, negative when spent */
+    amount              INT8 NOT NULL,
+    /* Fee paid in msats */
+    fee                 INT8 NOT NULL,
+    channel_id          BYTES,
+    timestamp           TIMESTAMP NOT NULL,
+    created             TIMESTAMP NOT NULL,
+    PRIMARY KEY ( entry_type, reference ),
+    INDEX ( timestamp )
+);
@@ -0,0 +1,9 @@
+/* Indexes for the time ranges and filters of the list endpoints */
+CREATE INDEX ON payments ( timestamp );
+CREATE INDEX ON payments ( status, timestamp );
+CREATE INDEX ON invoices ( timestamp );
+CREATE INDEX ON forwards ( timestamp );
+CREATE INDEX ON forwards ( status, timestamp );
+CREATE INDEX ON forwards ( inbound_channel_id, timestamp );
+CREATE INDEX ON forwards ( outbound_channel_id, timestamp );
+CREATE INDEX ON channels ( is_usable, update_timestamp );
@@ -0,0 +1,3 @@
+/* The kind of HTLCDestination of a failed forward, so that failures can be aggregated */
+ALTER TABLE forwards ADD COLUMN failure_reason STRING;
+CREATE INDEX ON forwards ( status, inbound_channel_id, failure_reason );
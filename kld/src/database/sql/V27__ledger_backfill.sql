@@ -0,0 +1,26 @@
+/* Payments and forwards that succeeded before the ledger was added, in the format of LedgerEntry::payment and LedgerEntry::forward */
+INSERT INTO ledger (entry_type, reference, amount, fee, channel_id, timestamp, created)
+SELECT
+    (CASE direction WHEN 'inbound' THEN 'payment_received' ELSE 'payment_sent' END)::ledger_entry_type,
+    encode(id, 'hex'),
+    CASE direction WHEN 'inbound' THEN amount ELSE -amount END,
+    COALESCE(fee, 0),
+    NULL,
+    timestamp,
+    current_timestamp()::TIMESTAMP
+FROM payments
+WHERE status = 'succeeded'
+ON CONFLICT DO NOTHING;
+
+INSERT INTO ledger (entry_type, reference, amount, fee, channel_id, timestamp, created)
+SELECT
+    'forward_fee',
+    id::STRING,
+    COALESCE(fee, 0),
+    0,
+    outbound_channel_id,
+    timestamp,
+    current_timestamp()::TIMESTAMP
+FROM forwards
+WHERE status = 'succeeded'
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use bitcoin::Txid;
+use time::OffsetDateTime;
+use tokio_postgres::Row;
+
+use super::{microsecond_timestamp, RowExt};
+
+/// A transfer of the hot wallet balance above its target to cold storage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sweep {
+    pub txid: Txid,
+    pub address: String,
+    /// Index of the address in the cold destination.
+    pub address_index: u32,
+    pub amount: u64,
+    /// The fee rate in sats per 1000 weight units.
+    pub fee_rate: u32,
+    pub created: OffsetDateTime,
+}
+
+impl Sweep {
+    pub fn new(
+        txid: Txid,
+        address: String,
+        address_index: u32,
+        amount: u64,
+        fee_rate: u32,
+    ) -> Sweep {
+        Sweep {
+            txid,
+            address,
+            address_index,
+            amount,
+            fee_rate,
+            created: microsecond_timestamp(),
+        }
+    }
+}
+
+impl TryFrom<Row> for Sweep {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self, Self::Error> {
+        Ok(Sweep {
+            txid: row.read("txid")?,
+            address: row.get("address"),
+            address_index: row.get::<&str, i64>("address_index") as u32,
+            amount: row.get::<&str, i64>("amount") as u64,
+            fee_rate: row.get::<&str, i64>("fee_rate") as u32,
+            created: row.get_timestamp("created"),
+        })
+    }
+}
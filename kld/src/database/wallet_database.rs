@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use super::DurableConnection;
 use crate::settings::Settings;
+use crate::wallet::FrozenUtxos;
 use anyhow::Result;
 use bdk::{
     database::{BatchDatabase, BatchOperations, Database, SyncTime},
@@ -47,6 +48,7 @@ macro_rules! query_blocking {
 pub struct WalletDatabase {
     settings: Arc<Settings>,
     durable_connection: Arc<DurableConnection>,
+    wallet: String,
 }
 
 impl WalletDatabase {
@@ -57,6 +59,16 @@ impl WalletDatabase {
         WalletDatabase {
             settings,
             durable_connection,
+            wallet: "bip84".to_string(),
+        }
+    }
+
+    /// The BDK wallets share the tables, the database of each wallet only sees its own rows.
+    pub fn for_wallet(&self, wallet: &str) -> WalletDatabase {
+        WalletDatabase {
+            settings: self.settings.clone(),
+            durable_connection: self.durable_connection.clone(),
+            wallet: wallet.to_string(),
         }
     }
 
@@ -67,8 +79,8 @@ impl WalletDatabase {
         script: &[u8],
     ) -> Result<i64, Error> {
         execute_blocking!(
-            "INSERT INTO wallet_script_pubkeys (keychain, child, script) VALUES ($1, $2, $3)",
-            &[&keychain, &(child as i32), &script],
+            "INSERT INTO wallet_script_pubkeys (keychain, child, script, wallet) VALUES ($1, $2, $3, $4)",
+            &[&keychain, &(child as i32), &script, &self.wallet],
             self
         )
         .map(|_| 0)
@@ -84,8 +96,8 @@ impl WalletDatabase {
         is_spent: bool,
     ) -> Result<i64, Error> {
         execute_blocking!(
-			"UPSERT INTO wallet_utxos (value, keychain, vout, txid, script, is_spent) VALUES ($1, $2, $3, $4, $5, $6)",
-			&[&(value as i64), &keychain, &(vout as i32), &txid, &script, &is_spent],
+			"UPSERT INTO wallet_utxos (value, keychain, vout, txid, script, is_spent, wallet) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+			&[&(value as i64), &keychain, &(vout as i32), &txid, &script, &is_spent, &self.wallet],
 			self
 		)
 		.map(|_| 0)
@@ -93,8 +105,8 @@ impl WalletDatabase {
 
     fn insert_transaction(&self, txid: &[u8], raw_tx: &[u8]) -> Result<i64, Error> {
         execute_blocking!(
-            "INSERT INTO wallet_transactions (txid, raw_tx) VALUES ($1, $2)",
-            &[&txid, &raw_tx],
+            "INSERT INTO wallet_transactions (txid, raw_tx, wallet) VALUES ($1, $2, $3)",
+            &[&txid, &raw_tx, &self.wallet],
             self
         )
         .map(|_| 0)
@@ -102,8 +114,8 @@ impl WalletDatabase {
 
     fn update_transaction(&self, txid: &[u8], raw_tx: &[u8]) -> Result<(), Error> {
         execute_blocking!(
-            "UPDATE wallet_transactions SET raw_tx=$1 WHERE txid=$2",
-            &[&txid, &raw_tx],
+            "UPDATE wallet_transactions SET raw_tx=$1 WHERE txid=$2 AND wallet=$3",
+            &[&raw_tx, &txid, &self.wallet],
             self
         )
         .map(|_| ())
@@ -121,14 +133,15 @@ impl WalletDatabase {
         let txid: &[u8] = transaction.txid.as_ref();
 
         execute_blocking!(
-			"INSERT INTO wallet_transaction_details (txid, timestamp, received, sent, fee, height) VALUES ($1, $2, $3, $4, $5, $6)",
+			"INSERT INTO wallet_transaction_details (txid, timestamp, received, sent, fee, height, wallet) VALUES ($1, $2, $3, $4, $5, $6, $7)",
 			&[
 				&txid,
 				&timestamp.map(|x| x as i64),
 				&(transaction.received as i64),
 				&(transaction.sent as i64),
 				&transaction.fee.map(|x| x as i64),
-				&height.map(|x| x as i64)
+				&height.map(|x| x as i64),
+				&self.wallet
 			],
 			self
 		)
@@ -147,7 +160,7 @@ impl WalletDatabase {
         let txid: &[u8] = transaction.txid.as_ref();
 
         execute_blocking!(
-			"UPDATE wallet_transaction_details SET timestamp=$1, received=$2, sent=$3, fee=$4, height=$5 WHERE txid=$6",
+			"UPDATE wallet_transaction_details SET timestamp=$1, received=$2, sent=$3, fee=$4, height=$5 WHERE txid=$6 AND wallet=$7",
 			&[
 				&timestamp.map(|x| x as i64),
 				&(transaction.received as i64),
@@ -155,6 +168,7 @@ impl WalletDatabase {
 				&transaction.fee.map(|x| x as i64),
 				&height.map(|x| x as i64),
 				&txid,
+				&self.wallet,
 			],
 			self
 		)
@@ -163,8 +177,8 @@ impl WalletDatabase {
 
     fn insert_last_derivation_index(&self, keychain: String, value: u32) -> Result<i64, Error> {
         execute_blocking!(
-            "INSERT INTO wallet_last_derivation_indices (keychain, value) VALUES ($1, $2)",
-            &[&keychain, &(value as i64)],
+            "INSERT INTO wallet_last_derivation_indices (keychain, value, wallet) VALUES ($1, $2, $3)",
+            &[&keychain, &(value as i64), &self.wallet],
             self
         )
         .map(|_| 0)
@@ -172,8 +186,8 @@ impl WalletDatabase {
 
     fn insert_checksum(&self, keychain: String, checksum: &[u8]) -> Result<i64, Error> {
         execute_blocking!(
-            "INSERT INTO wallet_checksums (keychain, checksum) VALUES ($1, $2)",
-            &[&keychain, &checksum],
+            "INSERT INTO wallet_checksums (keychain, checksum, wallet) VALUES ($1, $2, $3)",
+            &[&keychain, &checksum, &self.wallet],
             self
         )
         .map(|_| 0)
@@ -181,8 +195,8 @@ impl WalletDatabase {
 
     fn update_last_derivation_index(&self, keychain: String, value: u32) -> Result<(), Error> {
         execute_blocking!(
-            "UPSERT INTO wallet_last_derivation_indices (keychain, value) VALUES ($1, $2)",
-            &[&keychain, &(value as i64)],
+            "UPSERT INTO wallet_last_derivation_indices (keychain, value, wallet) VALUES ($1, $2, $3)",
+            &[&keychain, &(value as i64), &self.wallet],
             self
         )
         .map(|_| ())
@@ -190,10 +204,11 @@ impl WalletDatabase {
 
     fn update_sync_time(&self, data: SyncTime) -> Result<i64, Error> {
         execute_blocking!(
-            "UPSERT INTO wallet_sync_time (id, height, timestamp) VALUES (0, $1, $2)",
+            "UPSERT INTO wallet_sync_time (id, height, timestamp, wallet) VALUES (0, $1, $2, $3)",
             &[
                 &(data.block_time.height as i64),
-                &(data.block_time.timestamp as i64)
+                &(data.block_time.timestamp as i64),
+                &self.wallet
             ],
             self
         )
@@ -201,7 +216,11 @@ impl WalletDatabase {
     }
 
     fn select_script_pubkeys(&self) -> Result<Vec<ScriptBuf>, Error> {
-        let rows = query_blocking!("SELECT script FROM wallet_script_pubkeys", &[], self)?;
+        let rows = query_blocking!(
+            "SELECT script FROM wallet_script_pubkeys WHERE wallet=$1",
+            &[&self.wallet],
+            self
+        )?;
         let mut scripts: Vec<ScriptBuf> = vec![];
         for row in rows {
             let raw_script: Vec<u8> = row.get(0);
@@ -212,8 +231,8 @@ impl WalletDatabase {
 
     fn select_script_pubkeys_by_keychain(&self, keychain: String) -> Result<Vec<ScriptBuf>, Error> {
         let rows = query_blocking!(
-            "SELECT script FROM wallet_script_pubkeys WHERE keychain=$1",
-            &[&keychain],
+            "SELECT script FROM wallet_script_pubkeys WHERE keychain=$1 AND wallet=$2",
+            &[&keychain, &self.wallet],
             self
         )?;
         let mut scripts: Vec<ScriptBuf> = vec![];
@@ -230,8 +249,8 @@ impl WalletDatabase {
         child: u32,
     ) -> Result<Option<ScriptBuf>, Error> {
         let rows = query_blocking!(
-            "SELECT script FROM wallet_script_pubkeys WHERE keychain=$1 AND child=$2",
-            &[&keychain, &(child as i32)],
+            "SELECT script FROM wallet_script_pubkeys WHERE keychain=$1 AND child=$2 AND wallet=$3",
+            &[&keychain, &(child as i32), &self.wallet],
             self
         )?;
 
@@ -249,8 +268,8 @@ impl WalletDatabase {
         script: &[u8],
     ) -> Result<Option<(KeychainKind, u32)>, Error> {
         let rows = query_blocking!(
-            "SELECT keychain, child FROM wallet_script_pubkeys WHERE script=$1",
-            &[&script],
+            "SELECT keychain, child FROM wallet_script_pubkeys WHERE script=$1 AND wallet=$2",
+            &[&script, &self.wallet],
             self
         )?;
         match rows.first() {
@@ -266,8 +285,8 @@ impl WalletDatabase {
 
     fn select_utxos(&self) -> Result<Vec<LocalUtxo>, Error> {
         let rows = query_blocking!(
-            "SELECT value, keychain, vout, txid, script, is_spent FROM wallet_utxos",
-            &[],
+            "SELECT value, keychain, vout, txid, script, is_spent FROM wallet_utxos WHERE wallet=$1",
+            &[&self.wallet],
             self
         )?;
         let mut utxos: Vec<LocalUtxo> = vec![];
@@ -296,8 +315,8 @@ impl WalletDatabase {
 
     fn select_utxo_by_outpoint(&self, txid: &[u8], vout: u32) -> Result<Option<LocalUtxo>, Error> {
         let rows = query_blocking!(
-            "SELECT value, keychain, script, is_spent FROM wallet_utxos WHERE txid=$1 AND vout=$2",
-            &[&txid, &(vout as i32)],
+            "SELECT value, keychain, script, is_spent FROM wallet_utxos WHERE txid=$1 AND vout=$2 AND wallet=$3",
+            &[&txid, &(vout as i32), &self.wallet],
             self
         )?;
         match rows.first() {
@@ -323,7 +342,11 @@ impl WalletDatabase {
     }
 
     fn select_transactions(&self) -> Result<Vec<Transaction>, Error> {
-        let rows = query_blocking!("SELECT raw_tx FROM wallet_transactions", &[], self)?;
+        let rows = query_blocking!(
+            "SELECT raw_tx FROM wallet_transactions WHERE wallet=$1",
+            &[&self.wallet],
+            self
+        )?;
         let mut txs: Vec<Transaction> = vec![];
         for row in rows {
             let raw_tx: Vec<u8> = row.get(0);
@@ -335,8 +358,8 @@ impl WalletDatabase {
 
     fn select_transaction_by_txid(&self, txid: &[u8]) -> Result<Option<Transaction>, Error> {
         let rows = query_blocking!(
-            "SELECT raw_tx FROM wallet_transactions WHERE txid=$1",
-            &[&txid],
+            "SELECT raw_tx FROM wallet_transactions WHERE txid=$1 AND wallet=$2",
+            &[&txid, &self.wallet],
             self
         )?;
         match rows.first() {
@@ -350,7 +373,7 @@ impl WalletDatabase {
     }
 
     fn select_transaction_details_with_raw(&self) -> Result<Vec<TransactionDetails>, Error> {
-        let rows = query_blocking!("SELECT wtd.txid, wtd.timestamp, wtd.received, wtd.sent, wtd.fee, wtd.height, wt.raw_tx FROM wallet_transaction_details wtd, wallet_transactions wt WHERE wtd.txid = wt.txid", &[], self)?;
+        let rows = query_blocking!("SELECT wtd.txid, wtd.timestamp, wtd.received, wtd.sent, wtd.fee, wtd.height, wt.raw_tx FROM wallet_transaction_details wtd, wallet_transactions wt WHERE wtd.txid = wt.txid AND wtd.wallet = wt.wallet AND wtd.wallet=$1", &[&self.wallet], self)?;
         let mut transaction_details: Vec<TransactionDetails> = vec![];
         for row in rows {
             let txid: Vec<u8> = row.get(0);
@@ -394,8 +417,8 @@ impl WalletDatabase {
 
     fn select_transaction_details(&self) -> Result<Vec<TransactionDetails>, Error> {
         let rows = query_blocking!(
-            "SELECT txid, timestamp, received, sent, fee, height FROM wallet_transaction_details",
-            &[],
+            "SELECT txid, timestamp, received, sent, fee, height FROM wallet_transaction_details WHERE wallet=$1",
+            &[&self.wallet],
             self
         )?;
         let mut transaction_details: Vec<TransactionDetails> = vec![];
@@ -435,7 +458,7 @@ impl WalletDatabase {
         &self,
         txid: &[u8],
     ) -> Result<Option<TransactionDetails>, Error> {
-        let rows = query_blocking!("SELECT wtd.timestamp, wtd.received, wtd.sent, wtd.fee, wtd.height, wt.raw_tx FROM wallet_transaction_details wtd, wallet_transactions wt WHERE wtd.txid=wt.txid AND wtd.txid=$1", &[&txid], self)?;
+        let rows = query_blocking!("SELECT wtd.timestamp, wtd.received, wtd.sent, wtd.fee, wtd.height, wt.raw_tx FROM wallet_transaction_details wtd, wallet_transactions wt WHERE wtd.txid=wt.txid AND wtd.wallet=wt.wallet AND wtd.txid=$1 AND wtd.wallet=$2", &[&txid, &self.wallet], self)?;
 
         match rows.first() {
             Some(row) => {
@@ -483,8 +506,8 @@ impl WalletDatabase {
         keychain: String,
     ) -> Result<Option<u32>, Error> {
         let rows = query_blocking!(
-            "SELECT value FROM wallet_last_derivation_indices WHERE keychain=$1",
-            &[&keychain],
+            "SELECT value FROM wallet_last_derivation_indices WHERE keychain=$1 AND wallet=$2",
+            &[&keychain, &self.wallet],
             self
         )?;
         match rows.first() {
@@ -498,8 +521,8 @@ impl WalletDatabase {
 
     fn select_sync_time(&self) -> Result<Option<SyncTime>, Error> {
         let rows = query_blocking!(
-            "SELECT height, timestamp FROM wallet_sync_time WHERE id = 0",
-            &[],
+            "SELECT height, timestamp FROM wallet_sync_time WHERE id = 0 AND wallet=$1",
+            &[&self.wallet],
             self
         )?;
 
@@ -517,8 +540,8 @@ impl WalletDatabase {
 
     fn select_checksum_by_keychain(&self, keychain: String) -> Result<Option<Vec<u8>>, Error> {
         let rows = query_blocking!(
-            "SELECT checksum FROM wallet_checksums WHERE keychain=$1",
-            &[&keychain],
+            "SELECT checksum FROM wallet_checksums WHERE keychain=$1 AND wallet=$2",
+            &[&keychain, &self.wallet],
             self
         )?;
 
@@ -533,8 +556,8 @@ impl WalletDatabase {
 
     fn delete_script_pubkey_by_path(&self, keychain: String, child: u32) -> Result<(), Error> {
         execute_blocking!(
-            "DELETE FROM wallet_script_pubkeys WHERE keychain=$1 AND child=$2",
-            &[&keychain, &(child as i32)],
+            "DELETE FROM wallet_script_pubkeys WHERE keychain=$1 AND child=$2 AND wallet=$3",
+            &[&keychain, &(child as i32), &self.wallet],
             self
         )
         .map(|_| ())
@@ -542,8 +565,8 @@ impl WalletDatabase {
 
     fn delete_script_pubkey_by_script(&self, script: &[u8]) -> Result<(), Error> {
         execute_blocking!(
-            "DELETE FROM wallet_script_pubkeys WHERE script=$1",
-            &[&script],
+            "DELETE FROM wallet_script_pubkeys WHERE script=$1 AND wallet=$2",
+            &[&script, &self.wallet],
             self
         )
         .map(|_| ())
@@ -551,8 +574,8 @@ impl WalletDatabase {
 
     fn delete_utxo_by_outpoint(&self, txid: &[u8], vout: u32) -> Result<(), Error> {
         execute_blocking!(
-            "DELETE FROM wallet_utxos WHERE txid=$1 AND vout=$2",
-            &[&txid, &(vout as i32)],
+            "DELETE FROM wallet_utxos WHERE txid=$1 AND vout=$2 AND wallet=$3",
+            &[&txid, &(vout as i32), &self.wallet],
             self
         )
         .map(|_| ())
@@ -560,8 +583,8 @@ impl WalletDatabase {
 
     fn delete_transaction_by_txid(&self, txid: &[u8]) -> Result<(), Error> {
         execute_blocking!(
-            "DELETE FROM wallet_transactions WHERE txid=$1",
-            &[&txid],
+            "DELETE FROM wallet_transactions WHERE txid=$1 AND wallet=$2",
+            &[&txid, &self.wallet],
             self
         )
         .map(|_| ())
@@ -569,8 +592,8 @@ impl WalletDatabase {
 
     fn delete_transaction_details_by_txid(&self, txid: &[u8]) -> Result<(), Error> {
         execute_blocking!(
-            "DELETE FROM wallet_transaction_details WHERE txid=$1",
-            &[&txid],
+            "DELETE FROM wallet_transaction_details WHERE txid=$1 AND wallet=$2",
+            &[&txid, &self.wallet],
             self
         )
         .map(|_| ())
@@ -578,15 +601,20 @@ impl WalletDatabase {
 
     fn delete_last_derivation_index_by_keychain(&self, keychain: String) -> Result<(), Error> {
         execute_blocking!(
-            "DELETE FROM wallet_last_derivation_indices WHERE keychain=$1",
-            &[&keychain],
+            "DELETE FROM wallet_last_derivation_indices WHERE keychain=$1 AND wallet=$2",
+            &[&keychain, &self.wallet],
             self
         )
         .map(|_| ())
     }
 
     fn delete_sync_time(&self) -> Result<(), Error> {
-        execute_blocking!("DELETE FROM wallet_sync_time WHERE id = 0", &[], self).map(|_| ())
+        execute_blocking!(
+            "DELETE FROM wallet_sync_time WHERE id = 0 AND wallet=$1",
+            &[&self.wallet],
+            self
+        )
+        .map(|_| ())
     }
 }
 
@@ -869,10 +897,7 @@ impl BatchDatabase for WalletDatabase {
     fn begin_batch(&self) -> Result<Self::Batch, Error> {
         tokio::task::block_in_place(move || {
             Handle::current().block_on(async move {
-                let database = WalletDatabase {
-                    settings: self.settings.clone(),
-                    durable_connection: self.durable_connection.clone(),
-                };
+                let database = self.for_wallet(&self.wallet);
                 database
                     .durable_connection
                     .get()
@@ -899,3 +924,37 @@ impl BatchDatabase for WalletDatabase {
         })
     }
 }
+
+// Outpoints are unique across the wallets, so freezing is not limited to the rows of this wallet.
+impl FrozenUtxos for WalletDatabase {
+    fn frozen_utxos(&self) -> Result<Vec<OutPoint>> {
+        let rows = query_blocking!(
+            "SELECT txid, vout FROM wallet_utxos WHERE is_frozen = true AND is_spent = false",
+            &[],
+            self
+        )?;
+        let mut outpoints = vec![];
+        for row in rows {
+            let txid: Vec<u8> = row.get(0);
+            let vout = row.get::<usize, i32>(1) as u32;
+            outpoints.push(OutPoint::new(deserialize(&txid)?, vout));
+        }
+        Ok(outpoints)
+    }
+
+    fn set_frozen(&self, outpoints: &[OutPoint], frozen: bool) -> Result<Vec<OutPoint>> {
+        let mut updated = vec![];
+        for outpoint in outpoints {
+            let txid: &[u8] = outpoint.txid.as_ref();
+            let rows = execute_blocking!(
+                "UPDATE wallet_utxos SET is_frozen = $1 WHERE txid = $2 AND vout = $3",
+                &[&frozen, &txid, &(outpoint.vout as i32)],
+                self
+            )?;
+            if rows > 0 {
+                updated.push(*outpoint);
+            }
+        }
+        Ok(updated)
+    }
+}
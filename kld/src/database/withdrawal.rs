@@ -0,0 +1,27 @@
+use anyhow::Result;
+use bitcoin::Transaction;
+use time::OffsetDateTime;
+use tokio_postgres::Row;
+
+use super::RowExt;
+
+/// A withdrawal made with an idempotency key so that a retried request never pays twice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Withdrawal {
+    pub idempotency_key: String,
+    /// None while the transaction is being built.
+    pub tx: Option<Transaction>,
+    pub created: OffsetDateTime,
+}
+
+impl TryFrom<Row> for Withdrawal {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self, Self::Error> {
+        Ok(Withdrawal {
+            idempotency_key: row.get("idempotency_key"),
+            tx: row.read_optional("tx")?,
+            created: row.get_timestamp("created"),
+        })
+    }
+}
@@ -63,16 +63,17 @@ async fn run_kld(settings: Arc<Settings>) -> Result<()> {
 
     let bitcoind_client = Arc::new(BitcoindClient::new(&settings).await?);
 
-    // Hot fix the fee rate, due to Signet
-    // bitcoind_client.poll_for_fee_estimates();
-    bitcoind_client.set_lowest_fee_estimates();
+    bitcoind_client.update_fee_estimates().await;
+    bitcoind_client.poll_for_fee_estimates();
 
     let wallet = Arc::new(
         Wallet::new(
             &key_generator.wallet_seed(),
             settings.clone(),
             bitcoind_client.clone(),
-            wallet_database,
+            wallet_database.clone(),
+            wallet_database.for_wallet("bip86"),
+            Arc::new(wallet_database),
         )
         .context("Cannot create wallet")?,
     );
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::secp256k1::PublicKey;
+use lightning::ln::{channelmanager::ChannelDetails, msgs};
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use rand::random;
+use serde::{Deserialize, Serialize};
+
+use crate::api::SocketAddress;
+
+const BACKUP_VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// Everything we need to get our counterparties to force close our channels after a total
+/// loss of the database. It does not contain any channel state, so it never goes stale
+/// in a dangerous way and only needs to be exported again when channels are opened.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct StaticChannelBackup {
+    pub node_id: String,
+    pub timestamp: u64,
+    pub peers: Vec<PeerBackup>,
+    pub channels: Vec<ChannelBackup>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PeerBackup {
+    pub public_key: String,
+    pub addresses: Vec<SocketAddress>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ChannelBackup {
+    pub channel_id: String,
+    pub counterparty: String,
+    pub funding_txo: Option<String>,
+    pub channel_value_satoshis: u64,
+}
+
+impl StaticChannelBackup {
+    pub fn new(
+        node_id: PublicKey,
+        timestamp: u64,
+        channels: &[ChannelDetails],
+        peers: HashMap<PublicKey, Vec<msgs::SocketAddress>>,
+    ) -> StaticChannelBackup {
+        let mut peer_backups: Vec<PeerBackup> = vec![];
+        for channel in channels {
+            let public_key = channel.counterparty.node_id.to_string();
+            if !peer_backups.iter().any(|p| p.public_key == public_key) {
+                peer_backups.push(PeerBackup {
+                    public_key,
+                    addresses: vec![],
+                });
+            }
+        }
+        for (public_key, addresses) in peers {
+            let public_key = public_key.to_string();
+            let addresses: Vec<SocketAddress> = addresses.into_iter().map(SocketAddress).collect();
+            match peer_backups.iter_mut().find(|p| p.public_key == public_key) {
+                Some(backup) => backup.addresses = addresses,
+                None => peer_backups.push(PeerBackup {
+                    public_key,
+                    addresses,
+                }),
+            }
+        }
+        StaticChannelBackup {
+            node_id: node_id.to_string(),
+            timestamp,
+            peers: peer_backups,
+            channels: channels
+                .iter()
+                .map(|c| ChannelBackup {
+                    channel_id: c.channel_id.to_string(),
+                    counterparty: c.counterparty.node_id.to_string(),
+                    funding_txo: c.funding_txo.map(|o| format!("{}:{}", o.txid, o.index)),
+                    channel_value_satoshis: c.channel_value_satoshis,
+                })
+                .collect(),
+        }
+    }
+
+    /// The layout is: version (1 byte) | nonce (12 bytes) | tag (16 bytes) | ciphertext.
+    pub fn encrypt(&self, key: &[u8; 32]) -> Result<Vec<u8>> {
+        let plaintext = serde_json::to_vec(self)?;
+        let nonce: [u8; NONCE_LEN] = random();
+        let mut tag = [0u8; TAG_LEN];
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            key,
+            Some(&nonce),
+            &[BACKUP_VERSION],
+            &plaintext,
+            &mut tag,
+        )?;
+        let mut backup = Vec::with_capacity(1 + NONCE_LEN + TAG_LEN + ciphertext.len());
+        backup.push(BACKUP_VERSION);
+        backup.extend_from_slice(&nonce);
+        backup.extend_from_slice(&tag);
+        backup.extend_from_slice(&ciphertext);
+        Ok(backup)
+    }
+
+    pub fn decrypt(key: &[u8; 32], backup: &[u8]) -> Result<StaticChannelBackup> {
+        if backup.len() < 1 + NONCE_LEN + TAG_LEN {
+            bail!("Channel backup is too short");
+        }
+        if backup[0] != BACKUP_VERSION {
+            bail!("Unsupported channel backup version {}", backup[0]);
+        }
+        let nonce = &backup[1..1 + NONCE_LEN];
+        let tag = &backup[1 + NONCE_LEN..1 + NONCE_LEN + TAG_LEN];
+        let ciphertext = &backup[1 + NONCE_LEN + TAG_LEN..];
+        let plaintext = decrypt_aead(
+            Cipher::aes_256_gcm(),
+            key,
+            Some(nonce),
+            &[BACKUP_VERSION],
+            ciphertext,
+            tag,
+        )
+        .map_err(|_| {
+            anyhow!("Could not decrypt channel backup, was it made with this mnemonic?")
+        })?;
+        serde_json::from_slice(&plaintext).context("Malformed channel backup")
+    }
+}
+
+impl PeerBackup {
+    pub fn public_key(&self) -> Result<PublicKey> {
+        PublicKey::from_str(&self.public_key)
+            .with_context(|| format!("Invalid public key in channel backup {}", self.public_key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_utils::{random_public_key, TEST_PUBLIC_KEY};
+
+    #[test]
+    fn test_encrypt_decrypt() -> Result<()> {
+        let peers = HashMap::from([(
+            random_public_key(),
+            vec![msgs::SocketAddress::TcpIpV4 {
+                addr: [127, 0, 0, 1],
+                port: 9234,
+            }],
+        )]);
+        let backup = StaticChannelBackup::new(
+            PublicKey::from_str(TEST_PUBLIC_KEY)?,
+            1700000000,
+            &[],
+            peers,
+        );
+        assert_eq!(1, backup.peers.len());
+
+        let encrypted = backup.encrypt(&[1u8; 32])?;
+        assert_eq!(
+            backup,
+            StaticChannelBackup::decrypt(&[1u8; 32], &encrypted)?
+        );
+        assert!(StaticChannelBackup::decrypt(&[2u8; 32], &encrypted).is_err());
+        Ok(())
+    }
+}
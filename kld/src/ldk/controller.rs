@@ -1,11 +1,19 @@
 use crate::bitcoind::bitcoind_interface::BitcoindInterface;
 use crate::bitcoind::{BitcoindClient, BitcoindUtxoLookup};
-use crate::database::forward::{Forward, ForwardStatus, TotalForwards};
-use crate::database::invoice::Invoice;
-use crate::database::payment::{Payment, PaymentDirection};
-use crate::database::ChannelRecord;
+use crate::database::fee_bump::{FeeBump, FeeBumpMethod};
+use crate::database::forward::{ChannelForwardStats, Forward, ForwardStatus, TotalForwards};
+use crate::database::invoice::{Invoice, InvoiceStatusFilter};
+use crate::database::label::Label;
+use crate::database::ledger::{LedgerEntry, LedgerEntryType};
+use crate::database::node_announcement::NodeAnnouncementConfig;
+use crate::database::payment::{Payment, PaymentDirection, PaymentStatus};
+use crate::database::peer::{uptime, PeerEvent, UPTIME_WINDOW};
+use crate::database::psbt::{PendingPsbt, PsbtPurpose};
+use crate::database::scorer_parameters::ScorerParameters;
+use crate::database::sweep::Sweep;
+use crate::database::{microsecond_timestamp, ChannelRecord, Pagination};
 use crate::key_generator::KeyGenerator;
-use crate::wallet::{Wallet, WalletInterface};
+use crate::wallet::{Consolidation, Wallet, WalletInterface};
 use crate::{log_error, MillisatAmount, Service};
 
 use crate::api::payloads::FeeRate;
@@ -13,9 +21,12 @@ use crate::api::SocketAddress;
 use crate::database::{DurableConnection, LdkDatabase, WalletDatabase};
 use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
+use bdk::TransactionDetails;
+use bitcoin::psbt::PartiallySignedTransaction;
 use bitcoin::secp256k1::PublicKey;
-use bitcoin::{BlockHash, Network, Transaction};
+use bitcoin::{Address, BlockHash, Network, OutPoint, ScriptBuf, Transaction, TxOut, Txid};
 use lightning::chain;
+use lightning::chain::chaininterface::BroadcasterInterface;
 use lightning::chain::channelmonitor::ChannelMonitor;
 use lightning::chain::BestBlock;
 use lightning::chain::Watch;
@@ -28,16 +39,16 @@ use lightning::ln::peer_handler::{IgnoringMessageHandler, MessageHandler};
 use lightning::ln::ChannelId;
 use lightning::routing::gossip::{ChannelInfo, NodeId, NodeInfo, P2PGossipSync};
 use lightning::routing::router::{
-    DefaultRouter, Path, PaymentParameters, RouteParameters, Router,
-    ScorerAccountingForInFlightHtlcs,
+    Path, PaymentParameters, RouteParameters, Router, ScorerAccountingForInFlightHtlcs,
 };
 use lightning::routing::scoring::ScoreUpdate;
 use lightning::routing::scoring::{
     ProbabilisticScorer, ProbabilisticScoringDecayParameters, ProbabilisticScoringFeeParameters,
 };
-use lightning::sign::{InMemorySigner, KeysManager};
+use lightning::sign::{InMemorySigner, KeysManager, SignerProvider, SpendableOutputDescriptor};
 use lightning::util::config::UserConfig;
 use lightning::util::errors::APIError;
+use lightning::util::ser::{ReadableArgs, Writeable};
 
 use crate::ldk::peer_manager::KuutamoPeerManger;
 use crate::logger::KldLogger;
@@ -59,24 +70,38 @@ use log::{debug, error, info, trace, warn};
 use prometheus::IntCounter;
 use rand::random;
 use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
 use std::sync::Arc;
 use std::sync::OnceLock;
 use std::thread::sleep;
 use std::time::{Duration, SystemTime};
+use time::OffsetDateTime;
+use uuid::Uuid;
 
 use futures::{future::Shared, Future};
 use tokio::sync::oneshot::{self, Receiver, Sender};
 use tokio::sync::RwLock;
 
+use super::channel_backup::StaticChannelBackup;
 use super::event_handler::EventHandler;
-use super::peer_manager::PeerManager;
+use super::node_announcer::NodeAnnouncer;
+use super::peer_manager::{preferred_addresses, PeerManager};
+use super::rgs::{RgsClient, SnapshotSource};
+use super::rgs_server::RgsServer;
 use super::{
     ldk_error, lightning_error, payment_send_failure, retryable_send_failure,
-    sign_or_creation_error, ChainMonitor, ChannelManager, KldRouter, KuutamoCustomMessageHandler,
-    LightningInterface, LiquidityManager, NetworkGraph, OnionMessenger, OpenChannelResult, Peer,
-    PeerStatus, Scorer,
+    sign_or_creation_error, ChainMonitor, ChannelLiquidity, ChannelManager, KldRouter,
+    KuutamoCustomMessageHandler, LightningInterface, LiquidityManager, NetworkGraph,
+    OnionMessenger, OpenChannelResult, Peer, PeerHistory, PeerStatus, Scorer, ScorerLiquidity,
+    TransactionPurpose, TransactionTag,
 };
 
+/// Number of rounds (one per minute) to try connecting to peers from a channel backup.
+const RECOVERY_ATTEMPTS: usize = 60;
+
+/// How often the confirmed wallet transactions are appended to the ledger.
+const LEDGER_RECORD_INTERVAL: Duration = Duration::from_secs(60);
+
 #[async_trait]
 impl LightningInterface for Controller {
     fn identity_pubkey(&self) -> PublicKey {
@@ -101,6 +126,16 @@ impl LightningInterface for Controller {
         self.network_graph.read_only().channels().len()
     }
 
+    fn rgs_snapshot_timestamp(&self) -> Option<u32> {
+        self.network_graph.get_last_rapid_gossip_sync_timestamp()
+    }
+
+    fn rgs_snapshot(&self, last_sync_timestamp: u32) -> Option<Vec<u8>> {
+        self.rgs_server
+            .as_ref()
+            .and_then(|server| server.snapshot(last_sync_timestamp))
+    }
+
     fn num_peers(&self) -> usize {
         self.peer_manager.get_connected_peers().len()
     }
@@ -116,11 +151,11 @@ impl LightningInterface for Controller {
     }
 
     fn alias(&self) -> String {
-        self.settings.node_alias.clone()
+        self.node_announcer.config().alias
     }
 
     fn color(&self) -> String {
-        self.settings.node_alias_color.clone()
+        self.node_announcer.config().color
     }
 
     fn network(&self) -> bitcoin::Network {
@@ -212,6 +247,308 @@ impl LightningInterface for Controller {
         })
     }
 
+    async fn create_withdraw_psbt(
+        &self,
+        address: Address,
+        amount: u64,
+        fee_rate: Option<FeeRate>,
+        utxos: Vec<OutPoint>,
+        sign: bool,
+    ) -> Result<PendingPsbt> {
+        if !self.bitcoind_client.is_synchronised().await {
+            bail!("Bitcoind is synchronising blockchain")
+        }
+        let unspendable = self.pending_psbt_inputs().await?;
+        let psbt = self.wallet.create_psbt(
+            address.script_pubkey(),
+            amount,
+            fee_rate.unwrap_or_default(),
+            &utxos,
+            unspendable,
+            sign,
+        )?;
+        let psbt = PendingPsbt::withdraw(psbt, self.psbt_expiry());
+        self.database.persist_psbt(&psbt).await?;
+        info!("Created PSBT {} withdrawing to {address}", psbt.id);
+        Ok(psbt)
+    }
+
+    async fn create_funding_psbt(
+        &self,
+        their_network_key: PublicKey,
+        channel_value_satoshis: u64,
+        push_msat: Option<u64>,
+        fee_rate: Option<FeeRate>,
+        override_config: Option<UserConfig>,
+        sign: bool,
+    ) -> Result<PendingPsbt> {
+        if !self.bitcoind_client.is_synchronised().await {
+            bail!("Bitcoind is synchronising blockchain")
+        }
+        if !self.peer_manager.is_connected(&their_network_key) {
+            return Err(anyhow!("Peer not connected"));
+        }
+        let unspendable = self.pending_psbt_inputs().await?;
+        let user_channel_id: u64 = random::<u64>() / 2; // To fit into the database INT
+        let is_public = override_config
+            .map(|c| c.channel_handshake_config.announced_channel)
+            .unwrap_or_default();
+        let channel_id = self
+            .channel_manager
+            .create_channel(
+                their_network_key,
+                channel_value_satoshis,
+                push_msat.unwrap_or_default(),
+                user_channel_id as u128,
+                None,
+                override_config,
+            )
+            .map_err(ldk_error)?;
+        let receiver = self
+            .async_api_requests
+            .funding_psbts
+            .insert(
+                user_channel_id,
+                FundingPsbtRequest {
+                    fee_rate: fee_rate.unwrap_or_default(),
+                    unspendable,
+                    sign,
+                },
+            )
+            .await;
+        let psbt = receiver.await??;
+        let psbt =
+            PendingPsbt::fund_channel(psbt, channel_id, their_network_key, self.psbt_expiry());
+        self.database.persist_psbt(&psbt).await?;
+        if let Err(e) = self
+            .database
+            .persist_initializing_channel(&channel_id, is_public, &their_network_key, &psbt.txid())
+            .await
+        {
+            // This failure should not cause issues, the channel detail update will be retried later,
+            // triggered on the next event, so we do not retry and only log the error but not raise it here.
+            log_error(&e);
+        }
+        info!(
+            "Created PSBT {} funding channel {}",
+            psbt.id,
+            hex::encode(channel_id.0)
+        );
+        Ok(psbt)
+    }
+
+    async fn list_psbts(&self) -> Result<Vec<PendingPsbt>> {
+        self.database.fetch_pending_psbts().await
+    }
+
+    async fn pending_psbt_inputs(&self) -> Result<Vec<OutPoint>> {
+        Ok(self
+            .database
+            .fetch_pending_psbts()
+            .await?
+            .iter()
+            .flat_map(|psbt| psbt.inputs())
+            .collect())
+    }
+
+    async fn submit_psbt(&self, id: Uuid, psbt: PartiallySignedTransaction) -> Result<Transaction> {
+        let pending = self
+            .database
+            .fetch_psbt(&id)
+            .await?
+            .with_context(|| format!("PSBT {id} not found"))?;
+        if pending.completed {
+            bail!("PSBT {id} has already been submitted");
+        }
+        if pending.is_expired(OffsetDateTime::now_utc()) {
+            bail!("PSBT {id} has expired");
+        }
+        let tx = self.wallet.finalize_psbt(pending.combine(psbt)?)?;
+        match pending.purpose {
+            PsbtPurpose::Withdraw => {
+                self.bitcoind_client.broadcast_transactions(&[&tx]);
+            }
+            PsbtPurpose::FundChannel => {
+                let (Some(temporary_channel_id), Some(counterparty)) =
+                    (pending.temporary_channel_id, pending.counterparty)
+                else {
+                    bail!("PSBT {id} is missing the channel it funds");
+                };
+                self.channel_manager
+                    .funding_transaction_generated(&temporary_channel_id, &counterparty, tx.clone())
+                    .map_err(ldk_error)?;
+            }
+        }
+        info!("Submitted PSBT {id} with txid {}", tx.txid());
+        self.database
+            .persist_psbt(&PendingPsbt {
+                completed: true,
+                ..pending
+            })
+            .await?;
+        Ok(tx)
+    }
+
+    async fn withdraw_many(
+        &self,
+        outputs: Vec<(Address, u64)>,
+        fee_rate: Option<FeeRate>,
+        idempotency_key: Option<String>,
+    ) -> Result<Transaction> {
+        if !self.bitcoind_client.is_synchronised().await {
+            bail!("Bitcoind is synchronising blockchain")
+        }
+        let outputs: Vec<(ScriptBuf, u64)> = outputs
+            .into_iter()
+            .map(|(address, amount)| (address.script_pubkey(), amount))
+            .collect();
+        if let Some(key) = &idempotency_key {
+            if !self
+                .database
+                .reserve_withdrawal(key, microsecond_timestamp())
+                .await?
+            {
+                let withdrawal = self
+                    .database
+                    .fetch_withdrawal(key)
+                    .await?
+                    .with_context(|| format!("Withdrawal {key} not found"))?;
+                let Some(tx) = withdrawal.tx else {
+                    bail!("Withdrawal {key} is in progress");
+                };
+                // Besides the requested outputs the transaction can only have our change.
+                let same_outputs = match unmatched_outputs(&outputs, &tx) {
+                    Some(unmatched) if unmatched.len() <= 1 => {
+                        let mut change = true;
+                        for output in unmatched {
+                            change &= self.wallet.is_mine(&output.script_pubkey)?;
+                        }
+                        change
+                    }
+                    _ => false,
+                };
+                if !same_outputs {
+                    bail!("Idempotency key {key} was used for a different withdrawal");
+                }
+                // The node may have stopped after persisting the transaction and before broadcasting it.
+                info!(
+                    "Withdrawal {key} was already paid by {}, broadcasting it again",
+                    tx.txid()
+                );
+                self.bitcoind_client.broadcast_transactions(&[&tx]);
+                return Ok(tx);
+            }
+        }
+        let unspendable = self.pending_psbt_inputs().await?;
+        let result = self
+            .wallet
+            .transfer_many(outputs, fee_rate.unwrap_or_default(), unspendable);
+        let tx = match (result, &idempotency_key) {
+            (Ok((tx, _)), Some(key)) => match self.database.persist_withdrawal_tx(key, &tx).await {
+                Ok(()) => tx,
+                Err(e) => {
+                    self.database.delete_withdrawal(key).await?;
+                    return Err(e);
+                }
+            },
+            (Ok((tx, _)), None) => tx,
+            (Err(e), Some(key)) => {
+                self.database.delete_withdrawal(key).await?;
+                return Err(e);
+            }
+            (Err(e), None) => return Err(e),
+        };
+        info!(
+            "Withdrawing to {} outputs with txid {}",
+            tx.output.len(),
+            tx.txid()
+        );
+        self.bitcoind_client.broadcast_transactions(&[&tx]);
+        Ok(tx)
+    }
+
+    async fn list_sweeps(&self) -> Result<Vec<Sweep>> {
+        self.database.fetch_sweeps().await
+    }
+
+    async fn ledger(
+        &self,
+        from: Option<OffsetDateTime>,
+        to: Option<OffsetDateTime>,
+    ) -> Result<Vec<LedgerEntry>> {
+        self.database.fetch_ledger(from, to).await
+    }
+
+    async fn consolidation_report(&self) -> Result<Consolidation> {
+        let unspendable = self.pending_psbt_inputs().await?;
+        self.wallet.consolidate_utxos(unspendable, true)
+    }
+
+    async fn bump_fee(&self, txid: Txid, fee_rate: FeeRate) -> Result<(Transaction, FeeBump)> {
+        if !self.bitcoind_client.is_synchronised().await {
+            bail!("Bitcoind is synchronising blockchain")
+        }
+        let funding_channel_id = self.database.fetch_funding_channel_id(&txid).await?;
+        let (method, (tx, tx_details)) =
+            if funding_channel_id.is_none() && self.wallet.can_replace(&txid)? {
+                let unspendable = self.pending_psbt_inputs().await?;
+                (
+                    FeeBumpMethod::Rbf,
+                    self.wallet.bump_fee_rbf(&txid, fee_rate, unspendable)?,
+                )
+            } else {
+                (
+                    FeeBumpMethod::Cpfp,
+                    self.wallet.bump_fee_cpfp(&txid, fee_rate)?,
+                )
+            };
+        self.bitcoind_client.broadcast_transactions(&[&tx]);
+
+        // Bumping a transaction that was already bumped keeps it tied to the same channel or withdrawal.
+        let previous = self.database.fetch_fee_bump(&txid).await?;
+        let mut fee_bump = FeeBump::new(
+            tx.txid(),
+            previous.as_ref().map_or(txid, |bump| bump.original_txid),
+            method,
+            (self.wallet.to_bdk_fee_rate(fee_rate).as_sat_per_vb() * 250.0) as u32,
+            tx_details.fee.unwrap_or_default(),
+        );
+        fee_bump.channel_id = match previous.as_ref().and_then(|bump| bump.channel_id) {
+            Some(channel_id) => Some(channel_id),
+            None => funding_channel_id,
+        };
+        fee_bump.psbt_id = match previous.and_then(|bump| bump.psbt_id) {
+            Some(psbt_id) => Some(psbt_id),
+            None => self
+                .database
+                .fetch_psbt_by_txid(&txid)
+                .await?
+                .map(|psbt| psbt.id),
+        };
+        info!(
+            "Bumped fee of {txid} with {} transaction {}",
+            method.to_string(),
+            fee_bump.txid
+        );
+        self.database.persist_fee_bump(&fee_bump).await?;
+        Ok((tx, fee_bump))
+    }
+
+    async fn tag_transactions(
+        &self,
+        transactions: &[TransactionDetails],
+    ) -> Result<Vec<TransactionTag>> {
+        Controller::transaction_tags(&self.database, &self.keys_manager, transactions).await
+    }
+
+    async fn list_labels(&self) -> Result<Vec<Label>> {
+        self.database.fetch_labels().await
+    }
+
+    async fn import_labels(&self, labels: Vec<Label>) -> Result<()> {
+        self.database.persist_labels(&labels).await
+    }
+
     async fn close_channel(
         &self,
         channel_id: &ChannelId,
@@ -295,7 +632,13 @@ impl LightningInterface for Controller {
             .iter()
             .map(|c| c.counterparty.node_id)
             .collect();
-        let persistent_peers = self.database.fetch_peers().await?;
+        let mut persistent_peers = self.database.fetch_peers().await?;
+        let now = OffsetDateTime::now_utc();
+        let since = now - UPTIME_WINDOW;
+        let mut events: HashMap<PublicKey, Vec<PeerEvent>> = HashMap::new();
+        for event in self.database.fetch_peer_events(None, since).await? {
+            events.entry(event.public_key).or_default().push(event);
+        }
 
         let mut response = vec![];
 
@@ -323,11 +666,30 @@ impl LightningInterface for Controller {
                 net_address,
                 status,
                 alias: self.alias_of(&public_key).unwrap_or_default(),
+                addresses: persistent_peers.remove(&public_key).unwrap_or_default(),
+                uptime: events
+                    .get(&public_key)
+                    .and_then(|events| uptime(events, since, now)),
             });
         }
         Ok(response)
     }
 
+    async fn peer_history(&self, public_key: PublicKey) -> Result<PeerHistory> {
+        let now = OffsetDateTime::now_utc();
+        let since = now - UPTIME_WINDOW;
+        let events = self
+            .database
+            .fetch_peer_events(Some(&public_key), since)
+            .await?;
+        Ok(PeerHistory {
+            public_key,
+            addresses: self.database.fetch_peer_addresses(&public_key).await?,
+            uptime: uptime(&events, since, now),
+            events,
+        })
+    }
+
     async fn connect_peer(
         &self,
         public_key: PublicKey,
@@ -335,7 +697,12 @@ impl LightningInterface for Controller {
     ) -> Result<()> {
         if let Some(net_address) = peer_address {
             self.peer_manager
-                .connect_peer(self.database.clone(), public_key, net_address)
+                .connect_peer(
+                    self.database.clone(),
+                    public_key,
+                    net_address,
+                    self.settings.tor_proxy,
+                )
                 .await
         } else {
             let addresses: Vec<SocketAddress> = self
@@ -345,12 +712,20 @@ impl LightningInterface for Controller {
                 .context("No addresses found for node")?
                 .into_iter()
                 .map(|a| a.into())
-                .filter(|a: &SocketAddress| a.is_ipv4())
                 .collect();
-            for address in addresses {
+            for address in preferred_addresses(
+                addresses,
+                &self.settings.address_preference,
+                self.settings.tor_proxy,
+            ) {
                 if let Err(e) = self
                     .peer_manager
-                    .connect_peer(self.database.clone(), public_key, address.clone())
+                    .connect_peer(
+                        self.database.clone(),
+                        public_key,
+                        address.clone(),
+                        self.settings.tor_proxy,
+                    )
                     .await
                 {
                     info!("Could not connect to {public_key}@{address}. {}", e);
@@ -369,7 +744,30 @@ impl LightningInterface for Controller {
     }
 
     fn public_addresses(&self) -> Vec<SocketAddress> {
-        self.settings.public_addresses.clone()
+        self.node_announcer.config().addresses
+    }
+
+    async fn update_node_announcement(
+        &self,
+        alias: Option<String>,
+        color: Option<String>,
+        addresses: Option<Vec<SocketAddress>>,
+    ) -> Result<NodeAnnouncementConfig> {
+        let mut config = self.node_announcer.config();
+        if let Some(alias) = alias {
+            config.alias = alias;
+        }
+        if let Some(color) = color {
+            config.color = color;
+        }
+        if let Some(addresses) = addresses {
+            config.addresses = addresses;
+            config.merge_onion_address(&self.settings);
+        }
+        config.validate()?;
+        self.database.persist_node_announcement(&config).await?;
+        self.node_announcer.update(config.clone());
+        Ok(config)
     }
 
     fn get_node(&self, node_id: &NodeId) -> Option<NodeInfo> {
@@ -420,8 +818,15 @@ impl LightningInterface for Controller {
         Ok(invoice)
     }
 
-    async fn list_invoices(&self, label: Option<String>) -> Result<Vec<Invoice>> {
-        self.database.fetch_invoices(label).await
+    async fn list_invoices(
+        &self,
+        label: Option<String>,
+        status: Option<InvoiceStatusFilter>,
+        pagination: Pagination,
+    ) -> Result<Vec<Invoice>> {
+        self.database
+            .fetch_invoices(label, status, pagination)
+            .await
     }
 
     async fn pay_invoice(&self, invoice: Invoice, label: Option<String>) -> Result<Payment> {
@@ -519,9 +924,16 @@ impl LightningInterface for Controller {
         &self,
         invoice: Option<Invoice>,
         direction: Option<PaymentDirection>,
+        statuses: &[PaymentStatus],
+        pagination: Pagination,
     ) -> Result<Vec<Payment>> {
         self.database
-            .fetch_payments(invoice.map(|i| i.payment_hash), direction)
+            .fetch_payments(
+                invoice.map(|i| i.payment_hash),
+                direction,
+                statuses,
+                pagination,
+            )
             .await
     }
 
@@ -541,18 +953,160 @@ impl LightningInterface for Controller {
         self.database.fetch_total_forwards().await
     }
 
-    async fn fetch_forwards(&self, status: Option<ForwardStatus>) -> Result<Vec<Forward>> {
-        self.database.fetch_forwards(status).await
+    async fn routing_stats(
+        &self,
+        from: Option<OffsetDateTime>,
+        to: Option<OffsetDateTime>,
+    ) -> Result<Vec<ChannelForwardStats>> {
+        self.database.fetch_forward_stats(from, to).await
     }
 
-    async fn channel_history(&self) -> Result<Vec<ChannelRecord>> {
-        self.database.fetch_channel_history().await
+    async fn fetch_forwards(
+        &self,
+        status: Option<ForwardStatus>,
+        channel_id: Option<ChannelId>,
+        pagination: Pagination,
+    ) -> Result<Vec<Forward>> {
+        self.database
+            .fetch_forwards(status, channel_id, pagination)
+            .await
+    }
+
+    async fn channel_history(
+        &self,
+        channel_id: Option<ChannelId>,
+        pagination: Pagination,
+    ) -> Result<Vec<ChannelRecord>> {
+        self.database
+            .fetch_channel_history(channel_id, pagination)
+            .await
     }
 
     async fn scorer(&self) -> Result<Vec<u8>> {
         self.database.fetch_scorer_binary().await
     }
 
+    async fn upload_scorer(&self, scorer: Vec<u8>) -> Result<()> {
+        let decay_parameters = self.scorer_parameters().decay_parameters();
+        let uploaded = self.read_scorer(&scorer, decay_parameters)?;
+        validate_scorer(&self.network_graph, &uploaded)?;
+        self.set_scorer(uploaded)?;
+        self.database.persist_scorer_binary(&scorer).await?;
+        info!(
+            "Replaced scorer with an uploaded one of {} bytes",
+            scorer.len()
+        );
+        Ok(())
+    }
+
+    async fn scorer_liquidity(
+        &self,
+        node_id: Option<NodeId>,
+        short_channel_id: Option<u64>,
+    ) -> Result<ScorerLiquidity> {
+        // The scorer locks the graph itself so collect the channels first.
+        let channels: Vec<(u64, NodeId, NodeId, Option<u64>)> = self
+            .network_graph
+            .read_only()
+            .channels()
+            .unordered_iter()
+            .filter(|(scid, _)| short_channel_id.map_or(true, |id| id == **scid))
+            .filter(|(_, info)| {
+                node_id.map_or(true, |id| info.node_one == id || info.node_two == id)
+            })
+            .map(|(scid, info)| (*scid, info.node_one, info.node_two, info.capacity_sats))
+            .collect();
+        let mut liquidity = vec![];
+        {
+            let scorer = self
+                .scorer
+                .read()
+                .map_err(|e| anyhow!("failed to acquire lock on scorer {}", e))?;
+            for (scid, node_one, node_two, capacity_sats) in channels {
+                for (source, target) in [(node_one, node_two), (node_two, node_one)] {
+                    let Some((min_liquidity_msat, max_liquidity_msat)) =
+                        scorer.estimated_channel_liquidity_range(scid, &target)
+                    else {
+                        continue;
+                    };
+                    let historical =
+                        scorer.historical_estimated_channel_liquidity_probabilities(scid, &target);
+                    liquidity.push(ChannelLiquidity {
+                        short_channel_id: scid,
+                        source,
+                        target,
+                        capacity_sats,
+                        min_liquidity_msat,
+                        max_liquidity_msat,
+                        historical_min_liquidity_buckets: historical.map(|(min, _)| min.to_vec()),
+                        historical_max_liquidity_buckets: historical.map(|(_, max)| max.to_vec()),
+                    });
+                }
+            }
+        }
+        liquidity.sort_by_key(|l| l.short_channel_id);
+        Ok(ScorerLiquidity {
+            last_updated: self.database.fetch_scorer_timestamp().await?,
+            channels: liquidity,
+        })
+    }
+
+    fn scorer_parameters(&self) -> ScorerParameters {
+        match self.scorer_parameters.read() {
+            Ok(parameters) => parameters.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    async fn update_scorer_parameters(
+        &self,
+        parameters: ScorerParameters,
+    ) -> Result<ScorerParameters> {
+        parameters.validate()?;
+        {
+            // The decay parameters are part of the scorer so it has to be rebuilt with the new ones. The lock is
+            // held until the parameters are replaced too, so that concurrent updates cannot undo each other.
+            let mut scorer = self
+                .scorer
+                .write()
+                .map_err(|e| anyhow!("failed to acquire lock on scorer {}", e))?;
+            let encoded = scorer.encode();
+            *scorer = self.read_scorer(&encoded, parameters.decay_parameters())?;
+            self.router.set_fee_parameters(parameters.fee_parameters());
+            match self.scorer_parameters.write() {
+                Ok(mut guard) => *guard = parameters.clone(),
+                Err(e) => *e.into_inner() = parameters.clone(),
+            }
+        }
+        self.database.persist_scorer_parameters(&parameters).await?;
+        Ok(parameters)
+    }
+
+    async fn static_channel_backup(&self) -> Result<Vec<u8>> {
+        let peers = self
+            .database
+            .fetch_peers()
+            .await?
+            .into_iter()
+            .map(|(public_key, addresses)| {
+                (
+                    public_key,
+                    addresses.into_iter().map(|a| a.address).collect(),
+                )
+            })
+            .collect();
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+        StaticChannelBackup::new(
+            self.identity_pubkey(),
+            timestamp,
+            &self.channel_manager.list_channels(),
+            peers,
+        )
+        .encrypt(&self.backup_key)
+    }
+
     async fn update_channels(&self, channels: &[ChannelDetails]) {
         for channel in channels {
             if let Err(e) = self.database.persist_channel(channel).await {
@@ -564,8 +1118,62 @@ impl LightningInterface for Controller {
     }
 }
 
+// A scorer that deserializes can still have been built for another network or a stale graph,
+// so require that it knows the liquidity of at least one channel in our graph.
+fn validate_scorer(network_graph: &NetworkGraph, scorer: &Scorer) -> Result<()> {
+    let graph = network_graph.read_only();
+    if graph.channels().is_empty() {
+        return Ok(());
+    }
+    let known = graph.channels().unordered_iter().any(|(scid, info)| {
+        [info.node_one, info.node_two].iter().any(|target| {
+            scorer
+                .estimated_channel_liquidity_range(*scid, target)
+                .is_some()
+        })
+    });
+    if !known {
+        bail!("Invalid scorer: it has no liquidity estimates for channels in the network graph");
+    }
+    Ok(())
+}
+
+pub(crate) fn spendable_outpoint(descriptor: &SpendableOutputDescriptor) -> OutPoint {
+    match descriptor {
+        SpendableOutputDescriptor::StaticOutput { outpoint, .. } => outpoint,
+        SpendableOutputDescriptor::DelayedPaymentOutput(descriptor) => &descriptor.outpoint,
+        SpendableOutputDescriptor::StaticPaymentOutput(descriptor) => &descriptor.outpoint,
+    }
+    .into_bitcoin_outpoint()
+}
+
+/// The outputs of the transaction that are left after matching each of the outputs to one of them.
+/// None when one of the outputs is not paid by the transaction.
+fn unmatched_outputs<'a>(
+    outputs: &[(ScriptBuf, u64)],
+    tx: &'a Transaction,
+) -> Option<Vec<&'a TxOut>> {
+    let mut unmatched: Vec<&TxOut> = tx.output.iter().collect();
+    for (script, amount) in outputs {
+        let index = unmatched
+            .iter()
+            .position(|output| output.script_pubkey == *script && output.value == *amount)?;
+        unmatched.swap_remove(index);
+    }
+    Some(unmatched)
+}
+
+fn transaction_inputs(details: &TransactionDetails) -> Vec<OutPoint> {
+    details
+        .transaction
+        .iter()
+        .flat_map(|tx| tx.input.iter().map(|input| input.previous_output))
+        .collect()
+}
+
 pub(crate) struct AsyncAPIRequests {
     pub funding_transactions: AsyncSenders<u64, FeeRate, Result<Transaction>>,
+    pub funding_psbts: AsyncSenders<u64, FundingPsbtRequest, Result<PartiallySignedTransaction>>,
     pub payments: AsyncSenders<PaymentId, Payment, Result<Payment>>,
 }
 
@@ -573,11 +1181,20 @@ impl AsyncAPIRequests {
     fn new() -> AsyncAPIRequests {
         AsyncAPIRequests {
             funding_transactions: AsyncSenders::new(),
+            funding_psbts: AsyncSenders::new(),
             payments: AsyncSenders::new(),
         }
     }
 }
 
+#[derive(Clone)]
+pub(crate) struct FundingPsbtRequest {
+    pub fee_rate: FeeRate,
+    // Inputs of other pending PSBTs.
+    pub unspendable: Vec<OutPoint>,
+    pub sign: bool,
+}
+
 pub(crate) struct AsyncSenders<K, V, RV> {
     senders: RwLock<HashMap<K, (V, Sender<RV>)>>,
 }
@@ -628,15 +1245,407 @@ pub struct Controller {
     scorer: Arc<std::sync::RwLock<Scorer>>,
     wallet: Arc<Wallet<WalletDatabase, BitcoindClient>>,
     async_api_requests: Arc<AsyncAPIRequests>,
+    backup_key: [u8; 32],
+    node_announcer: Arc<NodeAnnouncer>,
+    rgs_server: Option<Arc<RgsServer>>,
+    scorer_parameters: std::sync::RwLock<ScorerParameters>,
 }
 
 impl Controller {
+    fn psbt_expiry(&self) -> time::Duration {
+        time::Duration::seconds(self.settings.psbt_expiry as i64)
+    }
+
+    /// Tag the wallet transactions by their purpose, from what we know about channels, spendable outputs and fee bumps.
+    async fn transaction_tags(
+        database: &LdkDatabase,
+        keys_manager: &KeysManager,
+        transactions: &[TransactionDetails],
+    ) -> Result<Vec<TransactionTag>> {
+        let txids: Vec<Txid> = transactions.iter().map(|details| details.txid).collect();
+        let spent_txids: Vec<Txid> = transactions
+            .iter()
+            .flat_map(transaction_inputs)
+            .map(|outpoint| outpoint.txid)
+            .collect();
+        let funding = database
+            .fetch_funding_transactions(&[txids.as_slice(), spent_txids.as_slice()].concat())
+            .await?;
+        let spendable_outputs = database
+            .fetch_spendable_outputs_by_txids(&spent_txids)
+            .await?;
+        let fee_bumps = database.fetch_fee_bumps_by_txids(&txids).await?;
+        // The proceeds of a cooperative close are paid to our shutdown script, other spendable outputs come from force closes.
+        let shutdown_script = keys_manager
+            .get_shutdown_scriptpubkey()
+            .ok()
+            .map(|script| script.into_inner());
+
+        let mut tags = vec![];
+        for details in transactions {
+            let inputs = transaction_inputs(details);
+            let tag = if let Some((_, _, channel_id)) =
+                funding.iter().find(|(txid, _, _)| *txid == details.txid)
+            {
+                TransactionTag {
+                    purpose: TransactionPurpose::ChannelFunding,
+                    channel_id: Some(*channel_id),
+                }
+            } else if let Some(fee_bump) = fee_bumps.iter().find(|bump| bump.txid == details.txid) {
+                TransactionTag {
+                    purpose: match fee_bump.method {
+                        FeeBumpMethod::Rbf => TransactionPurpose::Withdrawal,
+                        FeeBumpMethod::Cpfp => TransactionPurpose::FeeBump,
+                    },
+                    channel_id: fee_bump.channel_id,
+                }
+            } else if let Some((_, _, channel_id)) = funding.iter().find(|(txid, vout, _)| {
+                inputs.iter().any(|input| {
+                    input.txid == *txid && vout.map_or(true, |vout| vout == input.vout)
+                })
+            }) {
+                TransactionTag {
+                    purpose: TransactionPurpose::CooperativeClose,
+                    channel_id: Some(*channel_id),
+                }
+            } else {
+                let swept: Vec<_> = spendable_outputs
+                    .iter()
+                    .filter(|output| inputs.contains(&spendable_outpoint(&output.descriptor)))
+                    .collect();
+                if swept.is_empty() {
+                    TransactionTag {
+                        purpose: if details.sent > 0 {
+                            TransactionPurpose::Withdrawal
+                        } else {
+                            TransactionPurpose::Deposit
+                        },
+                        channel_id: None,
+                    }
+                } else {
+                    let cooperative = swept.iter().all(|output| {
+                        matches!(&output.descriptor,
+                            SpendableOutputDescriptor::StaticOutput { output, .. }
+                                if Some(&output.script_pubkey) == shutdown_script.as_ref())
+                    });
+                    TransactionTag {
+                        purpose: if cooperative {
+                            TransactionPurpose::CooperativeClose
+                        } else {
+                            TransactionPurpose::Sweep
+                        },
+                        channel_id: swept.iter().find_map(|output| output.channel_id),
+                    }
+                }
+            };
+            tags.push(tag);
+        }
+        Ok(tags)
+    }
+
+    /// Append the given wallet transactions to the ledger once they are confirmed. Entries that were already
+    /// recorded are left as they are.
+    pub(crate) async fn record_transactions(
+        wallet: &Wallet<WalletDatabase, BitcoindClient>,
+        database: &LdkDatabase,
+        keys_manager: &KeysManager,
+        txids: &[Txid],
+    ) -> Result<()> {
+        if txids.is_empty() {
+            return Ok(());
+        }
+        let mut entries = vec![];
+        let transactions = wallet.list_transactions()?;
+        // The value of every output that our transactions could spend, to work out the fees that BDK does not know.
+        let mut previous_outputs: HashMap<OutPoint, u64> = HashMap::new();
+        for tx in transactions
+            .iter()
+            .filter_map(|details| details.transaction.as_ref())
+        {
+            for (vout, output) in tx.output.iter().enumerate() {
+                previous_outputs.insert(OutPoint::new(tx.txid(), vout as u32), output.value);
+            }
+        }
+        for output in database.fetch_spendable_outputs().await? {
+            let value = match &output.descriptor {
+                SpendableOutputDescriptor::StaticOutput { output, .. } => output.value,
+                SpendableOutputDescriptor::DelayedPaymentOutput(descriptor) => {
+                    descriptor.output.value
+                }
+                SpendableOutputDescriptor::StaticPaymentOutput(descriptor) => {
+                    descriptor.output.value
+                }
+            };
+            previous_outputs.insert(spendable_outpoint(&output.descriptor), value);
+        }
+
+        let confirmed: Vec<TransactionDetails> = transactions
+            .into_iter()
+            .filter(|details| details.confirmation_time.is_some() && txids.contains(&details.txid))
+            .collect();
+        let tags = Controller::transaction_tags(database, keys_manager, &confirmed).await?;
+        for (details, tag) in confirmed.iter().zip(tags) {
+            let entry_type = match tag.purpose {
+                TransactionPurpose::ChannelFunding => LedgerEntryType::ChannelOpen,
+                TransactionPurpose::CooperativeClose => LedgerEntryType::ChannelClose,
+                TransactionPurpose::Sweep => LedgerEntryType::Sweep,
+                TransactionPurpose::FeeBump => LedgerEntryType::FeeBump,
+                TransactionPurpose::Withdrawal => LedgerEntryType::Withdrawal,
+                TransactionPurpose::Deposit => LedgerEntryType::Deposit,
+            };
+            // Inputs we know nothing about were paid by someone else, as was the fee.
+            let fee = details.fee.or_else(|| {
+                let tx = details.transaction.as_ref()?;
+                let input_value = tx
+                    .input
+                    .iter()
+                    .map(|input| previous_outputs.get(&input.previous_output).copied())
+                    .sum::<Option<u64>>()?;
+                input_value.checked_sub(tx.output.iter().map(|output| output.value).sum())
+            });
+            entries.extend(LedgerEntry::transaction(
+                details,
+                entry_type,
+                tag.channel_id,
+                fee.unwrap_or_default(),
+            ));
+        }
+        database.persist_ledger_entries(&entries).await
+    }
+
+    /// Every interval append the wallet transactions that confirmed since the last recorded height to the
+    /// ledger. Channel openings and sweeps are recorded by their events straight away, this catches deposits,
+    /// withdrawals, closes and late confirmations. The first interval after a start records the whole history.
+    fn keep_ledger_recorded(
+        wallet: Arc<Wallet<WalletDatabase, BitcoindClient>>,
+        database: Arc<LdkDatabase>,
+        keys_manager: Arc<KeysManager>,
+    ) {
+        tokio::spawn(async move {
+            let mut interval_timer = tokio::time::interval(LEDGER_RECORD_INTERVAL);
+            interval_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut recorded_height = 0;
+            loop {
+                interval_timer.tick().await;
+                if !wallet.synced().await {
+                    continue;
+                }
+                let transactions = match wallet.list_transactions() {
+                    Ok(transactions) => transactions,
+                    Err(e) => {
+                        error!("Failed to list wallet transactions for the ledger: {e}");
+                        continue;
+                    }
+                };
+                // Transactions of the last recorded block are included again, in case the wallet found more of them since.
+                let confirmed_heights: Vec<(Txid, u32)> = transactions
+                    .iter()
+                    .filter_map(|details| {
+                        let height = details.confirmation_time.as_ref()?.height;
+                        (height >= recorded_height).then_some((details.txid, height))
+                    })
+                    .collect();
+                let txids: Vec<Txid> = confirmed_heights.iter().map(|(txid, _)| *txid).collect();
+                match Controller::record_transactions(&wallet, &database, &keys_manager, &txids)
+                    .await
+                {
+                    Ok(()) => {
+                        if let Some(height) =
+                            confirmed_heights.iter().map(|(_, height)| *height).max()
+                        {
+                            recorded_height = height;
+                        }
+                    }
+                    Err(e) => error!("Failed to record wallet transactions in the ledger: {e}"),
+                }
+            }
+        });
+    }
+
+    /// Every interval merge the small UTXOs of the wallet, when the economy fee rate is low enough.
+    fn keep_utxos_consolidated(
+        interval: u64,
+        wallet: Arc<Wallet<WalletDatabase, BitcoindClient>>,
+        database: Arc<LdkDatabase>,
+        bitcoind_client: Arc<BitcoindClient>,
+    ) {
+        if interval == 0 {
+            return;
+        }
+        tokio::spawn(async move {
+            let mut interval_timer = tokio::time::interval(Duration::from_secs(interval));
+            interval_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval_timer.tick().await;
+                if !wallet.synced().await {
+                    continue;
+                }
+                let consolidate = async {
+                    let unspendable: Vec<OutPoint> = database
+                        .fetch_pending_psbts()
+                        .await?
+                        .iter()
+                        .flat_map(|psbt| psbt.inputs())
+                        .collect();
+                    wallet.consolidate_utxos(unspendable, false)
+                };
+                match consolidate.await {
+                    Ok(Consolidation { tx: Some(tx), .. }) => {
+                        info!(
+                            "Consolidating {} UTXOs with txid {}",
+                            tx.input.len(),
+                            tx.txid()
+                        );
+                        bitcoind_client.broadcast_transactions(&[&tx]);
+                    }
+                    Ok(Consolidation { skip_reason, .. }) => {
+                        debug!(
+                            "Not consolidating UTXOs: {}",
+                            skip_reason.unwrap_or_default()
+                        );
+                    }
+                    Err(e) => error!("Failed to consolidate UTXOs: {e}"),
+                }
+            }
+        });
+    }
+
+    /// Every interval sweep the hot wallet balance above its target to cold storage.
+    fn keep_treasury_swept(
+        settings: Arc<Settings>,
+        wallet: Arc<Wallet<WalletDatabase, BitcoindClient>>,
+        database: Arc<LdkDatabase>,
+        bitcoind_client: Arc<BitcoindClient>,
+    ) {
+        if settings.sweep_interval == 0 {
+            return;
+        }
+        if settings.sweep_destination.is_none() {
+            warn!("Sweeping to cold storage is disabled as there is no sweep destination");
+            return;
+        }
+        tokio::spawn(async move {
+            let mut interval_timer =
+                tokio::time::interval(Duration::from_secs(settings.sweep_interval));
+            interval_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval_timer.tick().await;
+                if !wallet.synced().await {
+                    continue;
+                }
+                if let Err(e) = Controller::sweep_to_cold_storage(
+                    &settings,
+                    &wallet,
+                    &database,
+                    &bitcoind_client,
+                )
+                .await
+                {
+                    error!("Failed to sweep to cold storage: {e}");
+                }
+            }
+        });
+    }
+
+    /// Transfer the confirmed balance above the hot wallet target to the next cold storage address.
+    async fn sweep_to_cold_storage(
+        settings: &Settings,
+        wallet: &Wallet<WalletDatabase, BitcoindClient>,
+        database: &LdkDatabase,
+        bitcoind_client: &BitcoindClient,
+    ) -> Result<Option<Sweep>> {
+        let Some(destination) = &settings.sweep_destination else {
+            return Ok(None);
+        };
+        // The inputs of an unconfirmed sweep still look unspent until the wallet syncs the next block. The sweep
+        // may have been replaced by a fee bump, and once it is evicted from the mempool it is gone from the wallet
+        // and its inputs can be swept again.
+        if let Some(last_sweep) = database.fetch_sweeps().await?.last() {
+            let mut txids = vec![last_sweep.txid];
+            txids.extend(
+                database
+                    .fetch_fee_bumps(&last_sweep.txid)
+                    .await?
+                    .iter()
+                    .filter(|bump| bump.method == FeeBumpMethod::Rbf)
+                    .map(|bump| bump.txid),
+            );
+            let transactions: Vec<TransactionDetails> = wallet
+                .list_transactions()?
+                .into_iter()
+                .filter(|details| txids.contains(&details.txid))
+                .collect();
+            if !transactions.is_empty()
+                && transactions
+                    .iter()
+                    .all(|details| details.confirmation_time.is_none())
+            {
+                warn!(
+                    "Waiting for sweep {} to confirm before sweeping again",
+                    last_sweep.txid
+                );
+                return Ok(None);
+            }
+        }
+        let unspendable: Vec<OutPoint> = database
+            .fetch_pending_psbts()
+            .await?
+            .iter()
+            .flat_map(|psbt| psbt.inputs())
+            .collect();
+        let address_index = database.next_sweep_address_index().await?;
+        let address = destination.address(address_index, settings.bitcoin_network)?;
+        let Some((tx, excess, fee_rate)) =
+            wallet.sweep_excess(address.script_pubkey(), unspendable)?
+        else {
+            return Ok(None);
+        };
+        let sweep = Sweep::new(
+            tx.txid(),
+            address.to_string(),
+            address_index,
+            excess,
+            fee_rate,
+        );
+        database.persist_sweep(&sweep).await?;
+        info!(
+            "Sweeping {excess} sats to cold storage address {address} with txid {}",
+            sweep.txid
+        );
+        bitcoind_client.broadcast_transactions(&[&tx]);
+        Ok(Some(sweep))
+    }
+
     pub fn stop(&self) {
         // Disconnect our peers and stop accepting new connections. This ensures we don't continue
         // updating our channel data after we've stopped the background processor.
         self.peer_manager.disconnect_all_peers();
     }
 
+    fn read_scorer(
+        &self,
+        scorer: &[u8],
+        decay_parameters: ProbabilisticScoringDecayParameters,
+    ) -> Result<Scorer> {
+        ProbabilisticScorer::read(
+            &mut Cursor::new(scorer),
+            (
+                decay_parameters,
+                self.network_graph.clone(),
+                KldLogger::global(),
+            ),
+        )
+        .map_err(|e| anyhow!("Invalid scorer: {e}"))
+    }
+
+    fn set_scorer(&self, scorer: Scorer) -> Result<()> {
+        *self
+            .scorer
+            .write()
+            .map_err(|e| anyhow!("failed to acquire lock on scorer {}", e))? = scorer;
+        Ok(())
+    }
+
     pub async fn start_ldk(
         settings: Arc<Settings>,
         durable_connection: Arc<DurableConnection>,
@@ -655,6 +1664,16 @@ impl Controller {
             durable_connection.clone(),
         ));
 
+        let backup_key = key_generator.backup_seed();
+        let recovery_backup = match &settings.recover_from_backup {
+            Some(path) => {
+                let bytes = std::fs::read(path)
+                    .with_context(|| format!("Could not read channel backup {path}"))?;
+                Some(StaticChannelBackup::decrypt(&backup_key, &bytes)?)
+            }
+            None => None,
+        };
+
         // BitcoindClient implements the FeeEstimator trait, so it'll act as our fee estimator.
         let fee_estimator = bitcoind_client.clone();
 
@@ -695,33 +1714,46 @@ impl Controller {
                 .context("Could not query network graph from database")?
                 .unwrap_or_else(|| NetworkGraph::new(network, KldLogger::global())),
         );
+        let rgs_client = settings.rgs_source.as_ref().map(|source| {
+            Arc::new(RgsClient::new(
+                network_graph.clone(),
+                SnapshotSource::new(source),
+            ))
+        });
+        let rgs_server = settings.rgs_server.then(|| {
+            Arc::new(RgsServer::new(
+                network_graph.clone(),
+                database.clone(),
+                network,
+                settings.rgs_snapshot_interval,
+            ))
+        });
+        let scorer_parameters = database
+            .fetch_scorer_parameters()
+            .await?
+            .unwrap_or_else(|| ScorerParameters::from_settings(&settings));
+        scorer_parameters.validate()?;
         let scorer = Arc::new(std::sync::RwLock::new(
             database
-                .fetch_scorer(
-                    ProbabilisticScoringDecayParameters::default(),
-                    network_graph.clone(),
-                )
+                .fetch_scorer(scorer_parameters.decay_parameters(), network_graph.clone())
                 .await?
                 .map(|s| s.0)
                 .unwrap_or_else(|| {
                     ProbabilisticScorer::new(
-                        ProbabilisticScoringDecayParameters::default(),
+                        scorer_parameters.decay_parameters(),
                         network_graph.clone(),
                         KldLogger::global(),
                     )
                 }),
         ));
-        let random_seed_bytes: [u8; 32] = random();
-        let router = Arc::new(DefaultRouter::new(
+        let router = Arc::new(KldRouter::new(
             network_graph.clone(),
-            KldLogger::global(),
-            random_seed_bytes,
             scorer.clone(),
-            ProbabilisticScoringFeeParameters::default(),
+            scorer_parameters.fee_parameters(),
         ));
 
         let mut channel_monitors = database
-            .fetch_channel_monitors(keys_manager.as_ref())
+            .fetch_channel_monitors(keys_manager.as_ref(), &broadcaster, &fee_estimator)
             .await?;
         let mut user_config = UserConfig::default();
         user_config
@@ -843,6 +1875,14 @@ impl Controller {
             .liquidity_manager
             .set_process_msgs_callback(process_msgs_callback);
         let async_api_requests = Arc::new(AsyncAPIRequests::new());
+        let node_announcement = match database.fetch_node_announcement().await? {
+            Some(mut node_announcement) => {
+                node_announcement.merge_onion_address(&settings);
+                node_announcement
+            }
+            None => NodeAnnouncementConfig::from_settings(&settings),
+        };
+        let node_announcer = Arc::new(NodeAnnouncer::new(peer_manager.clone(), node_announcement));
 
         let event_handler = EventHandler::new(
             channel_manager.clone(),
@@ -851,9 +1891,8 @@ impl Controller {
             network_graph.clone(),
             wallet.clone(),
             database.clone(),
-            peer_manager.clone(),
             async_api_requests.clone(),
-            settings.clone(),
+            node_announcer.clone(),
             kuutamo_handler.clone(),
         );
         let channel_manager_cloned = channel_manager.clone();
@@ -1043,22 +2082,55 @@ impl Controller {
             }
         }
 
+        let shutdown_signal = quit_signal.clone();
+        let shutdown_database = database.clone();
+        tokio::spawn(async move {
+            shutdown_signal.await;
+            // The peers are disconnected when the node stops, which is not recorded by the connection events.
+            if let Err(e) = shutdown_database
+                .close_peer_connections(Some(microsecond_timestamp()))
+                .await
+            {
+                log_error(&e);
+            }
+        });
+
         let bitcoind_client_clone = bitcoind_client.clone();
         let peer_manager_clone = peer_manager.clone();
         let wallet_clone = wallet.clone();
+        let keys_manager_clone = keys_manager.clone();
         let peer_port = settings.peer_port;
         let database_clone = database.clone();
         let channel_manager_clone = channel_manager.clone();
         let chain_monitor_clone = chain_monitor.clone();
         let scorer_clone = scorer.clone();
         let settings_clone = settings.clone();
+        let network_graph_clone = network_graph.clone();
+        let node_announcer_clone = node_announcer.clone();
+        let rgs_server_clone = rgs_server.clone();
         tokio::spawn(async move {
+            // Fall back to P2P gossip if the graph could not be bootstrapped with rapid gossip sync.
+            let gossip = match rgs_client {
+                Some(rgs_client) => {
+                    let gossip = match rgs_client.sync().await {
+                        Ok(_) => GossipSync::Rapid(rgs_client.rapid_sync()),
+                        Err(e) => {
+                            warn!("{e}, falling back to P2P gossip");
+                            GossipSync::P2P(gossip_sync)
+                        }
+                    };
+                    rgs_client
+                        .keep_synced(Duration::from_secs(settings_clone.rgs_refresh_interval));
+                    gossip
+                }
+                None => GossipSync::P2P(gossip_sync),
+            };
             bitcoind_client_clone
                 .wait_for_blockchain_synchronisation()
                 .await;
             if let Err(e) = Controller::sync_to_chain_tip(
                 network,
-                bitcoind_client_clone,
+                bitcoind_client_clone.clone(),
                 chain_monitor,
                 channel_manager_blockhash,
                 channel_manager_clone.clone(),
@@ -1071,23 +2143,53 @@ impl Controller {
             };
 
             wallet_clone.keep_sync_with_chain();
-            if let Err(e) = peer_manager_clone.listen(peer_port).await {
+            Controller::keep_utxos_consolidated(
+                settings_clone.consolidation_interval,
+                wallet_clone.clone(),
+                database_clone.clone(),
+                bitcoind_client_clone.clone(),
+            );
+            Controller::keep_treasury_swept(
+                settings_clone.clone(),
+                wallet_clone.clone(),
+                database_clone.clone(),
+                bitcoind_client_clone,
+            );
+            Controller::keep_ledger_recorded(
+                wallet_clone,
+                database_clone.clone(),
+                keys_manager_clone,
+            );
+            if let Err(e) = peer_manager_clone
+                .listen(settings_clone.peer_bind_address, peer_port)
+                .await
+            {
                 error!("could not listen on peer port: {e}");
                 std::process::exit(1)
             };
             peer_manager_clone.keep_channel_peers_connected(
                 database_clone.clone(),
                 channel_manager_clone.clone(),
+                settings_clone.tor_proxy,
             );
 
-            // hourly broadcast our node to the network
-            let peer_manager_clone2 = peer_manager_clone.clone();
-            let settings_clone2 = settings_clone.clone();
-            tokio::spawn(async move {
-                info!("broadcast node");
-                peer_manager_clone2.broadcast_node_announcement_from_settings(settings_clone2);
-                tokio::time::sleep(Duration::from_secs(60)).await;
-            });
+            if let Some(backup) = recovery_backup {
+                tokio::spawn(Controller::recover_from_backup(
+                    backup,
+                    settings_clone.clone(),
+                    database_clone.clone(),
+                    peer_manager_clone.clone(),
+                    channel_manager_clone.clone(),
+                    network_graph_clone,
+                ));
+            }
+
+            node_announcer_clone.keep_announced(Duration::from_secs(
+                settings_clone.node_announcement_interval,
+            ));
+            if let Some(rgs_server) = rgs_server_clone {
+                rgs_server.keep_updated();
+            }
 
             tokio::spawn(async move {
                 if let Err(e) = process_events_async(
@@ -1099,7 +2201,7 @@ impl Controller {
                     },
                     chain_monitor_clone,
                     channel_manager_clone,
-                    GossipSync::p2p(gossip_sync),
+                    gossip,
                     peer_manager_clone,
                     KldLogger::global(),
                     Some(scorer_clone),
@@ -1141,9 +2243,105 @@ impl Controller {
             scorer,
             wallet,
             async_api_requests,
+            backup_key,
+            node_announcer,
+            rgs_server,
+            scorer_parameters: std::sync::RwLock::new(scorer_parameters),
         })
     }
 
+    /// Get our counterparties to force close every channel in the backup. Channels that we still know
+    /// about might have stale state, so we never broadcast our own commitment for them. For channels we
+    /// lost, LDK answers the peers channel_reestablish with an error which makes them close the channel.
+    async fn recover_from_backup(
+        backup: StaticChannelBackup,
+        settings: Arc<Settings>,
+        database: Arc<LdkDatabase>,
+        peer_manager: Arc<PeerManager>,
+        channel_manager: Arc<ChannelManager>,
+        network_graph: Arc<NetworkGraph>,
+    ) {
+        info!(
+            "Recovering {} channels with {} peers from static channel backup",
+            backup.channels.len(),
+            backup.peers.len()
+        );
+        for channel in channel_manager.list_channels().iter().filter(|c| {
+            backup
+                .channels
+                .iter()
+                .any(|b| b.channel_id == c.channel_id.to_string())
+        }) {
+            if let Err(e) = channel_manager.force_close_without_broadcasting_txn(
+                &channel.channel_id,
+                &channel.counterparty.node_id,
+            ) {
+                warn!(
+                    "Could not force close channel {}: {e:?}",
+                    channel.channel_id
+                );
+            }
+        }
+
+        let mut unrecovered = vec![];
+        for peer in &backup.peers {
+            match peer.public_key() {
+                Ok(public_key) => unrecovered.push((public_key, peer.addresses.clone())),
+                Err(e) => log_error(&e),
+            }
+        }
+        for _ in 0..RECOVERY_ATTEMPTS {
+            let mut remaining = vec![];
+            for (public_key, mut addresses) in unrecovered {
+                let graph_addresses = network_graph
+                    .read_only()
+                    .get_addresses(&public_key)
+                    .unwrap_or_default();
+                for address in graph_addresses.into_iter().map(SocketAddress::from) {
+                    if !addresses.contains(&address) {
+                        addresses.push(address);
+                    }
+                }
+                let mut connected = peer_manager.is_connected(&public_key);
+                for address in preferred_addresses(
+                    addresses.clone(),
+                    &settings.address_preference,
+                    settings.tor_proxy,
+                ) {
+                    if connected {
+                        break;
+                    }
+                    match peer_manager
+                        .connect_peer(
+                            database.clone(),
+                            public_key,
+                            address.clone(),
+                            settings.tor_proxy,
+                        )
+                        .await
+                    {
+                        Ok(()) => connected = true,
+                        Err(e) => debug!("Could not connect to {public_key}@{address}. {e}"),
+                    }
+                }
+                if connected {
+                    info!("Requested channel closure from recovered peer {public_key}");
+                } else {
+                    remaining.push((public_key, addresses));
+                }
+            }
+            if remaining.is_empty() {
+                info!("Connected to all peers from static channel backup");
+                return;
+            }
+            unrecovered = remaining;
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        }
+        for (public_key, _) in unrecovered {
+            error!("Could not connect to peer {public_key} to recover channels");
+        }
+    }
+
     async fn sync_to_chain_tip(
         network: Network,
         bitcoind_client: Arc<BitcoindClient>,
@@ -1319,3 +2517,45 @@ async fn send_probe(
         trace!("Can not probe, because no route to {recipient:?}");
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bitcoin::Network;
+    use lightning::ln::features::ChannelFeatures;
+    use lightning::routing::scoring::{ProbabilisticScorer, ProbabilisticScoringDecayParameters};
+    use log::LevelFilter;
+    use test_utils::random_public_key;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_scorer() -> Result<()> {
+        KldLogger::init("test", LevelFilter::Info);
+        let scorer_for = |network_graph: &Arc<NetworkGraph>| {
+            ProbabilisticScorer::new(
+                ProbabilisticScoringDecayParameters::default(),
+                network_graph.clone(),
+                KldLogger::global(),
+            )
+        };
+        let network_graph = Arc::new(NetworkGraph::new(Network::Regtest, KldLogger::global()));
+        // Any scorer is valid while we know nothing of the network.
+        validate_scorer(&network_graph, &scorer_for(&network_graph))?;
+
+        network_graph
+            .add_channel_from_partial_announcement(
+                1,
+                100,
+                ChannelFeatures::empty(),
+                random_public_key(),
+                random_public_key(),
+            )
+            .map_err(|e| anyhow!(e.err))?;
+        // A scorer built for another graph knows none of our channels.
+        let other_graph = Arc::new(NetworkGraph::new(Network::Regtest, KldLogger::global()));
+        let error = validate_scorer(&network_graph, &scorer_for(&other_graph))
+            .expect_err("scorer should not match the graph");
+        assert!(error.to_string().contains("no liquidity estimates"));
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
@@ -5,14 +6,14 @@ use anyhow::{anyhow, bail, Context, Result};
 
 use bitcoin::blockdata::locktime::absolute::LockTime;
 use bitcoin::secp256k1::Secp256k1;
+use bitcoin::Txid;
 
 use crate::bitcoind::bitcoind_interface::BitcoindInterface;
 use crate::database::forward::Forward;
+use crate::database::ledger::LedgerEntry;
 use crate::database::payment::Payment;
 use crate::database::{LdkDatabase, WalletDatabase};
-use crate::ldk::peer_manager::KuutamoPeerManger;
 use crate::log_error;
-use crate::settings::Settings;
 use lightning::chain::chaininterface::{BroadcasterInterface, ConfirmationTarget, FeeEstimator};
 use lightning::events::{Event, PathFailure, PaymentPurpose};
 use lightning::ln::ChannelId;
@@ -26,8 +27,8 @@ use crate::bitcoind::BitcoindClient;
 use crate::ldk::{htlc_destination_to_string, ldk_error};
 use crate::wallet::{Wallet, WalletInterface};
 
-use super::controller::AsyncAPIRequests;
-use super::peer_manager::PeerManager;
+use super::controller::{spendable_outpoint, AsyncAPIRequests, Controller};
+use super::node_announcer::NodeAnnouncer;
 use super::{ChannelManager, KuutamoCustomMessageHandler, NetworkGraph};
 
 pub(crate) struct EventHandler {
@@ -37,9 +38,8 @@ pub(crate) struct EventHandler {
     network_graph: Arc<NetworkGraph>,
     wallet: Arc<Wallet<WalletDatabase, BitcoindClient>>,
     ldk_database: Arc<LdkDatabase>,
-    peer_manager: Arc<PeerManager>,
     async_api_requests: Arc<AsyncAPIRequests>,
-    settings: Arc<Settings>,
+    node_announcer: Arc<NodeAnnouncer>,
     runtime_handle: Handle,
     kuutamo_handler: Arc<KuutamoCustomMessageHandler>,
 }
@@ -53,9 +53,8 @@ impl EventHandler {
         network_graph: Arc<NetworkGraph>,
         wallet: Arc<Wallet<WalletDatabase, BitcoindClient>>,
         database: Arc<LdkDatabase>,
-        peer_manager: Arc<PeerManager>,
         async_api_requests: Arc<AsyncAPIRequests>,
-        settings: Arc<Settings>,
+        node_announcer: Arc<NodeAnnouncer>,
         kuutamo_handler: Arc<KuutamoCustomMessageHandler>,
     ) -> EventHandler {
         EventHandler {
@@ -65,9 +64,8 @@ impl EventHandler {
             network_graph,
             wallet,
             ldk_database: database,
-            peer_manager,
             async_api_requests,
-            settings,
+            node_announcer,
             runtime_handle: Handle::current(),
             kuutamo_handler,
         }
@@ -84,6 +82,23 @@ impl EventHandler {
                 output_script,
                 user_channel_id,
             } => {
+                // The funding transaction is signed externally, so only give the PSBT back to the API.
+                if let Some((request, respond)) = self
+                    .async_api_requests
+                    .funding_psbts
+                    .get(&(user_channel_id as u64))
+                    .await
+                {
+                    respond(self.wallet.create_psbt(
+                        output_script,
+                        channel_value_satoshis,
+                        request.fee_rate,
+                        &[],
+                        request.unspendable,
+                        request.sign,
+                    ));
+                    return Ok(());
+                }
                 let (fee_rate, respond) = self
                     .async_api_requests
                     .funding_transactions
@@ -93,17 +108,23 @@ impl EventHandler {
                         "Can't find funding transaction for user_channel_id {user_channel_id}"
                     ))?;
 
-                let funding_tx =
-                    match self
-                        .wallet
-                        .fund_tx(&output_script, &channel_value_satoshis, fee_rate)
-                    {
-                        Ok(tx) => tx,
-                        Err(e) => {
-                            respond(Err(anyhow!("Failed funding transaction: {e}")));
-                            return Err(anyhow!("Failed funding transaction: {e}"));
-                        }
-                    };
+                // The inputs of pending PSBTs are reserved for them.
+                let funding = match self.ldk_database.fetch_pending_psbts().await {
+                    Ok(psbts) => self.wallet.fund_tx(
+                        &output_script,
+                        &channel_value_satoshis,
+                        fee_rate,
+                        psbts.iter().flat_map(|psbt| psbt.inputs()).collect(),
+                    ),
+                    Err(e) => Err(e),
+                };
+                let funding_tx = match funding {
+                    Ok(tx) => tx,
+                    Err(e) => {
+                        respond(Err(anyhow!("Failed funding transaction: {e}")));
+                        return Err(anyhow!("Failed funding transaction: {e}"));
+                    }
+                };
 
                 // Give the funding transaction back to LDK for opening the channel.
                 if let Err(e) = self
@@ -200,6 +221,10 @@ impl EventHandler {
                     .find(|c| c.channel_id == channel_id)
                 {
                     self.ldk_database.persist_channel(channel_details).await?;
+                    // The funding transaction is confirmed now.
+                    if let Some(funding_txo) = channel_details.funding_txo {
+                        self.record_transactions(&[funding_txo.txid]).await;
+                    }
                 } else {
                     self.ldk_database
                         .close_channel(
@@ -208,9 +233,7 @@ impl EventHandler {
                         )
                         .await?;
                 }
-                info!("Broadcasting node announcement message");
-                self.peer_manager
-                    .broadcast_node_announcement_from_settings(self.settings.clone());
+                self.node_announcer.broadcast();
             }
             Event::ChannelClosed {
                 channel_id,
@@ -226,6 +249,13 @@ impl EventHandler {
                         Err(anyhow!("Channel closed due to {reason}")),
                     )
                     .await;
+                self.async_api_requests
+                    .funding_psbts
+                    .respond(
+                        &(user_channel_id as u64),
+                        Err(anyhow!("Channel closed due to {reason}")),
+                    )
+                    .await;
                 self.ldk_database
                     .close_channel(&channel_id, format!("{reason}"))
                     .await?;
@@ -330,6 +360,8 @@ impl EventHandler {
                     .persist_payment(&payment)
                     .await
                     .context("Failed to persist payment")?;
+                self.persist_ledger_entry(LedgerEntry::payment(&payment))
+                    .await;
             }
             Event::PaymentSent {
                 payment_id,
@@ -365,6 +397,8 @@ impl EventHandler {
                         hex::encode(payment_id.0)
                     ))?;
                 payment.succeeded(payment_hash, payment_preimage, fee_paid_msat);
+                self.persist_ledger_entry(LedgerEntry::payment(&payment))
+                    .await;
                 respond(Ok(payment));
             }
             Event::PaymentPathSuccessful {
@@ -526,7 +560,9 @@ impl EventHandler {
                     let forward =
                         Forward::success(inbound_channel_id, outbound_channel_id, amount, fee);
                     let id = forward.id.to_string();
+                    let entry = LedgerEntry::forward(&forward);
                     self.persist_forward(forward);
+                    self.persist_ledger_entry(entry).await;
                     format!(" with ID {id}")
                 } else {
                     "".to_string()
@@ -603,6 +639,14 @@ impl EventHandler {
                     self.persist_spendable_output(spendable_output, channel_id.as_ref(), true)
                         .await;
                 }
+                // The outputs are spendable once the closing transaction is confirmed.
+                let closing_txids: Vec<Txid> = outputs
+                    .iter()
+                    .map(|output| spendable_outpoint(output).txid)
+                    .collect::<HashSet<Txid>>()
+                    .into_iter()
+                    .collect();
+                self.record_transactions(&closing_txids).await;
             }
             Event::HTLCIntercepted {
                 intercept_id,
@@ -654,6 +698,25 @@ impl EventHandler {
         }
     }
 
+    async fn persist_ledger_entry(&self, entry: LedgerEntry) {
+        if let Err(e) = self.ldk_database.persist_ledger_entries(&[entry]).await {
+            log_error(&e)
+        }
+    }
+
+    async fn record_transactions(&self, txids: &[Txid]) {
+        if let Err(e) = Controller::record_transactions(
+            &self.wallet,
+            &self.ldk_database,
+            &self.keys_manager,
+            txids,
+        )
+        .await
+        {
+            log_error(&e)
+        }
+    }
+
     fn persist_forward(&self, forward: Forward) {
         let database = self.ldk_database.clone();
         self.runtime_handle.spawn(async move {
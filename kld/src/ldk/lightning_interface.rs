@@ -7,18 +7,33 @@ use lightning::{
 
 use crate::{
     database::{
-        forward::{Forward, ForwardStatus, TotalForwards},
-        invoice::Invoice,
-        payment::{Payment, PaymentDirection},
-        ChannelRecord,
+        fee_bump::FeeBump,
+        forward::{ChannelForwardStats, Forward, ForwardStatus, TotalForwards},
+        invoice::{Invoice, InvoiceStatusFilter},
+        label::Label,
+        ledger::LedgerEntry,
+        node_announcement::NodeAnnouncementConfig,
+        payment::{Payment, PaymentDirection, PaymentStatus},
+        peer::{PeerAddress, PeerEvent},
+        psbt::PendingPsbt,
+        scorer_parameters::ScorerParameters,
+        sweep::Sweep,
+        ChannelRecord, Pagination,
     },
+    wallet::Consolidation,
     MillisatAmount,
 };
 
 use crate::api::payloads::FeeRate;
 use crate::api::SocketAddress;
 use async_trait::async_trait;
-use bitcoin::{secp256k1::PublicKey, Network, Transaction, Txid};
+use bdk::TransactionDetails;
+use bitcoin::{
+    psbt::PartiallySignedTransaction, secp256k1::PublicKey, Address, Network, OutPoint,
+    Transaction, Txid,
+};
+use time::OffsetDateTime;
+use uuid::Uuid;
 
 #[async_trait]
 pub trait LightningInterface: Send + Sync {
@@ -44,6 +59,13 @@ pub trait LightningInterface: Send + Sync {
 
     fn graph_num_channels(&self) -> usize;
 
+    /// Timestamp of the last rapid gossip sync snapshot applied to the network graph.
+    fn rgs_snapshot_timestamp(&self) -> Option<u32>;
+
+    /// Rapid gossip sync snapshot for a client that last synced at the given timestamp.
+    /// None if we are not serving snapshots or have not generated any yet.
+    fn rgs_snapshot(&self, last_sync_timestamp: u32) -> Option<Vec<u8>>;
+
     fn num_peers(&self) -> usize;
 
     fn wallet_balance(&self) -> u64;
@@ -64,8 +86,20 @@ pub trait LightningInterface: Send + Sync {
 
     fn public_addresses(&self) -> Vec<SocketAddress>;
 
+    /// Update what we announce to the network, this overrides the startup settings except for the
+    /// configured onion address which is always announced.
+    async fn update_node_announcement(
+        &self,
+        alias: Option<String>,
+        color: Option<String>,
+        addresses: Option<Vec<SocketAddress>>,
+    ) -> Result<NodeAnnouncementConfig>;
+
     async fn list_peers(&self) -> Result<Vec<Peer>>;
 
+    /// Connection events for the peer over the uptime window.
+    async fn peer_history(&self, public_key: PublicKey) -> Result<PeerHistory>;
+
     async fn connect_peer(
         &self,
         public_key: PublicKey,
@@ -83,6 +117,75 @@ pub trait LightningInterface: Send + Sync {
         override_config: Option<UserConfig>,
     ) -> Result<OpenChannelResult>;
 
+    /// Create a PSBT withdrawing to the address, to be signed externally and submitted back with `submit_psbt`.
+    /// Set amount to u64::MAX to drain the wallet.
+    async fn create_withdraw_psbt(
+        &self,
+        address: Address,
+        amount: u64,
+        fee_rate: Option<FeeRate>,
+        utxos: Vec<OutPoint>,
+        sign: bool,
+    ) -> Result<PendingPsbt>;
+
+    /// Like `open_channel` but returns a PSBT for the funding transaction instead of signing it.
+    async fn create_funding_psbt(
+        &self,
+        their_network_key: PublicKey,
+        channel_value_satoshis: u64,
+        push_msat: Option<u64>,
+        fee_rate: Option<FeeRate>,
+        override_config: Option<UserConfig>,
+        sign: bool,
+    ) -> Result<PendingPsbt>;
+
+    /// PSBTs waiting to be signed and submitted.
+    async fn list_psbts(&self) -> Result<Vec<PendingPsbt>>;
+
+    /// Inputs of the PSBTs waiting to be signed, which must not be spent by other transactions.
+    async fn pending_psbt_inputs(&self) -> Result<Vec<OutPoint>>;
+
+    /// Finalize an externally signed PSBT, then broadcast it or fund the channel with it.
+    async fn submit_psbt(&self, id: Uuid, psbt: PartiallySignedTransaction) -> Result<Transaction>;
+
+    /// Pay all the outputs with a single transaction and broadcast it. A withdrawal retried with the
+    /// same idempotency key returns the transaction of the first one instead of paying again.
+    async fn withdraw_many(
+        &self,
+        outputs: Vec<(Address, u64)>,
+        fee_rate: Option<FeeRate>,
+        idempotency_key: Option<String>,
+    ) -> Result<Transaction>;
+
+    /// Transfers of the hot wallet balance to cold storage, oldest first.
+    async fn list_sweeps(&self) -> Result<Vec<Sweep>>;
+
+    /// The accounting ledger from (inclusive) and to (exclusive) the times, oldest first.
+    async fn ledger(
+        &self,
+        from: Option<OffsetDateTime>,
+        to: Option<OffsetDateTime>,
+    ) -> Result<Vec<LedgerEntry>>;
+
+    /// What consolidating the small UTXOs of the wallet would spend now, without broadcasting anything.
+    async fn consolidation_report(&self) -> Result<Consolidation>;
+
+    /// Bump the fee of our unconfirmed transaction to the fee rate, by RBF when it can be replaced
+    /// or else by CPFP. Funding transactions are always bumped by CPFP as the channel depends on their txid.
+    async fn bump_fee(&self, txid: Txid, fee_rate: FeeRate) -> Result<(Transaction, FeeBump)>;
+
+    /// Tag our wallet transactions by their purpose, from what we know about channels, spendable outputs and fee bumps.
+    async fn tag_transactions(
+        &self,
+        transactions: &[TransactionDetails],
+    ) -> Result<Vec<TransactionTag>>;
+
+    /// Labels of wallet records in BIP-329 form.
+    async fn list_labels(&self) -> Result<Vec<Label>>;
+
+    /// Add the labels, replacing any existing label of the same record.
+    async fn import_labels(&self, labels: Vec<Label>) -> Result<()>;
+
     async fn close_channel(
         &self,
         channel_id: &ChannelId,
@@ -119,12 +222,20 @@ pub trait LightningInterface: Send + Sync {
         expiry: Option<u32>,
     ) -> Result<Invoice>;
 
-    async fn list_invoices(&self, label: Option<String>) -> Result<Vec<Invoice>>;
+    async fn list_invoices(
+        &self,
+        label: Option<String>,
+        status: Option<InvoiceStatusFilter>,
+        pagination: Pagination,
+    ) -> Result<Vec<Invoice>>;
 
+    /// An empty list of statuses matches any status.
     async fn list_payments(
         &self,
         bolt11: Option<Invoice>,
         direction: Option<PaymentDirection>,
+        statuses: &[PaymentStatus],
+        pagination: Pagination,
     ) -> Result<Vec<Payment>>;
 
     async fn estimated_channel_liquidity_range(
@@ -133,14 +244,53 @@ pub trait LightningInterface: Send + Sync {
         target: &NodeId,
     ) -> Result<Option<(u64, u64)>>;
 
-    async fn fetch_forwards(&self, status: Option<ForwardStatus>) -> Result<Vec<Forward>>;
+    async fn fetch_forwards(
+        &self,
+        status: Option<ForwardStatus>,
+        channel_id: Option<ChannelId>,
+        pagination: Pagination,
+    ) -> Result<Vec<Forward>>;
 
     async fn fetch_total_forwards(&self) -> Result<TotalForwards>;
 
-    async fn channel_history(&self) -> Result<Vec<ChannelRecord>>;
+    /// Forwards from (inclusive) and to (exclusive) the times aggregated per channel.
+    async fn routing_stats(
+        &self,
+        from: Option<OffsetDateTime>,
+        to: Option<OffsetDateTime>,
+    ) -> Result<Vec<ChannelForwardStats>>;
+
+    async fn channel_history(
+        &self,
+        channel_id: Option<ChannelId>,
+        pagination: Pagination,
+    ) -> Result<Vec<ChannelRecord>>;
 
     async fn scorer(&self) -> Result<Vec<u8>>;
 
+    /// Replace our scorer with a serialized one, e.g. learned by another of our nodes.
+    async fn upload_scorer(&self, scorer: Vec<u8>) -> Result<()>;
+
+    fn scorer_parameters(&self) -> ScorerParameters;
+
+    /// What the scorer has learned about the liquidity of each channel direction, optionally limited to
+    /// the channels of one node or to a single channel.
+    async fn scorer_liquidity(
+        &self,
+        node_id: Option<NodeId>,
+        short_channel_id: Option<u64>,
+    ) -> Result<ScorerLiquidity>;
+
+    /// Update the scorer parameters, this overrides the startup settings. The payment router uses the
+    /// new fee parameters from the next route it finds.
+    async fn update_scorer_parameters(
+        &self,
+        parameters: ScorerParameters,
+    ) -> Result<ScorerParameters>;
+
+    /// Encrypted static channel backup, used to recover funds with `--recover-from-backup`.
+    async fn static_channel_backup(&self) -> Result<Vec<u8>>;
+
     async fn update_channels(&self, channels: &[ChannelDetails]);
 }
 
@@ -149,6 +299,34 @@ pub struct Peer {
     pub net_address: Option<SocketAddress>,
     pub status: PeerStatus,
     pub alias: String,
+    pub addresses: Vec<PeerAddress>,
+    // Percentage of the time we were connected over the uptime window, if known.
+    pub uptime: Option<f64>,
+}
+
+pub struct PeerHistory {
+    pub public_key: PublicKey,
+    pub addresses: Vec<PeerAddress>,
+    pub uptime: Option<f64>,
+    pub events: Vec<PeerEvent>,
+}
+
+pub struct ScorerLiquidity {
+    // LDK does not expose when each channel was last updated, so this is when the scorer was last persisted.
+    pub last_updated: Option<OffsetDateTime>,
+    pub channels: Vec<ChannelLiquidity>,
+}
+
+pub struct ChannelLiquidity {
+    pub short_channel_id: u64,
+    pub source: NodeId,
+    pub target: NodeId,
+    pub capacity_sats: Option<u64>,
+    pub min_liquidity_msat: u64,
+    pub max_liquidity_msat: u64,
+    // Relative weights of the historical min and max liquidity, in buckets of the channel capacity.
+    pub historical_min_liquidity_buckets: Option<Vec<u16>>,
+    pub historical_max_liquidity_buckets: Option<Vec<u16>>,
 }
 
 #[derive(Copy, Clone, PartialEq, Default)]
@@ -173,3 +351,33 @@ pub struct OpenChannelResult {
     pub txid: Txid,
     pub channel_id: ChannelId,
 }
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TransactionPurpose {
+    ChannelFunding,
+    CooperativeClose,
+    Sweep,
+    FeeBump,
+    Withdrawal,
+    Deposit,
+}
+
+impl ToString for TransactionPurpose {
+    fn to_string(&self) -> String {
+        match self {
+            TransactionPurpose::ChannelFunding => "channel_funding",
+            TransactionPurpose::CooperativeClose => "cooperative_close",
+            TransactionPurpose::Sweep => "sweep",
+            TransactionPurpose::FeeBump => "fee_bump",
+            TransactionPurpose::Withdrawal => "withdrawal",
+            TransactionPurpose::Deposit => "deposit",
+        }
+        .to_owned()
+    }
+}
+
+pub struct TransactionTag {
+    pub purpose: TransactionPurpose,
+    // The channel that was funded or closed.
+    pub channel_id: Option<ChannelId>,
+}
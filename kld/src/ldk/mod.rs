@@ -1,39 +1,44 @@
+pub mod channel_backup;
 pub mod channel_utils;
 pub mod controller;
 mod event_handler;
 pub mod lightning_interface;
+mod node_announcer;
 mod peer_manager;
+mod rgs;
+mod rgs_server;
+mod router;
 
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 
 use crate::database::LdkDatabase;
 use crate::logger::KldLogger;
 use anyhow::anyhow;
 use bitcoin::secp256k1::PublicKey;
-use lightning::ln::peer_handler::CustomMessageHandler;
+use lightning::ln::peer_handler::{CustomMessageHandler, IgnoringMessageHandler};
 use lightning::{
     chain::{chainmonitor, Filter},
     events::HTLCDestination,
     ln::{
-        channelmanager::{PaymentSendFailure, RetryableSendFailure, SimpleArcChannelManager},
+        channelmanager::{self, PaymentSendFailure, RetryableSendFailure},
         features::{InitFeatures, NodeFeatures},
         msgs::{DecodeError, LightningError},
         wire::CustomMessageReader,
     },
-    onion_message::messenger::SimpleArcOnionMessenger,
-    routing::{
-        gossip,
-        router::DefaultRouter,
-        scoring::{ProbabilisticScorer, ProbabilisticScoringFeeParameters},
-    },
+    onion_message::messenger::{self, DefaultMessageRouter},
+    routing::{gossip, scoring::ProbabilisticScorer},
     sign::{InMemorySigner, KeysManager},
     util::errors::APIError,
 };
 use lightning_invoice::SignOrCreationError;
 
 pub use controller::Controller;
-pub use lightning_interface::{LightningInterface, OpenChannelResult, Peer, PeerStatus};
+pub use lightning_interface::{
+    ChannelLiquidity, LightningInterface, OpenChannelResult, Peer, PeerHistory, PeerStatus,
+    ScorerLiquidity, TransactionPurpose, TransactionTag,
+};
 use log::warn;
+pub(crate) use router::KldRouter;
 
 use crate::bitcoind::BitcoindClient;
 
@@ -95,22 +100,28 @@ impl CustomMessageHandler for KuutamoCustomMessageHandler {
     }
 }
 
-pub(crate) type ChannelManager =
-    SimpleArcChannelManager<ChainMonitor, BitcoindClient, BitcoindClient, KldLogger>;
-
-pub(crate) type OnionMessenger =
-    SimpleArcOnionMessenger<ChainMonitor, BitcoindClient, BitcoindClient, KldLogger>;
-
-pub type Scorer = ProbabilisticScorer<Arc<NetworkGraph>, Arc<KldLogger>>;
+pub(crate) type ChannelManager = channelmanager::ChannelManager<
+    Arc<ChainMonitor>,
+    Arc<BitcoindClient>,
+    Arc<KeysManager>,
+    Arc<KeysManager>,
+    Arc<KeysManager>,
+    Arc<BitcoindClient>,
+    Arc<KldRouter>,
+    Arc<KldLogger>,
+>;
 
-pub(crate) type KldRouter = DefaultRouter<
-    Arc<NetworkGraph>,
+pub(crate) type OnionMessenger = messenger::OnionMessenger<
+    Arc<KeysManager>,
+    Arc<KeysManager>,
     Arc<KldLogger>,
-    Arc<RwLock<Scorer>>,
-    ProbabilisticScoringFeeParameters,
-    Scorer,
+    Arc<DefaultMessageRouter<Arc<NetworkGraph>, Arc<KldLogger>>>,
+    Arc<ChannelManager>,
+    IgnoringMessageHandler,
 >;
 
+pub type Scorer = ProbabilisticScorer<Arc<NetworkGraph>, Arc<KldLogger>>;
+
 pub fn ldk_error(error: APIError) -> anyhow::Error {
     anyhow::Error::msg(match error {
         APIError::APIMisuseError { ref err } => format!("Misuse error: {err}"),
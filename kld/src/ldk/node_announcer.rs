@@ -0,0 +1,67 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use log::info;
+use tokio::sync::Notify;
+
+use crate::database::node_announcement::NodeAnnouncementConfig;
+
+use super::peer_manager::PeerManager;
+
+/// Keeps our node announcement fresh on the network. It is broadcast periodically and
+/// immediately whenever the alias, colour or addresses are changed.
+pub(crate) struct NodeAnnouncer {
+    peer_manager: Arc<PeerManager>,
+    config: RwLock<NodeAnnouncementConfig>,
+    changed: Notify,
+}
+
+impl NodeAnnouncer {
+    pub fn new(peer_manager: Arc<PeerManager>, config: NodeAnnouncementConfig) -> NodeAnnouncer {
+        NodeAnnouncer {
+            peer_manager,
+            config: RwLock::new(config),
+            changed: Notify::new(),
+        }
+    }
+
+    pub fn config(&self) -> NodeAnnouncementConfig {
+        match self.config.read() {
+            Ok(config) => config.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    pub fn update(&self, config: NodeAnnouncementConfig) {
+        match self.config.write() {
+            Ok(mut guard) => *guard = config,
+            Err(e) => *e.into_inner() = config,
+        }
+        self.changed.notify_one();
+    }
+
+    pub fn broadcast(&self) {
+        let config = self.config();
+        info!("Broadcasting node announcement for {}", config.alias);
+        self.peer_manager.broadcast_node_announcement(
+            config.rgb(),
+            config.alias_bytes(),
+            config.addresses.into_iter().map(|a| a.inner()).collect(),
+        );
+    }
+
+    pub fn keep_announced(self: &Arc<Self>, interval: Duration) {
+        let announcer = self.clone();
+        tokio::spawn(async move {
+            let mut interval_timer = tokio::time::interval(interval);
+            interval_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = interval_timer.tick() => (),
+                    _ = announcer.changed.notified() => (),
+                }
+                announcer.broadcast();
+            }
+        });
+    }
+}
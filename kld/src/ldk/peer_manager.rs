@@ -1,29 +1,36 @@
-use std::{net::SocketAddr, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
 
-use crate::api::SocketAddress;
-use crate::bitcoind::{BitcoindClient, BitcoindUtxoLookup};
-use crate::database::{peer::Peer, LdkDatabase};
+use crate::api::{AddressType, SocketAddress};
+use crate::bitcoind::BitcoindUtxoLookup;
+use crate::database::{
+    peer::{Peer, PeerEvent, PeerEventType},
+    LdkDatabase,
+};
 use crate::logger::KldLogger;
-use crate::settings::Settings;
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
 use bitcoin::secp256k1::PublicKey;
-use hex::FromHex;
 use lightning::sign::KeysManager;
-use lightning::{
-    ln::{channelmanager::SimpleArcChannelManager, peer_handler},
-    onion_message::messenger::SimpleArcOnionMessenger,
-    routing::gossip,
-};
+use lightning::{ln::peer_handler, routing::gossip};
 use lightning_net_tokio::SocketDescriptor;
 use log::{error, info, warn};
+use time::OffsetDateTime;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
 use tokio::task::JoinHandle;
 
-use super::{ChainMonitor, ChannelManager, KuutamoCustomMessageHandler};
+use super::{ChannelManager, KuutamoCustomMessageHandler, OnionMessenger};
 
 pub(crate) type PeerManager = peer_handler::PeerManager<
     SocketDescriptor,
-    Arc<SimpleArcChannelManager<ChainMonitor, BitcoindClient, BitcoindClient, KldLogger>>,
+    Arc<ChannelManager>,
     Arc<
         gossip::P2PGossipSync<
             Arc<gossip::NetworkGraph<Arc<KldLogger>>>,
@@ -31,7 +38,7 @@ pub(crate) type PeerManager = peer_handler::PeerManager<
             Arc<KldLogger>,
         >,
     >,
-    Arc<SimpleArcOnionMessenger<ChainMonitor, BitcoindClient, BitcoindClient, KldLogger>>,
+    Arc<OnionMessenger>,
     Arc<KldLogger>,
     Arc<KuutamoCustomMessageHandler>,
     Arc<KeysManager>,
@@ -39,18 +46,20 @@ pub(crate) type PeerManager = peer_handler::PeerManager<
 
 #[async_trait]
 pub trait KuutamoPeerManger {
-    async fn listen(&self, port: u16) -> Result<()>;
+    async fn listen(&self, bind_address: IpAddr, port: u16) -> Result<()>;
     async fn connect_peer(
         &self,
         database: Arc<LdkDatabase>,
         public_key: PublicKey,
         peer_addr: SocketAddress,
+        tor_proxy: Option<SocketAddr>,
     ) -> Result<()>;
 
     fn keep_channel_peers_connected(
         &self,
         database: Arc<LdkDatabase>,
         channel_manager: Arc<ChannelManager>,
+        tor_proxy: Option<SocketAddr>,
     );
 
     fn get_connected_peers(&self) -> Vec<(PublicKey, Option<SocketAddress>)>;
@@ -62,15 +71,12 @@ pub trait KuutamoPeerManger {
         database: Arc<LdkDatabase>,
         node_id: PublicKey,
     ) -> Result<()>;
-
-    /// broadcast the node alias and public addresses of current setting
-    fn broadcast_node_announcement_from_settings(&self, settings: Arc<Settings>);
 }
 
 #[async_trait]
 impl KuutamoPeerManger for Arc<PeerManager> {
-    async fn listen(&self, port: u16) -> Result<()> {
-        let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port:}"))
+    async fn listen(&self, bind_address: IpAddr, port: u16) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(SocketAddr::new(bind_address, port))
             .await
             .context("Failed to bind to listen port")?;
         let peer_manager = self.clone();
@@ -104,11 +110,12 @@ impl KuutamoPeerManger for Arc<PeerManager> {
         database: Arc<LdkDatabase>,
         public_key: PublicKey,
         peer_addr: SocketAddress,
+        tor_proxy: Option<SocketAddr>,
     ) -> Result<()> {
         if self.is_connected(&public_key) {
             return Ok(());
         }
-        let handle = connect_peer(self.clone(), database, public_key, peer_addr).await?;
+        let handle = connect_peer(self.clone(), database, public_key, peer_addr, tor_proxy).await?;
         loop {
             if self.is_connected(&public_key) {
                 return Ok(());
@@ -123,29 +130,47 @@ impl KuutamoPeerManger for Arc<PeerManager> {
         &self,
         database: Arc<LdkDatabase>,
         channel_manager: Arc<ChannelManager>,
+        tor_proxy: Option<SocketAddr>,
     ) {
         let peer_manager = self.clone();
         tokio::spawn(async move {
+            // Connections that were still open when the node last stopped, without recording it, are closed first.
+            if let Err(e) = database.close_peer_connections(None).await {
+                error!("{e}");
+            }
+            let mut connected_peers: HashMap<PublicKey, Option<SocketAddress>> = HashMap::new();
             loop {
-                let connected_node_ids = peer_manager.get_peer_node_ids();
+                let connected_node_ids: HashMap<PublicKey, Option<SocketAddress>> =
+                    peer_manager.get_connected_peers().into_iter().collect();
+                record_connection_events(&database, &connected_peers, &connected_node_ids).await;
+                connected_peers = connected_node_ids;
+
+                let now = OffsetDateTime::now_utc();
                 for unconnected_node_id in channel_manager
                     .list_channels()
                     .iter()
                     .map(|chan| chan.counterparty.node_id)
-                    .filter(|id| !connected_node_ids.iter().any(|(pk, _)| pk == id))
+                    .filter(|id| !connected_peers.contains_key(id))
                 {
-                    match database.fetch_peer(&unconnected_node_id).await {
-                        Ok(Some(peer)) => {
-                            let _ = connect_peer(
-                                peer_manager.clone(),
-                                database.clone(),
-                                peer.public_key,
-                                peer.address.into(),
-                            )
-                            .await;
+                    match database.fetch_peer_addresses(&unconnected_node_id).await {
+                        Ok(addresses) => {
+                            for address in addresses.into_iter().filter(|a| !a.is_backing_off(now))
+                            {
+                                if connect_peer(
+                                    peer_manager.clone(),
+                                    database.clone(),
+                                    unconnected_node_id,
+                                    address.address.into(),
+                                    tor_proxy,
+                                )
+                                .await
+                                .is_ok()
+                                {
+                                    break;
+                                }
+                            }
                         }
                         Err(e) => error!("{}", e),
-                        _ => (),
                     }
                 }
                 tokio::time::sleep(Duration::from_secs(1)).await;
@@ -172,20 +197,6 @@ impl KuutamoPeerManger for Arc<PeerManager> {
         self.disconnect_by_node_id(node_id);
         database.delete_peer(&node_id).await
     }
-
-    fn broadcast_node_announcement_from_settings(&self, settings: Arc<Settings>) {
-        let mut alias = [0; 32];
-        alias[..settings.node_alias.len()].copy_from_slice(settings.node_alias.as_bytes());
-        let addresses: Vec<lightning::ln::msgs::SocketAddress> = settings
-            .public_addresses
-            .clone()
-            .into_iter()
-            .map(|a| a.inner())
-            .collect();
-
-        let color = <[u8; 3]>::from_hex(&settings.node_alias_color).unwrap_or([110, 44, 247]);
-        self.broadcast_node_announcement(color, alias, addresses);
-    }
 }
 
 async fn connect_peer(
@@ -193,21 +204,203 @@ async fn connect_peer(
     database: Arc<LdkDatabase>,
     public_key: PublicKey,
     address: SocketAddress,
+    tor_proxy: Option<SocketAddr>,
 ) -> Result<JoinHandle<()>> {
-    let socket_addr = SocketAddr::try_from(address.clone())?;
+    let peer = Peer {
+        public_key,
+        address: address.0.clone(),
+    };
     let connection_closed =
-        lightning_net_tokio::connect_outbound(peer_manager, public_key, socket_addr)
-            .await
-            .context("Could not connect to peer {public_key}@{peer_addr}")?;
-    database
-        .persist_peer(&Peer {
-            public_key,
-            address: address.0,
-        })
-        .await?;
-    info!("Connected to peer {public_key}@{socket_addr}");
+        match open_connection(peer_manager, public_key, &address, tor_proxy).await {
+            Ok(connection_closed) => connection_closed,
+            Err(e) => {
+                if let Err(e) = database.persist_peer_failure(&peer).await {
+                    error!("{e}");
+                }
+                if let Err(e) = database
+                    .persist_peer_event(&PeerEvent::new(
+                        public_key,
+                        Some(peer.address),
+                        PeerEventType::ConnectionFailed,
+                    ))
+                    .await
+                {
+                    error!("{e}");
+                }
+                return Err(e);
+            }
+        };
+    database.persist_peer(&peer).await?;
+    info!("Connected to peer {public_key}@{address}");
     Ok(tokio::spawn(async move {
         connection_closed.await;
-        info!("Disconnected from peer {public_key}@{socket_addr}");
+        info!("Disconnected from peer {public_key}@{address}");
     }))
 }
+
+async fn open_connection(
+    peer_manager: Arc<PeerManager>,
+    public_key: PublicKey,
+    address: &SocketAddress,
+    tor_proxy: Option<SocketAddr>,
+) -> Result<Pin<Box<dyn Future<Output = ()> + Send>>> {
+    Ok(match (tor_proxy, address.address_type()) {
+        (Some(proxy), AddressType::Ipv6 | AddressType::Onion | AddressType::Hostname) => {
+            let stream =
+                tokio::time::timeout(Duration::from_secs(30), socks5_connect(proxy, address))
+                    .await
+                    .with_context(|| format!("Timed out connecting to {address} through {proxy}"))??
+                    .into_std()?;
+            Box::pin(lightning_net_tokio::setup_outbound(
+                peer_manager,
+                public_key,
+                stream,
+            ))
+        }
+        (None, AddressType::Onion) => {
+            bail!("A tor proxy is required to connect to {address}")
+        }
+        _ => {
+            let socket_addr = resolve(address).await?;
+            Box::pin(
+                lightning_net_tokio::connect_outbound(peer_manager, public_key, socket_addr)
+                    .await
+                    .with_context(|| format!("Could not connect to peer {public_key}@{address}"))?,
+            )
+        }
+    })
+}
+
+/// Log peers that have connected or disconnected, in either direction, since we last looked.
+async fn record_connection_events(
+    database: &LdkDatabase,
+    before: &HashMap<PublicKey, Option<SocketAddress>>,
+    after: &HashMap<PublicKey, Option<SocketAddress>>,
+) {
+    let connected = after
+        .iter()
+        .filter(|(pk, _)| !before.contains_key(pk))
+        .map(|(pk, a)| (pk, a, PeerEventType::Connected));
+    let disconnected = before
+        .iter()
+        .filter(|(pk, _)| !after.contains_key(pk))
+        .map(|(pk, a)| (pk, a, PeerEventType::Disconnected));
+    for (public_key, address, event) in connected.chain(disconnected) {
+        let event = PeerEvent::new(*public_key, address.as_ref().map(|a| a.0.clone()), event);
+        if let Err(e) = database.persist_peer_event(&event).await {
+            error!("{e}");
+        }
+    }
+}
+
+async fn resolve(address: &SocketAddress) -> Result<SocketAddr> {
+    match (address.host_name(), address.port()) {
+        (Some(host), Some(port)) => tokio::net::lookup_host((host.as_str(), port))
+            .await?
+            .next()
+            .with_context(|| format!("Could not resolve {address}")),
+        _ => SocketAddr::try_from(address.clone()),
+    }
+}
+
+/// Open a connection to the address through a SOCKS5 proxy (RFC 1928) without authentication.
+/// Onion and hostnames are resolved by the proxy.
+async fn socks5_connect(proxy: SocketAddr, address: &SocketAddress) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect(proxy)
+        .await
+        .with_context(|| format!("Could not connect to SOCKS5 proxy {proxy}"))?;
+    stream.write_all(&[5, 1, 0]).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply != [5, 0] {
+        bail!("SOCKS5 proxy {proxy} requires authentication");
+    }
+
+    let mut request = vec![5, 1, 0];
+    match &address.0 {
+        lightning::ln::msgs::SocketAddress::TcpIpV4 { addr, .. } => {
+            request.push(1);
+            request.extend_from_slice(addr);
+        }
+        lightning::ln::msgs::SocketAddress::TcpIpV6 { addr, .. } => {
+            request.push(4);
+            request.extend_from_slice(addr);
+        }
+        _ => {
+            let host = address
+                .host_name()
+                .with_context(|| format!("Unsupported address {address}"))?;
+            request.push(3);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    let port = address
+        .port()
+        .with_context(|| format!("Unsupported address {address}"))?;
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0 {
+        bail!(
+            "SOCKS5 proxy could not connect to {address}, error code {}",
+            reply[1]
+        );
+    }
+    // Discard the bound address and port the proxy replies with.
+    let bound_address_len = match reply[3] {
+        1 => 4,
+        4 => 16,
+        3 => stream.read_u8().await? as usize,
+        t => bail!("SOCKS5 proxy replied with unknown address type {t}"),
+    };
+    let mut bound_address = vec![0u8; bound_address_len + 2];
+    stream.read_exact(&mut bound_address).await?;
+    Ok(stream)
+}
+
+/// Order the addresses by the configured address type preference, dropping the ones we cannot use.
+pub(crate) fn preferred_addresses(
+    addresses: Vec<SocketAddress>,
+    preference: &[AddressType],
+    tor_proxy: Option<SocketAddr>,
+) -> Vec<SocketAddress> {
+    let mut addresses: Vec<(usize, SocketAddress)> = addresses
+        .into_iter()
+        .filter(|a| tor_proxy.is_some() || !a.is_onion())
+        .filter_map(|a| {
+            preference
+                .iter()
+                .position(|t| *t == a.address_type())
+                .map(|i| (i, a))
+        })
+        .collect();
+    addresses.sort_by_key(|(i, _)| *i);
+    addresses.into_iter().map(|(_, a)| a).collect()
+}
+
+#[test]
+fn test_preferred_addresses() {
+    use std::str::FromStr;
+
+    let ipv4 = SocketAddress::from_str("127.0.0.1:9735").unwrap();
+    let ipv6 = SocketAddress::from_str("[2001:db8::1]:9735").unwrap();
+    let onion = SocketAddress::from_str(
+        "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion:9735",
+    )
+    .unwrap();
+    let addresses = vec![ipv4.clone(), onion.clone(), ipv6.clone()];
+    let preference = [AddressType::Onion, AddressType::Ipv6, AddressType::Ipv4];
+
+    assert_eq!(
+        vec![ipv6.clone(), ipv4.clone()],
+        preferred_addresses(addresses.clone(), &preference, None)
+    );
+    let proxy = SocketAddr::from_str("127.0.0.1:9050").ok();
+    assert_eq!(
+        vec![onion, ipv6],
+        preferred_addresses(addresses, &preference[..2], proxy)
+    );
+}
@@ -0,0 +1,135 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+
+use crate::logger::KldLogger;
+
+use super::NetworkGraph;
+
+pub(crate) type RapidGossipSync =
+    lightning_rapid_gossip_sync::RapidGossipSync<Arc<NetworkGraph>, Arc<KldLogger>>;
+
+/// Where rapid gossip sync snapshots are fetched from.
+pub(crate) enum SnapshotSource {
+    /// A full snapshot on disk, applied again on every refresh.
+    File(PathBuf),
+    /// An RGS server. The timestamp of the last applied snapshot is appended to the url
+    /// so that the server can reply with an incremental snapshot.
+    Url(String),
+}
+
+impl SnapshotSource {
+    pub fn new(source: &str) -> SnapshotSource {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            SnapshotSource::Url(source.trim_end_matches('/').to_string())
+        } else {
+            SnapshotSource::File(PathBuf::from(source))
+        }
+    }
+
+    async fn fetch(&self, last_sync_timestamp: u32) -> Result<Vec<u8>> {
+        match self {
+            SnapshotSource::File(path) => tokio::fs::read(path)
+                .await
+                .with_context(|| format!("Could not read snapshot {}", path.display())),
+            SnapshotSource::Url(url) => {
+                let url = format!("{url}/{last_sync_timestamp}");
+                let response = reqwest::get(&url)
+                    .await
+                    .with_context(|| format!("Could not fetch snapshot {url}"))?
+                    .error_for_status()?;
+                Ok(response.bytes().await?.to_vec())
+            }
+        }
+    }
+}
+
+impl Display for SnapshotSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotSource::File(path) => write!(f, "{}", path.display()),
+            SnapshotSource::Url(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+/// Bootstraps the network graph from rapid gossip sync snapshots and keeps it up to date,
+/// so that we can route long before P2P gossip has caught up.
+pub(crate) struct RgsClient {
+    rapid_sync: Arc<RapidGossipSync>,
+    network_graph: Arc<NetworkGraph>,
+    source: SnapshotSource,
+}
+
+impl RgsClient {
+    pub fn new(network_graph: Arc<NetworkGraph>, source: SnapshotSource) -> RgsClient {
+        RgsClient {
+            rapid_sync: Arc::new(RapidGossipSync::new(
+                network_graph.clone(),
+                KldLogger::global(),
+            )),
+            network_graph,
+            source,
+        }
+    }
+
+    pub fn rapid_sync(&self) -> Arc<RapidGossipSync> {
+        self.rapid_sync.clone()
+    }
+
+    /// Fetch and apply the next snapshot, returning its timestamp.
+    pub async fn sync(&self) -> Result<u32> {
+        let last_sync_timestamp = self
+            .network_graph
+            .get_last_rapid_gossip_sync_timestamp()
+            .unwrap_or_default();
+        let snapshot = self.source.fetch(last_sync_timestamp).await?;
+        let rapid_sync = self.rapid_sync.clone();
+        // Applying a full snapshot takes a while so keep it off the async runtime.
+        let timestamp =
+            tokio::task::spawn_blocking(move || rapid_sync.update_network_graph(&snapshot))
+                .await?
+                .map_err(|e| {
+                    anyhow!(
+                        "Could not apply rapid gossip sync snapshot from {}: {e:?}",
+                        self.source
+                    )
+                })?;
+        info!(
+            "Applied rapid gossip sync snapshot from {} with timestamp {timestamp}",
+            self.source
+        );
+        Ok(timestamp)
+    }
+
+    pub fn keep_synced(self: &Arc<Self>, interval: Duration) {
+        let client = self.clone();
+        tokio::spawn(async move {
+            let mut interval_timer = tokio::time::interval(interval);
+            interval_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes immediately and we have just synced on start up.
+            interval_timer.tick().await;
+            loop {
+                interval_timer.tick().await;
+                if let Err(e) = client.sync().await {
+                    warn!("{e}");
+                }
+            }
+        });
+    }
+}
+
+#[test]
+fn test_snapshot_source() {
+    assert!(matches!(
+        SnapshotSource::new("/var/lib/kld/rgs_snapshot"),
+        SnapshotSource::File(_)
+    ));
+    let source = SnapshotSource::new("http://127.0.0.1:8080/snapshot/");
+    assert!(matches!(source, SnapshotSource::Url(_)));
+    assert_eq!("http://127.0.0.1:8080/snapshot", source.to_string());
+}
@@ -0,0 +1,448 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::Result;
+use bitcoin::blockdata::constants::ChainHash;
+use bitcoin::Network;
+use lightning::ln::features::ChannelFeatures;
+use lightning::routing::gossip::NodeId;
+use lightning::util::ser::{BigSize, Writeable};
+use log::{info, warn};
+use time::OffsetDateTime;
+
+use crate::database::gossip::{GossipTimestamp, GossipType};
+use crate::database::LdkDatabase;
+
+use super::NetworkGraph;
+
+const RGS_PREFIX: [u8; 4] = [76, 68, 75, 1];
+
+/// Incremental snapshots are generated for gossip seen in the last 1, 2, 4 ... 128 intervals.
+/// Clients that last synced before that get the full snapshot.
+const INCREMENTAL_SNAPSHOTS: u32 = 8;
+
+// Channel update flags in the rapid gossip sync format.
+const FLAG_DIRECTION: u8 = 0b0000_0001;
+const FLAG_DISABLED: u8 = 0b0000_0010;
+const FLAG_CLTV_EXPIRY_DELTA: u8 = 0b0100_0000;
+const FLAG_HTLC_MINIMUM_MSAT: u8 = 0b0010_0000;
+const FLAG_FEE_BASE_MSAT: u8 = 0b0001_0000;
+const FLAG_FEE_PROPORTIONAL_MILLIONTHS: u8 = 0b0000_1000;
+const FLAG_HTLC_MAXIMUM_MSAT: u8 = 0b0000_0100;
+
+#[derive(Clone, Debug)]
+pub(crate) struct SnapshotAnnouncement {
+    pub short_channel_id: u64,
+    pub features: ChannelFeatures,
+    pub node_one: NodeId,
+    pub node_two: NodeId,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SnapshotUpdate {
+    pub short_channel_id: u64,
+    // False for updates from node one to node two.
+    pub two_to_one: bool,
+    pub enabled: bool,
+    pub cltv_expiry_delta: u16,
+    pub htlc_minimum_msat: u64,
+    pub htlc_maximum_msat: u64,
+    pub fee_base_msat: u32,
+    pub fee_proportional_millionths: u32,
+}
+
+struct Snapshot {
+    // Contains all gossip seen after this timestamp.
+    since: u32,
+    data: Vec<u8>,
+}
+
+/// Generates rapid gossip sync snapshots from our network graph so that our LSP clients
+/// can sync their graph from us instead of a third party RGS server.
+pub(crate) struct RgsServer {
+    network_graph: Arc<NetworkGraph>,
+    database: Arc<LdkDatabase>,
+    chain_hash: ChainHash,
+    interval: u32,
+    snapshots: RwLock<Vec<Snapshot>>,
+}
+
+impl RgsServer {
+    pub fn new(
+        network_graph: Arc<NetworkGraph>,
+        database: Arc<LdkDatabase>,
+        network: Network,
+        interval: u32,
+    ) -> RgsServer {
+        RgsServer {
+            network_graph,
+            database,
+            chain_hash: ChainHash::using_genesis_block(network),
+            interval: interval.max(1),
+            snapshots: RwLock::new(vec![]),
+        }
+    }
+
+    /// The snapshot with all gossip seen since the client last synced, or the full snapshot
+    /// if it has never synced or synced too long ago.
+    pub fn snapshot(&self, last_sync_timestamp: u32) -> Option<Vec<u8>> {
+        let snapshots = match self.snapshots.read() {
+            Ok(snapshots) => snapshots,
+            Err(e) => e.into_inner(),
+        };
+        snapshots
+            .iter()
+            .filter(|s| s.since <= last_sync_timestamp)
+            .max_by_key(|s| s.since)
+            .map(|s| s.data.clone())
+    }
+
+    /// Record when we first saw each piece of gossip in the graph and regenerate the snapshots.
+    pub async fn update(&self) -> Result<()> {
+        let now = OffsetDateTime::now_utc().unix_timestamp() as u32;
+        // Snapshot timestamps are aligned to the interval so clients all ask for the same ones.
+        let latest_seen = now - now % self.interval;
+        let seen = OffsetDateTime::from_unix_timestamp(latest_seen as i64)?;
+
+        let recorded: HashMap<(u64, GossipType), GossipTimestamp> = self
+            .database
+            .fetch_gossip_timestamps()
+            .await?
+            .into_iter()
+            .map(|t| ((t.short_channel_id, t.gossip_type), t))
+            .collect();
+
+        let mut announcements = vec![];
+        let mut updates = vec![];
+        let mut changed = vec![];
+        let mut timestamps = HashMap::new();
+        {
+            let graph = self.network_graph.read_only();
+            for (short_channel_id, channel) in graph.channels().unordered_iter() {
+                let short_channel_id = *short_channel_id;
+                let mut record = |gossip_type: GossipType, update_timestamp: u32| {
+                    let timestamp = match recorded.get(&(short_channel_id, gossip_type)) {
+                        Some(t) if t.update_timestamp == update_timestamp => t.clone(),
+                        _ => {
+                            let t = GossipTimestamp {
+                                short_channel_id,
+                                gossip_type,
+                                update_timestamp,
+                                seen,
+                            };
+                            changed.push(t.clone());
+                            t
+                        }
+                    };
+                    timestamps.insert((short_channel_id, gossip_type), timestamp.seen);
+                };
+                record(GossipType::Announcement, 0);
+                announcements.push(SnapshotAnnouncement {
+                    short_channel_id,
+                    features: channel.features.clone(),
+                    node_one: channel.node_one,
+                    node_two: channel.node_two,
+                });
+                for (two_to_one, update) in
+                    [(false, &channel.one_to_two), (true, &channel.two_to_one)]
+                {
+                    if let Some(update) = update {
+                        let gossip_type = if two_to_one {
+                            GossipType::TwoToOne
+                        } else {
+                            GossipType::OneToTwo
+                        };
+                        record(gossip_type, update.last_update);
+                        updates.push(SnapshotUpdate {
+                            short_channel_id,
+                            two_to_one,
+                            enabled: update.enabled,
+                            cltv_expiry_delta: update.cltv_expiry_delta,
+                            htlc_minimum_msat: update.htlc_minimum_msat,
+                            htlc_maximum_msat: update.htlc_maximum_msat,
+                            fee_base_msat: update.fees.base_msat,
+                            fee_proportional_millionths: update.fees.proportional_millionths,
+                        });
+                    }
+                }
+            }
+        }
+
+        let pruned: HashSet<u64> = recorded
+            .keys()
+            .filter(|key| key.1 == GossipType::Announcement && !timestamps.contains_key(key))
+            .map(|(short_channel_id, _)| *short_channel_id)
+            .collect();
+        self.database.persist_gossip_timestamps(&changed).await?;
+        self.database
+            .delete_gossip_timestamps(&pruned.into_iter().collect::<Vec<u64>>())
+            .await?;
+
+        announcements.sort_by_key(|a| a.short_channel_id);
+        updates.sort_by_key(|u| (u.short_channel_id, u.two_to_one));
+
+        let mut snapshots = vec![];
+        let incremental = (0..INCREMENTAL_SNAPSHOTS)
+            .map(|k| latest_seen.saturating_sub(self.interval << k))
+            .filter(|since| *since > 0);
+        for since in std::iter::once(0).chain(incremental) {
+            let since_time = OffsetDateTime::from_unix_timestamp(since as i64)?;
+            let is_new = |short_channel_id: u64, gossip_type: GossipType| {
+                timestamps
+                    .get(&(short_channel_id, gossip_type))
+                    .is_some_and(|seen| *seen > since_time)
+            };
+            let announcements: Vec<SnapshotAnnouncement> = announcements
+                .iter()
+                .filter(|a| is_new(a.short_channel_id, GossipType::Announcement))
+                .cloned()
+                .collect();
+            let updates: Vec<SnapshotUpdate> = updates
+                .iter()
+                .filter(|u| {
+                    let gossip_type = if u.two_to_one {
+                        GossipType::TwoToOne
+                    } else {
+                        GossipType::OneToTwo
+                    };
+                    is_new(u.short_channel_id, gossip_type)
+                })
+                .cloned()
+                .collect();
+            snapshots.push(Snapshot {
+                since,
+                data: serialize_snapshot(self.chain_hash, latest_seen, &announcements, &updates),
+            });
+        }
+        info!(
+            "Generated rapid gossip sync snapshots at {latest_seen} for {} channels, {} changes",
+            announcements.len(),
+            changed.len()
+        );
+        match self.snapshots.write() {
+            Ok(mut guard) => *guard = snapshots,
+            Err(e) => *e.into_inner() = snapshots,
+        }
+        Ok(())
+    }
+
+    pub fn keep_updated(self: &Arc<Self>) {
+        let server = self.clone();
+        tokio::spawn(async move {
+            let mut interval_timer =
+                tokio::time::interval(Duration::from_secs(server.interval as u64));
+            interval_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval_timer.tick().await;
+                if let Err(e) = server.update().await {
+                    warn!("Could not generate rapid gossip sync snapshots: {e}");
+                }
+            }
+        });
+    }
+}
+
+/// Serialize gossip into the version 1 rapid gossip sync format. Announcements and updates must be
+/// sorted by short channel id. Updates are always sent in full, relative to the most common values.
+pub(crate) fn serialize_snapshot(
+    chain_hash: ChainHash,
+    latest_seen: u32,
+    announcements: &[SnapshotAnnouncement],
+    updates: &[SnapshotUpdate],
+) -> Vec<u8> {
+    let mut data = vec![];
+    data.extend_from_slice(&RGS_PREFIX);
+    // Writing to a vec can not fail.
+    let _ = chain_hash.write(&mut data);
+    data.extend_from_slice(&latest_seen.to_be_bytes());
+
+    let mut node_ids: Vec<NodeId> = vec![];
+    let mut node_indexes: HashMap<NodeId, u64> = HashMap::new();
+    let mut index = |node_id: NodeId| {
+        *node_indexes.entry(node_id).or_insert_with(|| {
+            node_ids.push(node_id);
+            node_ids.len() as u64 - 1
+        })
+    };
+    let mut serialized_announcements = vec![];
+    let mut previous_scid = 0;
+    for announcement in announcements {
+        let _ = announcement.features.write(&mut serialized_announcements);
+        let _ = BigSize(announcement.short_channel_id - previous_scid)
+            .write(&mut serialized_announcements);
+        let _ = BigSize(index(announcement.node_one)).write(&mut serialized_announcements);
+        let _ = BigSize(index(announcement.node_two)).write(&mut serialized_announcements);
+        previous_scid = announcement.short_channel_id;
+    }
+    data.extend_from_slice(&(node_ids.len() as u32).to_be_bytes());
+    for node_id in &node_ids {
+        data.extend_from_slice(node_id.as_slice());
+    }
+    data.extend_from_slice(&(announcements.len() as u32).to_be_bytes());
+    data.extend_from_slice(&serialized_announcements);
+
+    data.extend_from_slice(&(updates.len() as u32).to_be_bytes());
+    if updates.is_empty() {
+        return data;
+    }
+    let cltv_expiry_delta = most_common(updates.iter().map(|u| u.cltv_expiry_delta));
+    let htlc_minimum_msat = most_common(updates.iter().map(|u| u.htlc_minimum_msat));
+    let fee_base_msat = most_common(updates.iter().map(|u| u.fee_base_msat));
+    let fee_proportional_millionths =
+        most_common(updates.iter().map(|u| u.fee_proportional_millionths));
+    let htlc_maximum_msat = most_common(updates.iter().map(|u| u.htlc_maximum_msat));
+    data.extend_from_slice(&cltv_expiry_delta.to_be_bytes());
+    data.extend_from_slice(&htlc_minimum_msat.to_be_bytes());
+    data.extend_from_slice(&fee_base_msat.to_be_bytes());
+    data.extend_from_slice(&fee_proportional_millionths.to_be_bytes());
+    data.extend_from_slice(&htlc_maximum_msat.to_be_bytes());
+
+    let mut previous_scid = 0;
+    for update in updates {
+        let _ = BigSize(update.short_channel_id - previous_scid).write(&mut data);
+        previous_scid = update.short_channel_id;
+        let mut flags = 0;
+        if update.two_to_one {
+            flags |= FLAG_DIRECTION;
+        }
+        if !update.enabled {
+            flags |= FLAG_DISABLED;
+        }
+        let mut fields = vec![];
+        if update.cltv_expiry_delta != cltv_expiry_delta {
+            flags |= FLAG_CLTV_EXPIRY_DELTA;
+            fields.extend_from_slice(&update.cltv_expiry_delta.to_be_bytes());
+        }
+        if update.htlc_minimum_msat != htlc_minimum_msat {
+            flags |= FLAG_HTLC_MINIMUM_MSAT;
+            fields.extend_from_slice(&update.htlc_minimum_msat.to_be_bytes());
+        }
+        if update.fee_base_msat != fee_base_msat {
+            flags |= FLAG_FEE_BASE_MSAT;
+            fields.extend_from_slice(&update.fee_base_msat.to_be_bytes());
+        }
+        if update.fee_proportional_millionths != fee_proportional_millionths {
+            flags |= FLAG_FEE_PROPORTIONAL_MILLIONTHS;
+            fields.extend_from_slice(&update.fee_proportional_millionths.to_be_bytes());
+        }
+        if update.htlc_maximum_msat != htlc_maximum_msat {
+            flags |= FLAG_HTLC_MAXIMUM_MSAT;
+            fields.extend_from_slice(&update.htlc_maximum_msat.to_be_bytes());
+        }
+        data.push(flags);
+        data.extend_from_slice(&fields);
+    }
+    data
+}
+
+fn most_common<T: Eq + Hash + Copy + Default>(values: impl Iterator<Item = T>) -> T {
+    let mut counts: HashMap<T, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(value, _)| value)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use log::LevelFilter;
+    use test_utils::random_public_key;
+
+    use crate::logger::KldLogger;
+
+    use super::super::rgs::RapidGossipSync;
+    use super::*;
+
+    #[test]
+    fn test_serialize_snapshot() {
+        KldLogger::init("test", LevelFilter::Info);
+        let node_one = NodeId::from_pubkey(&random_public_key());
+        let node_two = NodeId::from_pubkey(&random_public_key());
+        let node_three = NodeId::from_pubkey(&random_public_key());
+        let announcement = |short_channel_id, node_one, node_two| SnapshotAnnouncement {
+            short_channel_id,
+            features: ChannelFeatures::empty(),
+            node_one,
+            node_two,
+        };
+        let update = |short_channel_id, two_to_one, fee_base_msat| SnapshotUpdate {
+            short_channel_id,
+            two_to_one,
+            enabled: true,
+            cltv_expiry_delta: 144,
+            htlc_minimum_msat: 1000,
+            htlc_maximum_msat: 100_000_000,
+            fee_base_msat,
+            fee_proportional_millionths: 100,
+        };
+        let announcements = vec![
+            announcement(100, node_one, node_two),
+            announcement(200, node_two, node_three),
+        ];
+        let mut disabled = update(200, true, 1000);
+        disabled.enabled = false;
+        let updates = vec![
+            update(100, false, 1000),
+            update(100, true, 5000),
+            update(200, false, 1000),
+            disabled,
+        ];
+        let latest_seen = OffsetDateTime::now_utc().unix_timestamp() as u32;
+        let snapshot = serialize_snapshot(
+            ChainHash::using_genesis_block(Network::Regtest),
+            latest_seen,
+            &announcements,
+            &updates,
+        );
+
+        let network_graph = Arc::new(NetworkGraph::new(Network::Regtest, KldLogger::global()));
+        let rapid_sync = RapidGossipSync::new(network_graph.clone(), KldLogger::global());
+        assert_eq!(
+            latest_seen,
+            rapid_sync
+                .update_network_graph_no_std(&snapshot, None)
+                .unwrap()
+        );
+        let graph = network_graph.read_only();
+        assert_eq!(2, graph.channels().len());
+        assert_eq!(3, graph.nodes().len());
+        let channel = graph.channel(100).unwrap();
+        assert_eq!(node_one, channel.node_one);
+        assert_eq!(node_two, channel.node_two);
+        let one_to_two = channel.one_to_two.as_ref().unwrap();
+        assert_eq!(1000, one_to_two.fees.base_msat);
+        assert_eq!(100, one_to_two.fees.proportional_millionths);
+        assert_eq!(144, one_to_two.cltv_expiry_delta);
+        assert_eq!(100_000_000, one_to_two.htlc_maximum_msat);
+        assert_eq!(5000, channel.two_to_one.as_ref().unwrap().fees.base_msat);
+        let channel = graph.channel(200).unwrap();
+        assert!(channel.one_to_two.as_ref().unwrap().enabled);
+        assert!(!channel.two_to_one.as_ref().unwrap().enabled);
+    }
+
+    #[test]
+    fn test_serialize_empty_snapshot() {
+        KldLogger::init("test", LevelFilter::Info);
+        let snapshot = serialize_snapshot(
+            ChainHash::using_genesis_block(Network::Regtest),
+            1000,
+            &[],
+            &[],
+        );
+        let network_graph = Arc::new(NetworkGraph::new(Network::Regtest, KldLogger::global()));
+        let rapid_sync = RapidGossipSync::new(network_graph.clone(), KldLogger::global());
+        assert_eq!(
+            1000,
+            rapid_sync
+                .update_network_graph_no_std(&snapshot, None)
+                .unwrap()
+        );
+        assert_eq!(0, network_graph.read_only().channels().len());
+    }
+}
@@ -0,0 +1,167 @@
+use std::sync::{Arc, RwLock};
+
+use bitcoin::secp256k1::{self, PublicKey, Secp256k1};
+use lightning::{
+    blinded_path::{payment::ReceiveTlvs, BlindedPath},
+    ln::{channelmanager::ChannelDetails, msgs::LightningError},
+    offers::invoice::BlindedPayInfo,
+    onion_message::messenger::{Destination, MessageRouter, OnionMessagePath},
+    routing::{
+        router::{DefaultRouter, InFlightHtlcs, Route, RouteParameters, Router},
+        scoring::ProbabilisticScoringFeeParameters,
+    },
+    sign::EntropySource,
+};
+use rand::random;
+
+use crate::logger::KldLogger;
+
+use super::{NetworkGraph, Scorer};
+
+type Inner = DefaultRouter<
+    Arc<NetworkGraph>,
+    Arc<KldLogger>,
+    Arc<RwLock<Scorer>>,
+    ProbabilisticScoringFeeParameters,
+    Scorer,
+>;
+
+/// LDK's default router takes the scoring fee parameters by value. This one reads them on every
+/// route lookup so that updated parameters apply without restarting the node.
+pub(crate) struct KldRouter {
+    network_graph: Arc<NetworkGraph>,
+    scorer: Arc<RwLock<Scorer>>,
+    fee_parameters: RwLock<ProbabilisticScoringFeeParameters>,
+}
+
+impl KldRouter {
+    pub fn new(
+        network_graph: Arc<NetworkGraph>,
+        scorer: Arc<RwLock<Scorer>>,
+        fee_parameters: ProbabilisticScoringFeeParameters,
+    ) -> KldRouter {
+        KldRouter {
+            network_graph,
+            scorer,
+            fee_parameters: RwLock::new(fee_parameters),
+        }
+    }
+
+    pub fn fee_parameters(&self) -> ProbabilisticScoringFeeParameters {
+        match self.fee_parameters.read() {
+            Ok(parameters) => parameters.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    pub fn set_fee_parameters(&self, fee_parameters: ProbabilisticScoringFeeParameters) {
+        match self.fee_parameters.write() {
+            Ok(mut guard) => *guard = fee_parameters,
+            Err(e) => *e.into_inner() = fee_parameters,
+        }
+    }
+
+    // Cheap to build, it only holds references to the graph and the scorer.
+    fn inner(&self) -> Inner {
+        DefaultRouter::new(
+            self.network_graph.clone(),
+            KldLogger::global(),
+            random(),
+            self.scorer.clone(),
+            self.fee_parameters(),
+        )
+    }
+}
+
+impl Router for KldRouter {
+    fn find_route(
+        &self,
+        payer: &PublicKey,
+        route_params: &RouteParameters,
+        first_hops: Option<&[&ChannelDetails]>,
+        inflight_htlcs: InFlightHtlcs,
+    ) -> Result<Route, LightningError> {
+        self.inner()
+            .find_route(payer, route_params, first_hops, inflight_htlcs)
+    }
+
+    fn create_blinded_payment_paths<
+        ES: EntropySource + ?Sized,
+        T: secp256k1::Signing + secp256k1::Verification,
+    >(
+        &self,
+        recipient: PublicKey,
+        first_hops: Vec<ChannelDetails>,
+        tlvs: ReceiveTlvs,
+        amount_msats: u64,
+        entropy_source: &ES,
+        secp_ctx: &Secp256k1<T>,
+    ) -> Result<Vec<(BlindedPayInfo, BlindedPath)>, ()> {
+        self.inner().create_blinded_payment_paths(
+            recipient,
+            first_hops,
+            tlvs,
+            amount_msats,
+            entropy_source,
+            secp_ctx,
+        )
+    }
+}
+
+impl MessageRouter for KldRouter {
+    fn find_path(
+        &self,
+        sender: PublicKey,
+        peers: Vec<PublicKey>,
+        destination: Destination,
+    ) -> Result<OnionMessagePath, ()> {
+        self.inner().find_path(sender, peers, destination)
+    }
+
+    fn create_blinded_paths<
+        ES: EntropySource + ?Sized,
+        T: secp256k1::Signing + secp256k1::Verification,
+    >(
+        &self,
+        recipient: PublicKey,
+        peers: Vec<PublicKey>,
+        entropy_source: &ES,
+        secp_ctx: &Secp256k1<T>,
+    ) -> Result<Vec<BlindedPath>, ()> {
+        self.inner()
+            .create_blinded_paths(recipient, peers, entropy_source, secp_ctx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::Network;
+    use lightning::routing::scoring::{ProbabilisticScorer, ProbabilisticScoringDecayParameters};
+    use log::LevelFilter;
+
+    use super::*;
+
+    #[test]
+    fn test_fee_parameters_update_without_restart() {
+        KldLogger::init("test", LevelFilter::Info);
+        let network_graph = Arc::new(NetworkGraph::new(Network::Regtest, KldLogger::global()));
+        let scorer = Arc::new(RwLock::new(ProbabilisticScorer::new(
+            ProbabilisticScoringDecayParameters::default(),
+            network_graph.clone(),
+            KldLogger::global(),
+        )));
+        let router = KldRouter::new(
+            network_graph,
+            scorer,
+            ProbabilisticScoringFeeParameters::default(),
+        );
+        let mut fee_parameters = ProbabilisticScoringFeeParameters::default();
+        fee_parameters.base_penalty_msat = 1234;
+        fee_parameters.liquidity_penalty_multiplier_msat = 5678;
+        router.set_fee_parameters(fee_parameters);
+
+        let current = router.fee_parameters();
+        assert_eq!(current.base_penalty_msat, 1234);
+        assert_eq!(current.liquidity_penalty_multiplier_msat, 5678);
+    }
+}
@@ -36,6 +36,9 @@ static ANCHOR_CHANNEL_FEE: OnceLock<IntGauge> = OnceLock::new();
 static MIN_ALLOWED_ANCHOR_CHANNEL_REMOTE_FEE: OnceLock<IntGauge> = OnceLock::new();
 static MIN_ALLOWED_NON_ANCHOR_CHANNEL_REMOTE_FEE: OnceLock<IntGauge> = OnceLock::new();
 static SCORER_UPDATE_TIMESTAMP: OnceLock<IntGauge> = OnceLock::new();
+static RGS_SNAPSHOT_TIMESTAMP: OnceLock<IntGauge> = OnceLock::new();
+static SWEEP_COUNT: OnceLock<IntGauge> = OnceLock::new();
+static SWEPT_AMOUNT: OnceLock<IntGauge> = OnceLock::new();
 
 // NOTE:
 // Gauge will slow down about 20%~30%, unleast the count reach the limit, else we
@@ -154,6 +157,27 @@ async fn response_examples(
             ) {
                 g.set(ts.unix_timestamp());
             }
+            if let (Some(g), Some(ts)) = (
+                RGS_SNAPSHOT_TIMESTAMP.get(),
+                lightning_metrics.rgs_snapshot_timestamp(),
+            ) {
+                g.set(ts.into());
+            }
+            if let (Some(count), Some(amount), Ok(sweeps)) = (
+                SWEEP_COUNT.get(),
+                SWEPT_AMOUNT.get(),
+                lightning_metrics.list_sweeps().await,
+            ) {
+                count.set(sweeps.len().try_into().unwrap_or(i64::MAX));
+                amount.set(
+                    sweeps
+                        .iter()
+                        .map(|sweep| sweep.amount)
+                        .sum::<u64>()
+                        .try_into()
+                        .unwrap_or(i64::MAX),
+                );
+            }
 
             let metric_families = prometheus::gather();
             let mut buffer = vec![];
@@ -282,6 +306,24 @@ pub async fn start_prometheus_exporter(
             "The update time of scorer"
         )?)
         .unwrap_or_default();
+    RGS_SNAPSHOT_TIMESTAMP
+        .set(register_int_gauge!(
+            "rgs_snapshot_timestamp",
+            "The timestamp of the last rapid gossip sync snapshot applied to the graph"
+        )?)
+        .unwrap_or_default();
+    SWEEP_COUNT
+        .set(register_int_gauge!(
+            "sweep_count",
+            "The number of sweeps of the hot wallet balance to cold storage"
+        )?)
+        .unwrap_or_default();
+    SWEPT_AMOUNT
+        .set(register_int_gauge!(
+            "swept_amount",
+            "The total amount in sats swept to cold storage"
+        )?)
+        .unwrap_or_default();
     probe_metrics
         .0
         .set(register_int_counter!(
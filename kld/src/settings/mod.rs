@@ -1,9 +1,18 @@
 mod bitcoin_network;
 
-use crate::api::SocketAddress;
+use std::{
+    fmt::Display,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use crate::api::{AddressType, SocketAddress};
+use crate::bitcoind::FeePolicy;
+use crate::wallet::{CoinSelection, ColdDestination};
 pub use bitcoin::network::constants::Network;
 use bitcoin::secp256k1::PublicKey;
 use clap::{builder::OsStr, Parser};
+use lightning::chain::chaininterface::ConfirmationTarget;
 
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
@@ -42,6 +51,23 @@ pub struct Settings {
     /// The port to listen to new peer connections on.
     #[arg(long, default_value = "9234", env = "KLD_PEER_PORT")]
     pub peer_port: u16,
+    /// The address to listen to new peer connections on. Use 127.0.0.1 to only accept connections through an onion service.
+    #[arg(long, default_value = "0.0.0.0", env = "KLD_PEER_BIND_ADDRESS")]
+    pub peer_bind_address: IpAddr,
+    /// SOCKS5 proxy (Tor) used to connect to onion, ipv6 and hostname addresses of peers.
+    #[arg(long, env = "KLD_TOR_PROXY")]
+    pub tor_proxy: Option<SocketAddr>,
+    /// Onion service address forwarding to the peer port, which will be added to the node announcement.
+    #[arg(long, env = "KLD_ONION_ADDRESS")]
+    pub onion_address: Option<SocketAddress>,
+    /// The order in which to try the address types announced by a peer, types not in the list are never used.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "ipv4,ipv6,onion,hostname",
+        env = "KLD_ADDRESS_PREFERENCE"
+    )]
+    pub address_preference: Vec<AddressType>,
     /// The node alias on the lightning network.
     #[arg(long, default_value = "", env = "KLD_NODE_ALIAS")]
     pub node_alias: String,
@@ -51,14 +77,89 @@ pub struct Settings {
     /// Public addresses to broadcast to the lightning network.
     #[arg(long, value_delimiter = ',', env = "KLD_PUBLIC_ADDRESSES")]
     pub public_addresses: Vec<SocketAddress>,
+    /// The interval in seconds to broadcast our node announcement to the lightning network.
+    #[arg(long, default_value = "3600", env = "KLD_NODE_ANNOUNCEMENT_INTERVAL")]
+    pub node_announcement_interval: u64,
+    /// Rapid gossip sync snapshot to bootstrap the network graph from. Either a file path or the url of an
+    /// RGS server, in which case the timestamp of the last applied snapshot is appended to the url.
+    #[arg(long, env = "KLD_RGS_SOURCE")]
+    pub rgs_source: Option<String>,
+    /// The interval in seconds to refresh the network graph from the rapid gossip sync source.
+    #[arg(long, default_value = "3600", env = "KLD_RGS_REFRESH_INTERVAL")]
+    pub rgs_refresh_interval: u64,
+    /// Generate rapid gossip sync snapshots of our network graph and serve them to clients on the REST API.
+    #[arg(long, default_value = "false", env = "KLD_RGS_SERVER")]
+    pub rgs_server: bool,
+    /// The interval in seconds to generate new rapid gossip sync snapshots.
+    #[arg(long, default_value = "3600", env = "KLD_RGS_SNAPSHOT_INTERVAL")]
+    pub rgs_snapshot_interval: u32,
 
     #[arg(long, default_value = "127.0.0.1:2233", env = "KLD_EXPORTER_ADDRESS")]
     pub exporter_address: String,
     #[arg(long, default_value = "127.0.0.1:2244", env = "KLD_REST_API_ADDRESS")]
     pub rest_api_address: String,
 
+    /// Where the on chain wallet is synced from. Either electrs at the electrs url or the bitcoind we are
+    /// connected to, which keeps a watch only wallet with the wallet name.
+    #[arg(long, default_value = "electrs", env = "KLD_WALLET_BACKEND")]
+    pub wallet_backend: WalletBackend,
     #[arg(long, default_value = "127.0.0.1:60001", env = "KLD_ELECTRS_URL")]
     pub electrs_url: String,
+    /// Seconds until a PSBT that has not been signed and submitted expires. Channel funding PSBTs expire after
+    /// 50 minutes at most as LDK gives up on the channel after an hour.
+    #[arg(long, default_value = "86400", env = "KLD_PSBT_EXPIRY")]
+    pub psbt_expiry: u64,
+    /// Block height the wallet was created at, for restoring from the seed on a fresh database. The bitcoind
+    /// wallet backend only rescans the blocks after it on the first sync, otherwise the whole chain is rescanned.
+    #[arg(long, env = "KLD_WALLET_BIRTHDAY_HEIGHT")]
+    pub wallet_birthday_height: Option<u32>,
+    /// How the wallet picks the UTXOs to spend. One of branch-and-bound, largest-first, oldest-first or privacy,
+    /// which avoids spending several deposits in the same transaction.
+    #[arg(
+        long,
+        default_value = "branch-and-bound",
+        env = "KLD_WALLET_COIN_SELECTION"
+    )]
+    pub wallet_coin_selection: CoinSelection,
+    /// Number of consecutive unused addresses after which the wallet stops looking for transactions.
+    #[arg(long, default_value = "20", env = "KLD_WALLET_GAP_LIMIT")]
+    pub wallet_gap_limit: usize,
+    /// The interval in seconds to check whether the small UTXOs of the wallet should be consolidated, 0 will disable the feature.
+    #[arg(long, default_value = "0", env = "KLD_CONSOLIDATION_INTERVAL")]
+    pub consolidation_interval: u64,
+    /// UTXOs are only consolidated while the economy fee rate in sats per kw is at or below this.
+    /// The fee rate has the floor of its fee policy applied, so a lower value disables consolidation.
+    #[arg(long, default_value = "2500", env = "KLD_CONSOLIDATION_MAX_FEE_RATE")]
+    pub consolidation_max_fee_rate: u32,
+    /// UTXOs with a value in sats below this are consolidated.
+    #[arg(
+        long,
+        default_value = "100000",
+        env = "KLD_CONSOLIDATION_UTXO_THRESHOLD"
+    )]
+    pub consolidation_utxo_threshold: u64,
+    /// Maximum number of UTXOs spent by a consolidation transaction, the smallest are spent first.
+    #[arg(long, default_value = "50", env = "KLD_CONSOLIDATION_MAX_INPUTS")]
+    pub consolidation_max_inputs: usize,
+    /// Maximum fee in sats of a consolidation transaction.
+    #[arg(long, default_value = "20000", env = "KLD_CONSOLIDATION_MAX_FEE")]
+    pub consolidation_max_fee: u64,
+    /// The interval in seconds to sweep the wallet balance above the hot wallet target to cold storage, 0 will disable the feature.
+    #[arg(long, default_value = "0", env = "KLD_SWEEP_INTERVAL")]
+    pub sweep_interval: u64,
+    /// Confirmed balance in sats to keep in the hot wallet, the fee of a sweep is paid from it.
+    #[arg(long, default_value = "10000000", env = "KLD_SWEEP_TARGET_BALANCE")]
+    pub sweep_target_balance: u64,
+    /// The balance is only swept once it exceeds the target by this many sats.
+    #[arg(long, default_value = "1000000", env = "KLD_SWEEP_THRESHOLD")]
+    pub sweep_threshold: u64,
+    /// Cold storage to sweep to. Either a public descriptor or an xpub to derive a new address from for
+    /// every sweep, or a comma separated list of addresses used in turn.
+    #[arg(long, env = "KLD_SWEEP_DESTINATION")]
+    pub sweep_destination: Option<ColdDestination>,
+    /// Sweeps wait while the economy fee rate estimate in sats per kw is above this.
+    #[arg(long, default_value = "2000", env = "KLD_SWEEP_MAX_FEE_RATE")]
+    pub sweep_max_fee_rate: u32,
 
     #[arg(long, default_value = "127.0.0.1", env = "KLD_DATABASE_HOST")]
     pub database_host: String,
@@ -74,6 +175,84 @@ pub struct Settings {
     pub database_client_cert_path: String,
     #[arg(long, default_value = "", env = "KLD_DATABASE_CLIENT_KEY_PATH")]
     pub database_client_key_path: String,
+    /// Channel monitor updates are appended to the database and the whole monitor is only rewritten after this
+    /// many updates or when the channel closes. 1 rewrites the monitor on every update.
+    #[arg(
+        long,
+        default_value = "100",
+        env = "KLD_MONITOR_UPDATES_BEFORE_FULL_WRITE",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub monitor_updates_before_full_write: u64,
+
+    /// Fixed penalty in msats that the scorer applies to every channel. Unset scorer parameters use the LDK defaults.
+    #[arg(long, env = "KLD_SCORER_BASE_PENALTY_MSAT")]
+    pub scorer_base_penalty_msat: Option<u64>,
+    /// Penalty in msats per 64 msats of the payment amount, applied to every channel.
+    #[arg(long, env = "KLD_SCORER_BASE_PENALTY_AMOUNT_MULTIPLIER_MSAT")]
+    pub scorer_base_penalty_amount_multiplier_msat: Option<u64>,
+    /// Multiplier in msats for the penalty derived from the estimated success probability of a channel.
+    #[arg(long, env = "KLD_SCORER_LIQUIDITY_PENALTY_MULTIPLIER_MSAT")]
+    pub scorer_liquidity_penalty_multiplier_msat: Option<u64>,
+    /// Like the liquidity penalty multiplier but scaled by the payment amount.
+    #[arg(long, env = "KLD_SCORER_LIQUIDITY_PENALTY_AMOUNT_MULTIPLIER_MSAT")]
+    pub scorer_liquidity_penalty_amount_multiplier_msat: Option<u64>,
+    /// Multiplier in msats for the penalty derived from the historical liquidity of a channel.
+    #[arg(long, env = "KLD_SCORER_HISTORICAL_LIQUIDITY_PENALTY_MULTIPLIER_MSAT")]
+    pub scorer_historical_liquidity_penalty_multiplier_msat: Option<u64>,
+    /// Like the historical liquidity penalty multiplier but scaled by the payment amount.
+    #[arg(
+        long,
+        env = "KLD_SCORER_HISTORICAL_LIQUIDITY_PENALTY_AMOUNT_MULTIPLIER_MSAT"
+    )]
+    pub scorer_historical_liquidity_penalty_amount_multiplier_msat: Option<u64>,
+    /// Half life in seconds of what the scorer has learned about channel liquidity.
+    #[arg(long, env = "KLD_SCORER_LIQUIDITY_OFFSET_HALF_LIFE")]
+    pub scorer_liquidity_offset_half_life: Option<u64>,
+    /// Half life in seconds of the historical liquidity of channels we get no updates for.
+    #[arg(long, env = "KLD_SCORER_HISTORICAL_NO_UPDATES_HALF_LIFE")]
+    pub scorer_historical_no_updates_half_life: Option<u64>,
+
+    /// Fee policy for each confirmation target, written as
+    /// `<source>[,floor=<sats per kw>][,ceiling=<sats per kw>][,multiplier=<factor>]`. The source is one of
+    /// `estimatesmartfee:<blocks>[:economical]`, `mempool:<blocks>` or `static:<sats per kw>`.
+    /// The floor defaults to 2000 sats per kw.
+    #[arg(
+        long,
+        default_value = "estimatesmartfee:6",
+        env = "KLD_FEE_ON_CHAIN_SWEEP"
+    )]
+    pub fee_on_chain_sweep: FeePolicy,
+    #[arg(
+        long,
+        default_value = "estimatesmartfee:72,floor=253",
+        env = "KLD_FEE_MIN_ALLOWED_ANCHOR_CHANNEL_REMOTE_FEE"
+    )]
+    pub fee_min_allowed_anchor_channel_remote_fee: FeePolicy,
+    #[arg(
+        long,
+        default_value = "estimatesmartfee:72,floor=253",
+        env = "KLD_FEE_MIN_ALLOWED_NON_ANCHOR_CHANNEL_REMOTE_FEE"
+    )]
+    pub fee_min_allowed_non_anchor_channel_remote_fee: FeePolicy,
+    #[arg(
+        long,
+        default_value = "estimatesmartfee:72",
+        env = "KLD_FEE_ANCHOR_CHANNEL_FEE"
+    )]
+    pub fee_anchor_channel_fee: FeePolicy,
+    #[arg(
+        long,
+        default_value = "estimatesmartfee:18",
+        env = "KLD_FEE_NON_ANCHOR_CHANNEL_FEE"
+    )]
+    pub fee_non_anchor_channel_fee: FeePolicy,
+    #[arg(
+        long,
+        default_value = "estimatesmartfee:72",
+        env = "KLD_FEE_CHANNEL_CLOSE_MINIMUM"
+    )]
+    pub fee_channel_close_minimum: FeePolicy,
 
     /// The time interval to do random probe, 0 will disable the feature
     #[arg(long, default_value = "0", env = "KLD_PROBE_INTERVAL")]
@@ -88,12 +267,32 @@ pub struct Settings {
     /// The graceful period in seconds when a shutdown signal is received
     #[arg(long, default_value = "5", env = "KLD_SHUTDOWN_GRACEFUL_SEC")]
     pub shutdown_graceful_sec: u64,
+
+    /// Path to a static channel backup. On start up kld will reconnect to every peer in the backup
+    /// and get them to force close the channels, so funds can be recovered after losing the database.
+    #[arg(long, env = "KLD_RECOVER_FROM_BACKUP")]
+    pub recover_from_backup: Option<String>,
 }
 
 impl Settings {
     pub fn load() -> Settings {
         Settings::parse()
     }
+
+    pub fn fee_policy(&self, target: ConfirmationTarget) -> &FeePolicy {
+        match target {
+            ConfirmationTarget::OnChainSweep => &self.fee_on_chain_sweep,
+            ConfirmationTarget::MinAllowedAnchorChannelRemoteFee => {
+                &self.fee_min_allowed_anchor_channel_remote_fee
+            }
+            ConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee => {
+                &self.fee_min_allowed_non_anchor_channel_remote_fee
+            }
+            ConfirmationTarget::AnchorChannelFee => &self.fee_anchor_channel_fee,
+            ConfirmationTarget::NonAnchorChannelFee => &self.fee_non_anchor_channel_fee,
+            ConfirmationTarget::ChannelCloseMinimum => &self.fee_channel_close_minimum,
+        }
+    }
 }
 
 impl Default for Settings {
@@ -102,6 +301,33 @@ impl Default for Settings {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletBackend {
+    Electrs,
+    Bitcoind,
+}
+
+impl FromStr for WalletBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "electrs" => Ok(WalletBackend::Electrs),
+            "bitcoind" => Ok(WalletBackend::Bitcoind),
+            _ => anyhow::bail!("{s} is not a wallet backend (electrs, bitcoind)"),
+        }
+    }
+}
+
+impl Display for WalletBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalletBackend::Electrs => write!(f, "electrs"),
+            WalletBackend::Bitcoind => write!(f, "bitcoind"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::settings::Settings;
@@ -1,29 +1,44 @@
 use std::{
+    path::PathBuf,
     sync::{Arc, Mutex, OnceLock},
     time::Duration,
 };
 
-use crate::settings::Settings;
+use crate::settings::{Settings, WalletBackend};
 use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
 use bdk::{
     bitcoin::bip32::ExtendedPrivKey,
-    blockchain::{log_progress, ElectrumBlockchain, GetHeight},
+    bitcoincore_rpc::{self, RpcApi},
+    blockchain::{
+        log_progress,
+        rpc::{Auth, RpcBlockchain, RpcConfig, RpcSyncParams},
+        AnyBlockchain, ConfigurableBlockchain, ElectrumBlockchain, ElectrumBlockchainConfig,
+        GetHeight,
+    },
     database::{BatchDatabase, BatchOperations, Database},
-    electrum_client::Client,
-    template::Bip84,
-    wallet::AddressInfo,
-    Balance, FeeRate, KeychainKind, LocalUtxo, SignOptions, SyncOptions, TransactionDetails,
+    template::{Bip84, Bip86},
+    wallet::{
+        coin_selection::CoinSelectionAlgorithm,
+        tx_builder::{TxBuilder, TxBuilderContext},
+        AddressInfo,
+    },
+    Balance, FeeRate, KeychainKind, LocalUtxo, SignOptions, SyncOptions, TransactionDetails, Utxo,
+    WeightedUtxo,
 };
 use bitcoin::address::NetworkUnchecked;
-use bitcoin::{Address, OutPoint, Script, Transaction};
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::{Address, OutPoint, Script, ScriptBuf, Transaction, Txid};
 use lightning::chain::chaininterface::{BroadcasterInterface, ConfirmationTarget, FeeEstimator};
 use lightning_block_sync::BlockSource;
-use log::{error, info, warn};
+use log::{debug, error, info, warn};
 
 use crate::Service;
 
-use super::WalletInterface;
+use super::{AddressType, CoinSelection, Consolidation, FrozenUtxos, WalletInterface};
+
+/// Weight of a transaction input without its witness or script sig.
+const TXIN_BASE_WEIGHT: usize = (32 + 4 + 4 + 1) * 4;
 
 pub struct Wallet<
     D: Database + BatchDatabase + BatchOperations,
@@ -31,10 +46,15 @@ pub struct Wallet<
 > {
     // bdk::Wallet uses a RefCell to hold the database which is not thread safe so we use a mutex here.
     wallet: Arc<Mutex<bdk::Wallet<D>>>,
+    // Taproot (BIP86) addresses come from a second wallet. Transactions are built by the BIP84 wallet
+    // which spends the taproot outputs as foreign UTXOs. Always lock the BIP84 wallet first.
+    taproot_wallet: Arc<Mutex<bdk::Wallet<D>>>,
     bitcoind_client: Arc<B>,
     settings: Arc<Settings>,
-    blockchain: Arc<OnceLock<ElectrumBlockchain>>,
+    blockchain: Arc<OnceLock<AnyBlockchain>>,
+    taproot_blockchain: Arc<OnceLock<AnyBlockchain>>,
     network: bitcoin::network::constants::Network,
+    frozen_utxos: Arc<dyn FrozenUtxos>,
 }
 
 #[async_trait]
@@ -44,9 +64,11 @@ impl<
     > WalletInterface for Wallet<D, B>
 {
     fn balance(&self) -> Result<Balance> {
-        match self.wallet.try_lock() {
-            Ok(wallet) => Ok(wallet.get_balance()?),
-            Err(_) => {
+        match (self.wallet.try_lock(), self.taproot_wallet.try_lock()) {
+            (Ok(wallet), Ok(taproot_wallet)) => {
+                Ok(wallet.get_balance()? + taproot_wallet.get_balance()?)
+            }
+            _ => {
                 warn!("Wallet was locked when trying to get balance");
                 Ok(Balance::default())
             }
@@ -60,6 +82,7 @@ impl<
         fee_rate: Option<crate::api::payloads::FeeRate>,
         min_conf: Option<u8>,
         utxos: Vec<OutPoint>,
+        mut unspendable: Vec<OutPoint>,
     ) -> Result<(Transaction, TransactionDetails)> {
         if !self.bitcoind_client.is_synchronised().await {
             bail!("Bitcoind is synchronising the blockchain")
@@ -73,26 +96,60 @@ impl<
 
         let address = address.require_network(self.network)?;
         let script_pubkey = address.script_pubkey();
+        let frozen = self.frozen_utxos.frozen_utxos()?;
+        if let Some(utxo) = utxos.iter().find(|utxo| frozen.contains(utxo)) {
+            bail!("UTXO {utxo} is frozen");
+        }
+        if let Some(utxo) = utxos.iter().find(|utxo| unspendable.contains(utxo)) {
+            bail!("UTXO {utxo} is reserved by a pending PSBT");
+        }
+        unspendable.extend(frozen);
 
-        match self.wallet.lock() {
-            Ok(wallet) => {
-                let mut tx_builder = wallet.build_tx();
-                if amount == u64::MAX {
-                    tx_builder.drain_wallet().drain_to(script_pubkey);
+        match (self.wallet.lock(), self.taproot_wallet.lock()) {
+            (Ok(wallet), Ok(taproot_wallet)) => {
+                let (utxos, taproot_utxos) = split_utxos(&wallet, &taproot_wallet, &utxos)?;
+                let selected = !utxos.is_empty() || !taproot_utxos.is_empty();
+                let build = |extra_taproot_utxos: Vec<LocalUtxo>| -> Result<_> {
+                    let mut tx_builder = wallet
+                        .build_tx()
+                        .coin_selection(self.settings.wallet_coin_selection);
+                    // Selected UTXOs are the only ones spent, otherwise coin selection picks them.
+                    if selected {
+                        tx_builder.add_utxos(&utxos)?.manually_selected_only();
+                    } else if amount == u64::MAX {
+                        tx_builder.drain_wallet();
+                    }
+                    if amount == u64::MAX {
+                        tx_builder.drain_to(script_pubkey.clone());
+                    } else {
+                        tx_builder.add_recipient(script_pubkey.clone(), amount);
+                    }
+                    let mut foreign_utxos = taproot_utxos.clone();
+                    foreign_utxos.extend(extra_taproot_utxos);
+                    add_foreign_utxos(&taproot_wallet, &mut tx_builder, foreign_utxos)?;
+                    tx_builder.unspendable(unspendable.clone());
+                    tx_builder.current_height(
+                        min_conf.map_or_else(|| height, |min_conf| height - min_conf as u32),
+                    );
+                    if let Some(fee_rate) = fee_rate {
+                        tx_builder.fee_rate(self.to_bdk_fee_rate(fee_rate));
+                    }
+                    Ok(tx_builder.finish()?)
+                };
+                let (mut psbt, tx_details) = if selected {
+                    build(vec![])?
+                } else if amount == u64::MAX {
+                    // Draining the wallet spends the taproot outputs too.
+                    build(unspent_utxos(&taproot_wallet, &unspendable)?)?
                 } else {
-                    tx_builder
-                        .add_recipient(script_pubkey, amount)
-                        .drain_wallet()
-                        .add_utxos(&utxos)?;
+                    with_taproot_utxos(
+                        &taproot_wallet,
+                        self.settings.wallet_coin_selection,
+                        &unspendable,
+                        build,
+                    )?
                 };
-                tx_builder.current_height(
-                    min_conf.map_or_else(|| height, |min_conf| height - min_conf as u32),
-                );
-                if let Some(fee_rate) = fee_rate {
-                    tx_builder.fee_rate(self.to_bdk_fee_rate(fee_rate));
-                }
-                let (mut psbt, tx_details) = tx_builder.finish()?;
-                let _finalized = wallet.sign(&mut psbt, SignOptions::default())?;
+                let _finalized = sign(&wallet, &taproot_wallet, &mut psbt, SignOptions::default())?;
                 let tx = psbt.extract_tx();
 
                 info!(
@@ -102,13 +159,16 @@ impl<
                 self.bitcoind_client.broadcast_transactions(&[&tx]);
                 Ok((tx, tx_details))
             }
-            Err(_) => bail!("Wallet is still syncing with chain"),
+            _ => bail!("Wallet is still syncing with chain"),
         }
     }
 
-    fn new_external_address(&self) -> Result<AddressInfo> {
-        let address = self
-            .wallet
+    fn new_external_address(&self, address_type: AddressType) -> Result<AddressInfo> {
+        let wallet = match address_type {
+            AddressType::Bech32 => &self.wallet,
+            AddressType::P2tr => &self.taproot_wallet,
+        };
+        let address = wallet
             .lock()
             .unwrap()
             .get_address(bdk::wallet::AddressIndex::LastUnused)?;
@@ -125,21 +185,87 @@ impl<
     }
     fn list_utxos(&self) -> Result<Vec<(LocalUtxo, TransactionDetails)>> {
         let mut result = vec![];
-        match self.wallet.try_lock() {
-            Ok(wallet) => {
-                let utxos = wallet.list_unspent()?;
-                for utxo in utxos {
-                    if let Some(tx) = wallet.get_tx(&utxo.outpoint.txid, false)? {
-                        result.push((utxo, tx));
+        match (self.wallet.try_lock(), self.taproot_wallet.try_lock()) {
+            (Ok(wallet), Ok(taproot_wallet)) => {
+                for wallet in [&*wallet, &*taproot_wallet] {
+                    for utxo in wallet.list_unspent()? {
+                        if let Some(tx) = wallet.get_tx(&utxo.outpoint.txid, false)? {
+                            result.push((utxo, tx));
+                        }
                     }
                 }
             }
-            Err(_) => {
+            _ => {
                 warn!("Wallet was locked when trying to list utxos");
             }
         }
         Ok(result)
     }
+
+    fn frozen_utxos(&self) -> Result<Vec<OutPoint>> {
+        self.frozen_utxos.frozen_utxos()
+    }
+
+    fn freeze_utxos(&self, outpoints: &[OutPoint], frozen: bool) -> Result<Vec<OutPoint>> {
+        let updated = self.frozen_utxos.set_frozen(outpoints, frozen)?;
+        for outpoint in &updated {
+            info!(
+                "{} UTXO {outpoint}",
+                if frozen { "Froze" } else { "Unfroze" }
+            );
+        }
+        Ok(updated)
+    }
+
+    fn public_descriptors(&self) -> Result<Vec<(AddressType, KeychainKind, String)>> {
+        let mut descriptors = vec![];
+        for (address_type, wallet) in [
+            (AddressType::Bech32, &self.wallet),
+            (AddressType::P2tr, &self.taproot_wallet),
+        ] {
+            let wallet = wallet
+                .lock()
+                .map_err(|_| anyhow!("wallet lock is poisened"))?;
+            for keychain in [KeychainKind::External, KeychainKind::Internal] {
+                // The public descriptor has the xpub in place of the xprv, displayed with its checksum.
+                if let Some(descriptor) = wallet.public_descriptor(keychain)? {
+                    descriptors.push((address_type, keychain, descriptor.to_string()));
+                }
+            }
+        }
+        Ok(descriptors)
+    }
+
+    fn birthday_height(&self) -> Option<u32> {
+        self.settings.wallet_birthday_height
+    }
+
+    fn list_transactions(&self) -> Result<Vec<TransactionDetails>> {
+        match (self.wallet.try_lock(), self.taproot_wallet.try_lock()) {
+            (Ok(wallet), Ok(taproot_wallet)) => {
+                let mut transactions = wallet.list_transactions(true)?;
+                // A transaction between the two wallets is in both, each sees its own side of it.
+                for taproot_tx in taproot_wallet.list_transactions(true)? {
+                    match transactions
+                        .iter_mut()
+                        .find(|tx| tx.txid == taproot_tx.txid)
+                    {
+                        Some(tx) => {
+                            tx.received += taproot_tx.received;
+                            tx.sent += taproot_tx.sent;
+                            tx.fee = tx.fee.or(taproot_tx.fee);
+                        }
+                        None => transactions.push(taproot_tx),
+                    }
+                }
+                Ok(transactions)
+            }
+            _ => {
+                warn!("Wallet was locked when trying to list transactions");
+                Ok(vec![])
+            }
+        }
+    }
 }
 
 impl<
@@ -152,6 +278,8 @@ impl<
         settings: Arc<Settings>,
         bitcoind_client: Arc<B>,
         database: D,
+        taproot_database: D,
+        frozen_utxos: Arc<dyn FrozenUtxos>,
     ) -> Result<Wallet<D, B>> {
         let xprivkey = ExtendedPrivKey::new_master(settings.bitcoin_network, seed)?;
 
@@ -161,43 +289,70 @@ impl<
             settings.bitcoin_network,
             database,
         )?));
+        let taproot_wallet = Arc::new(Mutex::new(bdk::Wallet::new(
+            Bip86(xprivkey, KeychainKind::External),
+            Some(Bip86(xprivkey, KeychainKind::Internal)),
+            settings.bitcoin_network,
+            taproot_database,
+        )?));
         let network = settings.bitcoin_network;
         Ok(Wallet {
             wallet: bdk_wallet,
+            taproot_wallet,
             bitcoind_client,
             settings,
             blockchain: Arc::new(OnceLock::new()),
+            taproot_blockchain: Arc::new(OnceLock::new()),
             network,
+            frozen_utxos,
         })
     }
 
     pub async fn synced(&self) -> bool {
         if let Ok((_, Some(height))) = self.bitcoind_client.get_best_block().await {
-            if let Ok(wallet) = self.wallet.try_lock() {
-                if let Ok(Some(sync_time)) = wallet.database().get_sync_time() {
-                    return sync_time.block_time.height == height;
+            for wallet in [&self.wallet, &self.taproot_wallet] {
+                let Ok(wallet) = wallet.try_lock() else {
+                    return false;
+                };
+                match wallet.database().get_sync_time() {
+                    Ok(Some(sync_time)) if sync_time.block_time.height == height => {}
+                    _ => return false,
                 }
             }
+            return true;
         }
         false
     }
 
     pub fn keep_sync_with_chain(&self) {
-        let wallet_clone = self.wallet.clone();
-        let blockchain = self.blockchain.clone();
-        let electrs_url = self.settings.electrs_url.clone();
+        self.keep_wallet_sync_with_chain(
+            self.wallet.clone(),
+            self.blockchain.clone(),
+            self.settings.wallet_name.clone(),
+        );
+        self.keep_wallet_sync_with_chain(
+            self.taproot_wallet.clone(),
+            self.taproot_blockchain.clone(),
+            format!("{}-taproot", self.settings.wallet_name),
+        );
+    }
+
+    fn keep_wallet_sync_with_chain(
+        &self,
+        wallet_clone: Arc<Mutex<bdk::Wallet<D>>>,
+        blockchain: Arc<OnceLock<AnyBlockchain>>,
+        wallet_name: String,
+    ) {
+        let settings = self.settings.clone();
         tokio::task::spawn_blocking(move || loop {
             let sync = || -> Result<()> {
-                // ElectrumBlockchain will not be instantiated if electrs is down. So within this loop we can keep trying to connect and get in sync.
+                // The blockchain will not be instantiated if its backend is down. So within this loop we can keep trying to connect and get in sync.
                 if blockchain.get().is_none() {
-                    let client = Client::new(&electrs_url)?;
                     blockchain
-                        .set(ElectrumBlockchain::from(client))
-                        .map_err(|_| anyhow!("ElectrumBlockchain already set"))?;
+                        .set(connect_blockchain(&settings, &wallet_name)?)
+                        .map_err(|_| anyhow!("Blockchain already set"))?;
                 }
-                let blockchain = blockchain
-                    .get()
-                    .context("ElectrumBlockchain should be set")?;
+                let blockchain = blockchain.get().context("Blockchain should be set")?;
                 let height = blockchain.get_height()?;
                 let guard = wallet_clone
                     .lock()
@@ -209,49 +364,461 @@ impl<
                     .unwrap_or_default();
                 if sync_height < height as u64 {
                     drop(database);
-                    info!("Starting wallet sync from {sync_height} to {height}");
+                    info!("Starting {wallet_name} wallet sync from {sync_height} to {height}");
                     guard.sync(
                         blockchain,
                         SyncOptions {
                             progress: Some(Box::new(log_progress())),
                         },
                     )?;
-                    info!("Wallet is synchronised with electrs");
+                    info!(
+                        "Wallet {wallet_name} is synchronised with {}",
+                        settings.wallet_backend
+                    );
                 }
                 Ok(())
             };
             if let Err(e) = sync() {
-                error!("Failed to sync wallet: {e}");
+                error!("Failed to sync wallet {wallet_name}: {e}");
             };
 
             std::thread::sleep(Duration::from_secs(10));
         });
     }
 
+    /// Whether the script belongs to one of our wallets, such as the change of our transactions.
+    pub fn is_mine(&self, script: &Script) -> Result<bool> {
+        let wallet = self
+            .wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        let taproot_wallet = self
+            .taproot_wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        Ok(wallet.is_mine(script)? || taproot_wallet.is_mine(script)?)
+    }
+
+    /// Sign a funding transaction for the channel without spending any of the unspendable outputs.
     pub fn fund_tx(
         &self,
         output_script: &Script,
         channel_value_satoshis: &u64,
         fee_rate: crate::api::payloads::FeeRate,
+        mut unspendable: Vec<OutPoint>,
     ) -> Result<Transaction> {
+        unspendable.extend(self.frozen_utxos.frozen_utxos()?);
         let wallet = self.wallet.lock().unwrap();
+        let taproot_wallet = self.taproot_wallet.lock().unwrap();
 
-        let mut tx_builder = wallet.build_tx();
+        let build = |taproot_utxos: Vec<LocalUtxo>| -> Result<PartiallySignedTransaction> {
+            let mut tx_builder = wallet
+                .build_tx()
+                .coin_selection(self.settings.wallet_coin_selection);
+            tx_builder
+                .add_recipient(output_script.into(), *channel_value_satoshis)
+                .unspendable(unspendable.clone())
+                .fee_rate(self.to_bdk_fee_rate(fee_rate))
+                .enable_rbf();
+            add_foreign_utxos(&taproot_wallet, &mut tx_builder, taproot_utxos)?;
+            Ok(tx_builder.finish()?.0)
+        };
+        let mut psbt = with_taproot_utxos(
+            &taproot_wallet,
+            self.settings.wallet_coin_selection,
+            &unspendable,
+            build,
+        )?;
+
+        let _finalized = sign(&wallet, &taproot_wallet, &mut psbt, SignOptions::default())?;
+
+        let funding_tx = psbt.extract_tx();
+        Ok(funding_tx)
+    }
+
+    /// Pay all the outputs with a single transaction, with change back to the wallet. It is not broadcast.
+    pub fn transfer_many(
+        &self,
+        outputs: Vec<(ScriptBuf, u64)>,
+        fee_rate: crate::api::payloads::FeeRate,
+        mut unspendable: Vec<OutPoint>,
+    ) -> Result<(Transaction, TransactionDetails)> {
+        if outputs.is_empty() {
+            bail!("No outputs to pay");
+        }
+        unspendable.extend(self.frozen_utxos.frozen_utxos()?);
+        let wallet = self
+            .wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        let taproot_wallet = self
+            .taproot_wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        let build = |taproot_utxos: Vec<LocalUtxo>| -> Result<_> {
+            let mut tx_builder = wallet
+                .build_tx()
+                .coin_selection(self.settings.wallet_coin_selection);
+            tx_builder
+                .set_recipients(outputs.clone())
+                .unspendable(unspendable.clone())
+                .fee_rate(self.to_bdk_fee_rate(fee_rate))
+                .enable_rbf();
+            add_foreign_utxos(&taproot_wallet, &mut tx_builder, taproot_utxos)?;
+            Ok(tx_builder.finish()?)
+        };
+        let (mut psbt, tx_details) = with_taproot_utxos(
+            &taproot_wallet,
+            self.settings.wallet_coin_selection,
+            &unspendable,
+            build,
+        )?;
+        if !sign(&wallet, &taproot_wallet, &mut psbt, SignOptions::default())? {
+            bail!("Failed to sign the transaction");
+        }
+        Ok((psbt.extract_tx(), tx_details))
+    }
 
+    /// Merge the confirmed UTXOs below the size threshold into one output to an internal address,
+    /// within the configured limits. The transaction is only signed when it is not a dry run, it is not broadcast.
+    pub fn consolidate_utxos(
+        &self,
+        mut unspendable: Vec<OutPoint>,
+        dry_run: bool,
+    ) -> Result<Consolidation> {
+        unspendable.extend(self.frozen_utxos.frozen_utxos()?);
+        let fee_rate = self
+            .bitcoind_client
+            .get_est_sat_per_1000_weight(ConfirmationTarget::ChannelCloseMinimum);
+        let mut consolidation = Consolidation {
+            fee_rate,
+            ..Default::default()
+        };
+        let wallet = self
+            .wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        let taproot_wallet = self
+            .taproot_wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+
+        let mut candidates = vec![];
+        for (is_taproot, wallet) in [(false, &*wallet), (true, &*taproot_wallet)] {
+            for utxo in unspent_utxos(wallet, &unspendable)? {
+                if utxo.txout.value >= self.settings.consolidation_utxo_threshold {
+                    continue;
+                }
+                let confirmed = wallet
+                    .get_tx(&utxo.outpoint.txid, false)?
+                    .is_some_and(|tx| tx.confirmation_time.is_some());
+                // Outputs worth less than the fee to spend them would only lose us money.
+                let input_weight = TXIN_BASE_WEIGHT
+                    + wallet
+                        .get_descriptor_for_keychain(utxo.keychain)
+                        .max_weight_to_satisfy()?;
+                let input_fee = (fee_rate as u64 * input_weight as u64).div_ceil(1000);
+                if confirmed && utxo.txout.value > input_fee {
+                    candidates.push((is_taproot, utxo));
+                }
+            }
+        }
+        candidates.sort_by_key(|(_, utxo)| utxo.txout.value);
+        candidates.truncate(self.settings.consolidation_max_inputs);
+        if candidates.len() < 2 {
+            consolidation.skip_reason = Some(format!(
+                "{} UTXOs below {} sats to consolidate",
+                candidates.len(),
+                self.settings.consolidation_utxo_threshold
+            ));
+            return Ok(consolidation);
+        }
+
+        consolidation.amount = candidates.iter().map(|(_, utxo)| utxo.txout.value).sum();
+        let (taproot_utxos, utxos): (Vec<_>, Vec<_>) = candidates
+            .into_iter()
+            .partition(|(is_taproot, _)| *is_taproot);
+        let utxos: Vec<OutPoint> = utxos.into_iter().map(|(_, utxo)| utxo.outpoint).collect();
+        let taproot_utxos: Vec<LocalUtxo> =
+            taproot_utxos.into_iter().map(|(_, utxo)| utxo).collect();
+        consolidation.utxos = utxos
+            .iter()
+            .copied()
+            .chain(taproot_utxos.iter().map(|utxo| utxo.outpoint))
+            .collect();
+
+        let drain_script = wallet
+            .get_internal_address(bdk::wallet::AddressIndex::LastUnused)?
+            .script_pubkey();
+        let mut tx_builder = wallet.build_tx();
         tx_builder
-            .add_recipient(output_script.into(), *channel_value_satoshis)
-            .fee_rate(self.to_bdk_fee_rate(fee_rate))
+            .add_utxos(&utxos)?
+            .manually_selected_only()
+            .drain_to(drain_script)
+            .fee_rate(FeeRate::from_sat_per_kwu(fee_rate as f32))
             .enable_rbf();
+        add_foreign_utxos(&taproot_wallet, &mut tx_builder, taproot_utxos)?;
+        let (mut psbt, tx_details) = tx_builder.finish()?;
+        consolidation.fee = tx_details.fee.unwrap_or_default();
 
-        let (mut psbt, _tx_details) = tx_builder.finish()?;
+        if fee_rate > self.settings.consolidation_max_fee_rate {
+            consolidation.skip_reason = Some(format!(
+                "Economy fee rate {fee_rate} sats/kw is above {} sats/kw",
+                self.settings.consolidation_max_fee_rate
+            ));
+        } else if consolidation.fee > self.settings.consolidation_max_fee {
+            consolidation.skip_reason = Some(format!(
+                "Fee {} sats is above {} sats",
+                consolidation.fee, self.settings.consolidation_max_fee
+            ));
+        } else if !dry_run {
+            if !sign(&wallet, &taproot_wallet, &mut psbt, SignOptions::default())? {
+                bail!("Failed to sign the consolidation transaction");
+            }
+            consolidation.tx = Some(psbt.extract_tx());
+        }
+        Ok(consolidation)
+    }
+
+    /// Pay the confirmed balance above the hot wallet target to the script, once the excess reaches the sweep
+    /// threshold and while the economy fee rate is at or below the maximum. Returns the signed transaction, which
+    /// is not broadcast, with the amount swept and the fee rate.
+    pub fn sweep_excess(
+        &self,
+        script_pubkey: ScriptBuf,
+        mut unspendable: Vec<OutPoint>,
+    ) -> Result<Option<(Transaction, u64, u32)>> {
+        unspendable.extend(self.frozen_utxos.frozen_utxos()?);
+        let balance: u64 = self
+            .list_utxos()?
+            .iter()
+            .filter(|(utxo, details)| {
+                details.confirmation_time.is_some() && !unspendable.contains(&utxo.outpoint)
+            })
+            .map(|(utxo, _)| utxo.txout.value)
+            .sum();
+        let excess = balance.saturating_sub(self.settings.sweep_target_balance);
+        if excess == 0 || excess < self.settings.sweep_threshold {
+            return Ok(None);
+        }
+        let fee_rate = self
+            .bitcoind_client
+            .get_est_sat_per_1000_weight(ConfirmationTarget::ChannelCloseMinimum);
+        if fee_rate > self.settings.sweep_max_fee_rate {
+            debug!(
+                "Not sweeping {excess} sats while the fee rate {fee_rate} sats/kw is above {} sats/kw",
+                self.settings.sweep_max_fee_rate
+            );
+            return Ok(None);
+        }
+        let (tx, _) = self.transfer_many(
+            vec![(script_pubkey, excess)],
+            crate::api::payloads::FeeRate::PerKw(fee_rate),
+            unspendable,
+        )?;
+        Ok(Some((tx, excess, fee_rate)))
+    }
 
+    /// Build a PSBT paying the amount (u64::MAX to drain the wallet, or only the selected UTXOs) to the script
+    /// without spending any of the unspendable outputs. Only signed with the wallet key if requested, so that it can be signed by an external signer.
+    pub fn create_psbt(
+        &self,
+        script_pubkey: ScriptBuf,
+        amount: u64,
+        fee_rate: crate::api::payloads::FeeRate,
+        utxos: &[OutPoint],
+        mut unspendable: Vec<OutPoint>,
+        sign: bool,
+    ) -> Result<PartiallySignedTransaction> {
+        let frozen = self.frozen_utxos.frozen_utxos()?;
+        if let Some(utxo) = utxos.iter().find(|utxo| frozen.contains(utxo)) {
+            bail!("UTXO {utxo} is frozen");
+        }
+        unspendable.extend(frozen);
+        let wallet = self
+            .wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        let taproot_wallet = self
+            .taproot_wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        let (utxos, selected_taproot_utxos) = split_utxos(&wallet, &taproot_wallet, utxos)?;
+        let selected = !utxos.is_empty() || !selected_taproot_utxos.is_empty();
+        let build = |taproot_utxos: Vec<LocalUtxo>| -> Result<PartiallySignedTransaction> {
+            let mut tx_builder = wallet
+                .build_tx()
+                .coin_selection(self.settings.wallet_coin_selection);
+            if amount == u64::MAX {
+                if selected {
+                    tx_builder.add_utxos(&utxos)?.manually_selected_only();
+                } else {
+                    tx_builder.drain_wallet();
+                }
+                tx_builder.drain_to(script_pubkey.clone());
+            } else {
+                tx_builder
+                    .add_recipient(script_pubkey.clone(), amount)
+                    .add_utxos(&utxos)?;
+            }
+            tx_builder
+                .unspendable(unspendable.clone())
+                .fee_rate(self.to_bdk_fee_rate(fee_rate))
+                .enable_rbf();
+            add_foreign_utxos(&taproot_wallet, &mut tx_builder, taproot_utxos)?;
+            Ok(tx_builder.finish()?.0)
+        };
+        let mut psbt = if amount == u64::MAX && !selected {
+            build(unspent_utxos(&taproot_wallet, &unspendable)?)?
+        } else if amount == u64::MAX || !selected_taproot_utxos.is_empty() {
+            build(selected_taproot_utxos)?
+        } else {
+            with_taproot_utxos(
+                &taproot_wallet,
+                self.settings.wallet_coin_selection,
+                &unspendable,
+                build,
+            )?
+        };
+        if sign {
+            self::sign(
+                &wallet,
+                &taproot_wallet,
+                &mut psbt,
+                SignOptions {
+                    try_finalize: false,
+                    ..Default::default()
+                },
+            )?;
+        }
+        Ok(psbt)
+    }
+
+    /// Finalize a PSBT that has all its signatures and extract the transaction.
+    pub fn finalize_psbt(&self, mut psbt: PartiallySignedTransaction) -> Result<Transaction> {
+        let wallet = self
+            .wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        let taproot_wallet = self
+            .taproot_wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        // Each wallet finalizes its own inputs and skips the ones already finalized.
+        taproot_wallet.finalize_psbt(&mut psbt, SignOptions::default())?;
+        if !wallet.finalize_psbt(&mut psbt, SignOptions::default())? {
+            bail!("PSBT is missing signatures");
+        }
+        Ok(psbt.extract_tx())
+    }
+
+    /// Our unconfirmed transaction can be replaced if it signals RBF and only spends our outputs.
+    pub fn can_replace(&self, txid: &Txid) -> Result<bool> {
+        let wallet = self
+            .wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        let details = wallet
+            .get_tx(txid, true)?
+            .with_context(|| format!("Transaction {txid} not found in the wallet"))?;
+        if details.confirmation_time.is_some() {
+            bail!("Transaction {txid} is already confirmed");
+        }
+        Ok(details.fee.is_some() && details.transaction.is_some_and(|tx| tx.is_explicitly_rbf()))
+    }
+
+    /// Replace our unconfirmed transaction with one paying the higher fee rate (RBF).
+    pub fn bump_fee_rbf(
+        &self,
+        txid: &Txid,
+        fee_rate: crate::api::payloads::FeeRate,
+        mut unspendable: Vec<OutPoint>,
+    ) -> Result<(Transaction, TransactionDetails)> {
+        unspendable.extend(self.frozen_utxos.frozen_utxos()?);
+        let wallet = self
+            .wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        let mut tx_builder = wallet
+            .build_fee_bump(*txid)?
+            .coin_selection(self.settings.wallet_coin_selection);
+        tx_builder
+            .unspendable(unspendable)
+            .fee_rate(self.to_bdk_fee_rate(fee_rate))
+            .enable_rbf();
+        let (mut psbt, tx_details) = tx_builder.finish()?;
         let _finalized = wallet.sign(&mut psbt, SignOptions::default())?;
+        Ok((psbt.extract_tx(), tx_details))
+    }
 
-        let funding_tx = psbt.extract_tx();
-        Ok(funding_tx)
+    /// Spend our largest output of the unconfirmed transaction with a child paying enough
+    /// for both transactions together to reach the fee rate (CPFP).
+    pub fn bump_fee_cpfp(
+        &self,
+        txid: &Txid,
+        fee_rate: crate::api::payloads::FeeRate,
+    ) -> Result<(Transaction, TransactionDetails)> {
+        let wallet = self
+            .wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        let parent = wallet
+            .get_tx(txid, true)?
+            .with_context(|| format!("Transaction {txid} not found in the wallet"))?;
+        if parent.confirmation_time.is_some() {
+            bail!("Transaction {txid} is already confirmed");
+        }
+        let parent_tx = parent
+            .transaction
+            .with_context(|| format!("Transaction {txid} not found in the wallet"))?;
+        let parent_vsize = parent_tx.vsize();
+        // The BIP84 wallet does not know the fee when the parent spent taproot outputs.
+        let taproot_wallet = self
+            .taproot_wallet
+            .lock()
+            .map_err(|_| anyhow!("wallet lock is poisened"))?;
+        let parent_fee = match parent.fee {
+            Some(fee) => Some(fee),
+            None => fee(&wallet, &taproot_wallet, &parent_tx)?,
+        }
+        .with_context(|| format!("Fee of transaction {txid} is unknown"))?;
+        let frozen = self.frozen_utxos.frozen_utxos()?;
+        let utxo = wallet
+            .list_unspent()?
+            .into_iter()
+            .filter(|utxo| utxo.outpoint.txid == *txid && !frozen.contains(&utxo.outpoint))
+            .max_by_key(|utxo| utxo.txout.value)
+            .with_context(|| format!("Transaction {txid} has no unspent output in the wallet"))?;
+        let fee_rate = self.to_bdk_fee_rate(fee_rate);
+        let parent_target_fee = (fee_rate.as_sat_per_vb() * parent_vsize as f32).ceil() as u64;
+        if parent_fee >= parent_target_fee {
+            bail!("Transaction {txid} already pays the fee rate");
+        }
+        let drain_script = wallet
+            .get_internal_address(bdk::wallet::AddressIndex::New)?
+            .script_pubkey();
+        let build = |fee: Option<u64>| -> Result<(PartiallySignedTransaction, TransactionDetails)> {
+            let mut tx_builder = wallet.build_tx();
+            tx_builder
+                .add_utxo(utxo.outpoint)?
+                .manually_selected_only()
+                .drain_to(drain_script.clone())
+                .enable_rbf();
+            match fee {
+                Some(fee) => tx_builder.fee_absolute(fee),
+                None => tx_builder.fee_rate(fee_rate),
+            };
+            Ok(tx_builder.finish()?)
+        };
+        // The fee of the child at the fee rate pays for its own size, on top of that it pays what the parent is missing.
+        let (_, child) = build(None)?;
+        let child_fee = child.fee.unwrap_or_default() + parent_target_fee - parent_fee;
+        let (mut psbt, tx_details) = build(Some(child_fee))?;
+        let _finalized = sign(&wallet, &taproot_wallet, &mut psbt, SignOptions::default())?;
+        Ok((psbt.extract_tx(), tx_details))
     }
 
-    fn to_bdk_fee_rate(&self, fee_rate: crate::api::payloads::FeeRate) -> FeeRate {
+    pub fn to_bdk_fee_rate(&self, fee_rate: crate::api::payloads::FeeRate) -> FeeRate {
         match fee_rate {
             crate::api::payloads::FeeRate::Urgent => FeeRate::from_sat_per_kwu(
                 self.bitcoind_client
@@ -274,6 +841,211 @@ impl<
     }
 }
 
+/// Split the UTXOs between the BIP84 wallet and the taproot wallet.
+fn split_utxos<D: BatchDatabase>(
+    wallet: &bdk::Wallet<D>,
+    taproot_wallet: &bdk::Wallet<D>,
+    outpoints: &[OutPoint],
+) -> Result<(Vec<OutPoint>, Vec<LocalUtxo>)> {
+    let mut utxos = vec![];
+    let mut taproot_utxos = vec![];
+    for outpoint in outpoints {
+        if wallet.get_utxo(*outpoint)?.is_some() {
+            utxos.push(*outpoint);
+        } else if let Some(utxo) = taproot_wallet.get_utxo(*outpoint)? {
+            taproot_utxos.push(utxo);
+        } else {
+            bail!("UTXO {outpoint} not found in the wallet");
+        }
+    }
+    Ok((utxos, taproot_utxos))
+}
+
+fn unspent_utxos<D: BatchDatabase>(
+    wallet: &bdk::Wallet<D>,
+    unspendable: &[OutPoint],
+) -> Result<Vec<LocalUtxo>> {
+    Ok(wallet
+        .list_unspent()?
+        .into_iter()
+        .filter(|utxo| !unspendable.contains(&utxo.outpoint))
+        .collect())
+}
+
+/// Let the BIP84 wallet spend outputs of the taproot wallet.
+fn add_foreign_utxos<D: BatchDatabase, Cs: CoinSelectionAlgorithm<D>, Ctx: TxBuilderContext>(
+    taproot_wallet: &bdk::Wallet<D>,
+    tx_builder: &mut TxBuilder<'_, D, Cs, Ctx>,
+    utxos: Vec<LocalUtxo>,
+) -> Result<()> {
+    for utxo in utxos {
+        let satisfaction_weight = taproot_wallet
+            .get_descriptor_for_keychain(utxo.keychain)
+            .max_weight_to_satisfy()?;
+        let outpoint = utxo.outpoint;
+        let psbt_input = taproot_wallet.get_psbt_input(utxo, None, false)?;
+        tx_builder.add_foreign_utxo(outpoint, psbt_input, satisfaction_weight)?;
+    }
+    Ok(())
+}
+
+/// Coin selection only picks outputs of the BIP84 wallet. When those are not enough, the configured coin
+/// selection picks taproot outputs for the missing amount, until the transaction can be built.
+fn with_taproot_utxos<D: BatchDatabase, T>(
+    taproot_wallet: &bdk::Wallet<D>,
+    coin_selection: CoinSelection,
+    unspendable: &[OutPoint],
+    build: impl Fn(Vec<LocalUtxo>) -> Result<T>,
+) -> Result<T> {
+    let mut candidates = unspent_utxos(taproot_wallet, unspendable)?;
+    let mut taproot_utxos: Vec<LocalUtxo> = vec![];
+    loop {
+        let e = match build(taproot_utxos.clone()) {
+            Err(e) => e,
+            result => return result,
+        };
+        // The fee of the taproot inputs is part of what is missing in the next round.
+        let missing = match e.downcast_ref::<bdk::Error>() {
+            Some(bdk::Error::InsufficientFunds { needed, available }) => {
+                needed.saturating_sub(*available).max(1)
+            }
+            _ => return Err(e),
+        };
+        let selected = select_taproot_utxos(taproot_wallet, coin_selection, &candidates, missing)?;
+        if selected.is_empty() {
+            return Err(e);
+        }
+        candidates.retain(|utxo| !selected.contains(utxo));
+        taproot_utxos.extend(selected);
+    }
+}
+
+/// The taproot outputs that the coin selection picks to pay the amount, none when they are not enough.
+fn select_taproot_utxos<D: BatchDatabase>(
+    taproot_wallet: &bdk::Wallet<D>,
+    coin_selection: CoinSelection,
+    candidates: &[LocalUtxo],
+    amount: u64,
+) -> Result<Vec<LocalUtxo>> {
+    let mut weighted_utxos = vec![];
+    for utxo in candidates {
+        weighted_utxos.push(WeightedUtxo {
+            satisfaction_weight: taproot_wallet
+                .get_descriptor_for_keychain(utxo.keychain)
+                .max_weight_to_satisfy()?,
+            utxo: Utxo::Local(utxo.clone()),
+        });
+    }
+    let Ok(result) = coin_selection.coin_select(
+        &*taproot_wallet.database(),
+        vec![],
+        weighted_utxos,
+        FeeRate::from_sat_per_vb(0.0),
+        amount,
+        &ScriptBuf::new(),
+    ) else {
+        return Ok(vec![]);
+    };
+    Ok(candidates
+        .iter()
+        .filter(|utxo| {
+            result
+                .selected
+                .iter()
+                .any(|selected| selected.outpoint() == utxo.outpoint)
+        })
+        .cloned()
+        .collect())
+}
+
+/// Both wallets sign their own inputs, the taproot wallet first so that its inputs are finalized
+/// before the BIP84 wallet signs. Returns true when all the inputs are finalized.
+fn sign<D: BatchDatabase>(
+    wallet: &bdk::Wallet<D>,
+    taproot_wallet: &bdk::Wallet<D>,
+    psbt: &mut PartiallySignedTransaction,
+    sign_options: SignOptions,
+) -> Result<bool> {
+    taproot_wallet.sign(psbt, sign_options.clone())?;
+    Ok(wallet.sign(psbt, sign_options)?)
+}
+
+/// The fee of a transaction spending outputs of either wallet.
+fn fee<D: BatchDatabase>(
+    wallet: &bdk::Wallet<D>,
+    taproot_wallet: &bdk::Wallet<D>,
+    tx: &Transaction,
+) -> Result<Option<u64>> {
+    let mut input_value = 0;
+    for input in &tx.input {
+        let outpoint = input.previous_output;
+        let previous_tx = match wallet.get_tx(&outpoint.txid, true)? {
+            Some(details) => details.transaction,
+            None => taproot_wallet
+                .get_tx(&outpoint.txid, true)?
+                .and_then(|details| details.transaction),
+        };
+        match previous_tx.and_then(|tx| tx.output.get(outpoint.vout as usize).cloned()) {
+            Some(output) => input_value += output.value,
+            None => return Ok(None),
+        }
+    }
+    let output_value: u64 = tx.output.iter().map(|output| output.value).sum();
+    Ok(input_value.checked_sub(output_value))
+}
+
+fn connect_blockchain(settings: &Settings, wallet_name: &str) -> Result<AnyBlockchain> {
+    match settings.wallet_backend {
+        WalletBackend::Electrs => {
+            let config = ElectrumBlockchainConfig {
+                url: settings.electrs_url.clone(),
+                socks5: None,
+                retry: 1,
+                timeout: None,
+                stop_gap: settings.wallet_gap_limit,
+                validate_domain: true,
+            };
+            Ok(ElectrumBlockchain::from_config(&config)?.into())
+        }
+        WalletBackend::Bitcoind => {
+            Ok(RpcBlockchain::from_config(&rpc_config(settings, wallet_name)?)?.into())
+        }
+    }
+}
+
+/// How the bitcoind wallet backend connects to bitcoind and where its first sync starts.
+pub fn rpc_config(settings: &Settings, wallet_name: &str) -> Result<RpcConfig> {
+    let url = format!(
+        "http://{}:{}",
+        settings.bitcoind_rpc_host, settings.bitcoind_rpc_port
+    );
+    let cookie_file = PathBuf::from(&settings.bitcoin_cookie_path);
+    // Bitcoind only rescans for the wallet transactions from this time on the first sync,
+    // later syncs start from the last sync time.
+    let start_time = match settings.wallet_birthday_height {
+        Some(height) => {
+            let client = bitcoincore_rpc::Client::new(
+                &url,
+                bitcoincore_rpc::Auth::CookieFile(cookie_file.clone()),
+            )?;
+            let hash = client.get_block_hash(height as u64)?;
+            client.get_block_header(&hash)?.time as u64
+        }
+        None => 0,
+    };
+    Ok(RpcConfig {
+        url,
+        auth: Auth::Cookie { file: cookie_file },
+        network: settings.bitcoin_network,
+        wallet_name: wallet_name.to_string(),
+        sync_params: Some(RpcSyncParams {
+            start_script_count: settings.wallet_gap_limit,
+            start_time,
+            ..Default::default()
+        }),
+    })
+}
+
 #[cfg(test)]
 mod test {
     use std::{
@@ -283,13 +1055,48 @@ mod test {
 
     use crate::settings::Settings;
     use anyhow::Result;
-    use bdk::{database::MemoryDatabase, wallet::get_funded_wallet, Balance};
-    use bitcoin::Address;
-    use test_utils::{TEST_ADDRESS, TEST_WPKH};
+    use bdk::{
+        database::{AnyDatabase, MemoryDatabase},
+        wallet::get_funded_wallet,
+        Balance, KeychainKind, LocalUtxo,
+    };
+    use bitcoin::{hashes::Hash, Address, OutPoint, ScriptBuf, TxOut, Txid};
+    use test_utils::{TEST_ADDRESS, TEST_TR, TEST_WPKH};
 
-    use crate::{bitcoind::MockBitcoindClient, wallet::WalletInterface};
+    use crate::{
+        bitcoind::MockBitcoindClient,
+        ldk::MIN_FEERATE,
+        wallet::{AddressType, CoinSelection, WalletInterface},
+    };
+
+    use super::{select_taproot_utxos, FrozenUtxos, Wallet};
+
+    fn empty_taproot_wallet() -> Result<Arc<Mutex<bdk::Wallet<AnyDatabase>>>> {
+        Ok(Arc::new(Mutex::new(bdk::Wallet::new(
+            TEST_TR,
+            None,
+            bitcoin::Network::Regtest,
+            AnyDatabase::Memory(MemoryDatabase::new()),
+        )?)))
+    }
 
-    use super::Wallet;
+    #[derive(Default)]
+    struct TestFrozenUtxos(Mutex<Vec<OutPoint>>);
+
+    impl FrozenUtxos for TestFrozenUtxos {
+        fn frozen_utxos(&self) -> Result<Vec<OutPoint>> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+
+        fn set_frozen(&self, outpoints: &[OutPoint], frozen: bool) -> Result<Vec<OutPoint>> {
+            let mut frozen_utxos = self.0.lock().unwrap();
+            frozen_utxos.retain(|outpoint| !outpoints.contains(outpoint));
+            if frozen {
+                frozen_utxos.extend(outpoints);
+            }
+            Ok(outpoints.to_vec())
+        }
+    }
 
     #[test]
     fn test_fee_rate() -> Result<()> {
@@ -298,6 +1105,8 @@ mod test {
             Arc::new(Settings::default()),
             Arc::new(MockBitcoindClient::default()),
             MemoryDatabase::new(),
+            MemoryDatabase::new(),
+            Arc::new(TestFrozenUtxos::default()),
         )?;
 
         let balance = wallet.balance()?;
@@ -321,6 +1130,55 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_public_descriptors() -> Result<()> {
+        let wallet = Wallet::new(
+            &[0u8; 32],
+            Arc::new(Settings::default()),
+            Arc::new(MockBitcoindClient::default()),
+            MemoryDatabase::new(),
+            MemoryDatabase::new(),
+            Arc::new(TestFrozenUtxos::default()),
+        )?;
+
+        let descriptors = wallet.public_descriptors()?;
+        assert_eq!(4, descriptors.len());
+        for (address_type, _, descriptor) in descriptors {
+            assert!(!descriptor.contains("prv"));
+            assert!(descriptor.contains('#'));
+            match address_type {
+                AddressType::Bech32 => assert!(descriptor.starts_with("wpkh(")),
+                AddressType::P2tr => assert!(descriptor.starts_with("tr(")),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_mine() -> Result<()> {
+        let wallet = Wallet::new(
+            &[0u8; 32],
+            Arc::new(Settings::default()),
+            Arc::new(MockBitcoindClient::default()),
+            MemoryDatabase::new(),
+            MemoryDatabase::new(),
+            Arc::new(TestFrozenUtxos::default()),
+        )?;
+
+        let change = wallet.new_internal_address()?.address.script_pubkey();
+        assert!(wallet.is_mine(&change)?);
+        let taproot = wallet
+            .new_external_address(AddressType::P2tr)?
+            .address
+            .script_pubkey();
+        assert!(wallet.is_mine(&taproot)?);
+        let other = Address::from_str(TEST_ADDRESS)?
+            .assume_checked()
+            .script_pubkey();
+        assert!(!wallet.is_mine(&other)?);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_cannot_transfer_while_synchronising() -> Result<()> {
         let mut bitcoind_client = MockBitcoindClient::default();
@@ -330,9 +1188,12 @@ mod test {
         let wallet = Wallet {
             bitcoind_client: bitcoind_client.clone(),
             wallet: Arc::new(Mutex::new(bdk_wallet)),
+            taproot_wallet: empty_taproot_wallet()?,
             settings: Arc::new(Settings::default()),
             blockchain: Arc::new(OnceLock::new()),
+            taproot_blockchain: Arc::new(OnceLock::new()),
             network: bitcoin::network::constants::Network::Testnet,
+            frozen_utxos: Arc::new(TestFrozenUtxos::default()),
         };
 
         let res = wallet
@@ -342,6 +1203,7 @@ mod test {
                 None,
                 None,
                 vec![],
+                vec![],
             )
             .await;
         assert!(res.is_err());
@@ -356,9 +1218,12 @@ mod test {
         let wallet = Wallet {
             bitcoind_client: bitcoind_client.clone(),
             wallet: Arc::new(Mutex::new(bdk_wallet)),
+            taproot_wallet: empty_taproot_wallet()?,
             settings: Arc::new(Settings::default()),
             blockchain: Arc::new(OnceLock::new()),
+            taproot_blockchain: Arc::new(OnceLock::new()),
             network: bitcoin::network::constants::Network::Testnet,
+            frozen_utxos: Arc::new(TestFrozenUtxos::default()),
         };
 
         let (tx, tx_details) = wallet
@@ -368,6 +1233,7 @@ mod test {
                 None,
                 None,
                 vec![],
+                vec![],
             )
             .await?;
 
@@ -380,4 +1246,454 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_cannot_spend_frozen_utxos() -> Result<()> {
+        let bitcoind_client = MockBitcoindClient::default();
+        let (bdk_wallet, _, txid) = get_funded_wallet(TEST_WPKH);
+        let bitcoind_client = Arc::new(bitcoind_client);
+        let wallet = Wallet {
+            bitcoind_client: bitcoind_client.clone(),
+            wallet: Arc::new(Mutex::new(bdk_wallet)),
+            taproot_wallet: empty_taproot_wallet()?,
+            settings: Arc::new(Settings::default()),
+            blockchain: Arc::new(OnceLock::new()),
+            taproot_blockchain: Arc::new(OnceLock::new()),
+            network: bitcoin::network::constants::Network::Testnet,
+            frozen_utxos: Arc::new(TestFrozenUtxos::default()),
+        };
+        let outpoint = OutPoint::new(txid, 0);
+
+        assert_eq!(vec![outpoint], wallet.freeze_utxos(&[outpoint], true)?);
+        assert_eq!(vec![outpoint], wallet.frozen_utxos()?);
+        assert!(wallet
+            .transfer(
+                Address::from_str(TEST_ADDRESS)?,
+                10000,
+                None,
+                None,
+                vec![],
+                vec![]
+            )
+            .await
+            .is_err());
+        assert!(wallet
+            .transfer(
+                Address::from_str(TEST_ADDRESS)?,
+                10000,
+                None,
+                None,
+                vec![outpoint],
+                vec![]
+            )
+            .await
+            .is_err());
+
+        wallet.freeze_utxos(&[outpoint], false)?;
+        assert!(wallet.frozen_utxos()?.is_empty());
+        let (tx, _) = wallet
+            .transfer(
+                Address::from_str(TEST_ADDRESS)?,
+                10000,
+                None,
+                None,
+                vec![],
+                vec![],
+            )
+            .await?;
+        assert_eq!(outpoint, tx.input[0].previous_output);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transfer_spends_taproot_outputs() -> Result<()> {
+        let bitcoind_client = Arc::new(MockBitcoindClient::default());
+        let (bdk_wallet, _, txid) = get_funded_wallet(TEST_WPKH);
+        let (taproot_wallet, _, taproot_txid) = get_funded_wallet(TEST_TR);
+        let wallet = Wallet {
+            bitcoind_client: bitcoind_client.clone(),
+            wallet: Arc::new(Mutex::new(bdk_wallet)),
+            taproot_wallet: Arc::new(Mutex::new(taproot_wallet)),
+            settings: Arc::new(Settings::default()),
+            blockchain: Arc::new(OnceLock::new()),
+            taproot_blockchain: Arc::new(OnceLock::new()),
+            network: bitcoin::network::constants::Network::Testnet,
+            frozen_utxos: Arc::new(TestFrozenUtxos::default()),
+        };
+
+        let address = wallet.new_external_address(AddressType::P2tr)?;
+        assert_eq!(
+            Some(bitcoin::AddressType::P2tr),
+            address.address.address_type()
+        );
+        let balance = wallet.balance()?;
+        assert_eq!(100000, balance.confirmed);
+        assert_eq!(2, wallet.list_utxos()?.len());
+
+        let (tx, _) = wallet
+            .transfer(
+                Address::from_str(TEST_ADDRESS)?,
+                u64::MAX,
+                None,
+                None,
+                vec![],
+                vec![],
+            )
+            .await?;
+
+        let mut inputs: Vec<_> = tx.input.iter().map(|input| input.previous_output).collect();
+        inputs.sort();
+        let mut expected = vec![OutPoint::new(txid, 0), OutPoint::new(taproot_txid, 0)];
+        expected.sort();
+        assert_eq!(expected, inputs);
+        for input in &tx.input {
+            assert!(!input.witness.is_empty());
+        }
+        assert!(bitcoind_client.has_broadcast(tx.txid()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transfer_spends_only_selected_utxos() -> Result<()> {
+        let (bdk_wallet, _, txid) = get_funded_wallet(TEST_WPKH);
+        let (taproot_wallet, _, taproot_txid) = get_funded_wallet(TEST_TR);
+        let wallet = Wallet {
+            bitcoind_client: Arc::new(MockBitcoindClient::default()),
+            wallet: Arc::new(Mutex::new(bdk_wallet)),
+            taproot_wallet: Arc::new(Mutex::new(taproot_wallet)),
+            settings: Arc::new(Settings::default()),
+            blockchain: Arc::new(OnceLock::new()),
+            taproot_blockchain: Arc::new(OnceLock::new()),
+            network: bitcoin::network::constants::Network::Testnet,
+            frozen_utxos: Arc::new(TestFrozenUtxos::default()),
+        };
+
+        // Coin selection needs one of the outputs, the wallet is not drained.
+        let (tx, _) = wallet
+            .transfer(
+                Address::from_str(TEST_ADDRESS)?,
+                10000,
+                None,
+                None,
+                vec![],
+                vec![],
+            )
+            .await?;
+        assert_eq!(1, tx.input.len());
+
+        for outpoint in [OutPoint::new(taproot_txid, 0), OutPoint::new(txid, 0)] {
+            let (tx, _) = wallet
+                .transfer(
+                    Address::from_str(TEST_ADDRESS)?,
+                    10000,
+                    None,
+                    None,
+                    vec![outpoint],
+                    vec![],
+                )
+                .await?;
+            let inputs: Vec<_> = tx.input.iter().map(|input| input.previous_output).collect();
+            assert_eq!(vec![outpoint], inputs);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transfer_does_not_spend_unspendable_utxos() -> Result<()> {
+        let (bdk_wallet, _, txid) = get_funded_wallet(TEST_WPKH);
+        let (taproot_wallet, _, taproot_txid) = get_funded_wallet(TEST_TR);
+        let wallet = Wallet {
+            bitcoind_client: Arc::new(MockBitcoindClient::default()),
+            wallet: Arc::new(Mutex::new(bdk_wallet)),
+            taproot_wallet: Arc::new(Mutex::new(taproot_wallet)),
+            settings: Arc::new(Settings::default()),
+            blockchain: Arc::new(OnceLock::new()),
+            taproot_blockchain: Arc::new(OnceLock::new()),
+            network: bitcoin::network::constants::Network::Testnet,
+            frozen_utxos: Arc::new(TestFrozenUtxos::default()),
+        };
+        let reserved = OutPoint::new(txid, 0);
+
+        let (tx, _) = wallet
+            .transfer(
+                Address::from_str(TEST_ADDRESS)?,
+                10000,
+                None,
+                None,
+                vec![],
+                vec![reserved],
+            )
+            .await?;
+        let inputs: Vec<_> = tx.input.iter().map(|input| input.previous_output).collect();
+        assert_eq!(vec![OutPoint::new(taproot_txid, 0)], inputs);
+
+        assert!(wallet
+            .transfer(
+                Address::from_str(TEST_ADDRESS)?,
+                10000,
+                None,
+                None,
+                vec![reserved],
+                vec![reserved],
+            )
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fund_tx_does_not_spend_unspendable_utxos() -> Result<()> {
+        let (bdk_wallet, _, txid) = get_funded_wallet(TEST_WPKH);
+        let (taproot_wallet, _, taproot_txid) = get_funded_wallet(TEST_TR);
+        let wallet = Wallet {
+            bitcoind_client: Arc::new(MockBitcoindClient::default()),
+            wallet: Arc::new(Mutex::new(bdk_wallet)),
+            taproot_wallet: Arc::new(Mutex::new(taproot_wallet)),
+            settings: Arc::new(Settings::default()),
+            blockchain: Arc::new(OnceLock::new()),
+            taproot_blockchain: Arc::new(OnceLock::new()),
+            network: bitcoin::network::constants::Network::Testnet,
+            frozen_utxos: Arc::new(TestFrozenUtxos::default()),
+        };
+        let script_pubkey = Address::from_str(TEST_ADDRESS)?
+            .assume_checked()
+            .script_pubkey();
+        let reserved = OutPoint::new(txid, 0);
+
+        let tx = wallet.fund_tx(
+            &script_pubkey,
+            &10000,
+            crate::api::payloads::FeeRate::default(),
+            vec![reserved],
+        )?;
+        let inputs: Vec<_> = tx.input.iter().map(|input| input.previous_output).collect();
+        assert_eq!(vec![OutPoint::new(taproot_txid, 0)], inputs);
+
+        assert!(wallet
+            .fund_tx(
+                &script_pubkey,
+                &10000,
+                crate::api::payloads::FeeRate::default(),
+                vec![reserved, OutPoint::new(taproot_txid, 0)],
+            )
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_taproot_utxos() -> Result<()> {
+        let (taproot_wallet, _, _) = get_funded_wallet(TEST_TR);
+        let utxo = |vout, value| LocalUtxo {
+            outpoint: OutPoint::new(Txid::all_zeros(), vout),
+            txout: TxOut {
+                value,
+                script_pubkey: ScriptBuf::new(),
+            },
+            keychain: KeychainKind::External,
+            is_spent: false,
+        };
+        let candidates = vec![utxo(0, 30000), utxo(1, 60000), utxo(2, 20000)];
+
+        // Only as many taproot outputs as the amount needs.
+        let selected = select_taproot_utxos(
+            &taproot_wallet,
+            CoinSelection::LargestFirst,
+            &candidates,
+            25000,
+        )?;
+        assert_eq!(vec![utxo(1, 60000)], selected);
+
+        // Spending at most one deposit with the privacy coin selection.
+        let selected =
+            select_taproot_utxos(&taproot_wallet, CoinSelection::Privacy, &candidates, 25000)?;
+        assert_eq!(1, selected.len());
+
+        let selected = select_taproot_utxos(
+            &taproot_wallet,
+            CoinSelection::LargestFirst,
+            &candidates,
+            200000,
+        )?;
+        assert!(selected.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_psbt_drains_only_selected_utxos() -> Result<()> {
+        let (bdk_wallet, _, txid) = get_funded_wallet(TEST_WPKH);
+        let (taproot_wallet, _, taproot_txid) = get_funded_wallet(TEST_TR);
+        let wallet = Wallet {
+            bitcoind_client: Arc::new(MockBitcoindClient::default()),
+            wallet: Arc::new(Mutex::new(bdk_wallet)),
+            taproot_wallet: Arc::new(Mutex::new(taproot_wallet)),
+            settings: Arc::new(Settings::default()),
+            blockchain: Arc::new(OnceLock::new()),
+            taproot_blockchain: Arc::new(OnceLock::new()),
+            network: bitcoin::network::constants::Network::Testnet,
+            frozen_utxos: Arc::new(TestFrozenUtxos::default()),
+        };
+        let script_pubkey = Address::from_str(TEST_ADDRESS)?
+            .assume_checked()
+            .script_pubkey();
+
+        for outpoint in [OutPoint::new(taproot_txid, 0), OutPoint::new(txid, 0)] {
+            let psbt = wallet.create_psbt(
+                script_pubkey.clone(),
+                u64::MAX,
+                crate::api::payloads::FeeRate::default(),
+                &[outpoint],
+                vec![],
+                false,
+            )?;
+            let inputs: Vec<_> = psbt
+                .unsigned_tx
+                .input
+                .iter()
+                .map(|input| input.previous_output)
+                .collect();
+            assert_eq!(vec![outpoint], inputs);
+            assert_eq!(1, psbt.unsigned_tx.output.len());
+            assert_eq!(script_pubkey, psbt.unsigned_tx.output[0].script_pubkey);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_consolidate_utxos() -> Result<()> {
+        let (bdk_wallet, _, txid) = get_funded_wallet(TEST_WPKH);
+        let (taproot_wallet, _, taproot_txid) = get_funded_wallet(TEST_TR);
+        let mut wallet = Wallet {
+            bitcoind_client: Arc::new(MockBitcoindClient::default()),
+            wallet: Arc::new(Mutex::new(bdk_wallet)),
+            taproot_wallet: Arc::new(Mutex::new(taproot_wallet)),
+            settings: Arc::new(Settings::default()),
+            blockchain: Arc::new(OnceLock::new()),
+            taproot_blockchain: Arc::new(OnceLock::new()),
+            network: bitcoin::network::constants::Network::Testnet,
+            frozen_utxos: Arc::new(TestFrozenUtxos::default()),
+        };
+
+        let report = wallet.consolidate_utxos(vec![], true)?;
+        assert_eq!(500, report.fee_rate);
+        assert_eq!(None, report.skip_reason);
+        assert_eq!(100000, report.amount);
+        assert!(report.fee > 0);
+        assert!(report.tx.is_none());
+        let mut utxos = report.utxos.clone();
+        utxos.sort();
+        let mut expected = vec![OutPoint::new(txid, 0), OutPoint::new(taproot_txid, 0)];
+        expected.sort();
+        assert_eq!(expected, utxos);
+
+        let consolidation = wallet.consolidate_utxos(vec![], false)?;
+        let tx = consolidation.tx.expect("consolidation transaction");
+        assert_eq!(2, tx.input.len());
+        assert_eq!(1, tx.output.len());
+        assert_eq!(100000 - consolidation.fee, tx.output[0].value);
+        for input in &tx.input {
+            assert!(!input.witness.is_empty());
+        }
+
+        let report = wallet.consolidate_utxos(vec![OutPoint::new(txid, 0)], false)?;
+        assert!(report.skip_reason.is_some());
+        assert!(report.tx.is_none());
+
+        wallet.settings = Arc::new(Settings {
+            consolidation_max_fee_rate: 400,
+            ..Settings::default()
+        });
+        let report = wallet.consolidate_utxos(vec![], false)?;
+        assert_eq!(2, report.utxos.len());
+        assert!(report.skip_reason.is_some());
+        assert!(report.tx.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sweep_excess() -> Result<()> {
+        let (bdk_wallet, _, txid) = get_funded_wallet(TEST_WPKH);
+        let (taproot_wallet, _, _) = get_funded_wallet(TEST_TR);
+        let sweep_settings = Settings {
+            sweep_target_balance: 20000,
+            sweep_threshold: 10000,
+            ..Settings::default()
+        };
+        let mut wallet = Wallet {
+            bitcoind_client: Arc::new(MockBitcoindClient::default()),
+            wallet: Arc::new(Mutex::new(bdk_wallet)),
+            taproot_wallet: Arc::new(Mutex::new(taproot_wallet)),
+            settings: Arc::new(sweep_settings.clone()),
+            blockchain: Arc::new(OnceLock::new()),
+            taproot_blockchain: Arc::new(OnceLock::new()),
+            network: bitcoin::network::constants::Network::Testnet,
+            frozen_utxos: Arc::new(TestFrozenUtxos::default()),
+        };
+        let script_pubkey = Address::from_str(TEST_ADDRESS)?
+            .assume_checked()
+            .script_pubkey();
+
+        // The confirmed balance of 100000 is 80000 above the target.
+        let (tx, amount, fee_rate) = wallet
+            .sweep_excess(script_pubkey.clone(), vec![])?
+            .expect("sweep");
+        assert_eq!(80000, amount);
+        assert_eq!(500, fee_rate);
+        assert!(tx
+            .output
+            .iter()
+            .any(|output| output.script_pubkey == script_pubkey && output.value == 80000));
+
+        // Frozen UTXOs are neither counted nor spent.
+        let frozen = OutPoint::new(txid, 0);
+        wallet.freeze_utxos(&[frozen], true)?;
+        let (tx, amount, _) = wallet
+            .sweep_excess(script_pubkey.clone(), vec![])?
+            .expect("sweep");
+        assert_eq!(30000, amount);
+        assert!(tx.input.iter().all(|input| input.previous_output != frozen));
+        wallet.freeze_utxos(&[frozen], false)?;
+
+        // The excess is below the threshold.
+        wallet.settings = Arc::new(Settings {
+            sweep_threshold: 90000,
+            ..sweep_settings.clone()
+        });
+        assert!(wallet
+            .sweep_excess(script_pubkey.clone(), vec![])?
+            .is_none());
+
+        // The fee rate is above the maximum.
+        wallet.settings = Arc::new(Settings {
+            sweep_max_fee_rate: 400,
+            ..sweep_settings
+        });
+        assert!(wallet.sweep_excess(script_pubkey, vec![])?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_consolidate_utxos_at_fee_floor() -> Result<()> {
+        let (bdk_wallet, _, _) = get_funded_wallet(TEST_WPKH);
+        let (taproot_wallet, _, _) = get_funded_wallet(TEST_TR);
+        let settings = Arc::new(Settings::default());
+        let mut bitcoind_client = MockBitcoindClient::default();
+        bitcoind_client.set_fee_policies(settings.clone());
+        let wallet = Wallet {
+            bitcoind_client: Arc::new(bitcoind_client),
+            wallet: Arc::new(Mutex::new(bdk_wallet)),
+            taproot_wallet: Arc::new(Mutex::new(taproot_wallet)),
+            settings,
+            blockchain: Arc::new(OnceLock::new()),
+            taproot_blockchain: Arc::new(OnceLock::new()),
+            network: bitcoin::network::constants::Network::Testnet,
+            frozen_utxos: Arc::new(TestFrozenUtxos::default()),
+        };
+
+        // The economy estimate is below the floor of the default fee policy.
+        let report = wallet.consolidate_utxos(vec![], false)?;
+        assert_eq!(MIN_FEERATE, report.fee_rate);
+        assert_eq!(None, report.skip_reason);
+        assert!(report.tx.is_some());
+        Ok(())
+    }
 }
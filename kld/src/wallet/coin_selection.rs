@@ -0,0 +1,212 @@
+use std::{fmt::Display, str::FromStr};
+
+use bdk::{
+    database::Database,
+    wallet::coin_selection::{
+        BranchAndBoundCoinSelection, CoinSelectionAlgorithm, CoinSelectionResult,
+        LargestFirstCoinSelection, OldestFirstCoinSelection,
+    },
+    FeeRate, KeychainKind, Utxo, WeightedUtxo,
+};
+use bitcoin::Script;
+
+/// How the wallet picks the UTXOs to fund a transaction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CoinSelection {
+    /// Minimise the change, BDK's default.
+    #[default]
+    BranchAndBound,
+    LargestFirst,
+    OldestFirst,
+    /// Avoid linking deposits by spending at most one deposit UTXO together with change UTXOs.
+    /// Deposits are only merged when no single one is enough.
+    Privacy,
+}
+
+impl FromStr for CoinSelection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "branch-and-bound" => Ok(CoinSelection::BranchAndBound),
+            "largest-first" => Ok(CoinSelection::LargestFirst),
+            "oldest-first" => Ok(CoinSelection::OldestFirst),
+            "privacy" => Ok(CoinSelection::Privacy),
+            _ => anyhow::bail!(
+                "{s} is not a coin selection (branch-and-bound, largest-first, oldest-first, privacy)"
+            ),
+        }
+    }
+}
+
+impl Display for CoinSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoinSelection::BranchAndBound => write!(f, "branch-and-bound"),
+            CoinSelection::LargestFirst => write!(f, "largest-first"),
+            CoinSelection::OldestFirst => write!(f, "oldest-first"),
+            CoinSelection::Privacy => write!(f, "privacy"),
+        }
+    }
+}
+
+impl<D: Database> CoinSelectionAlgorithm<D> for CoinSelection {
+    fn coin_select(
+        &self,
+        database: &D,
+        required_utxos: Vec<WeightedUtxo>,
+        optional_utxos: Vec<WeightedUtxo>,
+        fee_rate: FeeRate,
+        target_amount: u64,
+        drain_script: &Script,
+    ) -> Result<CoinSelectionResult, bdk::Error> {
+        match self {
+            CoinSelection::BranchAndBound => BranchAndBoundCoinSelection::default().coin_select(
+                database,
+                required_utxos,
+                optional_utxos,
+                fee_rate,
+                target_amount,
+                drain_script,
+            ),
+            CoinSelection::LargestFirst => LargestFirstCoinSelection.coin_select(
+                database,
+                required_utxos,
+                optional_utxos,
+                fee_rate,
+                target_amount,
+                drain_script,
+            ),
+            CoinSelection::OldestFirst => OldestFirstCoinSelection.coin_select(
+                database,
+                required_utxos,
+                optional_utxos,
+                fee_rate,
+                target_amount,
+                drain_script,
+            ),
+            CoinSelection::Privacy => {
+                let (deposits, mut change): (Vec<_>, Vec<_>) =
+                    optional_utxos.into_iter().partition(|weighted| {
+                        matches!(&weighted.utxo, Utxo::Local(utxo) if utxo.keychain == KeychainKind::External)
+                    });
+                let select = |optional_utxos: Vec<WeightedUtxo>| {
+                    LargestFirstCoinSelection.coin_select(
+                        database,
+                        required_utxos.clone(),
+                        optional_utxos,
+                        fee_rate,
+                        target_amount,
+                        drain_script,
+                    )
+                };
+                // Change is already linked to us, so try without deposits first and then with the smallest deposit that is enough.
+                let mut result = select(change.clone());
+                let mut deposits_by_value = deposits.clone();
+                deposits_by_value.sort_by_key(|weighted| weighted.utxo.txout().value);
+                for deposit in deposits_by_value {
+                    if result.is_ok() {
+                        break;
+                    }
+                    result = select([change.as_slice(), &[deposit]].concat());
+                }
+                if result.is_err() {
+                    change.extend(deposits);
+                    result = select(change);
+                }
+                result
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bdk::{
+        database::MemoryDatabase, wallet::coin_selection::CoinSelectionAlgorithm, FeeRate,
+        KeychainKind, LocalUtxo, Utxo, WeightedUtxo,
+    };
+    use bitcoin::{hashes::Hash, OutPoint, ScriptBuf, TxOut, Txid};
+
+    use super::CoinSelection;
+
+    fn utxo(vout: u32, value: u64, keychain: KeychainKind) -> WeightedUtxo {
+        WeightedUtxo {
+            satisfaction_weight: 107,
+            utxo: Utxo::Local(LocalUtxo {
+                outpoint: OutPoint::new(Txid::all_zeros(), vout),
+                txout: TxOut {
+                    value,
+                    script_pubkey: ScriptBuf::new(),
+                },
+                keychain,
+                is_spent: false,
+            }),
+        }
+    }
+
+    fn deposits(selected: &[Utxo]) -> usize {
+        selected
+            .iter()
+            .filter(
+                |utxo| matches!(utxo, Utxo::Local(utxo) if utxo.keychain == KeychainKind::External),
+            )
+            .count()
+    }
+
+    #[test]
+    fn test_privacy_coin_selection() {
+        let database = MemoryDatabase::new();
+        let utxos = vec![
+            utxo(0, 30000, KeychainKind::External),
+            utxo(1, 60000, KeychainKind::External),
+            utxo(2, 20000, KeychainKind::Internal),
+        ];
+        let select = |target_amount| {
+            CoinSelection::Privacy.coin_select(
+                &database,
+                vec![],
+                utxos.clone(),
+                FeeRate::from_sat_per_vb(1.0),
+                target_amount,
+                &ScriptBuf::new(),
+            )
+        };
+
+        let result = select(10000).unwrap();
+        assert_eq!(0, deposits(&result.selected));
+
+        let result = select(45000).unwrap();
+        assert_eq!(1, deposits(&result.selected));
+
+        // The smaller deposit with the change is not enough.
+        let result = select(55000).unwrap();
+        assert_eq!(1, deposits(&result.selected));
+        assert!(result
+            .selected
+            .iter()
+            .any(|utxo| utxo.txout().value == 60000));
+
+        // Deposits are merged when no single one is enough.
+        let result = select(100000).unwrap();
+        assert_eq!(2, deposits(&result.selected));
+
+        assert!(select(200000).is_err());
+    }
+
+    #[test]
+    fn test_parse_coin_selection() {
+        for coin_selection in [
+            CoinSelection::BranchAndBound,
+            CoinSelection::LargestFirst,
+            CoinSelection::OldestFirst,
+            CoinSelection::Privacy,
+        ] {
+            assert_eq!(
+                coin_selection,
+                coin_selection.to_string().parse::<CoinSelection>().unwrap()
+            );
+        }
+        assert!("random".parse::<CoinSelection>().is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use bdk::miniscript::{Descriptor, DescriptorPublicKey};
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, Network};
+
+/// Cold storage that the excess hot wallet balance is swept to.
+#[derive(Clone, Debug)]
+pub enum ColdDestination {
+    /// Public descriptor to derive a new address from for every sweep.
+    Descriptor(Descriptor<DescriptorPublicKey>),
+    /// Fixed addresses used in turn, starting over after the last one.
+    Addresses(Vec<Address<NetworkUnchecked>>),
+}
+
+impl ColdDestination {
+    pub fn address(&self, index: u32, network: Network) -> Result<Address> {
+        match self {
+            ColdDestination::Descriptor(descriptor) => {
+                Ok(descriptor.at_derivation_index(index)?.address(network)?)
+            }
+            ColdDestination::Addresses(addresses) => Ok(addresses
+                [index as usize % addresses.len()]
+            .clone()
+            .require_network(network)?),
+        }
+    }
+}
+
+impl FromStr for ColdDestination {
+    type Err = anyhow::Error;
+
+    /// A descriptor, an xpub for native segwit addresses on its external chain or a comma separated list of addresses.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let descriptor = if s.starts_with("xpub") || s.starts_with("tpub") {
+            Descriptor::from_str(&format!("wpkh({s}/0/*)"))?
+        } else if s.contains('(') {
+            Descriptor::from_str(s)?
+        } else {
+            let addresses = s
+                .split(',')
+                .map(|address| Address::<NetworkUnchecked>::from_str(address.trim()))
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(ColdDestination::Addresses(addresses));
+        };
+        if descriptor.is_multipath() {
+            bail!("Multipath descriptors are not supported, use the descriptor of the receive addresses");
+        }
+        Ok(ColdDestination::Descriptor(descriptor))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use anyhow::Result;
+    use bitcoin::Network;
+
+    use super::ColdDestination;
+
+    const TPUB: &str = "tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp";
+
+    #[test]
+    fn test_cold_destination() -> Result<()> {
+        let destination = ColdDestination::from_str(TPUB)?;
+        let first = destination.address(0, Network::Regtest)?;
+        let second = destination.address(1, Network::Regtest)?;
+        assert_ne!(first, second);
+        assert_eq!(Some(bitcoin::AddressType::P2wpkh), first.address_type());
+
+        let destination = ColdDestination::from_str(&format!("tr({TPUB}/0/*)"))?;
+        assert_eq!(
+            Some(bitcoin::AddressType::P2tr),
+            destination.address(0, Network::Regtest)?.address_type()
+        );
+
+        let destination = ColdDestination::from_str(
+            "bcrt1qqyqszqgpqyqszqgpqyqszqgpqyqszqgpvxat9t, bcrt1qqgpqyqszqgpqyqszqgpqyqszqgpqyqszazmwwa",
+        )?;
+        let first = destination.address(0, Network::Regtest)?;
+        assert_ne!(first, destination.address(1, Network::Regtest)?);
+        assert_eq!(first, destination.address(2, Network::Regtest)?);
+        assert!(destination.address(0, Network::Bitcoin).is_err());
+
+        assert!(ColdDestination::from_str(&format!("wpkh({TPUB}/<0;1>/*)")).is_err());
+        assert!(ColdDestination::from_str("not an address").is_err());
+        Ok(())
+    }
+}
@@ -1,5 +1,68 @@
 mod bdk_wallet;
+mod coin_selection;
+mod cold_destination;
 mod wallet_interface;
 
-pub use bdk_wallet::Wallet;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use bitcoin::{OutPoint, Transaction};
+
+pub use bdk_wallet::{rpc_config, Wallet};
+pub use coin_selection::CoinSelection;
+pub use cold_destination::ColdDestination;
 pub use wallet_interface::WalletInterface;
+
+/// Keeps track of the UTXOs that the wallet must not spend.
+pub trait FrozenUtxos: Send + Sync {
+    fn frozen_utxos(&self) -> Result<Vec<OutPoint>>;
+
+    /// Returns the outpoints that were updated, UTXOs not in the wallet are ignored.
+    fn set_frozen(&self, outpoints: &[OutPoint], frozen: bool) -> Result<Vec<OutPoint>>;
+}
+
+/// The kind of address to receive deposits on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressType {
+    /// Native segwit P2WPKH (BIP84).
+    Bech32,
+    /// Taproot P2TR (BIP86).
+    P2tr,
+}
+
+impl FromStr for AddressType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bech32" => Ok(AddressType::Bech32),
+            "p2tr" => Ok(AddressType::P2tr),
+            _ => Err(anyhow!("Unsupported address type {s}")),
+        }
+    }
+}
+
+impl ToString for AddressType {
+    fn to_string(&self) -> String {
+        match self {
+            AddressType::Bech32 => "bech32",
+            AddressType::P2tr => "p2tr",
+        }
+        .to_string()
+    }
+}
+
+/// Merging the small UTXOs of the wallet into a single output to one of our internal addresses.
+#[derive(Clone, Debug, Default)]
+pub struct Consolidation {
+    /// Economy fee rate estimate in sats per kw.
+    pub fee_rate: u32,
+    pub utxos: Vec<OutPoint>,
+    /// Total value of the consolidated UTXOs.
+    pub amount: u64,
+    pub fee: u64,
+    /// Why the UTXOs are not consolidated now, None when they can be.
+    pub skip_reason: Option<String>,
+    /// The signed transaction, unless it was a dry run or the consolidation was skipped.
+    pub tx: Option<Transaction>,
+}
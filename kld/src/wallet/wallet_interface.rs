@@ -1,15 +1,17 @@
 use crate::api::payloads::FeeRate;
 use anyhow::Result;
 use async_trait::async_trait;
-use bdk::{wallet::AddressInfo, Balance, LocalUtxo, TransactionDetails};
+use bdk::{wallet::AddressInfo, Balance, KeychainKind, LocalUtxo, TransactionDetails};
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::{Address, OutPoint, Transaction};
 
+use super::AddressType;
+
 #[async_trait]
 pub trait WalletInterface {
     fn balance(&self) -> Result<Balance>;
 
-    /// Set amount to u64::MAX to drain the wallet.
+    /// Set amount to u64::MAX to drain the wallet. The unspendable outputs, such as the inputs of pending PSBTs, are never spent.
     async fn transfer(
         &self,
         address: Address<NetworkUnchecked>,
@@ -17,11 +19,27 @@ pub trait WalletInterface {
         fee_rate: Option<FeeRate>,
         min_conf: Option<u8>,
         utxos: Vec<OutPoint>,
+        unspendable: Vec<OutPoint>,
     ) -> Result<(Transaction, TransactionDetails)>;
 
-    fn new_external_address(&self) -> Result<AddressInfo>;
+    fn new_external_address(&self, address_type: AddressType) -> Result<AddressInfo>;
 
     fn new_internal_address(&self) -> Result<AddressInfo>;
 
     fn list_utxos(&self) -> Result<Vec<(LocalUtxo, TransactionDetails)>>;
+
+    /// UTXOs that are never spent by withdrawals or channel funding.
+    fn frozen_utxos(&self) -> Result<Vec<OutPoint>>;
+
+    /// Freeze or unfreeze the UTXOs, returns the ones found in the wallet.
+    fn freeze_utxos(&self, outpoints: &[OutPoint], frozen: bool) -> Result<Vec<OutPoint>>;
+
+    /// Public descriptors (with checksums) of the wallet keychains, for watch-only wallets.
+    fn public_descriptors(&self) -> Result<Vec<(AddressType, KeychainKind, String)>>;
+
+    /// Block height from which the wallet has transactions.
+    fn birthday_height(&self) -> Option<u32>;
+
+    /// All the transactions of the wallet with the raw transaction, in no particular order.
+    fn list_transactions(&self) -> Result<Vec<TransactionDetails>>;
 }
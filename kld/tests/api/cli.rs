@@ -16,15 +16,17 @@ use kld::api::codegen::{
     post_v1_peer_connect_response::PostV1PeerConnectResponse,
 };
 use kld::api::payloads::{
-    FeeRatesResponse, FundChannelResponse, GenerateInvoiceResponse, GetInfo, Invoice, ListFunds,
-    NetworkChannel, NetworkNode, PaymentResponse, Peer, SetChannelFeeResponse, SignResponse,
-    WalletBalance, WalletTransferResponse,
+    Bip329Label, BumpFeeResponse, ConsolidationReport, FeeRatesResponse, FreezeUtxos,
+    FundChannelResponse, GenerateInvoiceResponse, GetInfo, Invoice, LedgerRecord, ListFunds,
+    NetworkChannel, NetworkNode, PaymentResponse, Peer, PeerHistory, Psbt, RoutingReport,
+    ScorerLiquidity, ScorerParameters, SetChannelFeeResponse, SignResponse, WalletBalance,
+    WalletDescriptors, WalletSweep, WalletTransaction, WalletTransferResponse,
 };
 
 use super::rest::create_api_server;
 use crate::api::rest::mock_lightning;
 use serde::de;
-use test_utils::{TEST_ADDRESS, TEST_PUBLIC_KEY, TEST_SHORT_CHANNEL_ID};
+use test_utils::{TEST_ADDRESS, TEST_PUBLIC_KEY, TEST_SHORT_CHANNEL_ID, TEST_TX_ID};
 
 #[tokio::test]
 async fn test_cli_get_info() -> Result<()> {
@@ -51,6 +53,8 @@ async fn test_cli_get_balance() -> Result<()> {
 async fn test_cli_new_address() -> Result<()> {
     let output = run_cli("new-address", &[]).await?;
     let _: GetV1NewaddrResponse = deserialize(&output.stdout)?;
+    let output = run_cli("new-address", &["--address-type", "p2tr"]).await?;
+    let _: GetV1NewaddrResponse = deserialize(&output.stdout)?;
     Ok(())
 }
 
@@ -65,6 +69,99 @@ async fn test_cli_withdraw() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_cli_withdraw_many() -> Result<()> {
+    let first = format!("{TEST_ADDRESS}:1000");
+    let second = format!("{TEST_ADDRESS}:2000");
+    let output = run_cli(
+        "withdraw-many",
+        &[
+            first.as_str(),
+            second.as_str(),
+            "--idempotency-key",
+            "refund-1",
+        ],
+    )
+    .await?;
+    let _: WalletTransferResponse = deserialize(&output.stdout)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_bump_fee() -> Result<()> {
+    let output = run_cli("bump-fee", &[TEST_TX_ID, "5000perkw"]).await?;
+    let _: BumpFeeResponse = deserialize(&output.stdout)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_freeze_utxos() -> Result<()> {
+    let utxo = format!("{TEST_TX_ID}:0");
+    let output = run_cli("freeze-utxos", &[utxo.as_str()]).await?;
+    let frozen: FreezeUtxos = deserialize(&output.stdout)?;
+    assert_eq!(vec![utxo.clone()], frozen.utxos);
+    let output = run_cli("unfreeze-utxos", &[utxo.as_str()]).await?;
+    let _: FreezeUtxos = deserialize(&output.stdout)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_export_descriptors() -> Result<()> {
+    let output = run_cli("export-descriptors", &[]).await?;
+    let descriptors: WalletDescriptors = deserialize(&output.stdout)?;
+    assert!(!descriptors.descriptors.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_list_sweeps() -> Result<()> {
+    let output = run_cli("list-sweeps", &[]).await?;
+    let sweeps: Vec<WalletSweep> = deserialize(&output.stdout)?;
+    assert_eq!(1, sweeps.len());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_ledger() -> Result<()> {
+    let output = run_cli("ledger", &["--to", "1700000300"]).await?;
+    let ledger: Vec<LedgerRecord> = deserialize(&output.stdout)?;
+    assert_eq!(1, ledger.len());
+    assert_eq!("payment_received", ledger[0].entry_type);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_routing_report() -> Result<()> {
+    let output = run_cli("routing-report", &["--top", "1"]).await?;
+    let report: RoutingReport = deserialize(&output.stdout)?;
+    assert_eq!(1, report.channels.len());
+    assert_eq!(3000, report.channels[0].stats.fees_msat);
+    assert_eq!(1, report.peers.len());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_consolidation_report() -> Result<()> {
+    let output = run_cli("consolidation-report", &[]).await?;
+    let report: ConsolidationReport = deserialize(&output.stdout)?;
+    assert!(report.ready);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_list_transactions() -> Result<()> {
+    let output = run_cli("list-transactions", &["--limit", "10"]).await?;
+    let _: Vec<WalletTransaction> = deserialize(&output.stdout)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_export_labels() -> Result<()> {
+    let output = run_cli("export-labels", &[]).await?;
+    let _: Bip329Label = deserialize(&output.stdout)?;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_cli_list_funds() -> Result<()> {
     let output = run_cli("list-funds", &[]).await?;
@@ -72,6 +169,24 @@ async fn test_cli_list_funds() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_cli_create_withdraw_psbt() -> Result<()> {
+    let output = run_cli(
+        "create-withdraw-psbt",
+        &[TEST_ADDRESS, "1000", "--fee-rate", "3000perkw", "--sign"],
+    )
+    .await?;
+    let _: Psbt = deserialize(&output.stdout)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_list_psbts() -> Result<()> {
+    let output = run_cli("list-psbts", &[]).await?;
+    let _: Vec<Psbt> = deserialize(&output.stdout)?;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_cli_list_peer_channels() -> Result<()> {
     let output = run_cli("list-peer-channels", &[]).await?;
@@ -86,6 +201,29 @@ async fn test_cli_list_peers() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_cli_peer_history() -> Result<()> {
+    let output = run_cli("peer-history", &[TEST_PUBLIC_KEY]).await?;
+    let _: PeerHistory = deserialize(&output.stdout)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_scorer_liquidity() -> Result<()> {
+    let output = run_cli("scorer-liquidity", &["--node-id", TEST_PUBLIC_KEY]).await?;
+    let liquidity: ScorerLiquidity = deserialize(&output.stdout)?;
+    assert_eq!(1, liquidity.channels.len());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_update_scorer_parameters() -> Result<()> {
+    let output = run_cli("update-scorer-parameters", &["--base-penalty-msat", "1000"]).await?;
+    let parameters: ScorerParameters = deserialize(&output.stdout)?;
+    assert_eq!(1000, parameters.base_penalty_msat);
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_cli_connect_peer() -> Result<()> {
     let output = run_cli("connect-peer", &[TEST_PUBLIC_KEY]).await?;
@@ -222,6 +360,9 @@ async fn test_cli_generate_invoice() -> Result<()> {
 async fn test_cli_list_invoices() -> Result<()> {
     let output = run_cli("list-invoices", &["--label", "a label"]).await?;
     let _: Vec<Invoice> = deserialize(&output.stdout)?;
+    let output = run_cli("list-invoices", &["--status", "Unpaid", "--limit", "0"]).await?;
+    let invoices: Vec<Invoice> = deserialize(&output.stdout)?;
+    assert!(invoices.is_empty());
     Ok(())
 }
 
@@ -235,12 +376,22 @@ async fn test_cli_pay_invoice() -> Result<()> {
 
 #[tokio::test]
 async fn test_cli_list_payments() -> Result<()> {
+    let bolt11 = mock_lightning().invoice.bolt11.to_string();
     let output = run_cli(
         "list-payments",
-        &["--bolt11", "bolt11", "--direction", "inbound"],
+        &["--bolt11", &bolt11, "--direction", "inbound"],
     )
     .await?;
     let _: GetV1PayListPaymentsResponse = deserialize(&output.stdout)?;
+    let output = run_cli(
+        "list-payments",
+        &[
+            "--status", "pending", "--from", "0", "--offset", "0", "--limit", "10",
+        ],
+    )
+    .await?;
+    let response: GetV1PayListPaymentsResponse = deserialize(&output.stdout)?;
+    assert_eq!(1, response.payments.len());
     Ok(())
 }
 
@@ -273,6 +424,10 @@ async fn test_cli_get_fees() -> Result<()> {
 async fn test_cli_list_forwards() -> Result<()> {
     let output = run_cli("list-forwards", &["--status", "settled"]).await?;
     let _: Vec<GetV1ChannelListForwardsResponseItem> = deserialize(&output.stdout)?;
+    let channel = hex::encode([4u8; 32]);
+    let output = run_cli("list-forwards", &["--channel", &channel, "--limit", "5"]).await?;
+    let forwards: Vec<GetV1ChannelListForwardsResponseItem> = deserialize(&output.stdout)?;
+    assert_eq!(1, forwards.len());
     Ok(())
 }
 
@@ -280,6 +435,12 @@ async fn test_cli_list_forwards() -> Result<()> {
 async fn test_cli_channel_history() -> Result<()> {
     let output = run_cli("list-channel-history", &[]).await?;
     let _: Vec<GetV1ChannelHistoryResponseItem> = deserialize(&output.stdout)?;
+    let output = run_cli(
+        "list-channel-history",
+        &["--from", "0", "--to", "2000000000"],
+    )
+    .await?;
+    let _: Vec<GetV1ChannelHistoryResponseItem> = deserialize(&output.stdout)?;
     Ok(())
 }
 
@@ -43,11 +43,15 @@ use test_utils::{
 };
 
 use kld::api::payloads::{
-    ChannelFee, ChannelState, FeeRate, FeeRatesResponse, FundChannel, FundChannelResponse,
-    GenerateInvoice, GenerateInvoiceResponse, GetInfo, Invoice, InvoiceStatus, KeysendRequest,
-    ListFunds, NetworkChannel, NetworkNode, OutputStatus, PayInvoice, PaymentResponse, Peer,
-    SetChannelFeeResponse, SignRequest, SignResponse, WalletBalance, WalletTransfer,
-    WalletTransferResponse,
+    Bip329Label, BumpFee, BumpFeeResponse, ChannelFee, ChannelState, ConsolidationReport,
+    CreatePsbt, FeeRate, FeeRatesResponse, FreezeUtxos, FundChannel, FundChannelResponse,
+    GenerateInvoice, GenerateInvoiceResponse, GetInfo, ImportLabelsResponse, Invoice,
+    InvoiceStatus, KeysendRequest, LedgerRecord, ListFunds, NetworkChannel, NetworkNode,
+    NodeAnnouncement, OutputStatus, PayInvoice, PaymentResponse, Peer, PeerHistory, Psbt,
+    RoutingReport, ScorerLiquidity, ScorerParameters, SetChannelFeeResponse, SignRequest,
+    SignResponse, SubmitPsbt, UpdateNodeAnnouncement, UpdateScorerParameters, WalletBalance,
+    WalletDescriptors, WalletSweep, WalletTransaction, WalletTransfer, WalletTransferResponse,
+    WithdrawMany, WithdrawOutput,
 };
 use kld::api::routes;
 use tokio::runtime::Runtime;
@@ -72,12 +76,24 @@ pub async fn test_unauthorized() -> Result<()> {
             routes::FORCE_CLOSE_CHANNEL_WITHOUT_BROADCAST,
         ),
         (Method::POST, routes::WITHDRAW),
+        (Method::POST, routes::WITHDRAW_MANY),
         (Method::GET, routes::NEW_ADDR),
         (Method::POST, routes::CONNECT_PEER),
         (Method::DELETE, routes::DISCONNECT_PEER),
         (Method::POST, routes::KEYSEND),
         (Method::POST, routes::GENERATE_INVOICE),
         (Method::POST, routes::PAY_INVOICE),
+        (Method::GET, routes::BACKUP),
+        (Method::POST, routes::NODE_ANNOUNCEMENT),
+        (Method::POST, routes::SCORER),
+        (Method::POST, routes::SCORER_PARAMETERS),
+        (Method::POST, routes::PSBT),
+        (Method::POST, routes::SUBMIT_PSBT),
+        (Method::POST, routes::BUMP_FEE),
+        (Method::POST, routes::LABELS),
+        (Method::POST, routes::FREEZE_UTXOS),
+        (Method::POST, routes::UNFREEZE_UTXOS),
+        (Method::GET, routes::DESCRIPTORS),
     ];
     for (method, route) in &admin_functions {
         assert_eq!(
@@ -108,6 +124,16 @@ pub async fn test_unauthorized() -> Result<()> {
         (Method::GET, routes::LIST_CHANNEL_HISTORY),
         (Method::GET, routes::LIST_PEER_CHANNELS),
         (Method::GET, routes::DECODE_INVOICE),
+        (Method::GET, routes::PEER_HISTORY),
+        (Method::GET, routes::SCORER_PARAMETERS),
+        (Method::GET, routes::SCORER_LIQUIDITY),
+        (Method::GET, routes::PSBT),
+        (Method::GET, routes::LIST_TRANSACTIONS),
+        (Method::GET, routes::LABELS),
+        (Method::GET, routes::CONSOLIDATION),
+        (Method::GET, routes::LIST_SWEEPS),
+        (Method::GET, routes::LEDGER),
+        (Method::GET, routes::ROUTING_REPORT),
     ];
     readonly_functions.extend(admin_functions.into_iter());
     for (method, route) in readonly_functions {
@@ -220,6 +246,7 @@ async fn test_list_funds_readonly() -> Result<()> {
     assert_eq!(93, output.scriptpubkey.len());
     assert_eq!(OutputStatus::Confirmed, output.status);
     assert_eq!(Some(600000), output.block_height);
+    assert!(output.frozen);
 
     let channel = funds.channels.first().context("Missing channel")?;
     assert_eq!(TEST_PUBLIC_KEY, channel.peer_id);
@@ -426,6 +453,435 @@ async fn test_withdraw_admin() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_withdraw_many_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: WalletTransferResponse =
+        admin_request_with_body(&context, Method::POST, routes::WITHDRAW_MANY, || {
+            WithdrawMany {
+                outputs: vec![
+                    WithdrawOutput {
+                        address: TEST_ADDRESS.to_string(),
+                        satoshis: 1000,
+                    },
+                    WithdrawOutput {
+                        address: TEST_ADDRESS.to_string(),
+                        satoshis: 2000,
+                    },
+                ],
+                fee_rate: Some(FeeRate::PerKw(3000)),
+                idempotency_key: Some("refund-1".to_string()),
+            }
+        })?
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(TEST_TX, response.tx);
+    assert_eq!(TEST_TX_ID, response.txid);
+
+    let response = admin_request_with_body(&context, Method::POST, routes::WITHDRAW_MANY, || {
+        WithdrawMany {
+            outputs: vec![],
+            fee_rate: None,
+            idempotency_key: None,
+        }
+    })?
+    .send()
+    .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_create_withdraw_psbt_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: Psbt =
+        admin_request_with_body(&context, Method::POST, routes::PSBT, || CreatePsbt {
+            withdraw: Some(withdraw_request()),
+            ..Default::default()
+        })?
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!("withdraw", response.purpose);
+    assert_eq!(TEST_TX_ID, response.txid);
+    assert!(response.channel_id.is_none());
+    assert!(response.expires_at > response.created_at);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_create_funding_psbt_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: Psbt =
+        admin_request_with_body(&context, Method::POST, routes::PSBT, || CreatePsbt {
+            fund_channel: Some(fund_channel_request()),
+            ..Default::default()
+        })?
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!("fund_channel", response.purpose);
+    assert_eq!(TEST_TX_ID, response.txid);
+    assert!(response.channel_id.is_some());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_create_psbt_without_purpose_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let response =
+        admin_request_with_body(&context, Method::POST, routes::PSBT, CreatePsbt::default)?
+            .send()
+            .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_list_psbts_readonly() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: Vec<Psbt> = readonly_request(&context, Method::GET, routes::PSBT)?
+        .send()
+        .await?
+        .json()
+        .await?;
+    let psbt = response.first().context("expected psbt")?;
+    assert_eq!("withdraw", psbt.purpose);
+    assert_eq!(TEST_TX_ID, psbt.txid);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_submit_psbt_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let psbts: Vec<Psbt> = readonly_request(&context, Method::GET, routes::PSBT)?
+        .send()
+        .await?
+        .json()
+        .await?;
+    let psbt = psbts.first().context("expected psbt")?;
+    let response: WalletTransferResponse =
+        admin_request_with_body(&context, Method::POST, routes::SUBMIT_PSBT, || SubmitPsbt {
+            id: psbt.id.clone(),
+            psbt: psbt.psbt.clone(),
+        })?
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(TEST_TX_ID, response.txid);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_bump_fee_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: BumpFeeResponse =
+        admin_request_with_body(&context, Method::POST, routes::BUMP_FEE, || BumpFee {
+            txid: TEST_TX_ID.to_string(),
+            fee_rate: FeeRate::PerKw(5000),
+        })?
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(TEST_TX, response.tx);
+    assert_eq!(TEST_TX_ID, response.original_txid);
+    assert_eq!("cpfp", response.method);
+    assert_eq!(Some(hex::encode([1u8; 32])), response.channel_id);
+    assert!(response.psbt_id.is_none());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_export_descriptors_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: WalletDescriptors = admin_request(&context, Method::GET, routes::DESCRIPTORS)?
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(2, response.descriptors.len());
+    let external = response.descriptors.first().context("Missing descriptor")?;
+    assert!(!external.internal);
+    assert_eq!("bech32", external.address_type);
+    assert!(response.descriptors[1].internal);
+    assert_eq!(Some(600000), response.birthday_height);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_list_sweeps_readonly() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: Vec<WalletSweep> = readonly_request(&context, Method::GET, routes::LIST_SWEEPS)?
+        .send()
+        .await?
+        .json()
+        .await?;
+    let sweep = response.first().context("Missing sweep")?;
+    assert_eq!(TEST_TX_ID, sweep.txid);
+    assert_eq!(2000000, sweep.amount);
+    assert_eq!(1000, sweep.fee_rate);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_ledger_readonly() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: Vec<LedgerRecord> = readonly_request(&context, Method::GET, routes::LEDGER)?
+        .query(&[("from", "1700000300")])
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(
+        vec![LedgerRecord {
+            entry_type: "withdrawal".to_string(),
+            reference: TEST_TX_ID.to_string(),
+            amount_msat: -100000000,
+            fee_msat: 153000,
+            channel_id: None,
+            timestamp: 1700000600,
+        }],
+        response
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_ledger_csv_readonly() -> Result<()> {
+    let context = create_api_server().await?;
+    let response = readonly_request(&context, Method::GET, routes::LEDGER)?
+        .query(&[("format", "csv")])
+        .send()
+        .await?
+        .text()
+        .await?;
+    let lines: Vec<&str> = response.lines().collect();
+    assert_eq!(3, lines.len());
+    assert!(lines[0].starts_with("Date,Sent Amount,Sent Currency,Received Amount"));
+    assert_eq!(
+        format!(
+            "2023-11-14 22:13:20 UTC,,,0.0025,BTC,,,,,,payment_received,{}",
+            hex::encode([1u8; 32])
+        ),
+        lines[1]
+    );
+    assert_eq!(
+        format!("2023-11-14 22:23:20 UTC,0.001,BTC,,,0.00000153,BTC,,,,withdrawal,{TEST_TX_ID}"),
+        lines[2]
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_ledger_unknown_format() -> Result<()> {
+    let context = create_api_server().await?;
+    let response = readonly_request(&context, Method::GET, routes::LEDGER)?
+        .query(&[("format", "xml")])
+        .send()
+        .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_routing_report_readonly() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: RoutingReport = readonly_request(&context, Method::GET, routes::ROUTING_REPORT)?
+        .query(&[("from", "1700000000")])
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(Some(1700000000), response.from);
+    assert_eq!(2, response.channels.len());
+    let channel = &response.channels[0];
+    assert_eq!(hex::encode([1u8; 32]), channel.channel_id);
+    assert_eq!(Some(TEST_PUBLIC_KEY.to_string()), channel.counterparty);
+    assert_eq!(1, channel.stats.out_count);
+    assert_eq!(5000000, channel.stats.out_msat);
+    assert_eq!(3000, channel.stats.fees_msat);
+    assert_eq!(Some(1000000), channel.stats.capacity_sat);
+    assert_eq!(Some(0.003), channel.stats.fees_msat_per_capacity_sat);
+    let channel = &response.channels[1];
+    assert_eq!(hex::encode([3u8; 32]), channel.channel_id);
+    assert_eq!(None, channel.counterparty);
+    assert_eq!(5003000, channel.stats.in_msat);
+    assert_eq!(Some(&2), channel.stats.failures.get("UnknownNextHop"));
+    assert_eq!(None, channel.stats.fees_msat_per_capacity_sat);
+
+    assert_eq!(1, response.peers.len());
+    let peer = &response.peers[0];
+    assert_eq!(TEST_PUBLIC_KEY, peer.counterparty);
+    assert_eq!(1, peer.channels);
+    assert_eq!(3000, peer.stats.fees_msat);
+    assert_eq!(Some(1000000), peer.stats.capacity_sat);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_routing_report_top_by_volume() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: RoutingReport = readonly_request(&context, Method::GET, routes::ROUTING_REPORT)?
+        .query(&[("top", "1"), ("rank", "volume")])
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(1, response.channels.len());
+    assert_eq!(hex::encode([3u8; 32]), response.channels[0].channel_id);
+    assert_eq!(1, response.peers.len());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_routing_report_unknown_rank() -> Result<()> {
+    let context = create_api_server().await?;
+    let response = readonly_request(&context, Method::GET, routes::ROUTING_REPORT)?
+        .query(&[("rank", "age")])
+        .send()
+        .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_consolidation_report_readonly() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: ConsolidationReport =
+        readonly_request(&context, Method::GET, routes::CONSOLIDATION)?
+            .send()
+            .await?
+            .json()
+            .await?;
+    assert_eq!(500, response.fee_rate);
+    assert_eq!(2, response.utxos.len());
+    assert_eq!(60000, response.amount);
+    assert_eq!(400, response.fee);
+    assert!(response.ready);
+    assert!(response.skip_reason.is_none());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_freeze_utxos_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let utxo = format!("{TEST_TX_ID}:0");
+    for route in [routes::FREEZE_UTXOS, routes::UNFREEZE_UTXOS] {
+        let response: FreezeUtxos =
+            admin_request_with_body(&context, Method::POST, route, || FreezeUtxos {
+                utxos: vec![utxo.clone()],
+            })?
+            .send()
+            .await?
+            .json()
+            .await?;
+        assert_eq!(vec![utxo.clone()], response.utxos);
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_freeze_utxos_malformed() -> Result<()> {
+    let context = create_api_server().await?;
+    let response = admin_request_with_body(&context, Method::POST, routes::FREEZE_UTXOS, || {
+        FreezeUtxos {
+            utxos: vec![TEST_TX_ID.to_string()],
+        }
+    })?
+    .send()
+    .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_list_transactions_readonly() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: Vec<WalletTransaction> =
+        readonly_request(&context, Method::GET, routes::LIST_TRANSACTIONS)?
+            .query(&[("offset", "0"), ("limit", "10")])
+            .send()
+            .await?
+            .json()
+            .await?;
+    let transaction = response.first().context("expected transaction")?;
+    assert_eq!(TEST_TX_ID, transaction.txid);
+    assert!(transaction.confirmed);
+    assert_eq!(Some(600000), transaction.block_height);
+    assert_eq!(8800, transaction.amount);
+    assert_eq!(Some(20), transaction.fee);
+    assert_eq!("channel_funding", transaction.purpose);
+    assert_eq!(Some(hex::encode([1u8; 32])), transaction.channel_id);
+    assert_eq!(
+        Some("channel with the test node".to_string()),
+        transaction.label
+    );
+
+    let response: Vec<WalletTransaction> =
+        readonly_request(&context, Method::GET, routes::LIST_TRANSACTIONS)?
+            .query(&[("offset", "1")])
+            .send()
+            .await?
+            .json()
+            .await?;
+    assert!(response.is_empty());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_export_labels_readonly() -> Result<()> {
+    let context = create_api_server().await?;
+    let response = readonly_request(&context, Method::GET, routes::LABELS)?
+        .send()
+        .await?
+        .text()
+        .await?;
+    let labels = response
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<Bip329Label>, _>>()?;
+    assert_eq!(
+        vec![Bip329Label {
+            label_type: "tx".to_string(),
+            reference: TEST_TX_ID.to_string(),
+            label: Some("channel with the test node".to_string()),
+            origin: None,
+            spendable: None,
+        }],
+        labels
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_import_labels_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let labels = format!(
+        "{{\"type\":\"tx\",\"ref\":\"{TEST_TX_ID}\",\"label\":\"Withdrawal\"}}\n\
+        {{\"type\":\"output\",\"ref\":\"{TEST_TX_ID}:0\",\"label\":\"Deposit\",\"spendable\":false}}\n"
+    );
+    let response: ImportLabelsResponse = admin_request(&context, Method::POST, routes::LABELS)?
+        .body(labels)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(2, response.imported);
+
+    let response = admin_request(&context, Method::POST, routes::LABELS)?
+        .body("{\"type\":\"unknown\",\"ref\":\"x\"}")
+        .send()
+        .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_new_address_admin() -> Result<()> {
     let context = create_api_server().await?;
@@ -436,6 +892,18 @@ async fn test_new_address_admin() -> Result<()> {
         .json()
         .await?;
     assert_eq!(TEST_ADDRESS.to_string(), response.address);
+    let response: GetV1NewaddrResponse = admin_request(&context, Method::GET, routes::NEW_ADDR)?
+        .query(&[("addressType", "p2tr")])
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(TEST_ADDRESS.to_string(), response.address);
+    let response = admin_request(&context, Method::GET, routes::NEW_ADDR)?
+        .query(&[("addressType", "p2sh")])
+        .send()
+        .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
     Ok(())
 }
 
@@ -449,12 +917,40 @@ async fn test_list_peers_readonly() -> Result<()> {
         .await?;
     let socket_addr: SocketAddr = "127.0.0.1:5555".parse().unwrap();
     let netaddr = Some(socket_addr.to_string());
-    assert!(response.contains(&Peer {
-        id: TEST_PUBLIC_KEY.to_string(),
-        connected: true,
-        netaddr,
-        alias: TEST_ALIAS.to_string()
-    }));
+    let peer = response
+        .iter()
+        .find(|p| p.id == TEST_PUBLIC_KEY)
+        .context("expected peer")?;
+    assert!(peer.connected);
+    assert_eq!(netaddr, peer.netaddr);
+    assert_eq!(TEST_ALIAS, peer.alias);
+    assert_eq!(Some(100.0), peer.uptime);
+    assert_eq!(socket_addr.to_string(), peer.addresses[0].address);
+    assert_eq!(0, peer.addresses[0].failures);
+    assert_eq!(None, peer.addresses[0].retry_after);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_peer_history_readonly() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: PeerHistory = readonly_request(
+        &context,
+        Method::GET,
+        &routes::PEER_HISTORY.replace(":id", TEST_PUBLIC_KEY),
+    )?
+    .send()
+    .await?
+    .json()
+    .await?;
+    assert_eq!(TEST_PUBLIC_KEY, response.id);
+    assert_eq!(Some(100.0), response.uptime);
+    assert_eq!(1, response.addresses.len());
+    assert_eq!("connected", response.events[0].event);
+    assert_eq!(
+        Some("127.0.0.1:5555".to_string()),
+        response.events[0].address
+    );
     Ok(())
 }
 
@@ -539,6 +1035,15 @@ async fn test_list_network_nodes_readonly() -> Result<()> {
         TEST_PUBLIC_KEY,
         nodes.first().context("bad result")?.node_id
     );
+
+    let nodes: Vec<NetworkNode> =
+        readonly_request(&context, Method::GET, routes::LIST_NETWORK_NODES)?
+            .query(&[("offset", "1"), ("limit", "10")])
+            .send()
+            .await?
+            .json()
+            .await?;
+    assert!(nodes.is_empty());
     Ok(())
 }
 
@@ -582,6 +1087,17 @@ async fn test_fee_rates() -> Result<()> {
     .await?;
     let perkb = fee_rates.perkb.context("expected perkb fee rate")?;
     assert_eq!(1600000, perkb.urgent);
+    let target = fee_rates
+        .targets
+        .first()
+        .context("expected target fee rate")?;
+    assert_eq!("on_chain_sweep", target.target);
+    assert_eq!(
+        "estimatesmartfee:6,floor=2000,ceiling=500000",
+        target.policy
+    );
+    assert_eq!(Some(1560000), target.estimate);
+    assert_eq!(1600000, target.fee_rate);
     assert_eq!(800000, perkb.normal);
     assert_eq!(400000, perkb.slow);
     assert_eq!(3101, perkb.min_acceptable);
@@ -615,6 +1131,7 @@ async fn test_fee_rates() -> Result<()> {
     assert_eq!(100000, perkw.slow);
     assert_eq!(775, perkw.min_acceptable);
     assert_eq!(400000, perkw.max_acceptable);
+    assert_eq!(400000, fee_rates.targets[0].fee_rate);
     assert_eq!(
         121600,
         fee_rates.onchain_fee_estimates.opening_channel_satoshis
@@ -718,6 +1235,33 @@ async fn test_list_payments() -> Result<()> {
     assert_eq!(payment.amount, payment_response.amount_sent_msat);
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_list_payments_filters() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: GetV1PayListPaymentsResponse =
+        readonly_request(&context, Method::GET, routes::LIST_PAYMENTS)?
+            .query(&[("status", "pending"), ("limit", "1"), ("from", "0")])
+            .send()
+            .await?
+            .json()
+            .await?;
+    assert_eq!(1, response.payments.len());
+    let response: GetV1PayListPaymentsResponse =
+        readonly_request(&context, Method::GET, routes::LIST_PAYMENTS)?
+            .query(&[("status", "complete")])
+            .send()
+            .await?
+            .json()
+            .await?;
+    assert!(response.payments.is_empty());
+    let response = readonly_request(&context, Method::GET, routes::LIST_PAYMENTS)?
+        .query(&[("status", "unknown")])
+        .send()
+        .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    Ok(())
+}
 #[tokio::test(flavor = "multi_thread")]
 async fn test_pay_invoice() -> Result<()> {
     let context = create_api_server().await?;
@@ -827,7 +1371,7 @@ async fn test_fetch_forwards() -> Result<()> {
     assert_eq!(1, response.len());
     let forward: &GetV1ChannelListForwardsResponseItem =
         response.first().context("expected forward")?;
-    assert_eq!(Some(5000000), forward.in_msat);
+    assert_eq!(Some(5003000), forward.in_msat);
     assert_eq!(Some(3000), forward.fee_msat);
     assert_eq!(
         hex::encode(mock_lightning().forward.inbound_channel_id.0),
@@ -840,7 +1384,7 @@ async fn test_fetch_forwards() -> Result<()> {
             .map(|x| hex::encode(x.0)),
         forward.out_channel
     );
-    assert_eq!(Some(4997000), forward.out_msat);
+    assert_eq!(Some(5000000), forward.out_msat);
     assert_eq!(None, forward.payment_hash);
     assert!(forward.received_timestamp > 0);
     assert!(forward.resolved_timestamp.is_some());
@@ -849,6 +1393,41 @@ async fn test_fetch_forwards() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fetch_forwards_of_channel() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: Vec<GetV1ChannelListForwardsResponseItem> =
+        readonly_request(&context, Method::GET, routes::LIST_FORWARDS)?
+            .query(&[("channel", hex::encode([4u8; 32]))])
+            .send()
+            .await?
+            .json()
+            .await?;
+    assert_eq!(1, response.len());
+    let response: Vec<GetV1ChannelListForwardsResponseItem> =
+        readonly_request(&context, Method::GET, routes::LIST_FORWARDS)?
+            .query(&[("channel", hex::encode([9u8; 32]))])
+            .send()
+            .await?
+            .json()
+            .await?;
+    assert!(response.is_empty());
+    let response: Vec<GetV1ChannelListForwardsResponseItem> =
+        readonly_request(&context, Method::GET, routes::LIST_FORWARDS)?
+            .query(&[("limit", "0")])
+            .send()
+            .await?
+            .json()
+            .await?;
+    assert!(response.is_empty());
+    let response = readonly_request(&context, Method::GET, routes::LIST_FORWARDS)?
+        .query(&[("channel", "abcd")])
+        .send()
+        .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_channel_history() -> Result<()> {
     let context = create_api_server().await?;
@@ -915,6 +1494,168 @@ async fn test_decode_invoice() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_backup_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let response = admin_request(&context, Method::GET, routes::BACKUP)?
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    assert_eq!(vec![1u8; 64], response.bytes().await?.to_vec());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rgs_snapshot_unauthenticated() -> Result<()> {
+    let context = create_api_server().await?;
+    let response = unauthorized_request(
+        &context,
+        Method::GET,
+        &routes::RGS_SNAPSHOT.replace(":timestamp", "1700000000"),
+    )?
+    .send()
+    .await?;
+    assert!(response.status().is_success());
+    assert_eq!(vec![76, 68, 75, 1], response.bytes().await?.to_vec());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_upload_scorer_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let response = admin_request(&context, Method::POST, routes::SCORER)?
+        .body(vec![1u8; 32])
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    let response = admin_request(&context, Method::POST, routes::SCORER)?
+        .send()
+        .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_scorer_parameters_readonly() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: ScorerParameters =
+        readonly_request(&context, Method::GET, routes::SCORER_PARAMETERS)?
+            .send()
+            .await?
+            .json()
+            .await?;
+    assert!(response.liquidity_offset_half_life > 0);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_scorer_liquidity_readonly() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: ScorerLiquidity =
+        readonly_request(&context, Method::GET, routes::SCORER_LIQUIDITY)?
+            .send()
+            .await?
+            .json()
+            .await?;
+    assert!(response.last_updated.is_some());
+    let channel = response.channels.first().context("expected channel")?;
+    assert_eq!(TEST_SHORT_CHANNEL_ID, channel.short_channel_id);
+    assert_eq!(TEST_PUBLIC_KEY, channel.source);
+    assert_eq!(100000, channel.min_liquidity_msat);
+    assert_eq!(
+        Some(32),
+        channel
+            .historical_min_liquidity_buckets
+            .as_ref()
+            .map(|b| b.len())
+    );
+
+    let response: ScorerLiquidity = readonly_request(
+        &context,
+        Method::GET,
+        &format!("{}?scid=1", routes::SCORER_LIQUIDITY),
+    )?
+    .send()
+    .await?
+    .json()
+    .await?;
+    assert!(response.channels.is_empty());
+
+    let response = readonly_request(
+        &context,
+        Method::GET,
+        &format!("{}?node_id=abc", routes::SCORER_LIQUIDITY),
+    )?
+    .send()
+    .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_scorer_parameters_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: ScorerParameters =
+        admin_request_with_body(&context, Method::POST, routes::SCORER_PARAMETERS, || {
+            UpdateScorerParameters {
+                base_penalty_msat: Some(1000),
+                liquidity_offset_half_life: Some(3600),
+                ..Default::default()
+            }
+        })?
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(1000, response.base_penalty_msat);
+    assert_eq!(3600, response.liquidity_offset_half_life);
+
+    let response =
+        admin_request_with_body(&context, Method::POST, routes::SCORER_PARAMETERS, || {
+            UpdateScorerParameters {
+                liquidity_offset_half_life: Some(0),
+                ..Default::default()
+            }
+        })?
+        .send()
+        .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_node_announcement_admin() -> Result<()> {
+    let context = create_api_server().await?;
+    let response: NodeAnnouncement =
+        admin_request_with_body(&context, Method::POST, routes::NODE_ANNOUNCEMENT, || {
+            UpdateNodeAnnouncement {
+                alias: Some("new alias".to_string()),
+                color: None,
+                addresses: Some(vec!["127.0.0.1:9234".to_string()]),
+            }
+        })?
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!("new alias", response.alias);
+    assert_eq!("6e2cf7", response.color);
+    assert_eq!(vec!["127.0.0.1:9234".to_string()], response.addresses);
+
+    let response =
+        admin_request_with_body(&context, Method::POST, routes::NODE_ANNOUNCEMENT, || {
+            UpdateNodeAnnouncement {
+                addresses: Some(vec!["not an address".to_string()]),
+                ..Default::default()
+            }
+        })?
+        .send()
+        .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    Ok(())
+}
+
 fn withdraw_request() -> WalletTransfer {
     WalletTransfer {
         address: TEST_ADDRESS.to_string(),
@@ -3,6 +3,7 @@ use std::str::FromStr;
 use anyhow::{anyhow, bail, Result};
 use bitcoin::Address;
 use kld::bitcoind::bitcoind_interface::BitcoindInterface;
+use kld::wallet::rpc_config;
 use lightning::chain::chaininterface::{ConfirmationTarget, FeeEstimator};
 use lightning_block_sync::{BlockData, BlockSource};
 use test_utils::{test_settings, BitcoinManager, TempDir, TEST_ADDRESS};
@@ -73,3 +74,37 @@ pub async fn test_bitcoind_client() -> Result<()> {
     };
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_wallet_birthday_rpc_config() -> Result<()> {
+    let tmp_dir = TempDir::new()?;
+
+    let mut settings = test_settings(&tmp_dir, "birthday");
+    let bitcoind = BitcoinManager::new(&tmp_dir, &mut settings).await?;
+    let address = Address::from_str(TEST_ADDRESS)?;
+    bitcoind.generate_blocks(2, &address, false).await?;
+    let (birthday_hash, birthday_height) = bitcoind
+        .client
+        .get_best_block()
+        .await
+        .map_err(|e| anyhow!(e.into_inner()))?;
+    let birthday = bitcoind
+        .client
+        .get_header(&birthday_hash, None)
+        .await
+        .map_err(|e| anyhow!(e.into_inner()))?;
+    // The delay gives the later blocks a later time than the birthday.
+    bitcoind.generate_blocks(2, &address, true).await?;
+
+    settings.wallet_birthday_height = birthday_height;
+    let config = rpc_config(&settings, "birthday")?;
+    assert_eq!(
+        Some(birthday.header.time as u64),
+        config.sync_params.map(|params| params.start_time)
+    );
+
+    settings.wallet_birthday_height = None;
+    let config = rpc_config(&settings, "birthday")?;
+    assert_eq!(Some(0), config.sync_params.map(|params| params.start_time));
+    Ok(())
+}
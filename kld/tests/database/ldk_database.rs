@@ -6,12 +6,22 @@ use anyhow::{anyhow, Context, Result};
 use bitcoin::hashes::hex::FromHex;
 use bitcoin::hashes::{sha256, Hash};
 use bitcoin::secp256k1::{Secp256k1, SecretKey};
-use bitcoin::{Network, TxOut, Txid};
+use bitcoin::consensus::deserialize;
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::{Network, ScriptBuf, Transaction, TxOut, Txid, Witness};
+use kld::database::fee_bump::{FeeBump, FeeBumpMethod};
 use kld::database::forward::{Forward, ForwardStatus};
-use kld::database::invoice::Invoice;
-use kld::database::payment::{Payment, PaymentDirection};
-use kld::database::peer::Peer;
-use kld::database::ChannelRecord;
+use kld::database::gossip::{GossipTimestamp, GossipType};
+use kld::database::invoice::{Invoice, InvoiceStatusFilter};
+use kld::database::label::{Label, LabelType};
+use kld::database::ledger::{LedgerEntry, LedgerEntryType};
+use kld::database::node_announcement::NodeAnnouncementConfig;
+use kld::database::payment::{Payment, PaymentDirection, PaymentStatus};
+use kld::database::psbt::{PendingPsbt, PsbtPurpose};
+use kld::database::peer::{uptime, Peer, PeerEvent, PeerEventType};
+use kld::database::scorer_parameters::ScorerParameters;
+use kld::database::sweep::Sweep;
+use kld::database::{microsecond_timestamp, ChannelRecord, Pagination};
 use kld::database::LdkDatabase;
 use kld::ldk::Scorer;
 
@@ -25,22 +35,27 @@ use lightning::events::ClosureReason;
 use lightning::ln::channelmanager::{
     ChannelCounterparty, ChannelDetails, CounterpartyForwardingInfo,
 };
-use lightning::ln::features::{ChannelTypeFeatures, InitFeatures};
+use lightning::ln::features::{ChannelFeatures, ChannelTypeFeatures, InitFeatures};
+use lightning::ln::functional_test_utils::{
+    create_announced_chan_between_nodes, create_chanmon_cfgs, create_network, create_node_cfgs,
+    create_node_chanmgrs, send_payment,
+};
 use lightning::ln::msgs::SocketAddress;
 use lightning::ln::ChannelId;
 use lightning::ln::{PaymentHash, PaymentPreimage, PaymentSecret};
-use lightning::routing::gossip::NetworkGraph;
+use lightning::routing::gossip::{NetworkGraph, NodeId};
 use lightning::routing::router::DefaultRouter;
 use lightning::routing::scoring::{
     ProbabilisticScorer, ProbabilisticScoringDecayParameters, ProbabilisticScoringFeeParameters,
 };
 use lightning::sign::{InMemorySigner, KeysManager, SpendableOutputDescriptor};
 use lightning::util::persist::Persister;
+use lightning::util::ser::Writeable;
 use lightning_invoice::{Currency, InvoiceBuilder};
 use rand::random;
 use test_utils::{
     init_db_test_context, poll, random_public_key, TempDir, TEST_PRIVATE_KEY, TEST_PUBLIC_KEY,
-    TEST_TX_ID,
+    TEST_TX, TEST_TX_ID,
 };
 
 #[tokio::test(flavor = "multi_thread")]
@@ -57,16 +72,38 @@ pub async fn test_peers() -> Result<()> {
             port: 1000,
         },
     };
-    let saved_peer = database.fetch_peer(&peer.public_key).await?;
-    assert_eq!(None, saved_peer);
+    let addresses = database.fetch_peer_addresses(&peer.public_key).await?;
+    assert!(addresses.is_empty());
 
     database.persist_peer(&peer).await?;
 
-    let saved_peer = database.fetch_peer(&peer.public_key).await?;
-    assert_eq!(peer, saved_peer.unwrap());
+    let addresses = database.fetch_peer_addresses(&peer.public_key).await?;
+    assert_eq!(1, addresses.len());
+    assert_eq!(peer.address, addresses[0].address);
+    assert!(addresses[0].last_success.is_some());
+    assert_eq!(0, addresses[0].failures);
+
+    let other_address = Peer {
+        public_key: peer.public_key,
+        address: SocketAddress::TcpIpV4 {
+            addr: [128, 23, 34, 3],
+            port: 1000,
+        },
+    };
+    database.persist_peer(&other_address).await?;
+    database.persist_peer_failure(&other_address).await?;
+    database.persist_peer_failure(&other_address).await?;
+    let addresses = database.fetch_peer_addresses(&peer.public_key).await?;
+    assert_eq!(2, addresses.len());
+    let failing = addresses
+        .iter()
+        .find(|a| a.address == other_address.address)
+        .context("expected address")?;
+    assert_eq!(2, failing.failures);
+    assert!(failing.is_backing_off(microsecond_timestamp()));
 
     let peers = database.fetch_peers().await?;
-    assert!(peers.contains_key(&peer.public_key));
+    assert_eq!(2, peers.get(&peer.public_key).map(|a| a.len()).unwrap_or(0));
 
     database.delete_peer(&peer.public_key).await?;
     let peers = database.fetch_peers().await?;
@@ -75,6 +112,188 @@ pub async fn test_peers() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_peer_events() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+
+    let public_key = random_public_key();
+    let address = SocketAddress::TcpIpV4 {
+        addr: [128, 23, 34, 2],
+        port: 1000,
+    };
+    let now = microsecond_timestamp();
+    let mut before = PeerEvent::new(public_key, Some(address.clone()), PeerEventType::Connected);
+    before.timestamp = now - time::Duration::hours(2);
+    let mut older = PeerEvent::new(public_key, None, PeerEventType::ConnectionFailed);
+    older.timestamp = now - time::Duration::hours(3);
+    let after = PeerEvent::new(public_key, None, PeerEventType::Disconnected);
+    let other_peer = PeerEvent::new(random_public_key(), None, PeerEventType::Connected);
+    for event in [&before, &older, &after, &other_peer] {
+        database.persist_peer_event(event).await?;
+    }
+
+    // The last event before the period is included to give the state at the start.
+    let events = database
+        .fetch_peer_events(Some(&public_key), now - time::Duration::hours(1))
+        .await?;
+    assert_eq!(vec![before.clone(), after.clone()], events);
+
+    let events = database
+        .fetch_peer_events(None, now - time::Duration::hours(1))
+        .await?;
+    assert!(events.contains(&other_peer));
+    assert!(!events.contains(&older));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_close_peer_connections() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+
+    let now = microsecond_timestamp();
+    let event = |public_key, event, hours: i64| {
+        let mut event = PeerEvent::new(public_key, None, event);
+        event.timestamp = now - time::Duration::hours(hours);
+        event
+    };
+    // The node crashed an hour after its last event, while still connected to the peer.
+    let peer = random_public_key();
+    let other_peer = random_public_key();
+    database.persist_peer_event(&event(peer, PeerEventType::Connected, 4)).await?;
+    database.persist_peer_event(&event(other_peer, PeerEventType::Connected, 3)).await?;
+    database.persist_peer_event(&event(other_peer, PeerEventType::Disconnected, 2)).await?;
+
+    // On restart the connection is closed just after the last event, the downtime is not uptime.
+    database.close_peer_connections(None).await?;
+    let events = database
+        .fetch_peer_events(Some(&peer), now - time::Duration::hours(5))
+        .await?;
+    assert_eq!(2, events.len());
+    assert_eq!(PeerEventType::Disconnected, events[1].event);
+    assert_eq!(
+        now - time::Duration::hours(2) + time::Duration::microseconds(1),
+        events[1].timestamp
+    );
+    let peer_uptime = uptime(&events, now - time::Duration::hours(4), now).context("uptime")?;
+    assert!((peer_uptime - 50.0).abs() < 0.001);
+
+    let other_events = database
+        .fetch_peer_events(Some(&other_peer), now - time::Duration::hours(5))
+        .await?;
+    assert_eq!(2, other_events.len());
+
+    // Connections open at shutdown are closed at the time of the shutdown.
+    database.persist_peer_event(&event(peer, PeerEventType::Connected, 1)).await?;
+    let shutdown = microsecond_timestamp();
+    database.close_peer_connections(Some(shutdown)).await?;
+    database.close_peer_connections(None).await?;
+    let events = database
+        .fetch_peer_events(Some(&peer), now - time::Duration::hours(5))
+        .await?;
+    assert_eq!(4, events.len());
+    assert_eq!(PeerEventType::Disconnected, events[3].event);
+    assert_eq!(shutdown, events[3].timestamp);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_gossip_timestamps() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+    let now = microsecond_timestamp();
+    let timestamp = |short_channel_id, gossip_type, update_timestamp| GossipTimestamp {
+        short_channel_id,
+        gossip_type,
+        update_timestamp,
+        seen: now,
+    };
+    let announcement = timestamp(1, GossipType::Announcement, 0);
+    let mut update = timestamp(1, GossipType::OneToTwo, 1000);
+    let pruned = timestamp(2, GossipType::Announcement, 0);
+    database
+        .persist_gossip_timestamps(&[announcement.clone(), update.clone(), pruned.clone()])
+        .await?;
+    let timestamps = database.fetch_gossip_timestamps().await?;
+    assert_eq!(3, timestamps.len());
+    assert!(timestamps.contains(&pruned));
+
+    update.update_timestamp = 2000;
+    update.seen = now + time::Duration::hours(1);
+    database.persist_gossip_timestamps(&[update.clone()]).await?;
+    database.delete_gossip_timestamps(&[2]).await?;
+    let mut timestamps = database.fetch_gossip_timestamps().await?;
+    timestamps.sort_by_key(|t| t.gossip_type == GossipType::OneToTwo);
+    assert_eq!(vec![announcement, update], timestamps);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_scorer_parameters() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+    assert_eq!(None, database.fetch_scorer_parameters().await?);
+
+    let mut parameters = ScorerParameters {
+        base_penalty_msat: 1000,
+        liquidity_offset_half_life: Duration::from_secs(3600),
+        ..Default::default()
+    };
+    database.persist_scorer_parameters(&parameters).await?;
+    assert_eq!(
+        Some(parameters.clone()),
+        database.fetch_scorer_parameters().await?
+    );
+
+    parameters.liquidity_penalty_multiplier_msat = 50_000;
+    database.persist_scorer_parameters(&parameters).await?;
+    assert_eq!(Some(parameters), database.fetch_scorer_parameters().await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_node_announcement() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+    assert_eq!(None, database.fetch_node_announcement().await?);
+
+    let config = NodeAnnouncementConfig {
+        alias: "kld".to_string(),
+        color: "010203".to_string(),
+        addresses: vec![SocketAddress::TcpIpV4 {
+            addr: [128, 23, 34, 2],
+            port: 9234,
+        }
+        .into()],
+    };
+    database.persist_node_announcement(&config).await?;
+    assert_eq!(
+        Some(config.clone()),
+        database.fetch_node_announcement().await?
+    );
+
+    let config = NodeAnnouncementConfig {
+        alias: "new alias".to_string(),
+        addresses: vec![],
+        ..config
+    };
+    database.persist_node_announcement(&config).await?;
+    assert_eq!(Some(config), database.fetch_node_announcement().await?);
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 pub async fn test_forwards() -> Result<()> {
     let temp_dir = TempDir::new()?;
@@ -105,7 +324,9 @@ pub async fn test_forwards() -> Result<()> {
     assert_eq!(amount, total.amount);
     assert_eq!(fee, total.fee);
 
-    let forwards = database.fetch_forwards(None).await?;
+    let forwards = database
+        .fetch_forwards(None, None, Pagination::default())
+        .await?;
     assert_eq!(
         forwards.first().context("expected success forward")?,
         &forward_success
@@ -116,10 +337,100 @@ pub async fn test_forwards() -> Result<()> {
     );
 
     let forwards = database
-        .fetch_forwards(Some(ForwardStatus::Succeeded))
+        .fetch_forwards(Some(ForwardStatus::Succeeded), None, Pagination::default())
         .await?;
     assert_eq!(1, forwards.len());
 
+    let forwards = database
+        .fetch_forwards(
+            None,
+            Some(ChannelId::from_bytes([1u8; 32])),
+            Pagination::default(),
+        )
+        .await?;
+    assert_eq!(vec![forward_success.clone()], forwards);
+
+    let page = Pagination {
+        offset: Some(1),
+        limit: Some(1),
+        ..Default::default()
+    };
+    assert_eq!(
+        vec![forward_fail.clone()],
+        database.fetch_forwards(None, None, page).await?
+    );
+
+    let page = Pagination {
+        from: Some(forward_fail.timestamp),
+        ..Default::default()
+    };
+    assert_eq!(
+        vec![forward_fail],
+        database.fetch_forwards(None, None, page).await?
+    );
+    let page = Pagination {
+        to: Some(forward_success.timestamp),
+        ..Default::default()
+    };
+    assert!(database.fetch_forwards(None, None, page).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_forward_stats() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+
+    let channel = |i| ChannelId::from_bytes([i; 32]);
+    database
+        .persist_forward(Forward::success(channel(0), channel(1), 1000000, 100))
+        .await?;
+    database
+        .persist_forward(Forward::success(channel(1), channel(2), 2000, 2))
+        .await?;
+    let failure = Forward::failure(
+        channel(0),
+        lightning::events::HTLCDestination::UnknownNextHop {
+            requested_forward_scid: 5,
+        },
+    );
+    database.persist_forward(failure.clone()).await?;
+
+    let stats = database.fetch_forward_stats(None, None).await?;
+    assert_eq!(3, stats.len());
+
+    assert_eq!(channel(0), stats[0].channel_id);
+    assert_eq!(1, stats[0].inbound_count);
+    assert_eq!(1000100, stats[0].inbound_amount);
+    assert_eq!(0, stats[0].outbound_count);
+    assert_eq!(Some(&1), stats[0].failures.get("UnknownNextHop"));
+
+    assert_eq!(channel(1), stats[1].channel_id);
+    assert_eq!(1, stats[1].inbound_count);
+    assert_eq!(2002, stats[1].inbound_amount);
+    assert_eq!(1, stats[1].outbound_count);
+    assert_eq!(1000000, stats[1].outbound_amount);
+    assert_eq!(100, stats[1].fee);
+    assert!(stats[1].failures.is_empty());
+
+    assert_eq!(channel(2), stats[2].channel_id);
+    assert_eq!(2000, stats[2].outbound_amount);
+    assert_eq!(2, stats[2].fee);
+
+    let stats = database
+        .fetch_forward_stats(Some(failure.timestamp), None)
+        .await?;
+    assert_eq!(1, stats.len());
+    assert_eq!(0, stats[0].inbound_count);
+    assert_eq!(Some(&1), stats[0].failures.get("UnknownNextHop"));
+
+    let stats = database
+        .fetch_forward_stats(None, Some(time::OffsetDateTime::UNIX_EPOCH))
+        .await?;
+    assert!(stats.is_empty());
     Ok(())
 }
 
@@ -149,29 +460,51 @@ pub async fn test_invoice_payments() -> Result<()> {
     database.persist_invoice(&invoice).await?;
 
     let result = database
-        .fetch_invoices(Some(label.clone()))
+        .fetch_invoices(Some(label.clone()), None, Pagination::default())
         .await?
         .into_iter()
         .last()
         .context("expected invoice")?;
     assert_eq!(result, invoice);
+    assert_eq!(
+        1,
+        database
+            .fetch_invoices(None, Some(InvoiceStatusFilter::Unpaid), Pagination::default())
+            .await?
+            .len()
+    );
 
     let mut payment = Payment::of_invoice_outbound(&invoice, Some("label".to_string()));
     database.persist_payment(&payment).await?;
 
     let result = database
-        .fetch_invoices(Some(label.clone()))
+        .fetch_invoices(Some(label.clone()), None, Pagination::default())
         .await?
         .into_iter()
         .last()
         .context("expected invoice")?;
     assert_eq!(1, result.payments.len());
 
-    let result = database.fetch_invoices(None).await?;
+    let result = database
+        .fetch_invoices(None, None, Pagination::default())
+        .await?;
     assert_eq!(1, result.len());
+    let result = database
+        .fetch_invoices(None, Some(InvoiceStatusFilter::Paid), Pagination::default())
+        .await?;
+    assert_eq!(1, result.len());
+    let result = database
+        .fetch_invoices(None, Some(InvoiceStatusFilter::Unpaid), Pagination::default())
+        .await?;
+    assert!(result.is_empty());
+    let page = Pagination {
+        offset: Some(1),
+        ..Default::default()
+    };
+    assert!(database.fetch_invoices(None, None, page).await?.is_empty());
 
     let stored_payments = database
-        .fetch_payments(None, None)
+        .fetch_payments(None, None, &[], Pagination::default())
         .await?
         .into_iter()
         .find(|p| p.id == payment.id)
@@ -182,7 +515,12 @@ pub async fn test_invoice_payments() -> Result<()> {
     database.persist_payment(&payment).await?;
 
     let stored_payments = database
-        .fetch_payments(payment.hash, Some(PaymentDirection::Outbound))
+        .fetch_payments(
+            payment.hash,
+            Some(PaymentDirection::Outbound),
+            &[],
+            Pagination::default(),
+        )
         .await?;
     assert_eq!(1, stored_payments.len());
     assert_eq!(
@@ -190,6 +528,33 @@ pub async fn test_invoice_payments() -> Result<()> {
         &payment
     );
 
+    let stored_payments = database
+        .fetch_payments(
+            None,
+            None,
+            &[PaymentStatus::Pending],
+            Pagination::default(),
+        )
+        .await?;
+    assert!(stored_payments.is_empty());
+    let stored_payments = database
+        .fetch_payments(
+            None,
+            None,
+            &[PaymentStatus::Succeeded],
+            Pagination::default(),
+        )
+        .await?;
+    assert_eq!(vec![payment.clone()], stored_payments);
+    let page = Pagination {
+        to: Some(payment.timestamp),
+        ..Default::default()
+    };
+    assert!(database
+        .fetch_payments(None, None, &[], page)
+        .await?
+        .is_empty());
+
     Ok(())
 }
 
@@ -198,8 +563,10 @@ pub async fn test_network_graph() -> Result<()> {
     KldLogger::init("test", log::LevelFilter::Debug);
     let temp_dir = TempDir::new()?;
     let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+    let settings = Arc::new(settings);
+    let durable_connection = Arc::new(durable_connection);
 
-    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+    let database = LdkDatabase::new(settings.clone(), durable_connection.clone());
 
     let network_graph = Arc::new(NetworkGraph::new(Network::Regtest, KldLogger::global()));
     // how to make this less verbose?
@@ -230,8 +597,67 @@ pub async fn test_network_graph() -> Result<()> {
 
     network_graph.set_last_rapid_gossip_sync_timestamp(10);
     persist(&database, &network_graph)?;
-    assert!(database.fetch_graph().await.unwrap().is_some());
+    assert_eq!(
+        Some(10),
+        database
+            .fetch_graph()
+            .await?
+            .and_then(|g| g.get_last_rapid_gossip_sync_timestamp())
+    );
+
+    let (node_a, node_b, node_c) = (
+        random_public_key(),
+        random_public_key(),
+        random_public_key(),
+    );
+    network_graph
+        .add_channel_from_partial_announcement(1, 100, ChannelFeatures::empty(), node_a, node_b)
+        .map_err(|e| anyhow!(e.err))?;
+    network_graph
+        .add_channel_from_partial_announcement(2, 100, ChannelFeatures::empty(), node_b, node_c)
+        .map_err(|e| anyhow!(e.err))?;
+    persist(&database, &network_graph)?;
+    let graph = database.fetch_graph().await?.context("expected graph")?;
+    assert_eq!(2, graph.read_only().channels().len());
+    assert_eq!(3, graph.read_only().nodes().len());
+
+    // Only the remaining channel and nodes are left after the next persist.
+    network_graph.channel_failed_permanent(1);
+    persist(&database, &network_graph)?;
+    let database = LdkDatabase::new(settings, durable_connection);
+    let graph = database.fetch_graph().await?.context("expected graph")?;
+    let graph = graph.read_only();
+    assert_eq!(1, graph.channels().len());
+    assert!(graph.channel(2).is_some());
+    assert_eq!(2, graph.nodes().len());
+    assert!(graph.node(&NodeId::from_pubkey(&node_a)).is_none());
+    Ok(())
+}
 
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_migrate_network_graph_from_disk() -> Result<()> {
+    KldLogger::init("test", log::LevelFilter::Debug);
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+    let path = format!("{}/network_graph", settings.data_dir);
+
+    let network_graph = NetworkGraph::new(Network::Regtest, KldLogger::global());
+    network_graph.set_last_rapid_gossip_sync_timestamp(20);
+    std::fs::write(&path, network_graph.encode())?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+    let graph = database.fetch_graph().await?;
+    assert_eq!(
+        Some(20),
+        graph.and_then(|g| g.get_last_rapid_gossip_sync_timestamp())
+    );
+    assert!(!std::path::Path::new(&path).exists());
+    // Now loaded from the database.
+    let graph = database.fetch_graph().await?;
+    assert_eq!(
+        Some(20),
+        graph.and_then(|g| g.get_last_rapid_gossip_sync_timestamp())
+    );
     Ok(())
 }
 
@@ -340,6 +766,317 @@ pub async fn test_spendable_outputs() -> Result<()> {
 
     let spendable_outputs = database.fetch_spendable_outputs().await?;
     assert_eq!(1, spendable_outputs.len());
+    assert_eq!(Some(channel_id), spendable_outputs[0].channel_id);
+
+    let spendable_outputs = database
+        .fetch_spendable_outputs_by_txids(&[outpoint.txid])
+        .await?;
+    assert_eq!(1, spendable_outputs.len());
+    assert!(database
+        .fetch_spendable_outputs_by_txids(&[Txid::from_byte_array(random())])
+        .await?
+        .is_empty());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_labels() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+
+    let tx_label = Label::tx(TEST_TX_ID, "withdrawal");
+    let output_label = Label {
+        label_type: LabelType::Output,
+        reference: format!("{TEST_TX_ID}:1"),
+        label: None,
+        origin: Some("wpkh([d34db33f/84'/0'/0'])".to_string()),
+        spendable: Some(false),
+    };
+    database
+        .persist_labels(&[tx_label.clone(), output_label.clone()])
+        .await?;
+    assert_eq!(
+        vec![tx_label, output_label.clone()],
+        database.fetch_labels().await?
+    );
+
+    // Importing the same record again replaces its label.
+    let tx_label = Label::tx(TEST_TX_ID, "refund");
+    database.persist_labels(&[tx_label.clone()]).await?;
+    assert_eq!(
+        vec![tx_label, output_label],
+        database.fetch_labels().await?
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_psbts() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+
+    let mut tx = deserialize::<Transaction>(&Vec::<u8>::from_hex(TEST_TX)?)?;
+    for input in &mut tx.input {
+        input.script_sig = ScriptBuf::new();
+        input.witness = Witness::new();
+    }
+    let withdraw = PendingPsbt::withdraw(
+        PartiallySignedTransaction::from_unsigned_tx(tx.clone())?,
+        time::Duration::hours(1),
+    );
+    database.persist_psbt(&withdraw).await?;
+
+    let channel_id = ChannelId::from_bytes(random());
+    let fund_channel = PendingPsbt::fund_channel(
+        PartiallySignedTransaction::from_unsigned_tx(tx.clone())?,
+        channel_id,
+        random_public_key(),
+        time::Duration::hours(1),
+    );
+    database.persist_psbt(&fund_channel).await?;
+
+    let expired = PendingPsbt::withdraw(
+        PartiallySignedTransaction::from_unsigned_tx(tx)?,
+        time::Duration::seconds(-1),
+    );
+    database.persist_psbt(&expired).await?;
+
+    let fetched = database
+        .fetch_psbt(&fund_channel.id)
+        .await?
+        .context("expected psbt")?;
+    assert_eq!(PsbtPurpose::FundChannel, fetched.purpose);
+    assert_eq!(Some(channel_id), fetched.temporary_channel_id);
+    assert_eq!(fund_channel.counterparty, fetched.counterparty);
+    assert_eq!(fund_channel.psbt, fetched.psbt);
+    assert_eq!(TEST_TX_ID, fetched.txid().to_string());
+    assert!(!fetched.completed);
+
+    let pending = database.fetch_pending_psbts().await?;
+    assert_eq!(2, pending.len());
+    assert_eq!(withdraw.id, pending[0].id);
+    assert_eq!(fund_channel.id, pending[1].id);
+
+    let mut completed = fetched;
+    completed.completed = true;
+    database.persist_psbt(&completed).await?;
+    let pending = database.fetch_pending_psbts().await?;
+    assert_eq!(1, pending.len());
+    assert_eq!(withdraw.id, pending[0].id);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_withdrawals() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+
+    let now = microsecond_timestamp();
+    assert!(database.fetch_withdrawal("refund-1").await?.is_none());
+    assert!(database.reserve_withdrawal("refund-1", now).await?);
+    assert!(!database.reserve_withdrawal("refund-1", now).await?);
+    let withdrawal = database
+        .fetch_withdrawal("refund-1")
+        .await?
+        .context("expected withdrawal")?;
+    assert!(withdrawal.tx.is_none());
+
+    // A failed withdrawal releases its key.
+    database.delete_withdrawal("refund-1").await?;
+    assert!(database.reserve_withdrawal("refund-1", now).await?);
+
+    // A reservation left by a crash is taken over once it is stale.
+    let later = now + time::Duration::minutes(5);
+    assert!(!database.reserve_withdrawal("refund-1", later).await?);
+    let later = now + time::Duration::minutes(11);
+    assert!(database.reserve_withdrawal("refund-1", later).await?);
+
+    let tx = deserialize::<Transaction>(&Vec::<u8>::from_hex(TEST_TX)?)?;
+    database.persist_withdrawal_tx("refund-1", &tx).await?;
+    // Only one request gets to make the withdrawal.
+    assert!(database.persist_withdrawal_tx("refund-1", &tx).await.is_err());
+    database.delete_withdrawal("refund-1").await?;
+    let withdrawal = database
+        .fetch_withdrawal("refund-1")
+        .await?
+        .context("expected withdrawal")?;
+    assert_eq!(Some(tx), withdrawal.tx);
+
+    // Once made the withdrawal is never taken over.
+    let later = now + time::Duration::hours(1);
+    assert!(!database.reserve_withdrawal("refund-1", later).await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_sweeps() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+
+    assert_eq!(0, database.next_sweep_address_index().await?);
+    let sweep = Sweep::new(
+        Txid::from_str(TEST_TX_ID)?,
+        "bcrt1qqyqszqgpqyqszqgpqyqszqgpqyqszqgpvxat9t".to_string(),
+        0,
+        2000000,
+        1000,
+    );
+    database.persist_sweep(&sweep).await?;
+    assert_eq!(1, database.next_sweep_address_index().await?);
+
+    let tx = deserialize::<Transaction>(&Vec::<u8>::from_hex(TEST_TX)?)?;
+    let second_sweep = Sweep::new(
+        tx.txid(),
+        "bcrt1qqgpqyqszqgpqyqszqgpqyqszqgpqyqszazmwwa".to_string(),
+        1,
+        1500000,
+        2000,
+    );
+    database.persist_sweep(&second_sweep).await?;
+    assert_eq!(2, database.next_sweep_address_index().await?);
+    assert_eq!(vec![sweep, second_sweep], database.fetch_sweeps().await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_ledger() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+
+    let mut forward = Forward::success(
+        ChannelId::from_bytes([1u8; 32]),
+        ChannelId::from_bytes([2u8; 32]),
+        5000000,
+        1000,
+    );
+    forward.timestamp = time::OffsetDateTime::from_unix_timestamp(1700000000)?;
+    let forward_fee = LedgerEntry::forward(&forward);
+    let withdrawal = LedgerEntry {
+        entry_type: LedgerEntryType::Withdrawal,
+        reference: TEST_TX_ID.to_string(),
+        amount: -100000000,
+        fee: 153000,
+        channel_id: None,
+        timestamp: time::OffsetDateTime::from_unix_timestamp(1700000600)?,
+    };
+    database
+        .persist_ledger_entries(&[forward_fee.clone(), withdrawal.clone()])
+        .await?;
+    assert_eq!(
+        vec![forward_fee.clone(), withdrawal.clone()],
+        database.fetch_ledger(None, None).await?
+    );
+
+    // Recorded entries are never changed.
+    let mut changed = withdrawal.clone();
+    changed.fee = 200000;
+    database.persist_ledger_entries(&[changed]).await?;
+    assert_eq!(
+        vec![forward_fee.clone(), withdrawal.clone()],
+        database.fetch_ledger(None, None).await?
+    );
+
+    assert_eq!(
+        vec![withdrawal.clone()],
+        database
+            .fetch_ledger(Some(withdrawal.timestamp), None)
+            .await?
+    );
+    assert_eq!(
+        vec![forward_fee],
+        database
+            .fetch_ledger(None, Some(withdrawal.timestamp))
+            .await?
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_fee_bumps() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+
+    let funding_txid = Txid::from_str(TEST_TX_ID)?;
+    assert_eq!(None, database.fetch_funding_channel_id(&funding_txid).await?);
+
+    let initializing_channel_id = ChannelId::from_bytes(random());
+    database
+        .persist_initializing_channel(
+            &initializing_channel_id,
+            false,
+            &random_public_key(),
+            &funding_txid,
+        )
+        .await?;
+    assert_eq!(
+        Some(initializing_channel_id),
+        database.fetch_funding_channel_id(&funding_txid).await?
+    );
+    let channel_id = ChannelId::from_bytes(random());
+    database
+        .update_initializing_channel(
+            &initializing_channel_id,
+            Some((&channel_id, 1)),
+            None::<&str>,
+        )
+        .await?;
+    assert_eq!(
+        Some(channel_id),
+        database.fetch_funding_channel_id(&funding_txid).await?
+    );
+
+    let mut fee_bump = FeeBump::new(
+        Txid::from_byte_array(random()),
+        funding_txid,
+        FeeBumpMethod::Cpfp,
+        5000,
+        1000,
+    );
+    fee_bump.channel_id = Some(channel_id);
+    database.persist_fee_bump(&fee_bump).await?;
+
+    let replacement = FeeBump::new(
+        Txid::from_byte_array(random()),
+        funding_txid,
+        FeeBumpMethod::Rbf,
+        7500,
+        1500,
+    );
+    database.persist_fee_bump(&replacement).await?;
+
+    assert_eq!(
+        Some(fee_bump.clone()),
+        database.fetch_fee_bump(&fee_bump.txid).await?
+    );
+    assert_eq!(None, database.fetch_fee_bump(&funding_txid).await?);
+    assert_eq!(
+        vec![fee_bump.clone(), replacement],
+        database.fetch_fee_bumps(&funding_txid).await?
+    );
+    assert_eq!(
+        vec![fee_bump.clone()],
+        database
+            .fetch_fee_bumps_by_txids(&[fee_bump.txid, funding_txid])
+            .await?
+    );
+    assert_eq!(
+        vec![(funding_txid, Some(1), channel_id)],
+        database
+            .fetch_funding_transactions(&[fee_bump.txid, funding_txid])
+            .await?
+    );
     Ok(())
 }
 
@@ -413,7 +1150,9 @@ pub async fn test_channels() -> Result<()> {
         .await?;
     let mut channels = database.fetch_channels().await?;
     assert_eq!(0, channels.len());
-    channels = database.fetch_channel_history().await?;
+    channels = database
+        .fetch_channel_history(None, Pagination::default())
+        .await?;
     assert_eq!(0, channels.len());
 
     database.persist_channel(&channel).await?;
@@ -426,7 +1165,9 @@ pub async fn test_channels() -> Result<()> {
     } = channels.first().context("expected channel")?;
     assert_eq!(*detail, Some(channel.clone()));
     assert!(closure_reason.is_none());
-    channels = database.fetch_channel_history().await?;
+    channels = database
+        .fetch_channel_history(None, Pagination::default())
+        .await?;
     assert_eq!(0, channels.len());
 
     channel.is_usable = false;
@@ -436,7 +1177,9 @@ pub async fn test_channels() -> Result<()> {
         .await?;
     channels = database.fetch_channels().await?;
     assert_eq!(1, channels.len());
-    channels = database.fetch_channel_history().await?;
+    channels = database
+        .fetch_channel_history(None, Pagination::default())
+        .await?;
     assert_eq!(1, channels.len());
     let ChannelRecord {
         open_timestamp,
@@ -448,13 +1191,26 @@ pub async fn test_channels() -> Result<()> {
     assert!(update_timestamp > open_timestamp);
     assert_eq!(*detail, Some(channel));
     assert_eq!(*closure_reason, Some(reason.to_string()));
+    assert_eq!(
+        1,
+        database
+            .fetch_channel_history(Some(channel_id), Pagination::default())
+            .await?
+            .len()
+    );
+    assert!(database
+        .fetch_channel_history(Some(ChannelId::from_bytes([9; 32])), Pagination::default())
+        .await?
+        .is_empty());
 
     //
     // Test create a channel without detail
     //
     initializing_channel_id = ChannelId::from_bytes([2; 32]);
     channel_id = ChannelId::from_bytes([3; 32]);
-    channels = database.fetch_channel_history().await?;
+    channels = database
+        .fetch_channel_history(None, Pagination::default())
+        .await?;
     let previous_channel_num = channels.len();
     database
         .persist_initializing_channel(&initializing_channel_id, true, &counterparty, &txid)
@@ -466,7 +1222,9 @@ pub async fn test_channels() -> Result<()> {
             None::<&str>,
         )
         .await?;
-    channels = database.fetch_channel_history().await?;
+    channels = database
+        .fetch_channel_history(None, Pagination::default())
+        .await?;
     assert_eq!(previous_channel_num, channels.len());
     channels = database.fetch_channels().await?;
     assert_eq!(previous_channel_num, channels.len());
@@ -474,7 +1232,9 @@ pub async fn test_channels() -> Result<()> {
     database
         .create_channel(&channel_id, true, &counterparty)
         .await?;
-    channels = database.fetch_channel_history().await?;
+    channels = database
+        .fetch_channel_history(None, Pagination::default())
+        .await?;
     assert_eq!(previous_channel_num, channels.len());
     channels = database.fetch_channels().await?;
     assert_eq!(previous_channel_num + 1, channels.len());
@@ -491,7 +1251,9 @@ pub async fn test_channels() -> Result<()> {
         .close_channel(&channel_id, format!("{reason}"))
         .await?;
     // NOTE channel_history is not hanndle any channel without details
-    channels = database.fetch_channel_history().await?;
+    channels = database
+        .fetch_channel_history(None, Pagination::default())
+        .await?;
     assert_eq!(previous_channel_num, channels.len());
     channels = database.fetch_channels().await?;
     assert_eq!(previous_channel_num + 1, channels.len());
@@ -520,3 +1282,105 @@ type KldTestChainMonitor = ChainMonitor<
     Arc<KldLogger>,
     Arc<LdkDatabase>,
 >;
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_channel_monitor_updates() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+    let client = test_utils::cockroach_manager::connection(&settings).await?;
+    let count_updates = format!(
+        "SELECT count(*) FROM {}.channel_monitor_updates",
+        settings.database_name
+    );
+    let database = LdkDatabase::new(settings.into(), durable_connection.into());
+
+    let chanmon_cfgs = create_chanmon_cfgs(2);
+    let node_cfgs = create_node_cfgs(2, &chanmon_cfgs);
+    let node_chanmgrs = create_node_chanmgrs(2, &node_cfgs, &[None, None]);
+    let nodes = create_network(2, &node_cfgs, &node_chanmgrs);
+    let (_, _, channel_id, funding_tx) = create_announced_chan_between_nodes(&nodes, 0, 1);
+    let funding_txo = OutPoint {
+        txid: funding_tx.txid(),
+        index: 0,
+    };
+    let latest_update_id = || -> Result<u64> {
+        Ok(nodes[0]
+            .chain_monitor
+            .chain_monitor
+            .get_monitor(funding_txo)
+            .map_err(|_| anyhow!("missing monitor"))?
+            .get_latest_update_id())
+    };
+    let fetch_monitors = || {
+        database.fetch_channel_monitors(
+            nodes[0].keys_manager,
+            &nodes[0].tx_broadcaster,
+            &nodes[0].fee_estimator,
+        )
+    };
+
+    {
+        let monitor = nodes[0]
+            .chain_monitor
+            .chain_monitor
+            .get_monitor(funding_txo)
+            .map_err(|_| anyhow!("missing monitor"))?;
+        database.persist_channel_monitor(funding_txo, &monitor).await?;
+    }
+    let (_, initial_monitor) = fetch_monitors()
+        .await?
+        .pop()
+        .context("expected channel monitor")?;
+    let initial_update_id = initial_monitor.get_latest_update_id();
+    assert_eq!(latest_update_id()?, initial_update_id);
+
+    for _ in 0..3 {
+        send_payment(&nodes[0], &[&nodes[1]], 1000000);
+    }
+    let updates: Vec<_> = nodes[0]
+        .chain_monitor
+        .monitor_updates
+        .lock()
+        .unwrap()
+        .get(&channel_id)
+        .context("expected monitor updates")?
+        .iter()
+        .filter(|update| update.update_id > initial_update_id)
+        .cloned()
+        .collect();
+    assert!(updates.len() > 2);
+
+    // Only the updates up to the missing one are applied.
+    for update in updates.iter().skip(2) {
+        database
+            .persist_channel_monitor_update(funding_txo, update)
+            .await?;
+    }
+    database
+        .persist_channel_monitor_update(funding_txo, &updates[0])
+        .await?;
+    let monitors = fetch_monitors().await?;
+    assert_eq!(1, monitors.len());
+    assert_eq!(updates[0].update_id, monitors[0].1.get_latest_update_id());
+
+    database
+        .persist_channel_monitor_update(funding_txo, &updates[1])
+        .await?;
+    let monitors = fetch_monitors().await?;
+    assert_eq!(latest_update_id()?, monitors[0].1.get_latest_update_id());
+    let count: i64 = client.query_one(&count_updates, &[]).await?.get(0);
+    assert_eq!(0, count);
+
+    // A monitor or update that is written late does not replace the latest monitor.
+    database
+        .persist_channel_monitor(funding_txo, &initial_monitor)
+        .await?;
+    database
+        .persist_channel_monitor_update(funding_txo, &updates[0])
+        .await?;
+    let monitors = fetch_monitors().await?;
+    assert_eq!(latest_update_id()?, monitors[0].1.get_latest_update_id());
+    let count: i64 = client.query_one(&count_updates, &[]).await?.get(0);
+    assert_eq!(0, count);
+    Ok(())
+}
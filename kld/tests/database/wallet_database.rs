@@ -196,3 +196,48 @@ pub async fn test_sync_time() -> Result<()> {
     assert!(wallet_database.get_sync_time()?.is_none());
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_wallets_are_separate() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (settings, _cockroach, durable_connection) = init_db_test_context(&temp_dir).await?;
+
+    let mut wallet_database = WalletDatabase::new(settings.into(), durable_connection.into());
+    let mut taproot_database = wallet_database.for_wallet("bip86");
+
+    wallet_database.set_last_index(KeychainKind::External, 5)?;
+    taproot_database.set_last_index(KeychainKind::External, 2)?;
+    assert_eq!(
+        wallet_database.get_last_index(KeychainKind::External)?,
+        Some(5)
+    );
+    assert_eq!(
+        taproot_database.get_last_index(KeychainKind::External)?,
+        Some(2)
+    );
+
+    let sync_time = SyncTime {
+        block_time: BlockTime {
+            height: 100,
+            timestamp: 1000,
+        },
+    };
+    taproot_database.set_sync_time(sync_time)?;
+    assert!(wallet_database.get_sync_time()?.is_none());
+    assert!(taproot_database.get_sync_time()?.is_some());
+
+    let outpoint =
+        OutPoint::from_str("5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456:0")?;
+    taproot_database.set_utxo(&LocalUtxo {
+        outpoint,
+        txout: TxOut {
+            value: 1000,
+            script_pubkey: ScriptBuf::new(),
+        },
+        keychain: KeychainKind::External,
+        is_spent: false,
+    })?;
+    assert!(wallet_database.get_utxo(&outpoint)?.is_none());
+    assert_eq!(taproot_database.iter_utxos()?.len(), 1);
+    Ok(())
+}
@@ -4,8 +4,9 @@ use anyhow::Result;
 use async_trait::async_trait;
 use bitcoin::BlockHash;
 use bitcoincore_rpc_json::GetBlockchainInfoResult;
-use kld::bitcoind::{bitcoind_interface::BitcoindInterface, MempoolInfo};
+use kld::bitcoind::{bitcoind_interface::BitcoindInterface, FeePolicy, MempoolInfo, TargetFeeRate};
 use kld::settings::Network;
+use lightning::chain::chaininterface::ConfirmationTarget;
 use test_utils::TEST_BLOCK_HASH;
 
 pub struct MockBitcoind;
@@ -43,6 +44,15 @@ impl BitcoindInterface for MockBitcoind {
         (400000, 200000, 100000)
     }
 
+    fn target_fee_rates(&self) -> Vec<TargetFeeRate> {
+        vec![TargetFeeRate {
+            target: ConfirmationTarget::OnChainSweep,
+            policy: FeePolicy::from_str("estimatesmartfee:6,ceiling=500000").unwrap(),
+            estimate: Some(390000),
+            fee_rate: 400000,
+        }]
+    }
+
     async fn block_height(&self) -> Result<u64> {
         Ok(800000)
     }
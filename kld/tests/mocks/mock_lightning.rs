@@ -4,28 +4,42 @@ use std::{
     time::Duration,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use bitcoin::{
     consensus::deserialize,
     hashes::{hex::FromHex, sha256, Hash},
+    psbt::PartiallySignedTransaction,
     secp256k1::{PublicKey, Secp256k1, SecretKey},
-    Network, Txid,
+    Address, Network, ScriptBuf, Transaction, Txid, Witness,
 };
 use kld::api::payloads::FeeRate;
 use kld::{
     api::SocketAddress,
     database::{
-        forward::{Forward, ForwardStatus, TotalForwards},
-        microsecond_timestamp, ChannelRecord,
+        fee_bump::{FeeBump, FeeBumpMethod},
+        forward::{ChannelForwardStats, Forward, ForwardStatus, TotalForwards},
+        microsecond_timestamp,
+        node_announcement::NodeAnnouncementConfig,
+        peer::{PeerAddress, PeerEvent, PeerEventType},
+        psbt::PendingPsbt,
+        scorer_parameters::ScorerParameters,
+        sweep::Sweep,
+        ChannelRecord, Pagination,
     },
 };
 use kld::{
     database::{
-        invoice::Invoice,
-        payment::{Payment, PaymentDirection},
+        invoice::{Invoice, InvoiceStatusFilter},
+        label::Label,
+        ledger::{LedgerEntry, LedgerEntryType},
+        payment::{Payment, PaymentDirection, PaymentStatus},
     },
-    ldk::{LightningInterface, OpenChannelResult, Peer, PeerStatus},
+    ldk::{
+        ChannelLiquidity, LightningInterface, OpenChannelResult, Peer, PeerHistory, PeerStatus,
+        ScorerLiquidity, TransactionPurpose, TransactionTag,
+    },
+    wallet::Consolidation,
     MillisatAmount,
 };
 use lightning::{
@@ -44,9 +58,11 @@ use lightning::{
 };
 
 use lightning_invoice::{Currency, InvoiceBuilder};
+use time::OffsetDateTime;
 
 use test_utils::{
-    TEST_ALIAS, TEST_PRIVATE_KEY, TEST_PUBLIC_KEY, TEST_SHORT_CHANNEL_ID, TEST_TX, TEST_TX_ID,
+    random_public_key, TEST_ALIAS, TEST_PRIVATE_KEY, TEST_PUBLIC_KEY, TEST_SHORT_CHANNEL_ID,
+    TEST_TX, TEST_TX_ID,
 };
 
 pub struct MockLightning {
@@ -149,6 +165,17 @@ impl Default for MockLightning {
     }
 }
 
+impl MockLightning {
+    fn peer_address(&self) -> PeerAddress {
+        PeerAddress {
+            address: self.ipv4_address.0.clone(),
+            last_success: Some(microsecond_timestamp()),
+            last_failure: None,
+            failures: 0,
+        }
+    }
+}
+
 #[async_trait]
 impl LightningInterface for MockLightning {
     fn alias(&self) -> String {
@@ -176,6 +203,14 @@ impl LightningInterface for MockLightning {
         self.num_channels
     }
 
+    fn rgs_snapshot_timestamp(&self) -> Option<u32> {
+        None
+    }
+
+    fn rgs_snapshot(&self, _last_sync_timestamp: u32) -> Option<Vec<u8>> {
+        Some(vec![76, 68, 75, 1])
+    }
+
     fn network(&self) -> bitcoin::Network {
         Network::Bitcoin
     }
@@ -236,6 +271,19 @@ impl LightningInterface for MockLightning {
         vec![addr1.into(), addr2.into()]
     }
 
+    async fn update_node_announcement(
+        &self,
+        alias: Option<String>,
+        color: Option<String>,
+        addresses: Option<Vec<SocketAddress>>,
+    ) -> Result<NodeAnnouncementConfig> {
+        Ok(NodeAnnouncementConfig {
+            alias: alias.unwrap_or(self.alias()),
+            color: color.unwrap_or(self.color()),
+            addresses: addresses.unwrap_or(self.public_addresses()),
+        })
+    }
+
     async fn open_channel(
         &self,
         _their_network_key: PublicKey,
@@ -253,15 +301,177 @@ impl LightningInterface for MockLightning {
         })
     }
 
+    async fn create_withdraw_psbt(
+        &self,
+        _address: Address,
+        _amount: u64,
+        _fee_rate: Option<FeeRate>,
+        _utxos: Vec<bitcoin::OutPoint>,
+        _sign: bool,
+    ) -> Result<PendingPsbt> {
+        Ok(PendingPsbt::withdraw(
+            test_psbt()?,
+            time::Duration::hours(1),
+        ))
+    }
+
+    async fn create_funding_psbt(
+        &self,
+        their_network_key: PublicKey,
+        _channel_value_satoshis: u64,
+        _push_msat: Option<u64>,
+        _fee_rate: Option<FeeRate>,
+        _override_config: Option<UserConfig>,
+        _sign: bool,
+    ) -> Result<PendingPsbt> {
+        Ok(PendingPsbt::fund_channel(
+            test_psbt()?,
+            ChannelId::from_bytes([1u8; 32]),
+            their_network_key,
+            time::Duration::hours(1),
+        ))
+    }
+
+    async fn list_psbts(&self) -> Result<Vec<PendingPsbt>> {
+        Ok(vec![PendingPsbt::withdraw(
+            test_psbt()?,
+            time::Duration::hours(1),
+        )])
+    }
+
+    async fn pending_psbt_inputs(&self) -> Result<Vec<bitcoin::OutPoint>> {
+        Ok(self
+            .list_psbts()
+            .await?
+            .iter()
+            .flat_map(|psbt| psbt.inputs())
+            .collect())
+    }
+
+    async fn submit_psbt(
+        &self,
+        _id: uuid::Uuid,
+        psbt: PartiallySignedTransaction,
+    ) -> Result<Transaction> {
+        Ok(psbt.extract_tx())
+    }
+
+    async fn tag_transactions(
+        &self,
+        transactions: &[bdk::TransactionDetails],
+    ) -> Result<Vec<TransactionTag>> {
+        Ok(transactions
+            .iter()
+            .map(|_| TransactionTag {
+                purpose: TransactionPurpose::ChannelFunding,
+                channel_id: Some(ChannelId::from_bytes([1u8; 32])),
+            })
+            .collect())
+    }
+
+    async fn list_labels(&self) -> Result<Vec<Label>> {
+        Ok(vec![Label::tx(TEST_TX_ID, "channel with the test node")])
+    }
+
+    async fn import_labels(&self, _labels: Vec<Label>) -> Result<()> {
+        Ok(())
+    }
+
+    async fn withdraw_many(
+        &self,
+        _outputs: Vec<(Address, u64)>,
+        _fee_rate: Option<FeeRate>,
+        _idempotency_key: Option<String>,
+    ) -> Result<Transaction> {
+        Ok(deserialize::<Transaction>(&Vec::<u8>::from_hex(TEST_TX)?)?)
+    }
+
+    async fn list_sweeps(&self) -> Result<Vec<Sweep>> {
+        Ok(vec![Sweep::new(
+            Txid::from_str(TEST_TX_ID)?,
+            "bcrt1qqyqszqgpqyqszqgpqyqszqgpqyqszqgpvxat9t".to_string(),
+            0,
+            2000000,
+            1000,
+        )])
+    }
+
+    async fn ledger(
+        &self,
+        from: Option<OffsetDateTime>,
+        to: Option<OffsetDateTime>,
+    ) -> Result<Vec<LedgerEntry>> {
+        let entries = vec![
+            LedgerEntry {
+                entry_type: LedgerEntryType::PaymentReceived,
+                reference: hex::encode([1u8; 32]),
+                amount: 250000000,
+                fee: 0,
+                channel_id: None,
+                timestamp: OffsetDateTime::from_unix_timestamp(1700000000)?,
+            },
+            LedgerEntry {
+                entry_type: LedgerEntryType::Withdrawal,
+                reference: TEST_TX_ID.to_string(),
+                amount: -100000000,
+                fee: 153000,
+                channel_id: None,
+                timestamp: OffsetDateTime::from_unix_timestamp(1700000600)?,
+            },
+        ];
+        Ok(entries
+            .into_iter()
+            .filter(|entry| from.map_or(true, |from| entry.timestamp >= from))
+            .filter(|entry| to.map_or(true, |to| entry.timestamp < to))
+            .collect())
+    }
+
+    async fn consolidation_report(&self) -> Result<Consolidation> {
+        let tx = deserialize::<Transaction>(&Vec::<u8>::from_hex(TEST_TX)?)?;
+        Ok(Consolidation {
+            fee_rate: 500,
+            utxos: vec![
+                bitcoin::OutPoint::new(tx.txid(), 0),
+                bitcoin::OutPoint::new(tx.txid(), 1),
+            ],
+            amount: 60000,
+            fee: 400,
+            skip_reason: None,
+            tx: None,
+        })
+    }
+
+    async fn bump_fee(&self, txid: Txid, _fee_rate: FeeRate) -> Result<(Transaction, FeeBump)> {
+        let tx = deserialize::<Transaction>(&Vec::<u8>::from_hex(TEST_TX)?)?;
+        let mut fee_bump = FeeBump::new(tx.txid(), txid, FeeBumpMethod::Cpfp, 5000, 1000);
+        fee_bump.channel_id = Some(ChannelId::from_bytes([1u8; 32]));
+        Ok((tx, fee_bump))
+    }
+
     async fn list_peers(&self) -> Result<Vec<Peer>> {
         Ok(vec![Peer {
             public_key: self.public_key,
             net_address: Some(self.ipv4_address.clone()),
             status: PeerStatus::Connected,
             alias: TEST_ALIAS.to_string(),
+            addresses: vec![self.peer_address()],
+            uptime: Some(100.0),
         }])
     }
 
+    async fn peer_history(&self, public_key: PublicKey) -> Result<PeerHistory> {
+        Ok(PeerHistory {
+            public_key,
+            addresses: vec![self.peer_address()],
+            uptime: Some(100.0),
+            events: vec![PeerEvent::new(
+                public_key,
+                Some(self.ipv4_address.0.clone()),
+                PeerEventType::Connected,
+            )],
+        })
+    }
+
     async fn connect_peer(
         &self,
         _public_key: PublicKey,
@@ -347,12 +557,26 @@ impl LightningInterface for MockLightning {
         &self,
         _bolt11: Option<Invoice>,
         _direction: Option<PaymentDirection>,
+        statuses: &[PaymentStatus],
+        pagination: Pagination,
     ) -> Result<Vec<Payment>> {
-        Ok(vec![self.payment.clone()])
+        Ok(vec![self.payment.clone()]
+            .into_iter()
+            .filter(|payment| statuses.is_empty() || statuses.contains(&payment.status))
+            .take(pagination.limit.unwrap_or(u64::MAX) as usize)
+            .collect())
     }
 
-    async fn list_invoices(&self, _label: Option<String>) -> Result<Vec<Invoice>> {
-        Ok(vec![self.invoice.clone()])
+    async fn list_invoices(
+        &self,
+        _label: Option<String>,
+        _status: Option<InvoiceStatusFilter>,
+        pagination: Pagination,
+    ) -> Result<Vec<Invoice>> {
+        Ok(vec![self.invoice.clone()]
+            .into_iter()
+            .take(pagination.limit.unwrap_or(u64::MAX) as usize)
+            .collect())
     }
 
     async fn keysend_payment(&self, _payee: NodeId, _amount: MillisatAmount) -> Result<Payment> {
@@ -375,11 +599,45 @@ impl LightningInterface for MockLightning {
         })
     }
 
-    async fn fetch_forwards(&self, _status: Option<ForwardStatus>) -> Result<Vec<Forward>> {
-        Ok(vec![self.forward.clone()])
-    }
-
-    async fn channel_history(&self) -> Result<Vec<ChannelRecord>> {
+    async fn routing_stats(
+        &self,
+        _from: Option<OffsetDateTime>,
+        _to: Option<OffsetDateTime>,
+    ) -> Result<Vec<ChannelForwardStats>> {
+        let mut inbound = ChannelForwardStats::new(ChannelId::from_bytes([3u8; 32]));
+        inbound.inbound_count = 1;
+        inbound.inbound_amount = 5003000;
+        inbound.failures.insert("UnknownNextHop".to_string(), 2);
+        let mut outbound = ChannelForwardStats::new(self.channel.channel_id);
+        outbound.outbound_count = 1;
+        outbound.outbound_amount = 5000000;
+        outbound.fee = 3000;
+        Ok(vec![inbound, outbound])
+    }
+
+    async fn fetch_forwards(
+        &self,
+        _status: Option<ForwardStatus>,
+        channel_id: Option<ChannelId>,
+        pagination: Pagination,
+    ) -> Result<Vec<Forward>> {
+        Ok(vec![self.forward.clone()]
+            .into_iter()
+            .filter(|forward| {
+                channel_id.map_or(true, |channel_id| {
+                    forward.inbound_channel_id == channel_id
+                        || forward.outbound_channel_id == Some(channel_id)
+                })
+            })
+            .take(pagination.limit.unwrap_or(u64::MAX) as usize)
+            .collect())
+    }
+
+    async fn channel_history(
+        &self,
+        _channel_id: Option<ChannelId>,
+        _pagination: Pagination,
+    ) -> Result<Vec<ChannelRecord>> {
         Ok(vec![ChannelRecord {
             channel_id: self.channel.channel_id.to_string(),
             counterparty: self.channel.counterparty.node_id.to_string(),
@@ -394,5 +652,67 @@ impl LightningInterface for MockLightning {
         Ok(Vec::new())
     }
 
+    async fn upload_scorer(&self, scorer: Vec<u8>) -> Result<()> {
+        if scorer.is_empty() {
+            bail!("Invalid scorer");
+        }
+        Ok(())
+    }
+
+    async fn scorer_liquidity(
+        &self,
+        node_id: Option<NodeId>,
+        short_channel_id: Option<u64>,
+    ) -> Result<ScorerLiquidity> {
+        let source = NodeId::from_pubkey(&PublicKey::from_str(TEST_PUBLIC_KEY)?);
+        let target = NodeId::from_pubkey(&random_public_key());
+        let channel = ChannelLiquidity {
+            short_channel_id: TEST_SHORT_CHANNEL_ID,
+            source,
+            target,
+            capacity_sats: Some(1000000),
+            min_liquidity_msat: 100000,
+            max_liquidity_msat: 900000000,
+            historical_min_liquidity_buckets: Some(vec![0; 32]),
+            historical_max_liquidity_buckets: Some(vec![0; 32]),
+        };
+        let channels = if node_id.map_or(true, |id| id == source)
+            && short_channel_id.map_or(true, |id| id == TEST_SHORT_CHANNEL_ID)
+        {
+            vec![channel]
+        } else {
+            vec![]
+        };
+        Ok(ScorerLiquidity {
+            last_updated: Some(microsecond_timestamp()),
+            channels,
+        })
+    }
+
+    fn scorer_parameters(&self) -> ScorerParameters {
+        ScorerParameters::default()
+    }
+
+    async fn update_scorer_parameters(
+        &self,
+        parameters: ScorerParameters,
+    ) -> Result<ScorerParameters> {
+        parameters.validate()?;
+        Ok(parameters)
+    }
+
+    async fn static_channel_backup(&self) -> Result<Vec<u8>> {
+        Ok(vec![1u8; 64])
+    }
+
     async fn update_channels(&self, _channels: &[ChannelDetails]) {}
 }
+
+fn test_psbt() -> Result<PartiallySignedTransaction> {
+    let mut tx = deserialize::<Transaction>(&Vec::<u8>::from_hex(TEST_TX)?)?;
+    for input in &mut tx.input {
+        input.script_sig = ScriptBuf::new();
+        input.witness = Witness::new();
+    }
+    Ok(PartiallySignedTransaction::from_unsigned_tx(tx)?)
+}
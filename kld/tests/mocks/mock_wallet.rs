@@ -5,9 +5,9 @@ use async_trait::async_trait;
 use bdk::{wallet::AddressInfo, Balance, BlockTime, KeychainKind, LocalUtxo, TransactionDetails};
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::{consensus::deserialize, hashes::hex::FromHex, Address, OutPoint, Transaction};
-use kld::wallet::WalletInterface;
+use kld::wallet::{AddressType, WalletInterface};
 
-use test_utils::{TEST_ADDRESS, TEST_TX};
+use test_utils::{TEST_ADDRESS, TEST_PUBLIC_KEY, TEST_TX};
 
 pub struct MockWallet {
     balance: Balance,
@@ -27,6 +27,7 @@ impl WalletInterface for MockWallet {
         _fee_rate: Option<kld::api::payloads::FeeRate>,
         _min_conf: Option<u8>,
         _utxos: Vec<OutPoint>,
+        _unspendable: Vec<OutPoint>,
     ) -> Result<(Transaction, TransactionDetails)> {
         let details = TransactionDetails {
             transaction: Some(self.transaction.clone()),
@@ -39,7 +40,7 @@ impl WalletInterface for MockWallet {
         Ok((self.transaction.clone(), details))
     }
 
-    fn new_external_address(&self) -> Result<AddressInfo> {
+    fn new_external_address(&self, _address_type: AddressType) -> Result<AddressInfo> {
         Ok(AddressInfo {
             address: Address::from_str(TEST_ADDRESS)?.assume_checked(),
             index: 1,
@@ -72,6 +73,44 @@ impl WalletInterface for MockWallet {
         };
         Ok(vec![(utxo, details)])
     }
+
+    fn frozen_utxos(&self) -> Result<Vec<OutPoint>> {
+        Ok(vec![OutPoint::new(self.transaction.txid(), 0)])
+    }
+
+    fn freeze_utxos(&self, outpoints: &[OutPoint], _frozen: bool) -> Result<Vec<OutPoint>> {
+        Ok(outpoints.to_vec())
+    }
+
+    fn public_descriptors(&self) -> Result<Vec<(AddressType, KeychainKind, String)>> {
+        Ok(vec![
+            (
+                AddressType::Bech32,
+                KeychainKind::External,
+                format!("wpkh({TEST_PUBLIC_KEY}/0/*)"),
+            ),
+            (
+                AddressType::Bech32,
+                KeychainKind::Internal,
+                format!("wpkh({TEST_PUBLIC_KEY}/1/*)"),
+            ),
+        ])
+    }
+
+    fn birthday_height(&self) -> Option<u32> {
+        Some(600000)
+    }
+
+    fn list_transactions(&self) -> Result<Vec<TransactionDetails>> {
+        Ok(vec![TransactionDetails {
+            transaction: Some(self.transaction.clone()),
+            txid: self.transaction.txid(),
+            received: 10000,
+            sent: 1200,
+            fee: Some(20),
+            confirmation_time: BlockTime::new(Some(600000), Some(23293219)),
+        }])
+    }
 }
 
 impl Default for MockWallet {